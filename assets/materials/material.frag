@@ -0,0 +1,34 @@
+#version 330 core
+
+// Every uniform here (other than the matrices) is a material parameter, and the initializers are the defaults that
+// materials get for anything their material file doesn't mention
+
+in VS_OUTPUT {
+    vec3 Normal;
+    vec3 ViewNormal;
+    vec2 TexCoord;
+} IN;
+
+uniform vec3 DiffuseColor = vec3(0.8);
+uniform sampler2D DiffuseTexture;      // white when not set, so it doesn't change the color
+uniform vec2 TextureScale = vec2(1.0);  // how many times the texture repeats across the mesh
+uniform vec3 RimColor = vec3(0.0);     // a glow around the edges, as seen from the camera
+uniform float RimPower = 3.0;          // higher keeps the glow closer to the edges
+uniform float Opacity = 1.0;           // only does anything with blending turned on
+uniform bool Unlit = false;
+
+out vec4 Color;
+
+void main()
+{
+    vec3 base = DiffuseColor * texture(DiffuseTexture, IN.TexCoord * TextureScale).rgb;
+
+    // The same fixed light as mesh.frag
+    vec3 light_direction = normalize(vec3(0.4, 1.0, 0.7));
+    float shade = Unlit ? 1.0 : 0.3 + 0.7 * max(dot(normalize(IN.Normal), light_direction), 0.0);
+
+    // Surfaces seen edge-on have normals pointing sideways in view space, so their Z is close to 0
+    float rim = pow(1.0 - abs(normalize(IN.ViewNormal).z), RimPower);
+
+    Color = vec4(base * shade + RimColor * rim, Opacity);
+}
@@ -0,0 +1,23 @@
+#version 330 core
+
+layout (location = 0) in vec3 Position;
+layout (location = 2) in vec3 Normal;
+layout (location = 3) in vec2 TexCoord;
+
+uniform mat4 Model;
+uniform mat4 View;
+uniform mat4 Projection;
+
+out VS_OUTPUT {
+    vec3 Normal;      // in world space
+    vec3 ViewNormal;  // in view space, where the camera looks down -Z
+    vec2 TexCoord;
+} OUT;
+
+void main()
+{
+    gl_Position = Projection * View * Model * vec4(Position, 1.0);
+    OUT.Normal = mat3(Model) * Normal;
+    OUT.ViewNormal = mat3(View) * OUT.Normal;
+    OUT.TexCoord = TexCoord;
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+in vec2 Corner;
+in vec4 ParticleColor;
+
+out vec4 Color;
+
+void main()
+{
+    // Round rather than square, fading out towards the edge
+    float distance = length(Corner);
+    if (distance > 1.0) {
+        discard;
+    }
+    Color = vec4(ParticleColor.rgb, ParticleColor.a * (1.0 - distance));
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+// One point per particle, in view space, for the geometry shader to turn into a quad facing the camera
+layout (location = 0) in vec3 Position;
+layout (location = 2) in float Age;
+
+uniform mat4 View;
+
+out float ParticleAge;
+
+void main()
+{
+    gl_Position = View * vec4(Position, 1.0);
+    ParticleAge = Age;
+}
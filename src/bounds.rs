@@ -0,0 +1,132 @@
+// Bounding volumes, for quickly answering "could this possibly be on screen?" without looking at every triangle.
+//
+// `Aabb` is an axis-aligned bounding box: the smallest box, lined up with the axes, that contains something.  `Frustum`
+// is the chunk of space the camera can see, a box for orthographic projections or a pyramid with its tip cut off for
+// perspective ones.  Anything whose box is completely outside the frustum can be skipped.
+
+use crate::math::{Mat4, Vec3, Vec4};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+#[allow(dead_code)]
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// A box containing nothing at all, which grows to fit whatever gets added to it
+    pub fn empty() -> Aabb {
+        Aabb { min: Vec3::splat(f32::INFINITY), max: Vec3::splat(f32::NEG_INFINITY) }
+    }
+
+    pub fn from_points<I: IntoIterator<Item = Vec3>>(points: I) -> Aabb {
+        let mut aabb = Aabb::empty();
+        for p in points {
+            aabb.include_point(p);
+        }
+        aabb
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn include_point(&mut self, p: Vec3) {
+        self.min = self.min.min(p);
+        self.max = self.max.max(p);
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Half the box's size along each axis
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        p.x >= self.min.x && p.x <= self.max.x
+            && p.y >= self.min.y && p.y <= self.max.y
+            && p.z >= self.min.z && p.z <= self.max.z
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3::new(a.x, a.y, a.z), Vec3::new(b.x, a.y, a.z), Vec3::new(a.x, b.y, a.z), Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z), Vec3::new(b.x, a.y, b.z), Vec3::new(a.x, b.y, b.z), Vec3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    /// The box around this box after it's been moved by `transform`.  It's usually a bit bigger than it strictly
+    /// needs to be (a rotated box's corners stick out), but that's fine for culling
+    pub fn transformed(&self, transform: &Mat4) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+        Aabb::from_points(self.corners().iter().map(|&corner| transform.transform_point(corner)))
+    }
+}
+
+/// The six planes around what a camera can see, each stored as (normal, distance) in a `Vec4` with the normal
+/// pointing inwards, so a point `p` is on the inside of a plane when `normal.dot(p) + distance >= 0`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],  // left, right, bottom, top, near, far
+}
+
+#[allow(dead_code)]
+impl Frustum {
+    /// Pulls the planes out of a combined projection * view matrix (the Gribb/Hartmann method).  A point is visible
+    /// when its clip space coordinates satisfy -w <= x <= w and so on, and each of those six inequalities turns out
+    /// to be a plane made by adding or subtracting rows of the matrix.  With a projection matrix on its own you get
+    /// the planes in view space instead
+    pub fn from_matrix(view_projection: &Mat4) -> Frustum {
+        let m = view_projection;
+        let (x, y, z, w) = (m.row(0), m.row(1), m.row(2), m.row(3));
+        let mut planes = [w + x, w - x, w + y, w - y, w + z, w - z];
+        for plane in &mut planes {
+            let length = plane.truncate().length();
+            *plane = *plane / length;
+        }
+        Frustum { planes }
+    }
+
+    fn distance_to(plane: Vec4, p: Vec3) -> f32 {
+        plane.truncate().dot(p) + plane.w
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        self.planes.iter().all(|&plane| Frustum::distance_to(plane, p) >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes.iter().all(|&plane| Frustum::distance_to(plane, center) >= -radius)
+    }
+
+    /// False only if the box is definitely out of sight.  For each plane we check the box's corner that's furthest
+    /// along the plane's normal: if even that one is outside, the whole box is.  Big boxes near the frustum's corners
+    /// can slip through as "visible" when they aren't, which just costs a wasted draw call
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        if aabb.is_empty() {
+            return false;
+        }
+        self.planes.iter().all(|&plane| {
+            let furthest = Vec3::new(
+                if plane.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+            Frustum::distance_to(plane, furthest) >= 0.0
+        })
+    }
+}
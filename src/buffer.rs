@@ -0,0 +1,186 @@
+// Wrappers around GL buffer objects and vertex array objects, so we stop having to write `gl::GenBuffers` and friends
+// by hand (and stop leaking them, since these clean up after themselves when dropped).
+
+use std::marker::PhantomData;
+
+/// Which binding point a buffer gets bound to.  Using a type parameter for this (rather than storing the target in
+/// the struct) means the compiler stops us from, say, handing an index buffer to something expecting vertices
+pub trait BufferType {
+    const BUFFER_TYPE: gl::types::GLuint;
+}
+
+pub struct BufferTypeArray;
+impl BufferType for BufferTypeArray {
+    const BUFFER_TYPE: gl::types::GLuint = gl::ARRAY_BUFFER;
+}
+
+pub struct BufferTypeElementArray;
+impl BufferType for BufferTypeElementArray {
+    const BUFFER_TYPE: gl::types::GLuint = gl::ELEMENT_ARRAY_BUFFER;
+}
+
+/// Holds the values for a `uniform` block in a shader, so that lots of uniforms can be set in one go and shared between
+/// programs
+pub struct BufferTypeUniform;
+impl BufferType for BufferTypeUniform {
+    const BUFFER_TYPE: gl::types::GLuint = gl::UNIFORM_BUFFER;
+}
+
+/// Big, writable blocks of data for shaders (`buffer` blocks in GLSL), which compute shaders mostly work through.
+/// Needs GL 4.3
+pub struct BufferTypeShaderStorage;
+impl BufferType for BufferTypeShaderStorage {
+    const BUFFER_TYPE: gl::types::GLuint = gl::SHADER_STORAGE_BUFFER;
+}
+
+pub struct Buffer<B: BufferType> {
+    vbo: gl::types::GLuint,
+    size: usize,  // in bytes, of whatever we last uploaded
+    _marker: PhantomData<B>,
+}
+
+pub type ArrayBuffer = Buffer<BufferTypeArray>;
+pub type ElementArrayBuffer = Buffer<BufferTypeElementArray>;
+pub type UniformBuffer = Buffer<BufferTypeUniform>;
+#[allow(dead_code)]
+pub type StorageBuffer = Buffer<BufferTypeShaderStorage>;
+
+#[allow(dead_code)]
+impl<B: BufferType> Buffer<B> {
+    pub fn new() -> Buffer<B> {
+        let mut vbo: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut vbo);
+        }
+        Buffer { vbo, size: 0, _marker: PhantomData }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.vbo
+    }
+
+    /// Size in bytes of the data currently in the buffer
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBuffer(B::BUFFER_TYPE, self.vbo);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindBuffer(B::BUFFER_TYPE, 0);
+        }
+    }
+
+    /// Attaches the buffer to numbered binding point `index` of its type.  Only means something for the types with
+    /// numbered binding points, like uniform buffers, where shaders find their blocks by binding point
+    pub fn bind_base(&self, index: gl::types::GLuint) {
+        unsafe {
+            gl::BindBufferBase(B::BUFFER_TYPE, index, self.vbo);
+        }
+    }
+
+    /// Uploads data that we'll set once and draw many times.  The buffer needs to be bound first
+    pub fn static_draw_data<T>(&mut self, data: &[T]) {
+        self.buffer_data(data, gl::STATIC_DRAW);
+    }
+
+    /// Uploads data that we expect to change often.  The buffer needs to be bound first
+    pub fn dynamic_draw_data<T>(&mut self, data: &[T]) {
+        self.buffer_data(data, gl::DYNAMIC_DRAW);
+    }
+
+    /// Uploads data with whichever usage hint you like (`gl::STREAM_DRAW`, `gl::STATIC_READ`, ...)
+    pub fn buffer_data<T>(&mut self, data: &[T], usage: gl::types::GLenum) {
+        self.size = std::mem::size_of_val(data);
+        unsafe {
+            gl::BufferData(
+                B::BUFFER_TYPE,
+                self.size as gl::types::GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
+                usage,
+            );
+        }
+    }
+
+    /// Overwrites part of the buffer without reallocating it.  `offset` is in bytes.  The buffer needs to be bound first
+    pub fn sub_data<T>(&self, offset: usize, data: &[T]) {
+        unsafe {
+            gl::BufferSubData(
+                B::BUFFER_TYPE,
+                offset as gl::types::GLintptr,
+                std::mem::size_of_val(data) as gl::types::GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
+            );
+        }
+    }
+
+    /// Copies `count` values back from the GPU, starting `offset` bytes in.  The buffer needs to be bound first, and
+    /// if a shader wrote it, `compute::memory_barrier(gl::BUFFER_UPDATE_BARRIER_BIT)` needs to have happened since.
+    /// This waits for the GPU to catch up, so it's best kept out of anything that runs every frame
+    pub fn read<T: Copy + Default>(&self, offset: usize, count: usize) -> Vec<T> {
+        let mut values = vec![T::default(); count];
+        unsafe {
+            gl::GetBufferSubData(
+                B::BUFFER_TYPE,
+                offset as gl::types::GLintptr,
+                std::mem::size_of_val(values.as_slice()) as gl::types::GLsizeiptr,
+                values.as_mut_ptr() as *mut gl::types::GLvoid,
+            );
+        }
+        values
+    }
+}
+
+impl<B: BufferType> Drop for Buffer<B> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+        }
+    }
+}
+
+/// Remembers how to read vertices out of buffers: which buffers, which attributes, strides and offsets, and which index
+/// buffer (if any) to use
+pub struct VertexArray {
+    vao: gl::types::GLuint,
+}
+
+#[allow(dead_code)]
+impl VertexArray {
+    pub fn new() -> VertexArray {
+        let mut vao: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+        }
+        VertexArray { vao }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.vao
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindVertexArray(self.vao);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+#version 330 core
+
+// One source, five programs: `program_cache_demo.rs` compiles it once for each value of `SHADING`, and the cache keeps
+// each one separately, since the defines are part of the key
+in VS_OUTPUT {
+    vec3 Normal;
+    vec2 TexCoord;
+} IN;
+
+uniform vec3 DiffuseColor;
+
+out vec4 Color;
+
+void main()
+{
+    vec3 normal = normalize(IN.Normal);
+    vec3 light_direction = normalize(vec3(0.4, 1.0, 0.7));
+    float diffuse = max(dot(normal, light_direction), 0.0);
+
+#if SHADING == 0
+    // Plain diffuse, like mesh.frag
+    Color = vec4(DiffuseColor * (0.3 + 0.7 * diffuse), 1.0);
+#elif SHADING == 1
+    // Normals as colors
+    Color = vec4(normal * 0.5 + 0.5, 1.0);
+#elif SHADING == 2
+    // Checkered by texture coordinates
+    vec2 cell = floor(IN.TexCoord * 8.0);
+    Color = vec4(DiffuseColor * (0.4 + 0.6 * mod(cell.x + cell.y, 2.0)) * (0.3 + 0.7 * diffuse), 1.0);
+#elif SHADING == 3
+    // Cartoon-like, with the light in three hard steps
+    float steps = floor(diffuse * 3.0 + 0.5) / 3.0;
+    Color = vec4(DiffuseColor * (0.3 + 0.7 * steps), 1.0);
+#else
+    // Stripes, to show there's always a fallback
+    Color = vec4(mix(DiffuseColor, vec3(1.0), step(0.5, fract(IN.TexCoord.y * 10.0))) * (0.3 + 0.7 * diffuse), 1.0);
+#endif
+}
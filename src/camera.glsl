@@ -0,0 +1,10 @@
+// The `Camera` block, pasted into shaders by `camera::with_camera_block()`.  `camera::CameraUniforms` is the Rust side
+// of it, and `uniform_block::check_block()` makes sure the two still agree when a program is attached to the buffer
+
+layout (std140) uniform Camera {
+    mat4 View;
+    mat4 Projection;
+    mat4 ViewProjection;  // Projection * View
+    vec3 CameraPosition;  // in world space
+    float Time;           // packed into the end of CameraPosition's 16 bytes
+};
@@ -0,0 +1,393 @@
+// A camera is really just two matrices: the view matrix, which moves the whole world so the camera ends up at the
+// origin looking down -Z, and the projection matrix, which squashes what the camera can see into clip space.  The
+// controllers below are what turn SDL keyboard and mouse events into changes to the camera.
+//
+// Rather than every program having its own `View` and `Projection` uniforms set one at a time, shaders can paste in
+// the `Camera` uniform block from camera.glsl with `with_camera_block()`, and then one `BlockBuffer<CameraUniforms>`
+// upload a frame feeds all of them.
+
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+
+use crate::math::{self, Mat4, Quat, Vec3};
+use crate::render_gl;
+
+/// The uniform buffer binding point the `Camera` block is read from.  (`lighting::LIGHTS_BINDING` is 0)
+pub const CAMERA_BINDING: gl::types::GLuint = 1;
+
+crate::uniform_block! {
+    /// The Rust side of the `Camera` block in camera.glsl
+    #[derive(PartialEq)]
+    pub struct CameraUniforms {
+        pub view: Mat4 => "View",
+        pub projection: Mat4 => "Projection",
+        pub view_projection: Mat4 => "ViewProjection",
+        pub position: Vec3 => "CameraPosition",
+        pub time: f32 => "Time",  // seconds, for anything that wants to move
+    }
+}
+
+/// Pastes the `Camera` block from camera.glsl into a shader, just after its `#version`
+#[allow(dead_code)]
+pub fn with_camera_block(source: &str) -> String {
+    render_gl::insert_after_version(source, include_str!("camera.glsl"))
+}
+
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub fov_y: f32,  // radians
+    pub near: f32,
+    pub far: f32,
+    pub aspect: f32,
+}
+
+#[allow(dead_code)]
+impl Camera {
+    pub fn new(position: Vec3, aspect: f32) -> Camera {
+        Camera {
+            position,
+            orientation: Quat::IDENTITY,
+            fov_y: math::radians(45.0),
+            near: 0.1,
+            far: 100.0,
+            aspect,
+        }
+    }
+
+    /// Direction the camera is looking.  Like OpenGL, an un-rotated camera looks down -Z
+    pub fn forward(&self) -> Vec3 {
+        self.orientation.rotate(-Vec3::Z)
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.orientation.rotate(Vec3::X)
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.orientation.rotate(Vec3::Y)
+    }
+
+    /// The view matrix is the inverse of the camera's own placement in the world.  Since the rotation is a unit
+    /// quaternion its inverse is just the conjugate, so we don't need a full matrix inverse here
+    pub fn view_matrix(&self) -> Mat4 {
+        self.orientation.conjugate().to_mat4() * Mat4::translation(-self.position)
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        Mat4::perspective(self.fov_y, self.aspect, self.near, self.far)
+    }
+
+    pub fn view_projection_matrix(&self) -> Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    /// Everything the `Camera` block wants, for `time` seconds in
+    pub fn uniforms(&self, time: f32) -> CameraUniforms {
+        let (view, projection) = (self.view_matrix(), self.projection_matrix());
+        CameraUniforms { view, projection, view_projection: projection * view, position: self.position, time }
+    }
+
+    /// Call this whenever the window changes size so the projection doesn't end up stretched
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height.max(1) as f32;
+    }
+
+    /// Convenience for the event loop: picks window resize events out of the stream, updates the aspect ratio, and
+    /// resets the GL viewport to match.  Returns `true` if the event was a resize
+    pub fn handle_resize(&mut self, event: &Event) -> bool {
+        if let Event::Window { win_event: WindowEvent::Resized(w, h), .. } = *event {
+            self.set_viewport_size(w as u32, h as u32);
+            unsafe { gl::Viewport(0, 0, w, h); }
+            return true;
+        }
+        false
+    }
+}
+
+/// Something that moves a `Camera` around in response to input.  Scenes hold one of these in a `Box` so that they can
+/// swap controllers at runtime without caring which kind is active
+pub trait CameraController {
+    /// Feed every SDL event through here
+    fn handle_event(&mut self, camera: &mut Camera, event: &Event);
+
+    /// Called once per frame with the number of seconds since the last frame, for anything that should move smoothly
+    /// while a key is held rather than once per key press
+    fn update(&mut self, camera: &mut Camera, dt: f32);
+
+    /// Called when this controller takes over a camera, so it can pick up from wherever the last one left it
+    fn attach(&mut self, camera: &mut Camera);
+
+    fn name(&self) -> &'static str;
+}
+
+// Keeps the camera from flipping over when looking straight up or down
+const MAX_PITCH: f32 = 1.55;
+
+/// Free-flying first-person camera: WASD to move, Space/LCtrl to go up/down, hold Shift to go faster, and drag with the
+/// right mouse button held to look around
+pub struct FlyController {
+    pub speed: f32,
+    pub sensitivity: f32,
+    yaw: f32,
+    pitch: f32,
+    looking: bool,
+    forward_held: bool,
+    back_held: bool,
+    left_held: bool,
+    right_held: bool,
+    up_held: bool,
+    down_held: bool,
+    fast_held: bool,
+}
+
+#[allow(dead_code)]
+impl FlyController {
+    pub fn new() -> FlyController {
+        FlyController {
+            speed: 2.5,
+            sensitivity: 0.003,
+            yaw: 0.0,
+            pitch: 0.0,
+            looking: false,
+            forward_held: false,
+            back_held: false,
+            left_held: false,
+            right_held: false,
+            up_held: false,
+            down_held: false,
+            fast_held: false,
+        }
+    }
+
+    fn set_key(&mut self, keycode: Keycode, held: bool) {
+        match keycode {
+            Keycode::W => self.forward_held = held,
+            Keycode::S => self.back_held = held,
+            Keycode::A => self.left_held = held,
+            Keycode::D => self.right_held = held,
+            Keycode::Space => self.up_held = held,
+            Keycode::LCtrl => self.down_held = held,
+            Keycode::LShift | Keycode::RShift => self.fast_held = held,
+            _ => {},
+        }
+    }
+}
+
+impl CameraController for FlyController {
+    fn handle_event(&mut self, camera: &mut Camera, event: &Event) {
+        match *event {
+            Event::KeyDown { keycode: Some(keycode), .. } => self.set_key(keycode, true),
+            Event::KeyUp { keycode: Some(keycode), .. } => self.set_key(keycode, false),
+            Event::MouseButtonDown { mouse_btn: MouseButton::Right, .. } => self.looking = true,
+            Event::MouseButtonUp { mouse_btn: MouseButton::Right, .. } => self.looking = false,
+            Event::MouseMotion { xrel, yrel, .. } if self.looking => {
+                // Moving the mouse right turns us right, which is a negative rotation around +Y
+                self.yaw -= xrel as f32 * self.sensitivity;
+                self.pitch = (self.pitch - yrel as f32 * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+                camera.orientation = Quat::from_euler(self.yaw, self.pitch, 0.0);
+            },
+            _ => {},
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let axis = |positive: bool, negative: bool| (positive as i32 - negative as i32) as f32;
+
+        let direction = camera.forward() * axis(self.forward_held, self.back_held)
+            + camera.right() * axis(self.right_held, self.left_held)
+            + Vec3::Y * axis(self.up_held, self.down_held);
+
+        let speed = if self.fast_held { self.speed * 4.0 } else { self.speed };
+        camera.position += direction.normalize() * speed * dt;
+    }
+
+    fn attach(&mut self, camera: &mut Camera) {
+        // Work backwards from the camera's forward vector to the yaw and pitch that would produce it
+        let forward = camera.forward();
+        self.pitch = forward.y.clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH);
+        self.yaw = (-forward.x).atan2(-forward.z);
+        camera.orientation = Quat::from_euler(self.yaw, self.pitch, 0.0);
+    }
+
+    fn name(&self) -> &'static str {
+        "fly"
+    }
+}
+
+/// Orbits around a target point: drag with the left mouse button to rotate, drag with the right (or middle) button to
+/// pan the target around, and use the scroll wheel to zoom in and out
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub rotate_sensitivity: f32,
+    pub zoom_sensitivity: f32,
+    yaw: f32,
+    pitch: f32,
+    rotating: bool,
+    panning: bool,
+}
+
+#[allow(dead_code)]
+impl OrbitController {
+    pub fn new(target: Vec3, distance: f32) -> OrbitController {
+        OrbitController {
+            target,
+            distance,
+            min_distance: 0.1,
+            max_distance: 500.0,
+            rotate_sensitivity: 0.005,
+            zoom_sensitivity: 0.1,
+            yaw: 0.0,
+            pitch: 0.0,
+            rotating: false,
+            panning: false,
+        }
+    }
+
+    /// Puts the camera on a sphere around the target and points it back at the target
+    fn apply(&self, camera: &mut Camera) {
+        camera.orientation = Quat::from_euler(self.yaw, self.pitch, 0.0);
+        camera.position = self.target - camera.forward() * self.distance;
+    }
+}
+
+impl CameraController for OrbitController {
+    fn handle_event(&mut self, camera: &mut Camera, event: &Event) {
+        match *event {
+            Event::MouseButtonDown { mouse_btn: MouseButton::Left, .. } => self.rotating = true,
+            Event::MouseButtonUp { mouse_btn: MouseButton::Left, .. } => self.rotating = false,
+            Event::MouseButtonDown { mouse_btn: MouseButton::Right, .. }
+            | Event::MouseButtonDown { mouse_btn: MouseButton::Middle, .. } => self.panning = true,
+            Event::MouseButtonUp { mouse_btn: MouseButton::Right, .. }
+            | Event::MouseButtonUp { mouse_btn: MouseButton::Middle, .. } => self.panning = false,
+            Event::MouseMotion { xrel, yrel, .. } => {
+                if self.rotating {
+                    self.yaw -= xrel as f32 * self.rotate_sensitivity;
+                    self.pitch = (self.pitch - yrel as f32 * self.rotate_sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+                    self.apply(camera);
+                } else if self.panning {
+                    // Scale the pan by the distance so that the point under the cursor roughly follows the mouse
+                    // no matter how far out we've zoomed
+                    let scale = self.distance * 0.0015;
+                    self.target += camera.right() * (-xrel as f32 * scale) + camera.up() * (yrel as f32 * scale);
+                    self.apply(camera);
+                }
+            },
+            Event::MouseWheel { y, .. } => {
+                // Zoom multiplicatively so each wheel click feels the same whether we're close up or far away
+                self.distance *= 1.0 - y as f32 * self.zoom_sensitivity;
+                self.distance = self.distance.clamp(self.min_distance, self.max_distance);
+                self.apply(camera);
+            },
+            _ => {},
+        }
+    }
+
+    fn update(&mut self, _camera: &mut Camera, _dt: f32) {
+        // Everything the orbit camera does happens in response to events, so there's nothing to do per frame
+    }
+
+    fn attach(&mut self, camera: &mut Camera) {
+        // Keep looking at the same target, but start from wherever the camera currently is
+        let offset = camera.position - self.target;
+        self.distance = offset.length().clamp(self.min_distance, self.max_distance);
+        let forward = (-offset).normalize();
+        self.pitch = forward.y.clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH);
+        self.yaw = (-forward.x).atan2(-forward.z);
+        self.apply(camera);
+    }
+
+    fn name(&self) -> &'static str {
+        "orbit"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::keyboard::Mod;
+    use sdl2::mouse::MouseWheelDirection;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    fn key(keycode: Keycode, down: bool) -> Event {
+        let (timestamp, window_id, keycode, scancode) = (0, 0, Some(keycode), None);
+        let (keymod, repeat) = (Mod::empty(), false);
+        if down {
+            Event::KeyDown { timestamp, window_id, keycode, scancode, keymod, repeat }
+        } else {
+            Event::KeyUp { timestamp, window_id, keycode, scancode, keymod, repeat }
+        }
+    }
+
+    fn wheel(y: i32) -> Event {
+        Event::MouseWheel { timestamp: 0, window_id: 0, which: 0, x: 0, y, direction: MouseWheelDirection::Normal }
+    }
+
+    #[test]
+    fn view_matrix_matches_look_at() {
+        let mut camera = Camera::new(Vec3::new(1.0, 2.0, 3.0), 1.5);
+        camera.orientation = Quat::from_euler(0.8, -0.3, 0.0);
+        let expected = Mat4::look_at(camera.position, camera.position + camera.forward(), Vec3::Y);
+        let view = camera.view_matrix();
+        for i in 0..4 {
+            assert_close(view.col(i).truncate(), expected.col(i).truncate());
+        }
+
+        let uniforms = camera.uniforms(2.0);
+        assert_eq!(uniforms.view_projection, uniforms.projection * uniforms.view);
+        assert_eq!(uniforms.position, camera.position);
+    }
+
+    #[test]
+    fn fly_controller_moves_while_keys_are_held() {
+        let mut camera = Camera::new(Vec3::ZERO, 1.0);
+        let mut controller = FlyController::new();
+        controller.handle_event(&mut camera, &key(Keycode::W, true));
+        controller.update(&mut camera, 0.5);
+        assert_close(camera.position, Vec3::new(0.0, 0.0, -controller.speed * 0.5));
+
+        // Nothing held: no movement, and in particular no NaNs from normalizing a zero direction
+        controller.handle_event(&mut camera, &key(Keycode::W, false));
+        let before = camera.position;
+        controller.update(&mut camera, 0.5);
+        assert_eq!(camera.position, before);
+    }
+
+    #[test]
+    fn switching_controllers_keeps_the_view() {
+        let mut camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), 1.0);
+        camera.orientation = Quat::from_euler(0.4, 0.2, 0.0);
+        let forward = camera.forward();
+        FlyController::new().attach(&mut camera);
+        assert_close(camera.forward(), forward);
+
+        // The orbit controller points the camera at its target instead, from wherever the camera already is
+        let position = camera.position;
+        OrbitController::new(Vec3::ZERO, 1.0).attach(&mut camera);
+        assert_close(camera.position, position);
+        assert_close(camera.forward(), (-position).normalize());
+    }
+
+    #[test]
+    fn orbit_controller_zooms_within_its_limits() {
+        let mut camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), 1.0);
+        let mut orbit = OrbitController::new(Vec3::ZERO, 1.0);
+        orbit.attach(&mut camera);
+        assert!((orbit.distance - 5.0).abs() < 1e-4);
+
+        orbit.handle_event(&mut camera, &wheel(1));
+        assert_close(camera.position, Vec3::new(0.0, 0.0, 4.5));
+        orbit.handle_event(&mut camera, &wheel(100));
+        assert_eq!(orbit.distance, orbit.min_distance);
+        orbit.handle_event(&mut camera, &wheel(-100000));
+        assert_eq!(orbit.distance, orbit.max_distance);
+    }
+}
@@ -0,0 +1,172 @@
+use std::ffi::CString;
+use std::time::Instant;
+
+use crate::render_gl;
+use crate::program;
+use crate::camera::{Camera, CameraController, FlyController, OrbitController};
+use crate::math::{Mat4, Quat, Vec3};
+use crate::render_state::{CullMode, RenderState, RenderStateCache};
+use crate::debug_view::DebugViews;
+
+/// A ring of triangles that we can finally move around in.  Starts out with the orbit camera; press Tab to switch
+/// between orbiting and flying around freely.  F1-F7 switch between the debug views in `debug_view.rs`
+pub fn camera_controllers() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.5, 0.3, 0.3, 1.0);  // Color that window will default to when everything is cleared
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+
+    let vert_shader = render_gl::Shader::from_vert_source(
+        &CString::new(
+            include_str!("transformations.vert")
+        ).unwrap()
+    ).unwrap();
+
+    let frag_shader = render_gl::Shader::from_frag_source(
+        &CString::new(
+            include_str!("two_vaos_and_two_vbos.frag")
+        ).unwrap()
+    ).unwrap();
+
+    let shader_program = program::Program::from_shaders(
+        &[vert_shader, frag_shader]
+    ).unwrap();
+
+    let vertices: Vec<f32> = vec![
+        // positions        // colors
+        -0.5, -0.5, 0.0,    1.0, 0.0, 0.0,
+         0.5, -0.5, 0.0,    0.0, 1.0, 0.0,
+         0.0,  0.5, 0.0,    0.0, 0.0, 1.0,
+    ];
+
+    let mut vbo: gl::types::GLuint = 0;
+    let mut vao: gl::types::GLuint = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut vbo);
+        gl::GenVertexArrays(1, &mut vao);
+
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            (vertices.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+            vertices.as_ptr() as *const gl::types::GLvoid,
+            gl::STATIC_DRAW,
+        );
+
+        gl::EnableVertexAttribArray(0);  // this is `layout (location = 0)` in vertex shader
+        gl::VertexAttribPointer(
+            0,
+            3,
+            gl::FLOAT,
+            gl::FALSE,
+            (6 * std::mem::size_of::<f32>()) as gl::types::GLint,
+            std::ptr::null()
+        );
+
+        gl::EnableVertexAttribArray(1);  // this is `layout (location = 1)` in vertex shader
+        gl::VertexAttribPointer(
+            1,
+            3,
+            gl::FLOAT,
+            gl::FALSE,
+            (6 * std::mem::size_of::<f32>()) as gl::types::GLint,
+            (3 * std::mem::size_of::<f32>()) as *const gl::types::GLvoid
+        );
+
+        gl::BindVertexArray(0);
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    }
+
+    // Twelve triangles standing in a circle, each one turned to face the middle
+    let ring: Vec<Mat4> = (0..12)
+        .map(|i| {
+            let angle = i as f32 / 12.0 * std::f32::consts::PI * 2.0;
+            Mat4::from_trs(
+                Vec3::new(angle.sin() * 3.0, 0.0, angle.cos() * 3.0),
+                Quat::from_axis_angle(Vec3::Y, angle),
+                Vec3::ONE,
+            )
+        })
+        .collect();
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 2.0, 8.0), width as f32 / height as f32);
+
+    // Both controllers stick around so that switching back to one remembers its settings (like the orbit target)
+    let mut controllers: Vec<Box<dyn CameraController>> = vec![
+        Box::new(OrbitController::new(Vec3::ZERO, 8.0)),
+        Box::new(FlyController::new()),
+    ];
+    let mut active = 0;
+    controllers[active].attach(&mut camera);
+
+    // Triangles have no thickness, so don't cull anything or they'd vanish when we walk around behind them
+    let render_state = RenderState { cull_mode: CullMode::None, ..RenderState::opaque() };
+    let mut render_state_cache = RenderStateCache::new();
+    let mut debug_views = DebugViews::new().unwrap();
+
+    let mut last_frame = Instant::now();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Tab), repeat: false, .. } => {
+                    active = (active + 1) % controllers.len();
+                    controllers[active].attach(&mut camera);
+                    println!("Camera controller: {}", controllers[active].name());
+                },
+                _ if debug_views.handle_event(&event) => {},
+                _ => {
+                    camera.handle_resize(&event);
+                    controllers[active].handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let now = Instant::now();
+        let dt = now.duration_since(last_frame).as_secs_f32();
+        last_frame = now;
+        controllers[active].update(&mut camera, dt);
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        debug_views.near = camera.near;
+        debug_views.far = camera.far;
+        debug_views.draw(&mut render_state_cache, &render_state, &shader_program, |program| {
+            program.set_uniform_mat4("View", &camera.view_matrix());
+            program.set_uniform_mat4("Projection", &camera.projection_matrix());
+
+            unsafe {
+                gl::BindVertexArray(vao);
+                for model in &ring {
+                    program.set_uniform_mat4("Model", model);
+                    gl::DrawArrays(gl::TRIANGLES, 0, 3);
+                }
+            }
+        });
+
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,21 @@
+#version 330 core
+
+// A glow around the edges of a shape, where it turns away from the camera, pulsing in time.  Both come from the
+// `Camera` block, which `camera::with_camera_block()` pastes in above
+in VS_OUTPUT {
+    vec3 Normal;
+    vec2 TexCoord;
+} IN;
+in vec3 WorldPosition;
+
+uniform vec3 RimColor;
+
+out vec4 Color;
+
+void main()
+{
+    vec3 to_camera = normalize(CameraPosition - WorldPosition);
+    float rim = pow(1.0 - max(dot(normalize(IN.Normal), to_camera), 0.0), 3.0);
+    float pulse = 0.7 + 0.3 * sin(Time * 3.0);
+    Color = vec4(vec3(0.04) + RimColor * rim * pulse, 1.0);
+}
@@ -0,0 +1,119 @@
+// Compute shaders: programs that aren't part of drawing at all, just a grid of invocations that each do some work,
+// reading and writing buffers (shader storage blocks) and images directly.  They're handy for anything that's
+// "the same little job, lots of times": simulating particles, filtering images, building data for later passes.
+//
+// The work is split into work groups.  The shader says how big one group is (`layout (local_size_x = 64) in;`), and
+// `dispatch()` says how many groups to run.  The invocations in a group can share memory and wait for each other;
+// different groups can't.  GL doesn't promise anything about when what a compute shader wrote becomes visible to
+// whatever reads it next, either, so a `memory_barrier()` for the kind of read that's coming has to go between the two.
+//
+// All this needs GL 4.3, which the rest of the lessons don't: they ask for a 3.3 context, so a demo that wants
+// compute has to ask for more with `request_context()` before making its window.  (macOS stops at 4.1, so there it
+// just won't work; `ComputePipeline::new()` says so rather than failing somewhere confusing.)
+
+use std::ffi::CString;
+
+use crate::buffer::{Buffer, BufferType};
+use crate::program::Program;
+use crate::render_gl::{self, Shader};
+
+/// The oldest GL that has compute shaders and shader storage buffers
+pub const MIN_GL_VERSION: (u8, u8) = (4, 3);
+
+/// Asks for a context new enough for compute shaders.  Call this where a demo would otherwise ask for 3.3, before
+/// creating the window
+#[allow(dead_code)]
+pub fn request_context(video_subsystem: &sdl2::VideoSubsystem) {
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+    gl_attr.set_context_version(MIN_GL_VERSION.0, MIN_GL_VERSION.1);
+}
+
+/// Whether the current context can run compute shaders
+#[allow(dead_code)]
+pub fn is_supported() -> bool {
+    render_gl::context_version() >= (MIN_GL_VERSION.0 as i32, MIN_GL_VERSION.1 as i32)
+}
+
+/// Makes sure the kinds of access in `barriers` see everything shaders have written so far.  The bits say how the
+/// data is going to be read next, not how it was written: `gl::SHADER_STORAGE_BARRIER_BIT` before another shader reads
+/// a storage buffer, `gl::SHADER_IMAGE_ACCESS_BARRIER_BIT` before `imageLoad()`, `gl::TEXTURE_FETCH_BARRIER_BIT` before
+/// sampling an image that was written, `gl::COMMAND_BARRIER_BIT` before an indirect dispatch or draw reads its
+/// arguments, `gl::BUFFER_UPDATE_BARRIER_BIT` before `Buffer::read()`, and so on.  `gl::ALL_BARRIER_BITS` if in doubt
+#[allow(dead_code)]
+pub fn memory_barrier(barriers: gl::types::GLbitfield) {
+    unsafe {
+        gl::MemoryBarrier(barriers);
+    }
+}
+
+/// A compute shader, linked into a program of its own
+pub struct ComputePipeline {
+    program: Program,
+    local_size: [u32; 3],
+}
+
+#[allow(dead_code)]
+impl ComputePipeline {
+    /// Compiles and links a compute shader.  Fails straight away, with the reason, if the context is too old
+    pub fn new(source: &str) -> Result<ComputePipeline, String> {
+        let (major, minor) = render_gl::context_version();
+        if !is_supported() {
+            return Err(format!(
+                "compute shaders need OpenGL {}.{}, but this context is {}.{} (did it come from `request_context()`?)",
+                MIN_GL_VERSION.0, MIN_GL_VERSION.1, major, minor,
+            ));
+        }
+        let shader = Shader::from_compute_source(&CString::new(source).map_err(|e| e.to_string())?)?;
+        let program = Program::from_shaders(&[shader])?;
+
+        let mut local_size = [0 as gl::types::GLint; 3];
+        unsafe {
+            gl::GetProgramiv(program.id(), gl::COMPUTE_WORK_GROUP_SIZE, local_size.as_mut_ptr());
+        }
+        let local_size = [local_size[0] as u32, local_size[1] as u32, local_size[2] as u32];
+        Ok(ComputePipeline { program, local_size })
+    }
+
+    /// For setting uniforms and binding blocks.  Like any program, it has to be in use to set uniforms
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// How many invocations are in one work group, from the shader's `local_size_x`, `_y` and `_z`
+    pub fn local_size(&self) -> [u32; 3] {
+        self.local_size
+    }
+
+    /// Runs `x` by `y` by `z` work groups
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        self.program.set_used();
+        unsafe {
+            gl::DispatchCompute(x, y, z);
+        }
+    }
+
+    /// Runs enough work groups for at least `width` by `height` by `depth` invocations, one per item.  There'll
+    /// usually be some left over in the last group on each side, which the shader needs to check for and skip
+    pub fn dispatch_for(&self, width: u32, height: u32, depth: u32) {
+        let groups = |count: u32, size: u32| count.div_ceil(size.max(1));
+        self.dispatch(
+            groups(width, self.local_size[0]),
+            groups(height, self.local_size[1]),
+            groups(depth, self.local_size[2]),
+        );
+    }
+
+    /// Runs however many work groups three uints `offset` bytes into `buffer` say, which lets an earlier pass on the
+    /// GPU decide how much work there is without a round trip through the CPU.  The buffer can be any kind, since it's
+    /// usually a storage buffer that a shader wrote the numbers into; a `gl::COMMAND_BARRIER_BIT` barrier has to come
+    /// between that shader and this
+    pub fn dispatch_indirect<B: BufferType>(&self, buffer: &Buffer<B>, offset: usize) {
+        self.program.set_used();
+        unsafe {
+            gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, buffer.id());
+            gl::DispatchComputeIndirect(offset as gl::types::GLintptr);
+            gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, 0);
+        }
+    }
+}
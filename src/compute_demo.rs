@@ -0,0 +1,397 @@
+use std::ffi::CString;
+
+use crate::buffer::{StorageBuffer, VertexArray};
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::compute::{self, ComputePipeline};
+use crate::debug_draw::DebugDraw;
+use crate::framebuffer::{self, DepthAttachment, Framebuffer, FullscreenTriangle};
+use crate::math::{Mat4, Quat, Vec2, Vec3, Vec4};
+use crate::mesh::Mesh;
+use crate::program::Program;
+use crate::render_gl::{self, Shader};
+use crate::render_state::{BlendState, DepthState, RenderState, RenderStateCache};
+use crate::shapes;
+use crate::texture::Texture;
+use crate::uniform_block::{self, BlockLayout};
+
+const PARTICLE_COUNT: usize = 128 * 1024;
+const FILTERS: [&str; 4] = ["none", "blur", "edges", "pixelate"];
+
+crate::uniform_block! {
+    /// `Particle` in particles_common.glsl
+    struct Particle {
+        position: Vec3 => "Position",
+        life: f32 => "Life",
+        velocity: Vec3 => "Velocity",
+        seed: f32 => "Seed",
+    }
+}
+
+/// Where the fields of the `Counters` block in particles_common.glsl are, in uints
+const DEAD_COUNT: usize = 3;
+const EMIT_COUNT: usize = 4;
+
+fn particle_shader(source: &str) -> String {
+    render_gl::insert_after_version(source, include_str!("particles_common.glsl"))
+}
+
+/// Points a particle program's three storage blocks at the binding points the demo attaches the buffers to
+fn bind_particle_blocks(program: &Program) {
+    program.bind_storage_block("Particles", 0);
+    program.bind_storage_block("Counters", 1);
+    program.bind_storage_block("DeadList", 2);
+}
+
+struct Particles {
+    update: ComputePipeline,
+    args: ComputePipeline,
+    emit: ComputePipeline,
+    render: Program,
+    _particles: StorageBuffer,
+    counters: StorageBuffer,
+    _dead_list: StorageBuffer,
+    vao: VertexArray,  // empty, since the particles come straight out of the storage buffer, but GL wants one bound
+}
+
+impl Particles {
+    fn new() -> Result<Particles, String> {
+        let update = ComputePipeline::new(&particle_shader(include_str!("particles_update.comp")))?;
+        let args = ComputePipeline::new(&particle_shader(include_str!("particles_args.comp")))?;
+        let emit = ComputePipeline::new(&particle_shader(include_str!("particles_emit.comp")))?;
+        let vert_source = particle_shader(include_str!("particles.vert"));
+        let vert_shader = Shader::from_vert_source(&CString::new(vert_source).unwrap())?;
+        let frag_shader = Shader::from_frag_source(&CString::new(include_str!("particles.frag")).unwrap())?;
+        let render = Program::from_shaders(&[vert_shader, frag_shader])?;
+        for program in &[update.program(), args.program(), emit.program(), &render] {
+            bind_particle_blocks(program);
+        }
+
+        // Every particle starts out dead, so they all come pouring out of the emitter over the first few seconds
+        let mut seed = 12345u32;
+        let initial: Vec<Particle> = (0..PARTICLE_COUNT)
+            .map(|_| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                Particle { position: Vec3::ZERO, life: 0.0, velocity: Vec3::ZERO, seed: (seed >> 8) as f32 / 65536.0 }
+            })
+            .collect();
+        let mut particles = StorageBuffer::new();
+        particles.bind();
+        particles.dynamic_draw_data(&uniform_block::slice_to_bytes(&initial, BlockLayout::Std430));
+        particles.bind_base(0);
+        let mut counters = StorageBuffer::new();
+        counters.bind();
+        counters.dynamic_draw_data(&[0u32; 8]);
+        counters.bind_base(1);
+        let mut dead_list = StorageBuffer::new();
+        dead_list.bind();
+        dead_list.dynamic_draw_data(&vec![0u32; PARTICLE_COUNT]);
+        dead_list.bind_base(2);
+        dead_list.unbind();
+
+        Ok(Particles {
+            update,
+            args,
+            emit,
+            render,
+            _particles: particles,
+            counters,
+            _dead_list: dead_list,
+            vao: VertexArray::new(),
+        })
+    }
+
+    /// Runs a frame of the simulation entirely on the GPU: move everything, work out how many to respawn, and respawn
+    /// them.  Returns how many particles were dead at the start of the frame and how many of those came back, which
+    /// is read back from the counters the passes left behind last frame
+    fn step(&self, dt: f32, time: f32, emit_budget: i32) -> (u32, u32) {
+        compute::memory_barrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+        self.counters.bind();
+        let counts = self.counters.read::<u32>(0, 8);
+        self.counters.sub_data(DEAD_COUNT * 4, &[0u32]);
+        self.counters.unbind();
+
+        let program = self.update.program();
+        program.set_used();
+        program.set_uniform_f32("DeltaTime", dt);
+        program.set_uniform_vec3("Gravity", Vec3::new(0.0, -9.8, 0.0));
+        self.update.dispatch_for(PARTICLE_COUNT as u32, 1, 1);
+        compute::memory_barrier(gl::SHADER_STORAGE_BARRIER_BIT);
+
+        let program = self.args.program();
+        program.set_used();
+        program.set_uniform_i32("EmitBudget", emit_budget);
+        program.set_uniform_i32("EmitGroupSize", self.emit.local_size()[0] as i32);
+        self.args.dispatch(1, 1, 1);
+        compute::memory_barrier(gl::SHADER_STORAGE_BARRIER_BIT | gl::COMMAND_BARRIER_BIT);
+
+        let program = self.emit.program();
+        program.set_used();
+        program.set_uniform_vec3("Emitter", Vec3::new(0.0, 0.2, 0.0));
+        program.set_uniform_f32("Time", time);
+        program.set_uniform_f32("Spread", 3.0);
+        program.set_uniform_f32("Speed", 9.0);
+        self.emit.dispatch_indirect(&self.counters, 0);
+        // The vertex shader reads the particles as a storage buffer too
+        compute::memory_barrier(gl::SHADER_STORAGE_BARRIER_BIT);
+
+        (counts[DEAD_COUNT], counts[EMIT_COUNT])
+    }
+
+    fn draw(&self, view_projection: &Mat4, cache: &mut RenderStateCache) {
+        cache.apply(&RenderState {
+            depth: DepthState { write: false, ..RenderState::opaque().depth },
+            blend: BlendState::additive(),
+            point_size: 3.0,
+            ..RenderState::default()
+        });
+        self.render.set_used();
+        self.render.set_uniform_mat4("ViewProjection", view_projection);
+        self.vao.bind();
+        unsafe {
+            gl::DrawArrays(gl::POINTS, 0, PARTICLE_COUNT as gl::types::GLsizei);
+        }
+        self.vao.unbind();
+    }
+}
+
+/// Where the image filter scene draws to and filters through: the scene itself, and two images for the filter passes
+/// to go back and forth between
+struct FilterTargets {
+    scene: Framebuffer,
+    ping: Texture,
+    pong: Texture,
+}
+
+impl FilterTargets {
+    fn new(width: u32, height: u32) -> Result<FilterTargets, String> {
+        Ok(FilterTargets {
+            scene: Framebuffer::new(width, height, &[gl::RGBA8], DepthAttachment::Renderbuffer)?,
+            ping: Texture::empty(width, height, gl::RGBA8),
+            pong: Texture::empty(width, height, gl::RGBA8),
+        })
+    }
+}
+
+/// One pass of image_filter.comp, from `source` into `result`
+fn filter_pass(filter: &ComputePipeline, source: &Texture, result: &Texture, mode: usize, direction: (i32, i32),
+               radius: i32) {
+    source.bind_image(0, gl::READ_ONLY, gl::RGBA8);
+    result.bind_image(1, gl::WRITE_ONLY, gl::RGBA8);
+    let program = filter.program();
+    program.set_used();
+    program.set_uniform_i32("Source", 0);
+    program.set_uniform_i32("Result", 1);
+    program.set_uniform_i32("Filter", mode as i32);
+    program.set_uniform_i32("Radius", radius);
+    program.set_uniform_vec2("Direction", Vec2::new(direction.0 as f32, direction.1 as f32));
+    filter.dispatch_for(result.width(), result.height(), 1);
+    // The next pass either loads these pixels as an image or samples them as a texture
+    compute::memory_barrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT | gl::TEXTURE_FETCH_BARRIER_BIT);
+}
+
+/// Compute shaders, in two scenes.  The first is a fountain of 131,072 particles simulated entirely on the GPU: each
+/// frame one pass moves them all and lists the dead ones, a tiny pass works out how many of those to bring back, and a
+/// third pass, dispatched indirectly with the number of work groups the second one wrote, brings them back.  The
+/// particles are drawn straight out of the storage buffer, and the dead and respawned counts are read back for the
+/// title.  The second scene draws some shapes into a texture and runs it through an image filter before showing it.
+///
+/// Tab switches scenes.  In the particle scene, Up and Down change how many particles can respawn per frame.  In the
+/// filter scene, F cycles the filter, + and - change its radius, and R reads the filtered image back and prints its
+/// average color.  Dragging orbits the camera.  This needs OpenGL 4.3, so it asks for a newer context than the other
+/// lessons do
+pub fn compute_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here.  Compute shaders need 4.3, rather than the 3.3 everything else asks for
+    compute::request_context(&video_subsystem);
+    video_subsystem.gl_attr().set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.05, 0.05, 0.07, 1.0);
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+
+    let (width, height) = window.size();
+    let setup = || -> Result<(Particles, ComputePipeline, Program, Program, FilterTargets), String> {
+        let particles = Particles::new()?;
+        let filter = ComputePipeline::new(include_str!("image_filter.comp"))?;
+        let vert_shader = Shader::from_vert_source(&CString::new(include_str!("mesh.vert")).unwrap())?;
+        let frag_shader = Shader::from_frag_source(&CString::new(include_str!("mesh.frag")).unwrap())?;
+        let mesh_program = Program::from_shaders(&[vert_shader, frag_shader])?;
+        let view_program = framebuffer::fullscreen_program(include_str!("image_view.frag"))?;
+        Ok((particles, filter, mesh_program, view_program, FilterTargets::new(width, height)?))
+    };
+    let (particles, filter, mesh_program, view_program, mut targets) = match setup() {
+        Ok(setup) => setup,
+        Err(e) => {
+            println!("Failed to set up compute shaders: {}", e);
+            return;
+        },
+    };
+    let mut debug = match DebugDraw::new() {
+        Ok(debug) => debug,
+        Err(e) => {
+            println!("Failed to set up debug drawing: {}", e);
+            return;
+        },
+    };
+
+    let shapes: Vec<(Mesh, Vec3)> = vec![
+        (shapes::torus(1.0, 0.4, 48, 24).upload(), Vec3::new(0.9, 0.5, 0.3)),
+        (shapes::icosphere(1.0, 3).upload(), Vec3::new(0.3, 0.7, 0.9)),
+        (shapes::cube(1.4, 1).upload(), Vec3::new(0.5, 0.9, 0.4)),
+        (shapes::cone(0.9, 1.8, 32, 1).upload(), Vec3::new(0.9, 0.8, 0.4)),
+    ];
+    let fullscreen = FullscreenTriangle::new();
+
+    let mut camera = Camera::new(Vec3::new(0.0, 6.0, 16.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::new(0.0, 3.0, 0.0), 16.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut show_filter = false;
+    let mut filter_mode = 1;
+    let mut radius = 6;
+    let mut emit_budget = 1500;
+    let mut time = 0.0f32;
+    let frequency = timer.performance_frequency() as f64;
+    let mut last_counter = timer.performance_counter();
+
+    'main: loop {
+        let mut read_back = false;
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Tab), .. } => show_filter = !show_filter,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::F), .. } => {
+                    filter_mode = (filter_mode + 1) % FILTERS.len();
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Equals), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(Keycode::KpPlus), .. } => radius = (radius + 1).min(32),
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Minus), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(Keycode::KpMinus), .. } => radius = (radius - 1).max(1),
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
+                    emit_budget = (emit_budget * 2).min(PARTICLE_COUNT as i32);
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
+                    emit_budget = (emit_budget / 2).max(1);
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::R), .. } => read_back = true,
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let counter = timer.performance_counter();
+        let dt = (((counter - last_counter) as f64 / frequency) as f32).min(0.05);
+        last_counter = counter;
+        time += dt;
+        let (width, height) = window.size();
+        let view_projection = camera.view_projection_matrix();
+
+        if !show_filter {
+            let (dead, respawned) = particles.step(dt, time, emit_budget);
+            unsafe {
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            }
+            debug.grid(Vec3::ZERO, 20, 1.0, Vec4::new(0.25, 0.25, 0.3, 1.0));
+            debug.render(&view_projection, dt, &mut render_state_cache);
+            particles.draw(&view_projection, &mut render_state_cache);
+            window.set_title(&format!(
+                "{} of {} particles alive, {} respawned this frame (at most {})",
+                PARTICLE_COUNT as u32 - dead + respawned,
+                PARTICLE_COUNT,
+                respawned,
+                emit_budget,
+            )).unwrap();
+            window.gl_swap_window();
+            continue;
+        }
+
+        if targets.scene.width() != width || targets.scene.height() != height {
+            targets = match FilterTargets::new(width, height) {
+                Ok(targets) => targets,
+                Err(e) => {
+                    println!("Failed to resize the filter targets: {}", e);
+                    break 'main;
+                },
+            };
+        }
+
+        // Draw the shapes into the scene texture...
+        targets.scene.bind();
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        render_state_cache.apply(&RenderState::opaque());
+        mesh_program.set_used();
+        mesh_program.set_uniform_mat4("View", &camera.view_matrix());
+        mesh_program.set_uniform_mat4("Projection", &camera.projection_matrix());
+        for (i, (mesh, color)) in shapes.iter().enumerate() {
+            let angle = i as f32 / shapes.len() as f32 * std::f32::consts::PI * 2.0 + time * 0.3;
+            let position = Vec3::new(angle.cos() * 4.0, 2.0, angle.sin() * 4.0);
+            let rotation = Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.3).normalize(), time * (0.6 + i as f32 * 0.2));
+            mesh_program.set_uniform_mat4("Model", &Mat4::from_trs(position, rotation, Vec3::ONE));
+            mesh_program.set_uniform_vec3("DiffuseColor", *color);
+            mesh.draw();
+        }
+        Framebuffer::bind_default(width, height);
+
+        // ...run it through the filter...
+        let source = targets.scene.color(0);
+        let result = match filter_mode {
+            0 => source,
+            1 => {
+                filter_pass(&filter, source, &targets.ping, filter_mode, (1, 0), radius);
+                filter_pass(&filter, &targets.ping, &targets.pong, filter_mode, (0, 1), radius);
+                &targets.pong
+            },
+            _ => {
+                filter_pass(&filter, source, &targets.pong, filter_mode, (0, 0), radius);
+                &targets.pong
+            },
+        };
+        if read_back {
+            compute::memory_barrier(gl::TEXTURE_UPDATE_BARRIER_BIT);
+            let pixels = result.read_f32(4);
+            let count = (pixels.len() / 4).max(1) as f32;
+            let mut total = [0.0f32; 3];
+            for pixel in pixels.chunks_exact(4) {
+                for (sum, value) in total.iter_mut().zip(pixel) {
+                    *sum += value;
+                }
+            }
+            println!(
+                "Average color of the {} image: {:.3}, {:.3}, {:.3}",
+                FILTERS[filter_mode],
+                total[0] / count,
+                total[1] / count,
+                total[2] / count,
+            );
+        }
+
+        // ...and show it
+        render_state_cache.apply(&RenderState::default());
+        result.bind(0);
+        view_program.set_used();
+        view_program.set_uniform_i32("Image", 0);
+        fullscreen.draw();
+
+        window.set_title(&format!("Filter: {} (radius {})", FILTERS[filter_mode], radius)).unwrap();
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,21 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec3 Normal;
+    vec2 TexCoord;
+} IN;
+
+uniform float Near;
+uniform float Far;
+
+out vec4 Color;
+
+void main()
+{
+    // `gl_FragCoord.z` is non-linear (most of the precision is bunched up near the camera), so undo the perspective
+    // projection to get the actual distance, then scale it so that black is at the near plane and white at the far one
+    float ndc_z = gl_FragCoord.z * 2.0 - 1.0;
+    float linear_depth = (2.0 * Near * Far) / (Far + Near - ndc_z * (Far - Near));
+    float shade = (linear_depth - Near) / (Far - Near);
+    Color = vec4(vec3(shade), 1.0);
+}
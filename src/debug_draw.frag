@@ -0,0 +1,10 @@
+#version 330 core
+
+in vec4 VertexColor;
+
+out vec4 Color;
+
+void main()
+{
+    Color = VertexColor;
+}
@@ -0,0 +1,284 @@
+// Throw-away drawing for finding out what's going on: lines, boxes, spheres, axes and labels that can be asked for from
+// anywhere in the update code, the same way `println!()` can, and that all get drawn together at the end of the
+// frame.  Nothing keeps its own buffers; `DebugDraw` turns every shape into line segments and streams them all into
+// one vertex buffer, so a whole frame's worth of debug shapes is a couple of draw calls.
+//
+// Shapes are drawn once and then forgotten, unless `duration` is set when they're added, in which case they stick
+// around for that many seconds (handy for things that only happen for a frame, like a collision).  `depth_test`
+// decides whether they hide behind the scene or show through it.
+
+use std::ffi::CString;
+
+use crate::bounds::Aabb;
+use crate::buffer::{ArrayBuffer, VertexArray};
+use crate::math::{Mat4, Vec2, Vec3, Vec4};
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::sprite::Camera2D;
+use crate::text::{Align, FontAtlas, TextBatch, TextStyle};
+use crate::vertex::{attrib, VertexLayout};
+
+const CIRCLE_SEGMENTS: usize = 32;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct DebugVertex {
+    position: Vec3,
+    color: Vec4,
+}
+
+impl DebugVertex {
+    fn layout() -> VertexLayout {
+        VertexLayout::new(std::mem::size_of::<DebugVertex>())
+            .float(attrib::POSITION, 3, 0)
+            .float(attrib::COLOR, 4, 3 * std::mem::size_of::<f32>())
+    }
+}
+
+struct Line {
+    from: Vec3,
+    to: Vec3,
+    color: Vec4,
+    depth_test: bool,
+    remaining: f32,  // seconds left to show it for
+}
+
+struct Label {
+    position: Vec3,
+    text: String,
+    color: Vec4,
+    remaining: f32,
+}
+
+/// Collects debug shapes during the frame and draws them in `render()`
+pub struct DebugDraw {
+    program: Program,
+    vao: VertexArray,
+    vbo: ArrayBuffer,
+    vertices: Vec<DebugVertex>,
+    lines: Vec<Line>,
+    labels: Vec<Label>,
+    text: TextBatch,
+    font: Option<FontAtlas>,
+    pub depth_test: bool,  // whether shapes added from now on hide behind the scene
+    pub duration: f32,     // how many seconds shapes added from now on stay for, 0 for just the next `render()`
+}
+
+#[allow(dead_code)]
+impl DebugDraw {
+    pub fn new() -> Result<DebugDraw, String> {
+        let vert_shader = Shader::from_vert_source(&CString::new(include_str!("debug_draw.vert")).unwrap())?;
+        let frag_shader = Shader::from_frag_source(&CString::new(include_str!("debug_draw.frag")).unwrap())?;
+        let program = Program::from_shaders(&[vert_shader, frag_shader])?;
+
+        let vao = VertexArray::new();
+        let mut vbo = ArrayBuffer::new();
+        vao.bind();
+        vbo.bind();
+        vbo.buffer_data::<DebugVertex>(&[], gl::STREAM_DRAW);
+        DebugVertex::layout().apply();
+        vao.unbind();
+        vbo.unbind();
+
+        Ok(DebugDraw {
+            program,
+            vao,
+            vbo,
+            vertices: Vec::new(),
+            lines: Vec::new(),
+            labels: Vec::new(),
+            text: TextBatch::new()?,
+            font: None,
+            depth_test: true,
+            duration: 0.0,
+        })
+    }
+
+    /// The font `text3d()` labels are drawn with.  Until there is one, labels are quietly skipped
+    pub fn set_font(&mut self, font: FontAtlas) {
+        self.font = Some(font);
+    }
+
+    pub fn line(&mut self, from: Vec3, to: Vec3, color: Vec4) {
+        self.lines.push(Line { from, to, color, depth_test: self.depth_test, remaining: self.duration });
+    }
+
+    /// Lines joining `points` one after another, and back to the start if `closed`
+    pub fn polyline(&mut self, points: &[Vec3], closed: bool, color: Vec4) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], color);
+        }
+        if let (true, Some(&first), Some(&last)) = (closed && points.len() > 2, points.first(), points.last()) {
+            self.line(last, first, color);
+        }
+    }
+
+    /// The twelve edges of a box
+    pub fn aabb(&mut self, aabb: &Aabb, color: Vec4) {
+        self.transformed_box(aabb, &Mat4::identity(), color);
+    }
+
+    /// The edges of a box that's been moved by `transform`, like a mesh's own bounds placed with its model matrix.
+    /// Unlike `Aabb::transformed()` it turns with the transform rather than growing to stay lined up with the axes
+    pub fn transformed_box(&mut self, aabb: &Aabb, transform: &Mat4, color: Vec4) {
+        if aabb.is_empty() {
+            return;
+        }
+        let corners: Vec<Vec3> = aabb.corners().iter().map(|&corner| transform.transform_point(corner)).collect();
+        self.box_edges(&corners, color);
+    }
+
+    /// The view volume of a camera, from its combined projection * view matrix.  Handy for seeing what a second
+    /// camera (or a shadow map) can see
+    pub fn frustum(&mut self, view_projection: &Mat4, color: Vec4) {
+        let inverse = match view_projection.inverse() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+        // The corners of clip space's cube, in the same order as `Aabb::corners()`, taken back into the world
+        let clip = Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0));
+        let corners: Vec<Vec3> = clip.corners()
+            .iter()
+            .map(|&corner| {
+                let world = inverse * corner.extend(1.0);
+                world.truncate() / world.w
+            })
+            .collect();
+        self.box_edges(&corners, color);
+    }
+
+    /// Eight corners ordered like `Aabb::corners()` (x changing fastest, then y, then z), joined into a box
+    fn box_edges(&mut self, corners: &[Vec3], color: Vec4) {
+        const EDGES: [(usize, usize); 12] = [
+            (0, 1), (2, 3), (4, 5), (6, 7),  // along x
+            (0, 2), (1, 3), (4, 6), (5, 7),  // along y
+            (0, 4), (1, 5), (2, 6), (3, 7),  // along z
+        ];
+        for &(a, b) in &EDGES {
+            self.line(corners[a], corners[b], color);
+        }
+    }
+
+    /// A circle around `normal`
+    pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Vec4) {
+        let normal = normal.normalize();
+        let helper = if normal.y.abs() < 0.99 { Vec3::Y } else { Vec3::X };
+        let u = normal.cross(helper).normalize() * radius;
+        let v = normal.cross(u);
+        let points: Vec<Vec3> = (0..CIRCLE_SEGMENTS)
+            .map(|i| {
+                let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::PI * 2.0;
+                center + u * angle.cos() + v * angle.sin()
+            })
+            .collect();
+        self.polyline(&points, true, color);
+    }
+
+    /// A sphere, as three circles around the axes
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec4) {
+        self.circle(center, Vec3::X, radius, color);
+        self.circle(center, Vec3::Y, radius, color);
+        self.circle(center, Vec3::Z, radius, color);
+    }
+
+    /// Where `transform` puts the x, y and z axes, in red, green and blue, each `size` long
+    pub fn axes(&mut self, transform: &Mat4, size: f32) {
+        let origin = transform.transform_point(Vec3::ZERO);
+        self.line(origin, transform.transform_point(Vec3::X * size), Vec4::new(1.0, 0.2, 0.2, 1.0));
+        self.line(origin, transform.transform_point(Vec3::Y * size), Vec4::new(0.2, 1.0, 0.2, 1.0));
+        self.line(origin, transform.transform_point(Vec3::Z * size), Vec4::new(0.3, 0.4, 1.0, 1.0));
+    }
+
+    /// A square grid on the xz plane around `center`, `cells` across with `spacing` between lines
+    pub fn grid(&mut self, center: Vec3, cells: u32, spacing: f32, color: Vec4) {
+        let half = cells as f32 * spacing * 0.5;
+        for i in 0..=cells {
+            let offset = i as f32 * spacing - half;
+            self.line(center + Vec3::new(offset, 0.0, -half), center + Vec3::new(offset, 0.0, half), color);
+            self.line(center + Vec3::new(-half, 0.0, offset), center + Vec3::new(half, 0.0, offset), color);
+        }
+    }
+
+    /// A label centered on a point in the world.  It always faces the screen, stays the same size however far away it
+    /// is, and is drawn on top of everything whatever `depth_test` is
+    pub fn text3d(&mut self, position: Vec3, text: &str, color: Vec4) {
+        self.labels.push(Label { position, text: text.to_string(), color, remaining: self.duration });
+    }
+
+    /// Draws everything that's been added and is still around, then forgets whatever has run out of time.  `dt` is
+    /// how long the frame took, in seconds
+    pub fn render(&mut self, view_projection: &Mat4, dt: f32, cache: &mut RenderStateCache) {
+        // Depth tested lines first, then the ones that show through everything, from the one buffer
+        self.vertices.clear();
+        for &depth_test in &[true, false] {
+            for line in self.lines.iter().filter(|line| line.depth_test == depth_test) {
+                self.vertices.push(DebugVertex { position: line.from, color: line.color });
+                self.vertices.push(DebugVertex { position: line.to, color: line.color });
+            }
+        }
+        let tested = self.lines.iter().filter(|line| line.depth_test).count() * 2;
+
+        if !self.vertices.is_empty() {
+            self.program.set_used();
+            self.program.set_uniform_mat4("ViewProjection", view_projection);
+            self.vao.bind();
+            self.vbo.bind();
+            self.vbo.buffer_data(&self.vertices, gl::STREAM_DRAW);
+            for &(state, first, count) in &[
+                (RenderState::transparent(), 0, tested),
+                (RenderState::overlay(), tested, self.vertices.len() - tested),
+            ] {
+                if count > 0 {
+                    cache.apply(&state);
+                    unsafe {
+                        gl::DrawArrays(gl::LINES, first as gl::types::GLint, count as gl::types::GLsizei);
+                    }
+                }
+            }
+            self.vbo.unbind();
+            self.vao.unbind();
+        }
+
+        if let (Some(font), false) = (&self.font, self.labels.is_empty()) {
+            let mut viewport = [0; 4];
+            unsafe {
+                gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            }
+            let (width, height) = (viewport[2] as f32, viewport[3] as f32);
+            self.text.begin(&Camera2D::new(viewport[2] as u32, viewport[3] as u32));
+            for label in &self.labels {
+                let clip = *view_projection * label.position.extend(1.0);
+                if clip.w <= 0.0 {
+                    continue;  // behind the camera
+                }
+                let screen = Vec2::new((clip.x / clip.w + 1.0) * 0.5 * width, (1.0 - clip.y / clip.w) * 0.5 * height);
+                let style = TextStyle { color: label.color, align: Align::Center, ..TextStyle::new(font.pixel_size()) };
+                let size = font.measure(&label.text, &style);
+                let position = Vec2::new((screen.x - size.x * 0.5).round(), (screen.y - size.y * 0.5).round());
+                self.text.draw(font, &label.text, position, &style);
+            }
+            self.text.end(cache);
+        }
+
+        for line in &mut self.lines {
+            line.remaining -= dt;
+        }
+        for label in &mut self.labels {
+            label.remaining -= dt;
+        }
+        self.lines.retain(|line| line.remaining > 0.0);
+        self.labels.retain(|label| label.remaining > 0.0);
+    }
+
+    /// Throws away everything, including shapes that still had time left
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.labels.clear();
+    }
+
+    /// Lines waiting to be drawn by the next `render()`
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+// Debug lines arrive already in world space, each end with its own color
+
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec4 Color;
+
+uniform mat4 ViewProjection;
+
+out vec4 VertexColor;
+
+void main()
+{
+    gl_Position = ViewProjection * vec4(Position, 1.0);
+    VertexColor = Color;
+}
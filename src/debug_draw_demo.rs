@@ -0,0 +1,168 @@
+use std::ffi::CString;
+use std::path::Path;
+
+use crate::bounds::Aabb;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::debug_draw::DebugDraw;
+use crate::math::{Mat4, Quat, Vec3, Vec4};
+use crate::mesh::Mesh;
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::resources::Resources;
+use crate::shapes;
+use crate::text::{self, AtlasKind, Font, FontAtlas};
+
+/// A few shapes spinning on a turntable, with their debug shapes drawn over them: each one's own box turning with it
+/// (yellow), the axis-aligned box around that (grey), a bounding sphere, and its axes.  A little spotlight-like camera
+/// sweeps back and forth with its frustum drawn, and a ball flies around leaving a trail of lines that fade out after
+/// a few seconds.
+///
+/// X switches the debug shapes between hiding behind the meshes and showing through them, and Space pauses.  Dragging
+/// orbits the camera
+pub fn debug_draw_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+
+    let setup = || -> Result<(Program, DebugDraw), String> {
+        let vert_shader = Shader::from_vert_source(&CString::new(include_str!("mesh.vert")).unwrap())?;
+        let frag_shader = Shader::from_frag_source(&CString::new(include_str!("mesh.frag")).unwrap())?;
+        Ok((Program::from_shaders(&[vert_shader, frag_shader])?, DebugDraw::new()?))
+    };
+    let (program, mut debug) = match setup() {
+        Ok(setup) => setup,
+        Err(e) => {
+            println!("Failed to set up debug drawing: {}", e);
+            return;
+        },
+    };
+    // Labels are nice to have, but the rest of the demo works fine without them
+    let res = Resources::from_crate_dir(Path::new("assets"));
+    match Font::load(&res, "fonts/DejaVuSans.ttf")
+        .and_then(|font| FontAtlas::bake(&font, 14.0, AtlasKind::Bitmap, text::ASCII))
+    {
+        Ok(font) => debug.set_font(font),
+        Err(e) => println!("Failed to load the label font, so there won't be any labels: {}", e),
+    }
+
+    let shapes: Vec<(&str, Mesh, Aabb, Vec3)> = vec![
+        ("cube", shapes::cube(1.5, 1), Vec3::new(0.9, 0.5, 0.3)),
+        ("torus", shapes::torus(0.8, 0.3, 32, 16), Vec3::new(0.3, 0.7, 0.9)),
+        ("cone", shapes::cone(0.8, 1.8, 24, 1), Vec3::new(0.5, 0.9, 0.4)),
+        ("capsule", shapes::capsule(0.5, 1.2, 24, 8), Vec3::new(0.9, 0.8, 0.4)),
+    ]
+    .into_iter()
+    .map(|(name, data, color)| {
+        let (min, max) = data.bounds();
+        (name, data.upload(), Aabb::new(min, max), color)
+    })
+    .collect();
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 6.0, 12.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 12.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut paused = false;
+    let mut time = 0.0f32;
+    let mut last_counter = timer.performance_counter();
+    let frequency = timer.performance_frequency() as f64;
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::X), .. } => debug.depth_test = !debug.depth_test,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Space), .. } => paused = !paused,
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let counter = timer.performance_counter();
+        let dt = ((counter - last_counter) as f64 / frequency) as f32;
+        last_counter = counter;
+        let dt = if paused { 0.0 } else { dt };
+        time += dt;
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let (view, projection) = (camera.view_matrix(), camera.projection_matrix());
+        render_state_cache.apply(&RenderState::opaque());
+        program.set_used();
+        program.set_uniform_mat4("View", &view);
+        program.set_uniform_mat4("Projection", &projection);
+
+        let grey = Vec4::new(0.5, 0.5, 0.5, 1.0);
+        debug.grid(Vec3::ZERO, 20, 1.0, Vec4::new(0.3, 0.3, 0.35, 1.0));
+        debug.axes(&Mat4::identity(), 1.0);
+        for (i, (name, mesh, bounds, color)) in shapes.iter().enumerate() {
+            let angle = i as f32 / shapes.len() as f32 * std::f32::consts::PI * 2.0 + time * 0.3;
+            let position = Vec3::new(angle.cos() * 4.0, 1.5, angle.sin() * 4.0);
+            let rotation = Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.3).normalize(), time * (0.6 + i as f32 * 0.2));
+            let model = Mat4::from_trs(position, rotation, Vec3::ONE);
+            program.set_uniform_mat4("Model", &model);
+            program.set_uniform_vec3("DiffuseColor", *color);
+            mesh.draw();
+
+            debug.transformed_box(bounds, &model, Vec4::new(1.0, 0.9, 0.2, 1.0));
+            debug.aabb(&bounds.transformed(&model), grey);
+            debug.sphere(position, bounds.half_extents().length(), Vec4::new(0.4, 0.8, 1.0, 0.6));
+            debug.axes(&model, 1.2);
+            debug.text3d(position + Vec3::new(0.0, 1.8, 0.0), name, Vec4::new(1.0, 1.0, 1.0, 1.0));
+        }
+
+        // A camera on a pole, sweeping back and forth across the turntable
+        let eye = Vec3::new(0.0, 5.0, -7.0);
+        let target = Vec3::new((time * 0.7).sin() * 4.0, 0.0, 0.0);
+        let spy = Mat4::perspective(0.6, 1.5, 0.5, 10.0) * Mat4::look_at(eye, target, Vec3::Y);
+        debug.line(Vec3::new(eye.x, 0.0, eye.z), eye, grey);
+        debug.frustum(&spy, Vec4::new(1.0, 0.4, 0.8, 1.0));
+        debug.text3d(eye + Vec3::new(0.0, 0.5, 0.0), "camera", Vec4::new(1.0, 0.6, 0.9, 1.0));
+
+        // The ball leaves a segment behind every frame, each lasting a few seconds, so the trail fades out behind it
+        let ball = |t: f32| Vec3::new((t * 1.3).sin() * 6.0, 3.0 + (t * 2.1).sin(), (t * 0.9).cos() * 6.0);
+        debug.sphere(ball(time), 0.2, Vec4::new(1.0, 0.3, 0.3, 1.0));
+        if dt > 0.0 {
+            debug.duration = 3.0;
+            debug.line(ball(time - dt), ball(time), Vec4::new(1.0, 0.5, 0.3, 1.0));
+            debug.duration = 0.0;
+        }
+
+        let debug_lines = debug.line_count();
+        debug.render(&(projection * view), dt, &mut render_state_cache);
+
+        window.set_title(&format!(
+            "{} debug lines, {}",
+            debug_lines,
+            if debug.depth_test { "hidden behind the scene" } else { "drawn on top" },
+        )).unwrap();
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,9 @@
+#version 330 core
+
+in vec3 LineColor;
+out vec4 Color;
+
+void main()
+{
+    Color = vec4(LineColor, 1.0);
+}
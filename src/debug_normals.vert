@@ -0,0 +1,26 @@
+#version 330 core
+
+layout (location = 0) in vec3 Position;
+layout (location = 2) in vec3 Normal;
+layout (location = 4) in vec3 Tangent;
+
+uniform mat4 Model;
+
+out VS_OUTPUT {
+    vec3 Normal;
+    vec3 Tangent;
+} OUT;
+
+// Meshes without normals or tangents get (0, 0, 0) for the missing attribute, and normalizing that is undefined
+vec3 safe_normalize(vec3 v)
+{
+    return length(v) > 0.0 ? normalize(v) : v;
+}
+
+void main()
+{
+    // Stay in world space here; the geometry shader needs to add the normal to the position before projecting
+    gl_Position = Model * vec4(Position, 1.0);
+    OUT.Normal = safe_normalize(mat3(Model) * Normal);
+    OUT.Tangent = safe_normalize(mat3(Model) * Tangent);
+}
@@ -0,0 +1,10 @@
+#version 330 core
+
+out vec4 Color;
+
+void main()
+{
+    // Every fragment adds a little bit of this with additive blending, so the more times a pixel gets drawn, the more it
+    // heats up from dark red through orange towards white
+    Color = vec4(0.25, 0.08, 0.02, 1.0);
+}
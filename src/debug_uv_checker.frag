@@ -0,0 +1,20 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec3 Normal;
+    vec2 TexCoord;
+} IN;
+
+uniform float CheckerScale;
+
+out vec4 Color;
+
+void main()
+{
+    // Alternate between light and dark squares, and tint by the UV itself so that flipped or stretched UVs are easy
+    // to spot (red goes up with U, green goes up with V)
+    vec2 cell = floor(IN.TexCoord * CheckerScale);
+    float checker = mod(cell.x + cell.y, 2.0);
+    vec3 tint = vec3(fract(IN.TexCoord), 0.5);
+    Color = vec4(mix(tint * 0.4, tint, checker), 1.0);
+}
@@ -0,0 +1,183 @@
+// Debug views for figuring out what geometry is actually making it to the GPU.  When a vertex layout is set up wrong
+// (wrong stride, wrong offset, attribute pointing at the wrong thing) the normal shaders tend to just show garbage or
+// nothing at all, and it's hard to tell why.  These views swap in their own programs and/or render state on top of
+// whatever a scene is already doing, so they work with any scene that uses the `vertex::attrib` locations and the usual
+// `Model`/`View`/`Projection` uniforms.
+//
+// Hotkeys: F1 normal, F2 wireframe, F3 points, F4 normals and tangents, F5 UV checkerboard, F6 depth, F7 overdraw
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use std::ffi::CString;
+
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{BlendState, CullMode, DepthState, PolygonMode, RenderState, RenderStateCache};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    None,
+    Wireframe,
+    Points,
+    Normals,
+    UvChecker,
+    Depth,
+    Overdraw,
+}
+
+impl DebugView {
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::None => "none",
+            DebugView::Wireframe => "wireframe",
+            DebugView::Points => "points",
+            DebugView::Normals => "normals/tangents",
+            DebugView::UvChecker => "UV checkerboard",
+            DebugView::Depth => "depth",
+            DebugView::Overdraw => "overdraw",
+        }
+    }
+
+    fn from_keycode(keycode: Keycode) -> Option<DebugView> {
+        match keycode {
+            Keycode::F1 => Some(DebugView::None),
+            Keycode::F2 => Some(DebugView::Wireframe),
+            Keycode::F3 => Some(DebugView::Points),
+            Keycode::F4 => Some(DebugView::Normals),
+            Keycode::F5 => Some(DebugView::UvChecker),
+            Keycode::F6 => Some(DebugView::Depth),
+            Keycode::F7 => Some(DebugView::Overdraw),
+            _ => None,
+        }
+    }
+}
+
+pub struct DebugViews {
+    pub mode: DebugView,
+    pub near: f32,  // camera clip planes, needed to make sense of the depth buffer
+    pub far: f32,
+    pub normal_length: f32,
+    pub point_size: f32,
+    uv_checker_program: Program,
+    depth_program: Program,
+    overdraw_program: Program,
+    normals_program: Program,
+}
+
+fn compile(vert: &str, geom: Option<&str>, frag: &str) -> Result<Program, String> {
+    let mut shaders = vec![Shader::from_vert_source(&CString::new(vert).unwrap())?];
+    if let Some(geom) = geom {
+        shaders.push(Shader::from_geometry_source(&CString::new(geom).unwrap())?);
+    }
+    shaders.push(Shader::from_frag_source(&CString::new(frag).unwrap())?);
+    Program::from_shaders(&shaders)
+}
+
+#[allow(dead_code)]
+impl DebugViews {
+    /// Compiles all of the debug programs up front, so switching views at runtime never stalls
+    pub fn new() -> Result<DebugViews, String> {
+        Ok(DebugViews {
+            mode: DebugView::None,
+            near: 0.1,
+            far: 100.0,
+            normal_length: 0.2,
+            point_size: 4.0,
+            uv_checker_program: compile(include_str!("debug_view.vert"), None, include_str!("debug_uv_checker.frag"))?,
+            depth_program: compile(include_str!("debug_view.vert"), None, include_str!("debug_depth.frag"))?,
+            overdraw_program: compile(include_str!("debug_view.vert"), None, include_str!("debug_overdraw.frag"))?,
+            normals_program: compile(
+                include_str!("debug_normals.vert"),
+                Some(include_str!("debug_normals.geom")),
+                include_str!("debug_normals.frag"),
+            )?,
+        })
+    }
+
+    /// Switches views if `event` is one of the hotkeys.  Returns `true` if the event was used up
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        if let Event::KeyDown { keycode: Some(keycode), repeat: false, .. } = *event {
+            if let Some(mode) = DebugView::from_keycode(keycode) {
+                self.mode = mode;
+                println!("Debug view: {}", mode.name());
+                return true;
+            }
+        }
+        false
+    }
+
+    /// What the scene's render state turns into under the current view
+    pub fn render_state(&self, base: &RenderState) -> RenderState {
+        match self.mode {
+            DebugView::None | DebugView::Normals | DebugView::UvChecker | DebugView::Depth => *base,
+
+            // Turn off culling too, since seeing the back-facing edges is half the point of a wireframe
+            DebugView::Wireframe => RenderState { polygon_mode: PolygonMode::Line, cull_mode: CullMode::None, ..*base },
+            DebugView::Points => RenderState {
+                polygon_mode: PolygonMode::Point,
+                point_size: self.point_size,
+                cull_mode: CullMode::None,
+                ..*base
+            },
+
+            // Every fragment needs to land, even ones that would be hidden, so no depth test and no culling
+            DebugView::Overdraw => RenderState {
+                depth: DepthState { test: false, write: false, ..base.depth },
+                blend: BlendState::additive(),
+                cull_mode: CullMode::None,
+                ..*base
+            },
+        }
+    }
+
+    /// The program to draw the scene's geometry with, or `None` to keep using the scene's own program
+    pub fn replacement_program(&self) -> Option<&Program> {
+        match self.mode {
+            DebugView::UvChecker => Some(&self.uv_checker_program),
+            DebugView::Depth => Some(&self.depth_program),
+            DebugView::Overdraw => Some(&self.overdraw_program),
+            _ => None,
+        }
+    }
+
+    /// A program to draw the scene's geometry a second time with on top of the normal pass, if the view wants one
+    pub fn overlay_program(&self) -> Option<&Program> {
+        match self.mode {
+            DebugView::Normals => Some(&self.normals_program),
+            _ => None,
+        }
+    }
+
+    /// Draws a scene under the current debug view.  `draw` gets called with whichever program should be used (already
+    /// bound with `set_used()`), and is expected to set the `Model`, `View` and `Projection` uniforms on it and then
+    /// issue its draw calls.  It may get called twice when a view draws an overlay on top of the scene
+    pub fn draw<F>(&self, cache: &mut RenderStateCache, state: &RenderState, scene_program: &Program, mut draw: F)
+        where F: FnMut(&Program)
+    {
+        if self.mode == DebugView::Overdraw {
+            // Overdraw accumulates on top of black, otherwise the clear color would throw off the heat map.  Put the
+            // scene's clear color back afterwards so it doesn't stay black once we switch views again
+            let mut clear_color = [0.0f32; 4];
+            unsafe {
+                gl::GetFloatv(gl::COLOR_CLEAR_VALUE, clear_color.as_mut_ptr());
+                gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+                gl::ClearColor(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+            }
+        }
+
+        cache.apply(&self.render_state(state));
+        let program = self.replacement_program().unwrap_or(scene_program);
+        program.set_used();
+        program.set_uniform_f32("CheckerScale", 8.0);
+        program.set_uniform_f32("Near", self.near);
+        program.set_uniform_f32("Far", self.far);
+        draw(program);
+
+        if let Some(overlay) = self.overlay_program() {
+            overlay.set_used();
+            overlay.set_uniform_f32("LineLength", self.normal_length);
+            draw(overlay);
+        }
+    }
+}
@@ -0,0 +1,22 @@
+#version 330 core
+
+// Uses the same attribute locations as `vertex::attrib`, so it can stand in for any scene's own vertex shader
+layout (location = 0) in vec3 Position;
+layout (location = 2) in vec3 Normal;
+layout (location = 3) in vec2 TexCoord;
+
+uniform mat4 Model;
+uniform mat4 View;
+uniform mat4 Projection;
+
+out VS_OUTPUT {
+    vec3 Normal;
+    vec2 TexCoord;
+} OUT;
+
+void main()
+{
+    gl_Position = Projection * View * Model * vec4(Position, 1.0);
+    OUT.Normal = mat3(Model) * Normal;
+    OUT.TexCoord = TexCoord;
+}
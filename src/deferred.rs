@@ -0,0 +1,555 @@
+// Deferred shading.  Forward rendering (`Scene::draw()` with lit.frag) lights every pixel of every mesh with every
+// light, and pays for pixels that get drawn over later too.  With a lot of lights that adds up fast.
+//
+// A deferred renderer splits drawing into two halves.  First the scene is drawn once into a "G-buffer": several
+// textures at once (multiple render targets), holding what each pixel's surface is like rather than what color it
+// ends up.  Then the lights are drawn, each one reading the G-buffer back and adding its light only to the pixels it
+// can reach:
+//
+// * Directional lights (and the ambient light) reach everything, so they're one pass over the whole screen.
+// * Point and spot lights only reach so far, so each one draws a "light volume" (a sphere or cone just big enough
+//   to hold everything in range), and only the pixels that volume covers get shaded.
+//
+// Optionally, an SSAO pass in between darkens the ambient light in corners and creases.
+//
+// It draws the same `Scene`, `DrawList` and lit materials as the forward path, reading the same `LightBuffer` (and
+// `ShadowRenderer`, if there is one).  The catch is that each pixel only has room for one surface, so anything with
+// blending turned on is left out, and every surface is lit with Blinn-Phong.
+
+use std::ffi::CString;
+
+use crate::framebuffer::{self, DepthAttachment, Framebuffer, FullscreenTriangle};
+use crate::lighting;
+use crate::math::{self, Mat4, Quat, Vec2, Vec3};
+use crate::mesh::{Mesh, MeshData};
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{BlendState, CompareFunc, CullMode, DepthState, RenderState, RenderStateCache};
+use crate::scene::{DrawList, LightKind, Scene, WorldLight};
+use crate::shadow;
+use crate::shapes;
+use crate::texture::Texture;
+
+/// Points sampled around each pixel by the SSAO pass.  Has to match ssao.frag
+pub const SSAO_KERNEL_SIZE: usize = 16;
+
+/// The SSAO noise texture is this many pixels across, and tiles over the screen
+const NOISE_SIZE: u32 = 4;
+
+/// Sides around the light volume meshes.  More means a tighter fit, but more triangles
+const VOLUME_SEGMENTS: u32 = 16;
+
+/// Spot lights wider than this get a sphere instead of a cone, which would be mostly wasted space anyway
+const MAX_CONE_ANGLE: f32 = 1.2;
+
+/// Which way to draw the scene, picked once at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum RenderPath {
+    Forward,
+    Deferred,
+}
+
+#[allow(dead_code)]
+impl RenderPath {
+    /// `--forward` or `--deferred` from the command line, or `default` if neither was given
+    pub fn from_args(default: RenderPath) -> RenderPath {
+        std::env::args()
+            .filter_map(|arg| match arg.as_str() {
+                "--forward" => Some(RenderPath::Forward),
+                "--deferred" => Some(RenderPath::Deferred),
+                _ => None,
+            })
+            .next_back()
+            .unwrap_or(default)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderPath::Forward => "forward",
+            RenderPath::Deferred => "deferred",
+        }
+    }
+}
+
+/// How the SSAO pass looks around each pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SsaoSettings {
+    pub radius: f32,  // how far out to look for things blocking the light, in world units
+    pub bias: f32,    // how far in front of a point something has to be to count, so flat surfaces stay unshaded
+}
+
+impl Default for SsaoSettings {
+    fn default() -> SsaoSettings {
+        SsaoSettings { radius: 0.5, bias: 0.025 }
+    }
+}
+
+/// One of the buffers `DeferredRenderer::draw_buffer()` can show, numbered the same as in deferred_debug.frag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum GBufferView {
+    Albedo,
+    Normals,
+    Material,
+    Emissive,
+    Depth,
+    Occlusion,
+}
+
+#[allow(dead_code)]
+impl GBufferView {
+    pub const ALL: [GBufferView; 6] = [
+        GBufferView::Albedo,
+        GBufferView::Normals,
+        GBufferView::Material,
+        GBufferView::Emissive,
+        GBufferView::Depth,
+        GBufferView::Occlusion,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GBufferView::Albedo => "albedo",
+            GBufferView::Normals => "normals",
+            GBufferView::Material => "material",
+            GBufferView::Emissive => "emissive",
+            GBufferView::Depth => "depth",
+            GBufferView::Occlusion => "ambient occlusion",
+        }
+    }
+}
+
+/// The G-buffer's color attachments, in the order gbuffer.frag writes them
+const GBUFFER_FORMATS: [gl::types::GLenum; 4] = [
+    gl::RGBA8,    // albedo
+    gl::RGBA16F,  // normals, which need the sign and more than 8 bits to stay smooth
+    gl::RGBA8,    // specular color and shininess
+    gl::RGBA16F,  // emissive, which can be brighter than 1
+];
+
+/// Units the lighting passes read the G-buffer from.  Well clear of the shadow maps' units
+const ALBEDO_UNIT: u32 = 0;
+const NORMAL_UNIT: u32 = 1;
+const MATERIAL_UNIT: u32 = 2;
+const EMISSIVE_UNIT: u32 = 3;
+const DEPTH_UNIT: u32 = 4;
+const OCCLUSION_UNIT: u32 = 5;
+
+/// A mesh to draw a light volume with, and how far its faces are from its middle.  The corners are on the unit sphere
+/// (or circle), but the faces between them cut inside it, so the mesh needs scaling up by this to contain it
+struct Volume {
+    mesh: Mesh,
+    inner_radius: f32,
+}
+
+impl Volume {
+    fn new(data: &MeshData, inner_radius: f32) -> Volume {
+        Volume { mesh: data.upload(), inner_radius }
+    }
+}
+
+/// Closest any triangle of `data` comes to the origin
+fn closest_face(data: &MeshData) -> f32 {
+    data.indices.chunks(3)
+        .filter_map(|triangle| {
+            let corner = |i: usize| data.vertices[triangle[i] as usize].position;
+            let (a, b, c) = (corner(0), corner(1), corner(2));
+            let normal = (b - a).cross(c - a);
+            if normal.length() == 0.0 {
+                return None;  // the slivers at a UV sphere's poles
+            }
+            Some(a.dot(normal.normalize()).abs())
+        })
+        .fold(1.0, f32::min)
+}
+
+pub struct DeferredRenderer {
+    gbuffer: Option<Framebuffer>,  // made the first time something's drawn, and remade whenever the size changes
+    occlusion: Option<(Framebuffer, Framebuffer)>,  // the SSAO pass's output, then the blurred version of it
+    gbuffer_program: Program,
+    ambient_program: Program,  // full screen: ambient light, emissive surfaces and directional lights
+    volume_program: Program,   // one point or spot light's volume
+    ssao_program: Program,
+    blur_program: Program,
+    debug_program: Program,
+    noise: Texture,
+    kernel: Vec<Vec3>,
+    sphere: Volume,
+    cone: Volume,
+    fullscreen: FullscreenTriangle,
+    max_lights: usize,
+    pub ssao: Option<SsaoSettings>,  // `None` skips the SSAO pass
+    volumes_drawn: usize,
+    skipped: usize,
+}
+
+#[allow(dead_code)]
+impl DeferredRenderer {
+    /// A renderer for up to `max_lights` lights, which has to match the `LightBuffer` the lights are uploaded to
+    pub fn new(max_lights: usize) -> Result<DeferredRenderer, String> {
+        // The G-buffer pass takes the same vertex data and material parameters as lit.vert and lit.frag
+        let gbuffer_vert = Shader::from_vert_source(&CString::new(include_str!("lit.vert")).unwrap())?;
+        let gbuffer_frag = Shader::from_frag_source(&CString::new(include_str!("gbuffer.frag")).unwrap())?;
+        let gbuffer_program = Program::from_shaders(&[gbuffer_vert, gbuffer_frag])?;
+
+        let light_source = lighting::with_lights(include_str!("deferred_light.frag"), max_lights);
+        let light_program = |vert_source: &str| -> Result<Program, String> {
+            let vert_shader = Shader::from_vert_source(&CString::new(vert_source).unwrap())?;
+            let frag_shader = Shader::from_frag_source(&CString::new(light_source.as_str()).unwrap())?;
+            let program = Program::from_shaders(&[vert_shader, frag_shader])?;
+            lighting::prepare_program(&program);
+            program.set_uniform_i32("AlbedoTexture", ALBEDO_UNIT as i32);
+            program.set_uniform_i32("NormalTexture", NORMAL_UNIT as i32);
+            program.set_uniform_i32("MaterialTexture", MATERIAL_UNIT as i32);
+            program.set_uniform_i32("EmissiveTexture", EMISSIVE_UNIT as i32);
+            program.set_uniform_i32("DepthTexture", DEPTH_UNIT as i32);
+            program.set_uniform_i32("OcclusionTexture", OCCLUSION_UNIT as i32);
+            Ok(program)
+        };
+        let ambient_program = light_program(include_str!("fullscreen.vert"))?;
+        let volume_program = light_program(include_str!("deferred_light.vert"))?;
+
+        let ssao_program = framebuffer::fullscreen_program(include_str!("ssao.frag"))?;
+        let kernel = ssao_kernel();
+        ssao_program.set_used();
+        ssao_program.set_uniform_i32("DepthTexture", 0);
+        ssao_program.set_uniform_i32("NormalTexture", 1);
+        ssao_program.set_uniform_i32("NoiseTexture", 2);
+        for (i, &point) in kernel.iter().enumerate() {
+            ssao_program.set_uniform_vec3(&format!("Kernel[{}]", i), point);
+        }
+        let blur_program = framebuffer::fullscreen_program(include_str!("ssao_blur.frag"))?;
+        let debug_program = framebuffer::fullscreen_program(include_str!("deferred_debug.frag"))?;
+
+        let noise = Texture::from_f32(NOISE_SIZE, NOISE_SIZE, 2, &ssao_noise(), gl::RG16F);
+        noise.set_filter(gl::NEAREST, gl::NEAREST);
+        noise.set_wrap(gl::REPEAT, gl::REPEAT);
+
+        // The cone points up +Y from a base at -0.5; `volume_matrix()` turns it to point down the spot light instead
+        let sphere_data = shapes::uv_sphere(1.0, VOLUME_SEGMENTS, VOLUME_SEGMENTS / 2);
+        let sphere = Volume::new(&sphere_data, closest_face(&sphere_data));
+        let cone_data = shapes::cone(1.0, 1.0, VOLUME_SEGMENTS, 1);
+        let cone = Volume::new(&cone_data, (std::f32::consts::PI / VOLUME_SEGMENTS as f32).cos());
+
+        Ok(DeferredRenderer {
+            gbuffer: None,
+            occlusion: None,
+            gbuffer_program,
+            ambient_program,
+            volume_program,
+            ssao_program,
+            blur_program,
+            debug_program,
+            noise,
+            kernel,
+            sphere,
+            cone,
+            fullscreen: FullscreenTriangle::new(),
+            max_lights,
+            ssao: Some(SsaoSettings::default()),
+            volumes_drawn: 0,
+            skipped: 0,
+        })
+    }
+
+    /// Makes sure the G-buffer (and SSAO buffers) are `width` by `height`
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), String> {
+        let (width, height) = (width.max(1), height.max(1));
+        if matches!(&self.gbuffer, Some(g) if g.width() == width && g.height() == height) {
+            return Ok(());
+        }
+        let gbuffer = Framebuffer::new(width, height, &GBUFFER_FORMATS, DepthAttachment::Texture)?;
+        // Every lookup lands on the middle of a texel, so there's nothing to filter, and blending normals or depths
+        // from different surfaces along an edge would only give nonsense
+        for i in 0..GBUFFER_FORMATS.len() {
+            gbuffer.color(i).set_filter(gl::NEAREST, gl::NEAREST);
+        }
+        if let Some(depth) = gbuffer.depth_texture() {
+            depth.set_filter(gl::NEAREST, gl::NEAREST);
+        }
+        self.gbuffer = Some(gbuffer);
+        self.occlusion = Some((
+            Framebuffer::new(width, height, &[gl::R8], DepthAttachment::None)?,
+            Framebuffer::new(width, height, &[gl::R8], DepthAttachment::None)?,
+        ));
+        Ok(())
+    }
+
+    /// Draws the opaque calls in `list` (from `scene.collect_draw_calls()`) lit by `lights`, which need to have been
+    /// uploaded to the `LightBuffer` already, in the same order.  The result goes to whatever framebuffer was bound,
+    /// which is `width` by `height`.  Pixels nothing was drawn at are left alone, so clear it first.
+    ///
+    /// Only color is drawn there; the depth stays in the G-buffer
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        scene: &Scene,
+        list: &DrawList,
+        lights: &[WorldLight],
+        view: &Mat4,
+        projection: &Mat4,
+        width: u32,
+        height: u32,
+        cache: &mut RenderStateCache,
+    ) -> Result<(), String> {
+        let mut previous_framebuffer: gl::types::GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+        }
+        self.resize(width, height)?;
+        self.fill_gbuffer(scene, list, view, projection, cache);
+        if let Some(settings) = self.ssao {
+            self.ambient_occlusion(settings, view, projection, cache);
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_framebuffer as gl::types::GLuint);
+            gl::Viewport(0, 0, width as gl::types::GLsizei, height as gl::types::GLsizei);
+        }
+        self.light(lights, view, projection, width, height, cache);
+        Ok(())
+    }
+
+    /// The geometry pass: every opaque surface's material, normal and depth into the G-buffer
+    fn fill_gbuffer(
+        &mut self,
+        scene: &Scene,
+        list: &DrawList,
+        view: &Mat4,
+        projection: &Mat4,
+        cache: &mut RenderStateCache,
+    ) {
+        let gbuffer = self.gbuffer.as_ref().expect("resize() makes the G-buffer");
+        gbuffer.bind();
+        cache.apply(&RenderState::opaque());
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let program = &self.gbuffer_program;
+        program.set_used();
+        program.set_uniform_mat4("View", view);
+        program.set_uniform_mat4("Projection", projection);
+        self.skipped = 0;
+        let mut current_material = None;
+        for call in &list.calls {
+            if call.render_state.blend.enabled {
+                self.skipped += 1;
+                continue;
+            }
+            if current_material != Some(call.material) {
+                scene.materials.apply_to(call.material, program);
+                current_material = Some(call.material);
+            }
+            cache.apply(&call.render_state);
+            program.set_uniform_mat4("Model", &call.model);
+            scene.meshes[call.mesh].draw();
+        }
+    }
+
+    /// Works out the ambient occlusion from the G-buffer, then blurs it
+    fn ambient_occlusion(&self, settings: SsaoSettings, view: &Mat4, projection: &Mat4, cache: &mut RenderStateCache) {
+        let gbuffer = self.gbuffer.as_ref().expect("resize() makes the G-buffer");
+        let (raw, blurred) = self.occlusion.as_ref().expect("resize() makes the SSAO buffers");
+        cache.apply(&RenderState::default());
+
+        raw.bind();
+        self.ssao_program.set_used();
+        self.ssao_program.set_uniform_mat4("View", view);
+        self.ssao_program.set_uniform_mat4("Projection", projection);
+        self.ssao_program.set_uniform_mat4("InverseProjection", &projection.inverse().unwrap_or_else(Mat4::identity));
+        self.ssao_program.set_uniform_f32("Radius", settings.radius);
+        self.ssao_program.set_uniform_f32("Bias", settings.bias);
+        let noise_scale = Vec2::new(
+            gbuffer.width() as f32 / NOISE_SIZE as f32,
+            gbuffer.height() as f32 / NOISE_SIZE as f32,
+        );
+        self.ssao_program.set_uniform_vec2("NoiseScale", noise_scale);
+        if let Some(depth) = gbuffer.depth_texture() {
+            depth.bind(0);
+        }
+        gbuffer.color(1).bind(1);
+        self.noise.bind(2);
+        self.fullscreen.draw();
+
+        blurred.bind();
+        self.blur_program.set_used();
+        self.blur_program.set_uniform_i32("OcclusionTexture", 0);
+        raw.color(0).bind(0);
+        self.fullscreen.draw();
+    }
+
+    /// The lighting passes, into whatever's bound
+    fn light(
+        &mut self,
+        lights: &[WorldLight],
+        view: &Mat4,
+        projection: &Mat4,
+        width: u32,
+        height: u32,
+        cache: &mut RenderStateCache,
+    ) {
+        let gbuffer = self.gbuffer.as_ref().expect("resize() makes the G-buffer");
+        for (i, unit) in [ALBEDO_UNIT, NORMAL_UNIT, MATERIAL_UNIT, EMISSIVE_UNIT].iter().enumerate() {
+            gbuffer.color(i).bind(*unit);
+        }
+        if let Some(depth) = gbuffer.depth_texture() {
+            depth.bind(DEPTH_UNIT);
+        }
+        if let Some((_, blurred)) = &self.occlusion {
+            blurred.color(0).bind(OCCLUSION_UNIT);
+        }
+
+        let inverse_view_projection = (*projection * *view).inverse().unwrap_or_else(Mat4::identity);
+        let camera_position = view.inverse().map_or(Vec3::ZERO, |world| world.transform_point(Vec3::ZERO));
+        let screen_size = Vec2::new(width as f32, height as f32);
+        for program in &[&self.ambient_program, &self.volume_program] {
+            program.set_used();
+            program.set_uniform_mat4("View", view);
+            program.set_uniform_mat4("InverseViewProjection", &inverse_view_projection);
+            program.set_uniform_vec3("CameraPosition", camera_position);
+            program.set_uniform_vec2("ScreenSize", screen_size);
+        }
+
+        // The ambient pass covers everything and goes down first, so there's no need to blend it
+        cache.apply(&RenderState::default());
+        self.ambient_program.set_used();
+        self.ambient_program.set_uniform_i32("LightIndex", -1);
+        self.ambient_program.set_uniform_i32("UseOcclusion", self.ssao.is_some() as i32);
+        self.fullscreen.draw();
+
+        // Each volume adds its light on top.  Drawing their back faces rather than their front ones means they still
+        // cover the screen when the camera's inside them.  There's no depth buffer here to test them against, so
+        // pixels in front of or behind the light still run the shader, and get thrown out by its range check
+        cache.apply(&RenderState {
+            blend: BlendState::additive(),
+            cull_mode: CullMode::Front,
+            depth: DepthState { test: false, write: false, func: CompareFunc::Always },
+            ..RenderState::default()
+        });
+        self.volume_program.set_used();
+        self.volume_program.set_uniform_mat4("Projection", projection);
+        self.volumes_drawn = 0;
+        for (i, light) in lights.iter().take(self.max_lights).enumerate() {
+            let (volume, model) = match self.volume_matrix(light) {
+                Some(volume) => volume,
+                None => continue,
+            };
+            self.volume_program.set_uniform_mat4("Model", &model);
+            self.volume_program.set_uniform_i32("LightIndex", i as i32);
+            volume.mesh.draw();
+            self.volumes_drawn += 1;
+        }
+    }
+
+    /// The mesh and model matrix for a light's volume, or `None` for directional lights, which don't have one
+    fn volume_matrix(&self, light: &WorldLight) -> Option<(&Volume, Mat4)> {
+        let range = light.light.range;
+        let sphere = |volume: &Volume| {
+            Mat4::from_trs(light.position, Quat::IDENTITY, Vec3::splat(range / volume.inner_radius))
+        };
+        match light.light.kind {
+            LightKind::Directional => None,
+            LightKind::Point => Some((&self.sphere, sphere(&self.sphere))),
+            LightKind::Spot { outer_angle, .. } if outer_angle > MAX_CONE_ANGLE => {
+                Some((&self.sphere, sphere(&self.sphere)))
+            },
+            LightKind::Spot { outer_angle, .. } => {
+                // Stand the cone on its point at the light, then turn it so that it opens out down the light's -Z
+                let radius = range * outer_angle.tan() / self.cone.inner_radius;
+                let local = Mat4::from_trs(
+                    Vec3::new(0.0, 0.0, -range / 2.0),
+                    Quat::from_axis_angle(Vec3::X, math::radians(90.0)),
+                    Vec3::new(radius, range, radius),
+                );
+                let target = light.position + light.direction;
+                let world = Mat4::look_at(light.position, target, shadow::up_for(light.direction)).inverse()?;
+                Some((&self.cone, world * local))
+            },
+        }
+    }
+
+    /// Shows one of the buffers from the last `render()` over the whole of whatever's bound.  `near` and `far` are the
+    /// camera's, for turning depth back into distance
+    pub fn draw_buffer(&self, buffer: GBufferView, near: f32, far: f32, cache: &mut RenderStateCache) {
+        let gbuffer = match &self.gbuffer {
+            Some(gbuffer) => gbuffer,
+            None => return,
+        };
+        let texture = match buffer {
+            GBufferView::Albedo => gbuffer.color(0),
+            GBufferView::Normals => gbuffer.color(1),
+            GBufferView::Material => gbuffer.color(2),
+            GBufferView::Emissive => gbuffer.color(3),
+            GBufferView::Depth => match gbuffer.depth_texture() {
+                Some(depth) => depth,
+                None => return,
+            },
+            GBufferView::Occlusion => match &self.occlusion {
+                Some((_, blurred)) => blurred.color(0),
+                None => return,
+            },
+        };
+        cache.apply(&RenderState::default());
+        texture.bind(0);
+        self.debug_program.set_used();
+        self.debug_program.set_uniform_i32("Buffer", 0);
+        self.debug_program.set_uniform_i32("Channel", buffer as i32);
+        self.debug_program.set_uniform_f32("Near", near);
+        self.debug_program.set_uniform_f32("Far", far);
+        self.fullscreen.draw();
+    }
+
+    pub fn max_lights(&self) -> usize {
+        self.max_lights
+    }
+
+    /// How many point and spot light volumes the last `render()` drew
+    pub fn volumes_drawn(&self) -> usize {
+        self.volumes_drawn
+    }
+
+    /// How many blended draw calls the last `render()` had to leave out
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// The SSAO sample points, in case anything wants to show them
+    pub fn ssao_kernel(&self) -> &[Vec3] {
+        &self.kernel
+    }
+}
+
+/// Same tiny hash as the solar system's asteroids, for numbers that look random but come out the same every run
+fn hash(n: u32) -> f32 {
+    let n = n.wrapping_mul(0x9E37_79B9) ^ (n >> 15);
+    (n.wrapping_mul(0x85EB_CA6B) >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Points scattered through a unit hemisphere around +Z, bunched up towards the middle so that things right next to a
+/// surface count for more than things at the edge of the radius
+fn ssao_kernel() -> Vec<Vec3> {
+    (0..SSAO_KERNEL_SIZE as u32)
+        .map(|i| {
+            let direction = Vec3::new(
+                hash(i * 4) * 2.0 - 1.0,
+                hash(i * 4 + 1) * 2.0 - 1.0,
+                hash(i * 4 + 2),
+            );
+            let direction = if direction.length() > 0.0 { direction.normalize() } else { Vec3::Z };
+            let t = i as f32 / SSAO_KERNEL_SIZE as f32;
+            direction * hash(i * 4 + 3).max(0.1) * (0.1 + 0.9 * t * t)
+        })
+        .collect()
+}
+
+/// Random directions in the XY plane, for turning the SSAO kernel around the normal
+fn ssao_noise() -> Vec<f32> {
+    (0..NOISE_SIZE * NOISE_SIZE)
+        .flat_map(|i| {
+            let angle = hash(1000 + i) * std::f32::consts::PI * 2.0;
+            vec![angle.cos(), angle.sin()]
+        })
+        .collect()
+}
@@ -0,0 +1,31 @@
+#version 330 core
+
+// Shows one of `DeferredRenderer`'s buffers on its own, for seeing what the lighting passes have to work with
+
+in vec2 TexCoord;
+
+uniform sampler2D Buffer;
+uniform int Channel;  // 0 albedo, 1 normals, 2 material parameters, 3 emissive, 4 depth, 5 ambient occlusion
+uniform float Near;
+uniform float Far;
+
+out vec4 Color;
+
+void main()
+{
+    vec4 value = texture(Buffer, TexCoord);
+    if (Channel == 1) {
+        Color = vec4(value.xyz * 0.5 + 0.5, 1.0);  // -1 to 1 squeezed into colors
+    } else if (Channel == 2) {
+        Color = vec4(value.rgb, 1.0);  // specular color only; shininess is in the alpha channel
+    } else if (Channel == 4) {
+        // Depth is mostly crowded up near 1, so turn it back into a distance to see anything
+        float z = value.r * 2.0 - 1.0;
+        float distance = 2.0 * Near * Far / (Far + Near - z * (Far - Near));
+        Color = vec4(vec3(distance / Far), 1.0);
+    } else if (Channel == 5) {
+        Color = vec4(value.rrr, 1.0);
+    } else {
+        Color = vec4(value.rgb, 1.0);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::bounds::Aabb;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::deferred::{DeferredRenderer, GBufferView, RenderPath};
+use crate::lighting::{self, LightBuffer};
+use crate::material::{MaterialLibrary, MaterialValue};
+use crate::math::{radians, Quat, Vec3};
+use crate::mesh::MeshData;
+use crate::render_state::RenderStateCache;
+use crate::scene::{Light, LightKind, Renderable, Scene};
+use crate::shapes;
+
+const MAX_LIGHTS: usize = 128;
+const POINT_LIGHTS: u32 = 120;
+
+fn add_material(library: &mut MaterialLibrary, program: usize, name: &str, params: &[(&str, MaterialValue)]) -> usize {
+    let mut material = library.create_material(name, program);
+    for &(param, value) in params {
+        material.set(param, value);
+    }
+    library.add_material(material)
+}
+
+/// A field of pillars, balls and boxes lit by a hundred and twenty small colored lights drifting around between them,
+/// plus a couple of spot lights and dim moonlight.  The scene is built once and drawn either forward or deferred,
+/// picked at startup with `--forward` or `--deferred` (deferred if neither is given), and the title shows how long
+/// each frame takes, for comparing the two.
+///
+/// O switches SSAO on and off, and B steps through the G-buffer's contents (deferred only).  Space pauses, and
+/// dragging orbits the camera
+pub fn deferred_demo() {
+    let path = RenderPath::from_args(RenderPath::Deferred);
+
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.02, 0.02, 0.05, 1.0);  // Night sky
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let mut timer = _sdl.timer().unwrap();
+
+    // Both paths share the lit program's materials, so it's made either way, but the deferred renderer only when it's
+    // going to be used
+    let setup = || -> Result<_, String> {
+        let deferred = match path {
+            RenderPath::Deferred => Some(DeferredRenderer::new(MAX_LIGHTS)?),
+            RenderPath::Forward => None,
+        };
+        Ok((lighting::create_program(MAX_LIGHTS)?, deferred))
+    };
+    let (lit_program, mut deferred) = match setup() {
+        Ok(setup) => setup,
+        Err(e) => {
+            println!("Failed to set up the {} renderer: {}", path.name(), e);
+            return;
+        },
+    };
+    let light_buffer = LightBuffer::new(MAX_LIGHTS);
+
+    let mut scene = Scene::new();
+    let program = scene.materials.add_program(lit_program);
+    let solid = |scene: &mut Scene, name: &str, color: Vec3| {
+        add_material(&mut scene.materials, program, name, &[
+            ("DiffuseColor", MaterialValue::Vec3(color)),
+            ("SpecularColor", MaterialValue::Vec3(Vec3::splat(0.4))),
+            ("Shininess", MaterialValue::Float(48.0)),
+        ])
+    };
+    let ground_material = solid(&mut scene, "ground", Vec3::new(0.5, 0.5, 0.52));
+    let stone_material = solid(&mut scene, "stone", Vec3::new(0.75, 0.72, 0.68));
+    let white_material = solid(&mut scene, "white", Vec3::splat(0.9));
+
+    let add_object = |scene: &mut Scene, name: &str, data: &MeshData, material: usize, position: Vec3| {
+        let mesh = scene.add_mesh_data(data);
+        let (min, max) = data.bounds();
+        let node = scene.add_node(name, None);
+        scene.node_mut(node).set_translation(position);
+        scene.node_mut(node).renderable = Some(Renderable::new(mesh, material, Aabb::new(min, max)));
+        node
+    };
+    add_object(&mut scene, "ground", &shapes::plane_grid(60.0, 60.0, 1, 1), ground_material, Vec3::ZERO);
+
+    let pillar = shapes::cylinder(0.4, 3.0, 24, 1);
+    let ball = shapes::uv_sphere(0.7, 32, 16);
+    let block = shapes::cube(1.2, 1);
+    for x in -4i32..=4 {
+        for z in -4..=4 {
+            let position = Vec3::new(x as f32 * 4.0, 0.0, z as f32 * 4.0);
+            match (x + z).rem_euclid(3) {
+                0 => add_object(&mut scene, "pillar", &pillar, stone_material, position + Vec3::Y * 1.5),
+                1 => add_object(&mut scene, "ball", &ball, white_material, position + Vec3::Y * 0.7),
+                _ => add_object(&mut scene, "block", &block, stone_material, position + Vec3::Y * 0.6),
+            };
+        }
+    }
+
+    let moon = scene.add_node("moon", None);
+    scene.node_mut(moon).light = Some(Light {
+        kind: LightKind::Directional,
+        color: Vec3::new(0.6, 0.7, 1.0),
+        intensity: 0.15,
+        range: 0.0,
+        shadow: None,
+    });
+    scene.node_mut(moon).set_rotation(
+        Quat::from_axis_angle(Vec3::Y, radians(-30.0)) * Quat::from_axis_angle(Vec3::X, radians(-50.0)),
+    );
+    for (i, &x) in [-10.0f32, 10.0].iter().enumerate() {
+        let spot = scene.add_node("spot light", None);
+        scene.node_mut(spot).light = Some(Light {
+            kind: LightKind::Spot { inner_angle: radians(15.0), outer_angle: radians(25.0) },
+            color: Vec3::new(1.0, 0.9, 0.7),
+            intensity: 60.0,
+            range: 25.0,
+            shadow: None,
+        });
+        scene.node_mut(spot).set_translation(Vec3::new(x, 10.0, 0.0));
+        let turn = if i == 0 { 1.0 } else { -1.0 };  // lean in towards the middle
+        scene.node_mut(spot).set_rotation(
+            Quat::from_axis_angle(Vec3::Z, radians(30.0) * turn) * Quat::from_axis_angle(Vec3::X, radians(-90.0)),
+        );
+    }
+
+    // The small lights each carry a glowing ball, so it's easy to see where they are.  They're given colors and orbits
+    // with the same tiny hash the solar system's asteroids use, so they're the same every run
+    let hash = |n: u32| {
+        let n = n.wrapping_mul(0x9E37_79B9) ^ (n >> 15);
+        (n.wrapping_mul(0x85EB_CA6B) >> 8) as f32 / (1 << 24) as f32
+    };
+    let palette = [
+        Vec3::new(1.0, 0.3, 0.2),
+        Vec3::new(0.3, 1.0, 0.4),
+        Vec3::new(0.3, 0.5, 1.0),
+        Vec3::new(1.0, 0.8, 0.2),
+        Vec3::new(0.9, 0.3, 1.0),
+        Vec3::new(0.2, 0.9, 1.0),
+    ];
+    let marker_materials: Vec<usize> = palette.iter()
+        .map(|&color| {
+            add_material(&mut scene.materials, program, "marker", &[
+                ("DiffuseColor", MaterialValue::Vec3(Vec3::ZERO)),
+                ("SpecularColor", MaterialValue::Vec3(Vec3::ZERO)),
+                ("EmissiveColor", MaterialValue::Vec3(color)),
+            ])
+        })
+        .collect();
+    let marker_data = shapes::uv_sphere(0.08, 8, 4);
+    let marker_mesh = scene.add_mesh_data(&marker_data);
+    let (marker_min, marker_max) = marker_data.bounds();
+    let mut movers = Vec::new();  // (node, orbit center, orbit radius, speed, phase)
+    for i in 0..POINT_LIGHTS {
+        let color = (hash(i * 5) * palette.len() as f32) as usize % palette.len();
+        let node = scene.add_node("point light", None);
+        scene.node_mut(node).light = Some(Light {
+            kind: LightKind::Point,
+            color: palette[color],
+            intensity: 3.0,
+            range: 4.0,
+            shadow: None,
+        });
+        let marker = scene.add_node("marker", Some(node));
+        scene.node_mut(marker).renderable =
+            Some(Renderable::new(marker_mesh, marker_materials[color], Aabb::new(marker_min, marker_max)));
+
+        let (x, y, z) = (hash(i * 5 + 1), hash(i * 5 + 2), hash(i * 5 + 3));
+        let center = Vec3::new(x * 36.0 - 18.0, 0.4 + y * 1.5, z * 36.0 - 18.0);
+        let speed = (0.2 + hash(i * 5 + 4) * 0.6) * if i % 2 == 0 { 1.0 } else { -1.0 };
+        movers.push((node, center, 1.0 + hash(i * 7) * 2.0, speed, hash(i * 11) * std::f32::consts::PI * 2.0));
+    }
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 14.0, 24.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 28.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut buffer_view: Option<usize> = None;  // index into `GBufferView::ALL`
+    let mut playing = true;
+    let mut time = 0.0f32;
+    let mut frame_ms = 0.0f32;
+    let mut last_ticks = timer.ticks();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::O), .. } => {
+                    if let Some(deferred) = &mut deferred {
+                        deferred.ssao = if deferred.ssao.is_some() { None } else { Some(Default::default()) };
+                    }
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::B), .. } => {
+                    buffer_view = match buffer_view {
+                        None => Some(0),
+                        Some(i) if i + 1 < GBufferView::ALL.len() => Some(i + 1),
+                        Some(_) => None,
+                    };
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Space), .. } => playing = !playing,
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let ticks = timer.ticks();
+        let dt = (ticks - last_ticks) as f32 / 1000.0;
+        last_ticks = ticks;
+        if playing {
+            time += dt;
+        }
+        frame_ms = frame_ms * 0.95 + dt * 1000.0 * 0.05;  // smoothed, so the title is readable
+
+        for &(node, center, radius, speed, phase) in &movers {
+            let angle = time * speed + phase;
+            let bob = (time * 1.3 + phase).sin() * 0.3;
+            scene.node_mut(node).set_translation(center + Vec3::new(angle.cos() * radius, bob, angle.sin() * radius));
+        }
+
+        let (view, projection) = (camera.view_matrix(), camera.projection_matrix());
+        let draw_list = scene.collect_draw_calls(&(projection * view), camera.position);
+        let world_lights = scene.lights();
+        light_buffer.update(Vec3::splat(0.05), &world_lights);
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let (width, height) = window.size();
+        match &mut deferred {
+            Some(deferred) => {
+                let result = deferred.render(
+                    &scene,
+                    &draw_list,
+                    &world_lights,
+                    &view,
+                    &projection,
+                    width,
+                    height,
+                    &mut render_state_cache,
+                );
+                if let Err(e) = result {
+                    println!("Failed to draw the deferred frame: {}", e);
+                    break 'main;
+                }
+                if let Some(i) = buffer_view {
+                    deferred.draw_buffer(GBufferView::ALL[i], camera.near, camera.far, &mut render_state_cache);
+                }
+            },
+            None => scene.draw(&draw_list, &mut render_state_cache, &view, &projection),
+        }
+
+        let extra = match &deferred {
+            Some(deferred) => format!(
+                ", {} light volumes, SSAO {}, showing {}",
+                deferred.volumes_drawn(),
+                if deferred.ssao.is_some() { "on" } else { "off" },
+                buffer_view.map_or("the lit scene", |i| GBufferView::ALL[i].name()),
+            ),
+            None => String::new(),
+        };
+        window.set_title(&format!(
+            "{} path, {} lights, {:.1} ms per frame{}",
+            path.name(),
+            world_lights.len().min(MAX_LIGHTS),
+            frame_ms,
+            extra,
+        )).unwrap();
+        window.gl_swap_window();
+    }
+}
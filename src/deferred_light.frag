@@ -0,0 +1,97 @@
+#version 330 core
+
+// The lighting half of deferred shading.  Reads back what gbuffer.frag wrote for a pixel and lights it with the same
+// Blinn-Phong maths as lit.frag.  It's drawn two ways by `DeferredRenderer`: once over the whole screen for the
+// ambient light, emissive surfaces and every directional light, then once per point or spot light as a light volume,
+// blended on top.
+//
+// The `Lights` block (and shadows) come from lights.glsl, pasted in by `lighting::with_lights()`
+
+uniform sampler2D AlbedoTexture;
+uniform sampler2D NormalTexture;
+uniform sampler2D MaterialTexture;
+uniform sampler2D EmissiveTexture;
+uniform sampler2D DepthTexture;
+uniform sampler2D OcclusionTexture;  // from the SSAO pass, if `UseOcclusion` is on
+uniform bool UseOcclusion = false;
+
+uniform mat4 InverseViewProjection;  // clip space back to world space, to find where each pixel is
+uniform vec3 CameraPosition;
+uniform vec2 ScreenSize;             // in pixels, for turning `gl_FragCoord` into G-buffer coordinates
+uniform int LightIndex = -1;         // which light a volume is for; -1 for the full screen pass
+
+out vec4 Color;
+
+// Undoes gbuffer.frag's `encode_shininess()`
+float decode_shininess(float encoded)
+{
+    return exp2(encoded * 11.0);
+}
+
+// What one light adds to a surface at `p`, facing `n`, seen from the direction `v`
+vec3 shade(Light light, vec3 p, vec3 n, vec3 v, vec3 diffuse_color, vec3 specular_color, float shininess)
+{
+    int type = int(light.PositionType.w);
+    vec3 l;
+    float attenuation = 1.0;
+    if (type == LIGHT_DIRECTIONAL) {
+        l = -normalize(light.DirectionRange.xyz);
+    } else {
+        vec3 to_light = light.PositionType.xyz - p;
+        float distance = length(to_light);
+        if (distance >= light.DirectionRange.w) {
+            return vec3(0.0);  // the volume is bigger than what the light reaches, so some pixels can stop here
+        }
+        l = to_light / distance;
+        float window = clamp(1.0 - pow(distance / light.DirectionRange.w, 4.0), 0.0, 1.0);
+        attenuation = window * window / (1.0 + distance * distance);
+        if (type == LIGHT_SPOT) {
+            float angle_cos = dot(-l, normalize(light.DirectionRange.xyz));
+            attenuation *= smoothstep(light.Cone.y, light.Cone.x, angle_cos);
+        }
+    }
+
+    float diffuse = max(dot(n, l), 0.0);
+    if (diffuse <= 0.0 || attenuation <= 0.0) {
+        return vec3(0.0);
+    }
+    vec3 h = normalize(l + v);
+    float specular = pow(max(dot(n, h), 0.0), shininess);
+
+    // The G-buffer only keeps the normal mapped normal, so that's what the shadow lookup gets offset along too
+    attenuation *= shadow_factor(light, p, n);
+    vec3 radiance = light.ColorIntensity.rgb * light.ColorIntensity.a * attenuation;
+    return radiance * (diffuse_color * diffuse + specular_color * specular);
+}
+
+void main()
+{
+    vec2 uv = gl_FragCoord.xy / ScreenSize;
+    float depth = texture(DepthTexture, uv).r;
+    if (depth >= 1.0) {
+        discard;  // nothing was drawn here, so leave the background alone
+    }
+
+    vec4 world = InverseViewProjection * vec4(vec3(uv, depth) * 2.0 - 1.0, 1.0);
+    vec3 p = world.xyz / world.w;
+    vec3 n = normalize(texture(NormalTexture, uv).xyz);
+    vec3 v = normalize(CameraPosition - p);
+    vec3 diffuse_color = texture(AlbedoTexture, uv).rgb;
+    vec4 params = texture(MaterialTexture, uv);
+    float shininess = decode_shininess(params.a);
+
+    if (LightIndex >= 0) {
+        Color = vec4(shade(LightList[LightIndex], p, n, v, diffuse_color, params.rgb, shininess), 1.0);
+        return;
+    }
+
+    // Ambient occlusion only darkens the ambient light, since that's the light that's meant to come from everywhere
+    float occlusion = UseOcclusion ? texture(OcclusionTexture, uv).r : 1.0;
+    vec3 result = Ambient.rgb * diffuse_color * occlusion + texture(EmissiveTexture, uv).rgb;
+    for (int i = 0; i < min(LightCount.x, MAX_LIGHTS); i++) {
+        if (int(LightList[i].PositionType.w) == LIGHT_DIRECTIONAL) {
+            result += shade(LightList[i], p, n, v, diffuse_color, params.rgb, shininess);
+        }
+    }
+    Color = vec4(result, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+// Light volumes for `DeferredRenderer`: a sphere or cone around everything a point or spot light can reach, so that
+// deferred_light.frag only runs for the pixels the light might touch
+
+layout (location = 0) in vec3 Position;
+
+uniform mat4 Model;
+uniform mat4 View;
+uniform mat4 Projection;
+
+void main()
+{
+    gl_Position = Projection * View * Model * vec4(Position, 1.0);
+}
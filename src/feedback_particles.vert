@@ -0,0 +1,60 @@
+#version 330 core
+
+// Moves every particle on by one frame.  Nothing gets drawn: the outputs are recorded by transform feedback into the
+// other buffer, which is next frame's input.  Particles that have lived out their `Lifetime` start again at the emitter
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec3 Velocity;
+layout (location = 2) in float Age;  // in seconds, and negative while it's still waiting to be born
+
+out vec3 OutPosition;
+out vec3 OutVelocity;
+out float OutAge;
+
+uniform float DeltaTime;
+uniform float Time;
+uniform float Lifetime;
+uniform vec3 Emitter;
+
+const vec3 GRAVITY = vec3(0.0, -9.8, 0.0);
+
+// A float from 0 to 1 that's different for every particle and every frame
+float random(uint n)
+{
+    n = (n ^ 61u) ^ (n >> 16u);
+    n *= 9u;
+    n = n ^ (n >> 4u);
+    n *= 0x27d4eb2du;
+    n = n ^ (n >> 15u);
+    return float(n & 0xffffffu) / float(0x1000000);
+}
+
+void main()
+{
+    vec3 position = Position;
+    vec3 velocity = Velocity;
+    float age = Age + DeltaTime;
+
+    if (age >= Lifetime || (Age < 0.0 && age >= 0.0)) {
+        // Born (or born again): shoot up out of the emitter in a random direction within a cone
+        uint seed = uint(gl_VertexID) * 3u + uint(Time * 1000.0) * 7919u;
+        float angle = random(seed) * 6.2831853;
+        float spread = random(seed + 1u) * 0.35;
+        float speed = 7.0 + random(seed + 2u) * 3.0;
+        velocity = normalize(vec3(cos(angle) * spread, 1.0, sin(angle) * spread)) * speed;
+        position = Emitter;
+        age = mod(age, Lifetime);
+    } else if (age >= 0.0) {
+        velocity += GRAVITY * DeltaTime;
+        position += velocity * DeltaTime;
+        // Bounce off the floor, losing some speed each time
+        if (position.y < 0.0) {
+            position.y = -position.y;
+            velocity.y = -velocity.y * 0.5;
+            velocity.xz *= 0.8;
+        }
+    }
+
+    OutPosition = position;
+    OutVelocity = velocity;
+    OutAge = age;
+}
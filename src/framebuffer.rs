@@ -0,0 +1,272 @@
+// Framebuffers are somewhere to draw other than the window.  Drawing into textures instead lets a later pass read back
+// what was drawn: post-processing like tone mapping, precomputing lookup tables and cubemaps, shadow maps, and so on.
+//
+// Those later passes usually want to run a shader once per pixel of the whole target, which is what
+// `FullscreenTriangle` is for: one triangle big enough to cover the screen, clipped down to exactly the screen's
+// rectangle.  (One triangle rather than two, so that no pixels along the diagonal get shaded twice.)
+
+use std::ffi::CString;
+
+use crate::buffer::VertexArray;
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::texture::{Cubemap, Texture, TextureArray};
+
+/// What, if anything, a framebuffer uses for depth testing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum DepthAttachment {
+    None,
+    Renderbuffer,  // can be depth tested against, but not read by shaders
+    Texture,       // `Framebuffer::depth_texture()` can be sampled afterwards
+}
+
+pub struct Framebuffer {
+    id: gl::types::GLuint,
+    width: u32,
+    height: u32,
+    colors: Vec<Texture>,
+    depth_texture: Option<Texture>,
+    depth_renderbuffer: Option<gl::types::GLuint>,
+}
+
+#[allow(dead_code)]
+impl Framebuffer {
+    /// A framebuffer with one color texture per entry of `color_formats` (like `gl::RGBA16F`), which fragment shader
+    /// outputs 0, 1, 2, ... get written to
+    pub fn new(
+        width: u32,
+        height: u32,
+        color_formats: &[gl::types::GLenum],
+        depth: DepthAttachment,
+    ) -> Result<Framebuffer, String> {
+        let mut framebuffer = Framebuffer::empty();
+        framebuffer.width = width;
+        framebuffer.height = height;
+        framebuffer.colors = color_formats.iter().map(|&format| Texture::empty(width, height, format)).collect();
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.id);
+            for (i, texture) in framebuffer.colors.iter().enumerate() {
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0 + i as u32,
+                    gl::TEXTURE_2D,
+                    texture.id(),
+                    0,
+                );
+            }
+
+            // Without this, only the first color attachment would be drawn to.  With no color attachments at all
+            // (like a shadow map), GL needs telling that too or it says the framebuffer is incomplete
+            if framebuffer.colors.is_empty() {
+                gl::DrawBuffer(gl::NONE);
+                gl::ReadBuffer(gl::NONE);
+            } else {
+                let draw_buffers: Vec<gl::types::GLenum> =
+                    (0..framebuffer.colors.len() as u32).map(|i| gl::COLOR_ATTACHMENT0 + i).collect();
+                gl::DrawBuffers(draw_buffers.len() as gl::types::GLsizei, draw_buffers.as_ptr());
+            }
+
+            match depth {
+                DepthAttachment::None => {},
+                DepthAttachment::Renderbuffer => {
+                    let mut renderbuffer: gl::types::GLuint = 0;
+                    gl::GenRenderbuffers(1, &mut renderbuffer);
+                    gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+                    gl::RenderbufferStorage(
+                        gl::RENDERBUFFER,
+                        gl::DEPTH24_STENCIL8,
+                        width as gl::types::GLsizei,
+                        height as gl::types::GLsizei,
+                    );
+                    gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+                    gl::FramebufferRenderbuffer(
+                        gl::FRAMEBUFFER,
+                        gl::DEPTH_STENCIL_ATTACHMENT,
+                        gl::RENDERBUFFER,
+                        renderbuffer,
+                    );
+                    framebuffer.depth_renderbuffer = Some(renderbuffer);
+                },
+                DepthAttachment::Texture => {
+                    let texture = Texture::empty(width, height, gl::DEPTH_COMPONENT24);
+                    gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::TEXTURE_2D, texture.id(), 0);
+                    framebuffer.depth_texture = Some(texture);
+                },
+            }
+
+            let status = framebuffer.status();
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            status.map(|_| framebuffer)
+        }
+    }
+
+    /// A framebuffer with nothing attached, for drawing into parts of other textures (see `attach_cubemap_face()`)
+    pub fn empty() -> Framebuffer {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+        }
+        Framebuffer { id, width: 0, height: 0, colors: Vec::new(), depth_texture: None, depth_renderbuffer: None }
+    }
+
+    /// Makes mip level `level` of one face of `cubemap` the thing that gets drawn to, and sizes the framebuffer to
+    /// match.  Binds the framebuffer
+    pub fn attach_cubemap_face(&mut self, cubemap: &Cubemap, face: u32, level: u32) -> Result<(), String> {
+        self.width = (cubemap.size() >> level).max(1);
+        self.height = self.width;
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                cubemap.id(),
+                level as gl::types::GLint,
+            );
+        }
+        self.status()
+    }
+
+    /// Makes `texture` the thing that gets drawn to, and sizes the framebuffer to match.  Binds the framebuffer
+    pub fn attach_texture(&mut self, texture: &Texture) -> Result<(), String> {
+        self.width = texture.width();
+        self.height = texture.height();
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, texture.id(), 0);
+        }
+        self.status()
+    }
+
+    /// Makes layer `layer` of `array` the depth buffer, with nothing to draw color into, for rendering shadow maps.
+    /// Sizes the framebuffer to match, and binds it
+    pub fn attach_depth_layer(&mut self, array: &TextureArray, layer: u32) -> Result<(), String> {
+        self.width = array.width();
+        self.height = array.height();
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::FramebufferTextureLayer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                array.id(),
+                0,
+                layer as gl::types::GLint,
+            );
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+        }
+        self.status()
+    }
+
+    /// Makes one face of a depth `cubemap` the depth buffer, with nothing to draw color into, for rendering a point
+    /// light's shadows.  Sizes the framebuffer to match, and binds it
+    pub fn attach_depth_cubemap_face(&mut self, cubemap: &Cubemap, face: u32) -> Result<(), String> {
+        self.width = cubemap.size();
+        self.height = cubemap.size();
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                cubemap.id(),
+                0,
+            );
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+        }
+        self.status()
+    }
+
+    /// Expects the framebuffer to be bound
+    fn status(&self) -> Result<(), String> {
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        match status {
+            gl::FRAMEBUFFER_COMPLETE => Ok(()),
+            gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => Err("framebuffer has an attachment it can't use".to_string()),
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => Err("framebuffer has nothing attached".to_string()),
+            gl::FRAMEBUFFER_UNSUPPORTED => Err("framebuffer format isn't supported by this driver".to_string()),
+            _ => Err(format!("framebuffer is incomplete (status 0x{:X})", status)),
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Color texture number `index`, in the order the formats were given to `new()`
+    pub fn color(&self, index: usize) -> &Texture {
+        &self.colors[index]
+    }
+
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth_texture.as_ref()
+    }
+
+    /// Draws go here from now on.  Also sets the viewport to cover the whole framebuffer
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::Viewport(0, 0, self.width as gl::types::GLsizei, self.height as gl::types::GLsizei);
+        }
+    }
+
+    /// Goes back to drawing to the window, which is `width` by `height`
+    pub fn bind_default(width: u32, height: u32) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, width as gl::types::GLsizei, height as gl::types::GLsizei);
+        }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(renderbuffer) = self.depth_renderbuffer {
+                gl::DeleteRenderbuffers(1, &renderbuffer);
+            }
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+    }
+}
+
+/// Draws one triangle that covers the whole viewport.  There aren't any vertex buffers: `fullscreen.vert` works out
+/// the corners from `gl_VertexID`, but GL still insists on a VAO being bound to draw anything
+pub struct FullscreenTriangle {
+    vao: VertexArray,
+}
+
+#[allow(dead_code)]
+impl FullscreenTriangle {
+    pub fn new() -> FullscreenTriangle {
+        FullscreenTriangle { vao: VertexArray::new() }
+    }
+
+    pub fn draw(&self) {
+        self.vao.bind();
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        self.vao.unbind();
+    }
+}
+
+/// Builds a program for a full screen pass out of `fullscreen.vert` and the given fragment shader source, which gets a
+/// `TexCoord` going from 0 to 1 across the screen
+#[allow(dead_code)]
+pub fn fullscreen_program(fragment_source: &str) -> Result<Program, String> {
+    let vert_shader = Shader::from_vert_source(&CString::new(include_str!("fullscreen.vert")).unwrap())?;
+    let frag_shader = Shader::from_frag_source(&CString::new(fragment_source).map_err(|e| e.to_string())?)?;
+    Program::from_shaders(&[vert_shader, frag_shader])
+}
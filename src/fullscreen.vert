@@ -0,0 +1,16 @@
+#version 330 core
+
+// The corners of a triangle that covers the screen, worked out from the vertex number so there's no need for a vertex
+// buffer: (-1, -1), (3, -1) and (-1, 3).  Everything outside -1 to 1 gets clipped away.
+//
+// The depth is as far away as it can be, so that skyboxes drawn with this (and a depth test of "less or equal") only
+// show up where nothing else was drawn.  Passes that don't depth test don't care
+
+out vec2 TexCoord;
+
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    TexCoord = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 1.0, 1.0);
+}
@@ -0,0 +1,57 @@
+#version 330 core
+
+// Fills the G-buffer for `DeferredRenderer`, with lit.vert as the vertex shader.  The material parameters are the same
+// ones lit.frag has, so a lit material can be drawn either way without changing it.  Instead of lighting anything,
+// this just writes down everything the lighting passes will need to know about the surface
+
+in VS_OUTPUT {
+    vec3 WorldPosition;
+    vec3 Normal;
+    vec3 Tangent;
+    vec2 TexCoord;
+    vec3 CameraPosition;
+} IN;
+
+uniform vec3 DiffuseColor = vec3(0.8);
+uniform sampler2D DiffuseTexture;
+uniform vec3 SpecularColor = vec3(0.5);
+uniform sampler2D SpecularTexture;
+uniform float Shininess = 32.0;
+uniform sampler2D NormalTexture;
+uniform bool UseNormalTexture = false;
+uniform vec3 EmissiveColor = vec3(0.0);
+uniform vec2 TextureScale = vec2(1.0);
+
+// One output per color attachment of the G-buffer, in the order `DeferredRenderer` creates them
+layout (location = 0) out vec4 Albedo;          // rgb: diffuse color
+layout (location = 1) out vec4 Normal;          // xyz: world space normal, after normal mapping
+layout (location = 2) out vec4 MaterialParams;  // rgb: specular color, a: shininess (see `encode_shininess()`)
+layout (location = 3) out vec4 Emissive;        // rgb: emissive color
+
+// Shininess goes from 1 to a couple of thousand, which doesn't fit an 8 bit channel as it is.  Its logarithm does, and
+// keeps more precision at the low end, where a small change makes a bigger difference to the highlight
+float encode_shininess(float shininess)
+{
+    return clamp(log2(max(shininess, 1.0)) / 11.0, 0.0, 1.0);
+}
+
+vec3 surface_normal(vec2 uv)
+{
+    vec3 n = normalize(IN.Normal);
+    if (!UseNormalTexture) {
+        return n;
+    }
+    vec3 t = normalize(IN.Tangent - n * dot(n, IN.Tangent));
+    vec3 b = cross(n, t);
+    vec3 mapped = texture(NormalTexture, uv).xyz * 2.0 - 1.0;
+    return normalize(mat3(t, b, n) * mapped);
+}
+
+void main()
+{
+    vec2 uv = IN.TexCoord * TextureScale;
+    Albedo = vec4(DiffuseColor * texture(DiffuseTexture, uv).rgb, 1.0);
+    Normal = vec4(surface_normal(uv), 0.0);
+    MaterialParams = vec4(SpecularColor * texture(SpecularTexture, uv).rgb, encode_shininess(Shininess));
+    Emissive = vec4(EmissiveColor, 1.0);
+}
@@ -0,0 +1,292 @@
+use std::ffi::CString;
+
+use crate::buffer::{ArrayBuffer, VertexArray};
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::debug_draw::DebugDraw;
+use crate::math::{Mat4, Quat, Vec2, Vec3, Vec4};
+use crate::mesh::Mesh;
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{BlendState, CullMode, RenderState, RenderStateCache};
+use crate::shapes;
+use crate::transform_feedback::{self, TransformFeedback};
+use crate::vertex::VertexLayout;
+
+const PARTICLE_COUNT: usize = 20_000;
+const LIFETIME: f32 = 4.0;  // seconds
+
+/// One particle, as feedback_particles.vert reads it and records it
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,  // negative while it's waiting to be born
+}
+
+impl Particle {
+    /// Locations 0, 1 and 2, which only feedback_particles.vert and billboard.vert use
+    fn layout() -> VertexLayout {
+        let f = std::mem::size_of::<f32>();
+        VertexLayout::new(std::mem::size_of::<Particle>())
+            .float(0, 3, 0)
+            .float(1, 3, 3 * f)
+            .float(2, 1, 6 * f)
+    }
+}
+
+/// The particles live in two buffers, and each frame the update reads one and records into the other
+struct FeedbackParticles {
+    buffers: [ArrayBuffer; 2],
+    vaos: [VertexArray; 2],  // each reading from the buffer with the same index
+    current: usize,          // the buffer holding this frame's particles
+    update: Program,
+    draw: Program,
+    feedback: TransformFeedback,
+}
+
+impl FeedbackParticles {
+    fn new() -> Result<FeedbackParticles, String> {
+        let update_shader = Shader::from_vert_source(&CString::new(include_str!("feedback_particles.vert")).unwrap())?;
+        let update = Program::from_shaders_with_feedback(
+            &[update_shader],
+            &["OutPosition", "OutVelocity", "OutAge"],
+            gl::INTERLEAVED_ATTRIBS,
+        )?;
+        let draw = load_program(
+            include_str!("billboard.vert"),
+            Some(include_str!("billboard.geom")),
+            include_str!("billboard.frag"),
+        )?;
+        draw.check_draw_mode(gl::POINTS)?;
+
+        // Spread the births out over one lifetime, so they don't all come out at once and then all die together
+        let particles: Vec<Particle> = (0..PARTICLE_COUNT)
+            .map(|i| Particle { age: -LIFETIME * i as f32 / PARTICLE_COUNT as f32, ..Particle::default() })
+            .collect();
+        let mut buffers = [ArrayBuffer::new(), ArrayBuffer::new()];
+        let vaos = [VertexArray::new(), VertexArray::new()];
+        for (buffer, vao) in buffers.iter_mut().zip(vaos.iter()) {
+            vao.bind();
+            buffer.bind();
+            // Written by the GPU and read by the GPU, so neither of the `_DRAW` usages
+            buffer.buffer_data(&particles, gl::DYNAMIC_COPY);
+            Particle::layout().apply();
+            vao.unbind();
+            buffer.unbind();
+        }
+        Ok(FeedbackParticles { buffers, vaos, current: 0, update, draw, feedback: TransformFeedback::new() })
+    }
+
+    /// Runs the update over every particle, recording the results into the other buffer, which then becomes current
+    fn step(&mut self, dt: f32, time: f32, emitter: Vec3) {
+        let next = 1 - self.current;
+        self.update.set_used();
+        self.update.set_uniform_f32("DeltaTime", dt);
+        self.update.set_uniform_f32("Time", time);
+        self.update.set_uniform_f32("Lifetime", LIFETIME);
+        self.update.set_uniform_vec3("Emitter", emitter);
+        transform_feedback::bind_buffer(0, &self.buffers[next]);
+        let vao = &self.vaos[self.current];
+        self.feedback.capture(gl::POINTS, true, || {
+            vao.bind();
+            unsafe {
+                gl::DrawArrays(gl::POINTS, 0, PARTICLE_COUNT as gl::types::GLsizei);
+            }
+            vao.unbind();
+        });
+        unsafe {
+            gl::BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, 0, 0);
+        }
+        self.current = next;
+    }
+
+    fn render(&self, camera: &Camera) {
+        self.draw.set_used();
+        self.draw.set_uniform_mat4("View", &camera.view_matrix());
+        self.draw.set_uniform_mat4("Projection", &camera.projection_matrix());
+        self.draw.set_uniform_f32("Lifetime", LIFETIME);
+        self.draw.set_uniform_f32("ParticleSize", 0.25);
+        self.vaos[self.current].bind();
+        unsafe {
+            gl::DrawArrays(gl::POINTS, 0, PARTICLE_COUNT as gl::types::GLsizei);
+        }
+        self.vaos[self.current].unbind();
+    }
+}
+
+fn load_program(vert: &str, geom: Option<&str>, frag: &str) -> Result<Program, String> {
+    let mut shaders = vec![Shader::from_vert_source(&CString::new(vert).unwrap())?];
+    if let Some(geom) = geom {
+        shaders.push(Shader::from_geometry_source(&CString::new(geom).unwrap())?);
+    }
+    shaders.push(Shader::from_frag_source(&CString::new(frag).unwrap())?);
+    Program::from_shaders(&shaders)
+}
+
+/// Geometry shaders and transform feedback, in two scenes.  The first outlines a shape's silhouette: the shape is drawn
+/// a second time with adjacency, so a geometry shader can see each triangle's neighbours and draw the edges where one
+/// faces the camera and the other faces away.  The second is a fountain of 20,000 particles updated entirely on the
+/// GPU by a vertex shader whose outputs are recorded by transform feedback, then drawn as points that a geometry
+/// shader turns into camera-facing squares.  Before anything's drawn, the silhouette program is checked against a
+/// plain triangle draw, to show what `Program::check_draw_mode()` says about it.
+///
+/// Tab switches scenes.  In the silhouette scene, N picks the next shape and Up and Down change the edge width.
+/// Dragging orbits the camera
+pub fn geometry_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+
+    let setup = || -> Result<(Program, Program, FeedbackParticles, DebugDraw), String> {
+        let shaded = load_program(include_str!("mesh.vert"), None, include_str!("mesh.frag"))?;
+        let silhouette = load_program(
+            include_str!("silhouette.vert"),
+            Some(include_str!("silhouette.geom")),
+            include_str!("silhouette.frag"),
+        )?;
+        Ok((shaded, silhouette, FeedbackParticles::new()?, DebugDraw::new()?))
+    };
+    let (shaded, silhouette, mut particles, mut debug) = match setup() {
+        Ok(setup) => setup,
+        Err(e) => {
+            println!("Failed to set up geometry shaders: {}", e);
+            return;
+        },
+    };
+    match silhouette.check_draw_mode(gl::TRIANGLES) {
+        Ok(()) => println!("The silhouette program somehow takes plain triangles"),
+        Err(e) => println!("As expected, the silhouette program can't draw plain triangles: {}", e),
+    }
+
+    // Each shape twice: once to draw normally, and once with adjacency to find its silhouette
+    let shapes: Vec<(&str, Mesh, Mesh)> = vec![
+        ("torus", shapes::torus(1.2, 0.5, 48, 24)),
+        ("icosphere", shapes::icosphere(1.4, 2)),
+        ("cube", shapes::cube(2.0, 1)),
+        ("cone", shapes::cone(1.2, 2.4, 32, 1)),
+    ].into_iter().map(|(name, data)| (name, data.upload(), data.upload_with_adjacency())).collect();
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 3.0, 8.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::new(0.0, 0.5, 0.0), 8.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    // The edge quads wind whichever way the edge happens to go, so nothing can be culled
+    let edge_state = RenderState { cull_mode: CullMode::None, ..RenderState::opaque() };
+    let particle_state = RenderState { blend: BlendState::additive(), ..RenderState::transparent() };
+    let mut showing_particles = false;
+    let mut shape = 0;
+    let mut edge_width = 3.0f32;
+    let mut time = 0.0f32;
+    let mut since_title = 1.0f32;
+    let frequency = timer.performance_frequency() as f64;
+    let mut last_counter = timer.performance_counter();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Tab), .. } => {
+                    showing_particles = !showing_particles;
+                    since_title = 1.0;
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::N), .. } => {
+                    shape = (shape + 1) % shapes.len();
+                    since_title = 1.0;
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
+                    edge_width = (edge_width + 1.0).min(12.0);
+                    since_title = 1.0;
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
+                    edge_width = (edge_width - 1.0).max(1.0);
+                    since_title = 1.0;
+                },
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let counter = timer.performance_counter();
+        // Capped, so a long stall doesn't fling every particle through the floor
+        let dt = (((counter - last_counter) as f64 / frequency) as f32).min(0.05);
+        last_counter = counter;
+        time += dt;
+        since_title += dt;
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        if showing_particles {
+            // Updated even while it's not showing would be more realistic, but this way it's easier to see it start
+            particles.step(dt, time, Vec3::ZERO);
+            debug.grid(Vec3::ZERO, 20, 1.0, Vec4::new(0.4, 0.4, 0.45, 1.0));
+            debug.render(&camera.view_projection_matrix(), dt, &mut render_state_cache);
+            render_state_cache.apply(&particle_state);
+            particles.render(&camera);
+            if since_title >= 0.5 {
+                // Asking waits for the GPU to catch up, so only every so often
+                let recorded = particles.feedback.primitives_written();
+                window.set_title(&format!("Transform feedback: {} particles updated on the GPU", recorded)).unwrap();
+                since_title = 0.0;
+            }
+        } else {
+            let (name, mesh, adjacency) = &shapes[shape];
+            let model = Mat4::from_trs(Vec3::new(0.0, 0.5, 0.0),
+                                       Quat::from_axis_angle(Vec3::new(0.3, 1.0, 0.2).normalize(), time * 0.3),
+                                       Vec3::ONE);
+            render_state_cache.apply(&RenderState::opaque());
+            shaded.set_used();
+            shaded.set_uniform_mat4("Model", &model);
+            shaded.set_uniform_mat4("View", &camera.view_matrix());
+            shaded.set_uniform_mat4("Projection", &camera.projection_matrix());
+            shaded.set_uniform_vec3("DiffuseColor", Vec3::new(0.35, 0.55, 0.8));
+            mesh.draw();
+
+            let (width, height) = window.size();
+            render_state_cache.apply(&edge_state);
+            silhouette.set_used();
+            silhouette.set_uniform_mat4("Model", &model);
+            silhouette.set_uniform_mat4("View", &camera.view_matrix());
+            silhouette.set_uniform_mat4("Projection", &camera.projection_matrix());
+            silhouette.set_uniform_vec3("CameraPosition", camera.position);
+            silhouette.set_uniform_vec2("ViewportSize", Vec2::new(width as f32, height as f32));
+            silhouette.set_uniform_f32("EdgeWidth", edge_width);
+            silhouette.set_uniform_vec3("EdgeColor", Vec3::new(1.0, 0.85, 0.3));
+            adjacency.draw();
+
+            if since_title >= 0.5 {
+                window.set_title(&format!("Silhouette of a {} ({} triangles), edges {} pixels wide", name,
+                                          mesh.count() / 3, edge_width)).unwrap();
+                since_title = 0.0;
+            }
+        }
+
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,65 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec3 WorldPosition;
+    vec3 Normal;
+    vec3 Tangent;
+    vec2 TexCoord;
+    vec4 Color;
+} IN;
+
+// glTF's metallic-roughness material.  Every texture is always bound (to a plain white or flat normal texture if the
+// material doesn't have one), so there's no need for "has texture" flags
+uniform vec4 BaseColorFactor;
+uniform float MetallicFactor;
+uniform float RoughnessFactor;
+uniform float NormalScale;
+uniform float OcclusionStrength;
+uniform vec3 EmissiveFactor;
+uniform float AlphaCutoff;  // negative unless the material uses alpha masking
+
+uniform sampler2D BaseColorTexture;
+uniform sampler2D MetallicRoughnessTexture;
+uniform sampler2D NormalTexture;
+uniform sampler2D OcclusionTexture;
+uniform sampler2D EmissiveTexture;
+
+uniform vec3 CameraPosition;
+
+out vec4 Color;
+
+void main()
+{
+    vec4 base_color = BaseColorFactor * IN.Color * texture(BaseColorTexture, IN.TexCoord);
+    if (base_color.a < AlphaCutoff) {
+        discard;
+    }
+
+    // Normal map, in tangent space
+    vec3 n = normalize(IN.Normal);
+    vec3 t = normalize(IN.Tangent - n * dot(n, IN.Tangent));
+    vec3 mapped = texture(NormalTexture, IN.TexCoord).xyz * 2.0 - 1.0;
+    mapped.xy *= NormalScale;
+    if (length(IN.Tangent) > 0.0) {
+        n = normalize(mat3(t, cross(n, t), n) * mapped);
+    }
+
+    vec2 metallic_roughness = texture(MetallicRoughnessTexture, IN.TexCoord).bg;
+    float metallic = MetallicFactor * metallic_roughness.x;
+    float roughness = RoughnessFactor * metallic_roughness.y;
+    float occlusion = mix(1.0, texture(OcclusionTexture, IN.TexCoord).r, OcclusionStrength);
+
+    // Simple shading from a fixed light: diffuse, plus a highlight that gets tighter as the surface gets smoother.
+    // Metals have no diffuse, and tint their highlight with the base color instead
+    vec3 light_direction = normalize(vec3(0.4, 1.0, 0.7));
+    float diffuse = 0.3 + 0.7 * max(dot(n, light_direction), 0.0);
+    vec3 view_direction = normalize(CameraPosition - IN.WorldPosition);
+    vec3 half_vector = normalize(light_direction + view_direction);
+    float shininess = mix(256.0, 4.0, roughness);
+    float highlight = pow(max(dot(n, half_vector), 0.0), shininess) * (1.0 - roughness);
+    vec3 specular_color = mix(vec3(0.04), base_color.rgb, metallic);
+
+    vec3 color = base_color.rgb * (1.0 - metallic) * diffuse * occlusion + specular_color * highlight;
+    color += EmissiveFactor * texture(EmissiveTexture, IN.TexCoord).rgb;
+    Color = vec4(color, base_color.a);
+}
@@ -0,0 +1,1552 @@
+// Loader for glTF 2.0 scenes, in both flavours: `.gltf` (JSON, with the binary data either in separate `.bin` files or
+// base64-encoded right inside the JSON) and `.glb` (the same JSON plus the binary data packed into a single file).
+//
+// glTF is layered, and it helps to know the layers when reading this:
+//
+//     buffers       raw bytes
+//     bufferViews   slices of a buffer (with an optional stride between elements)
+//     accessors     typed arrays inside a bufferView, like "240 VEC3s of floats"
+//     meshes        lists of primitives, whose vertex attributes and indices are accessors
+//     nodes         a tree of transforms, each optionally holding a mesh, camera, light or skin
+//
+// Everything refers to everything else by index into those top-level arrays, so that's also how the types below refer
+// to each other (e.g. `GltfNode::mesh` is an index into `GltfModel::meshes`).
+
+use crate::image::Image;
+use crate::json::{self, JsonValue};
+use crate::math::{Mat4, Quat, Vec2, Vec3, Vec4};
+use crate::mesh::{Mesh, MeshData};
+use crate::program::Program;
+use crate::render_state::{CullMode, RenderState};
+use crate::resources::{self, Resources};
+use crate::texture::Texture;
+use crate::vertex::{attrib, Vertex, VertexLayout};
+
+/// Most bones the skinning shader supports for one mesh.  Needs to match `MAX_JOINTS` in `gltf.vert`
+pub const MAX_JOINTS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    Mask,   // fully transparent below `alpha_cutoff`, fully opaque above it
+    Blend,
+}
+
+/// Which texture to use, and which set of texture coordinates to read it with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureRef {
+    pub texture: usize,
+    pub tex_coord: usize,  // we only import TEXCOORD_0, so anything else falls back to that
+}
+
+/// A metallic-roughness PBR material.  The factors get multiplied by the matching texture, if there is one
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color_factor: Vec4,
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<TextureRef>,  // roughness in green, metallic in blue
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,           // in red
+    pub occlusion_strength: f32,
+    pub emissive_texture: Option<TextureRef>,
+    pub emissive_factor: Vec3,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+impl Default for GltfMaterial {
+    /// What the spec says to use for primitives without a material
+    fn default() -> GltfMaterial {
+        GltfMaterial {
+            name: String::new(),
+            base_color_factor: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive_texture: None,
+            emissive_factor: Vec3::ZERO,
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl GltfMaterial {
+    /// Render state this material wants: no culling for double-sided materials, and blending for transparent ones
+    pub fn render_state(&self) -> RenderState {
+        let mut state = match self.alpha_mode {
+            AlphaMode::Blend => RenderState::transparent(),
+            _ => RenderState::opaque(),
+        };
+        if self.double_sided {
+            state.cull_mode = CullMode::None;
+        }
+        state
+    }
+}
+
+/// Filtering and wrapping for a texture.  glTF uses GL's own enum values for these, so they can go straight to GL
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GltfSampler {
+    pub mag_filter: gl::types::GLenum,
+    pub min_filter: gl::types::GLenum,
+    pub wrap_s: gl::types::GLenum,
+    pub wrap_t: gl::types::GLenum,
+}
+
+impl Default for GltfSampler {
+    fn default() -> GltfSampler {
+        GltfSampler {
+            mag_filter: gl::LINEAR,
+            min_filter: gl::LINEAR_MIPMAP_LINEAR,
+            wrap_s: gl::REPEAT,
+            wrap_t: gl::REPEAT,
+        }
+    }
+}
+
+/// An image plus the sampler to read it with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GltfTexture {
+    pub image: Option<usize>,
+    pub sampler: GltfSampler,
+}
+
+/// One draw call's worth of a mesh
+#[derive(Debug, Clone)]
+pub struct GltfPrimitive {
+    pub data: MeshData,
+    pub colors: Vec<Vec4>,         // COLOR_0, empty if the primitive doesn't have vertex colors
+    pub joints: Vec<[f32; 4]>,     // JOINTS_0, indices into the skin's joint list.  Empty unless skinned
+    pub weights: Vec<[f32; 4]>,    // WEIGHTS_0
+    pub material: Option<usize>,
+    pub mode: gl::types::GLenum,   // `gl::TRIANGLES`, `gl::LINES`, ...
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GltfMesh {
+    pub name: String,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GltfCamera {
+    Perspective { aspect_ratio: Option<f32>, y_fov: f32, z_near: f32, z_far: Option<f32> },
+    Orthographic { x_mag: f32, y_mag: f32, z_near: f32, z_far: f32 },
+}
+
+#[allow(dead_code)]
+impl GltfCamera {
+    /// `aspect` is the window's, which is used unless the camera insists on its own
+    pub fn projection_matrix(&self, aspect: f32) -> Mat4 {
+        match *self {
+            GltfCamera::Perspective { aspect_ratio, y_fov, z_near, z_far: Some(z_far) } => {
+                Mat4::perspective(y_fov, aspect_ratio.unwrap_or(aspect), z_near, z_far)
+            },
+            GltfCamera::Perspective { aspect_ratio, y_fov, z_near, z_far: None } => {
+                // No far plane means an infinite projection, which is the limit of `perspective()` as far goes to
+                // infinity
+                let mut m = Mat4::perspective(y_fov, aspect_ratio.unwrap_or(aspect), z_near, z_near * 2.0);
+                m.cols[2][2] = -1.0;
+                m.cols[3][2] = -2.0 * z_near;
+                m
+            },
+            GltfCamera::Orthographic { x_mag, y_mag, z_near, z_far } => {
+                Mat4::orthographic(-x_mag, x_mag, -y_mag, y_mag, z_near, z_far)
+            },
+        }
+    }
+}
+
+/// A light from the `KHR_lights_punctual` extension.  Lights shine down their node's -Z axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot { inner_cone_angle: f32, outer_cone_angle: f32 },
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GltfLight {
+    pub name: String,
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,      // candela for point and spot lights, lux for directional ones
+    pub range: Option<f32>,  // `None` means the light never fully fades out
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GltfNode {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub skin: Option<usize>,
+    pub camera: Option<usize>,
+    pub light: Option<usize>,
+    // A node has either a matrix or translation/rotation/scale.  Animations can only target the latter, so nodes with a
+    // matrix are stuck where they are
+    pub matrix: Option<Mat4>,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+#[allow(dead_code)]
+impl GltfNode {
+    /// Transform relative to the parent node
+    pub fn local_transform(&self) -> Mat4 {
+        self.matrix.unwrap_or_else(|| Mat4::from_trs(self.translation, self.rotation, self.scale))
+    }
+}
+
+/// Bones for skinning.  `joints` are node indices, and the inverse bind matrices take a vertex from the mesh's space
+/// into each joint's space, as it was when the mesh was bound to the skeleton
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GltfSkin {
+    pub name: String,
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Vec<Mat4>,
+    pub skeleton: Option<usize>,
+}
+
+#[allow(dead_code)]
+impl GltfSkin {
+    /// One matrix per joint, taking a vertex from the mesh's space to where the joint has moved it, still in the
+    /// mesh's space.  `world_transforms` comes from `GltfModel::world_transforms()`, and `mesh_node` is the node that
+    /// this skin is being drawn for
+    pub fn joint_matrices(&self, world_transforms: &[Mat4], mesh_node: usize) -> Vec<Mat4> {
+        let to_mesh_space = world_transforms[mesh_node].inverse().unwrap_or_else(Mat4::identity);
+        self.joints.iter().enumerate()
+            .map(|(i, &joint)| {
+                let inverse_bind = self.inverse_bind_matrices.get(i).cloned().unwrap_or_else(Mat4::identity);
+                to_mesh_space * world_transforms[joint] * inverse_bind
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationPath {
+    Translation,
+    Rotation,
+    Scale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    CubicSpline,  // each keyframe has an in-tangent, a value and an out-tangent
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationSampler {
+    pub times: Vec<f32>,    // in seconds, increasing
+    pub values: Vec<f32>,   // 3 or 4 floats per keyframe (times 3 for cubic splines)
+    pub interpolation: Interpolation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationChannel {
+    pub node: usize,
+    pub path: AnimationPath,
+    pub sampler: usize,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GltfAnimation {
+    pub name: String,
+    pub channels: Vec<AnimationChannel>,
+    pub samplers: Vec<AnimationSampler>,
+    pub duration: f32,
+}
+
+#[allow(dead_code)]
+impl GltfAnimation {
+    /// Poses the nodes as they are `time` seconds into the animation.  Times past the end hold the last keyframe, so
+    /// use `time % duration` to loop
+    pub fn apply(&self, nodes: &mut [GltfNode], time: f32) {
+        for channel in &self.channels {
+            let sampler = &self.samplers[channel.sampler];
+            let node = &mut nodes[channel.node];
+            match channel.path {
+                AnimationPath::Translation => {
+                    let v = sampler.sample(time, 3);
+                    node.translation = Vec3::new(v[0], v[1], v[2]);
+                },
+                AnimationPath::Scale => {
+                    let v = sampler.sample(time, 3);
+                    node.scale = Vec3::new(v[0], v[1], v[2]);
+                },
+                AnimationPath::Rotation => {
+                    let v = sampler.sample(time, 4);
+                    node.rotation = Quat::new(v[0], v[1], v[2], v[3]).normalize();
+                },
+            }
+        }
+    }
+}
+
+impl AnimationSampler {
+    fn keyframe(&self, index: usize, components: usize) -> &[f32] {
+        // Cubic splines store (in-tangent, value, out-tangent) for every keyframe, so the value is the middle one
+        let (stride, skip) = match self.interpolation {
+            Interpolation::CubicSpline => (3 * components, components),
+            _ => (components, 0),
+        };
+        &self.values[index * stride + skip..index * stride + skip + components]
+    }
+
+    fn sample(&self, time: f32, components: usize) -> Vec<f32> {
+        let last = self.times.len() - 1;
+        if time <= self.times[0] {
+            return self.keyframe(0, components).to_vec();
+        }
+        if time >= self.times[last] {
+            return self.keyframe(last, components).to_vec();
+        }
+
+        // Find the keyframes on either side of `time`.  A NaN time (or keyframe times that aren't in order) might not
+        // find one, so hold the last keyframe rather than panicking mid-animation
+        let next = match self.times.iter().position(|&t| t > time) {
+            Some(next) if next > 0 => next,
+            _ => return self.keyframe(last, components).to_vec(),
+        };
+        let previous = next - 1;
+        let dt = self.times[next] - self.times[previous];
+        let t = (time - self.times[previous]) / dt;
+        let (a, b) = (self.keyframe(previous, components), self.keyframe(next, components));
+
+        match self.interpolation {
+            Interpolation::Step => a.to_vec(),
+            Interpolation::Linear if components == 4 => {
+                let q = Quat::new(a[0], a[1], a[2], a[3]).slerp(Quat::new(b[0], b[1], b[2], b[3]), t);
+                vec![q.x, q.y, q.z, q.w]
+            },
+            Interpolation::Linear => a.iter().zip(b).map(|(a, b)| a + (b - a) * t).collect(),
+            Interpolation::CubicSpline => {
+                // Hermite spline between the two values, using the out-tangent of the first keyframe and the
+                // in-tangent of the second.  The tangents are per second, hence scaling them by `dt`
+                let stride = 3 * components;
+                let out_tangent = &self.values[previous * stride + 2 * components..previous * stride + stride];
+                let in_tangent = &self.values[next * stride..next * stride + components];
+                let (t2, t3) = (t * t, t * t * t);
+                (0..components)
+                    .map(|i| {
+                        (2.0 * t3 - 3.0 * t2 + 1.0) * a[i]
+                            + (t3 - 2.0 * t2 + t) * dt * out_tangent[i]
+                            + (-2.0 * t3 + 3.0 * t2) * b[i]
+                            + (t3 - t2) * dt * in_tangent[i]
+                    })
+                    .collect()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfModel {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub textures: Vec<GltfTexture>,
+    pub images: Vec<Option<Image>>,  // `None` for images we couldn't decode; see `warnings`
+    pub nodes: Vec<GltfNode>,
+    pub scenes: Vec<Vec<usize>>,     // root nodes of each scene
+    pub scene: Option<usize>,        // which scene to show by default
+    pub cameras: Vec<GltfCamera>,
+    pub lights: Vec<GltfLight>,
+    pub skins: Vec<GltfSkin>,
+    pub animations: Vec<GltfAnimation>,
+    // Things that didn't stop the model from loading but probably mean it won't look quite right, like a texture in a
+    // format we can't decode
+    pub warnings: Vec<String>,
+}
+
+/// Loads a `.gltf` or `.glb` file, plus any buffers and images it refers to
+#[allow(dead_code)]
+pub fn load(res: &Resources, resource_name: &str) -> Result<GltfModel, String> {
+    let bytes = res.load_buffer(resource_name).map_err(|e| format!("{}: {}", resource_name, e))?;
+    let load_external = |uri: &str| {
+        let name = resources::relative_to(resource_name, &percent_decode(uri));
+        res.load_buffer(&name).map_err(|e| format!("{}: {}", name, e))
+    };
+
+    if bytes.starts_with(b"glTF") {
+        let (json_source, bin) = split_glb(&bytes).map_err(|e| format!("{}: {}", resource_name, e))?;
+        parse_gltf(&json_source, bin, resource_name, load_external)
+    } else {
+        let json_source = String::from_utf8(bytes).map_err(|_| format!("{}: file is not valid UTF-8", resource_name))?;
+        parse_gltf(&json_source, None, resource_name, load_external)
+    }
+}
+
+/// Pulls the JSON and binary chunks out of a `.glb` file.  The layout is a 12 byte header (magic, version, length)
+/// followed by chunks of (length, type, data)
+pub fn split_glb(bytes: &[u8]) -> Result<(String, Option<Vec<u8>>), String> {
+    let read_u32 = |offset: usize| -> Result<u32, String> {
+        bytes.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| "GLB file is truncated".to_string())
+    };
+    if read_u32(4)? != 2 {
+        return Err(format!("unsupported GLB version {}", read_u32(4)?));
+    }
+    let total_length = (read_u32(8)? as usize).min(bytes.len());
+
+    let mut json_source = None;
+    let mut bin = None;
+    let mut pos = 12;
+    while pos + 8 <= total_length {
+        let length = read_u32(pos)? as usize;
+        let kind = read_u32(pos + 4)?;
+        let data = bytes.get(pos + 8..pos + 8 + length).ok_or("GLB chunk runs past the end of the file")?;
+        match kind {
+            0x4E4F_534A => {  // "JSON"
+                json_source = Some(String::from_utf8(data.to_vec()).map_err(|_| "GLB JSON chunk is not valid UTF-8")?)
+            },
+            0x004E_4942 => bin = Some(data.to_vec()),  // "BIN\0"
+            _ => {},  // unknown chunks are meant to be skipped
+        }
+        pos += 8 + length;
+    }
+
+    Ok((json_source.ok_or("GLB file has no JSON chunk")?, bin))
+}
+
+/// Does the actual parsing.  `bin` is the binary chunk of a `.glb` file, and `load_external` gets called with the URI
+/// of every buffer or image that lives in its own file (this is split out so the parsing doesn't depend on where
+/// files come from)
+pub fn parse_gltf<F>(json_source: &str, bin: Option<Vec<u8>>, name: &str, mut load_external: F)
+    -> Result<GltfModel, String>
+    where F: FnMut(&str) -> Result<Vec<u8>, String>
+{
+    let doc = json::parse(json_source).map_err(|e| format!("{}:{}", name, e))?;
+    let version = doc.field("asset").field("version").as_str().unwrap_or("");
+    if !version.starts_with("2.") {
+        return Err(format!("{}: only glTF 2.x is supported, but the file says version '{}'", name, version));
+    }
+
+    let error = |message: String| format!("{}: {}", name, message);
+
+    let mut bin = bin;
+    let mut buffers = Vec::new();
+    for (i, buffer) in doc.field("buffers").items().iter().enumerate() {
+        let data = match buffer.field("uri").as_str() {
+            Some(uri) => load_uri(uri, &mut load_external).map_err(|e| error(format!("buffer {}: {}", i, e)))?,
+            // Only the first buffer of a `.glb` is allowed to leave out the URI, and it means the binary chunk
+            None if i == 0 => bin.take()
+                .ok_or_else(|| error("buffer 0 has no URI and there's no GLB binary chunk".to_string()))?,
+            None => return Err(error(format!("buffer {} has no URI", i))),
+        };
+        let expected = buffer.field("byteLength").as_usize().unwrap_or(0);
+        if data.len() < expected {
+            return Err(error(format!("buffer {} is {} bytes, but should be {}", i, data.len(), expected)));
+        }
+        buffers.push(data);
+    }
+    let reader = AccessorReader { doc: &doc, buffers };
+
+    let mut model = GltfModel {
+        meshes: Vec::new(),
+        materials: Vec::new(),
+        textures: Vec::new(),
+        images: Vec::new(),
+        nodes: Vec::new(),
+        scenes: Vec::new(),
+        scene: doc.field("scene").as_usize(),
+        cameras: Vec::new(),
+        lights: Vec::new(),
+        skins: Vec::new(),
+        animations: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    for (i, image) in doc.field("images").items().iter().enumerate() {
+        let bytes = match (image.field("uri").as_str(), image.field("bufferView").as_usize()) {
+            (Some(uri), _) => load_uri(uri, &mut load_external),
+            (None, Some(view)) => reader.buffer_view(view).map(|bytes| bytes.to_vec()),
+            (None, None) => Err("has neither a URI nor a buffer view".to_string()),
+        };
+        match bytes.and_then(|bytes| Image::decode(&bytes)) {
+            Ok(decoded) => model.images.push(Some(decoded)),
+            Err(e) => {
+                model.warnings.push(format!("image {}: {}", i, e));
+                model.images.push(None);
+            },
+        }
+    }
+
+    let samplers: Vec<GltfSampler> = doc.field("samplers").items().iter()
+        .map(|sampler| {
+            let default = GltfSampler::default();
+            let get = |key: &str, default: gl::types::GLenum| {
+                sampler.field(key).as_usize().map(|v| v as gl::types::GLenum).unwrap_or(default)
+            };
+            GltfSampler {
+                mag_filter: get("magFilter", default.mag_filter),
+                min_filter: get("minFilter", default.min_filter),
+                wrap_s: get("wrapS", default.wrap_s),
+                wrap_t: get("wrapT", default.wrap_t),
+            }
+        })
+        .collect();
+    for texture in doc.field("textures").items() {
+        model.textures.push(GltfTexture {
+            image: texture.field("source").as_usize(),
+            sampler: texture.field("sampler").as_usize().and_then(|s| samplers.get(s).cloned()).unwrap_or_default(),
+        });
+    }
+
+    for material in doc.field("materials").items() {
+        model.materials.push(parse_material(material));
+    }
+
+    for (i, mesh) in doc.field("meshes").items().iter().enumerate() {
+        let mut primitives = Vec::new();
+        for (j, primitive) in mesh.field("primitives").items().iter().enumerate() {
+            let primitive = parse_primitive(&reader, primitive, &model.materials)
+                .map_err(|e| error(format!("mesh {} primitive {}: {}", i, j, e)))?;
+            primitives.push(primitive);
+        }
+        model.meshes.push(GltfMesh { name: string_field(mesh, "name"), primitives });
+    }
+
+    for camera in doc.field("cameras").items() {
+        let camera = match camera.field("type").as_str() {
+            Some("orthographic") => {
+                let o = camera.field("orthographic");
+                GltfCamera::Orthographic {
+                    x_mag: o.field("xmag").as_f32().unwrap_or(1.0),
+                    y_mag: o.field("ymag").as_f32().unwrap_or(1.0),
+                    z_near: o.field("znear").as_f32().unwrap_or(0.0),
+                    z_far: o.field("zfar").as_f32().unwrap_or(100.0),
+                }
+            },
+            _ => {
+                let p = camera.field("perspective");
+                GltfCamera::Perspective {
+                    aspect_ratio: p.field("aspectRatio").as_f32(),
+                    y_fov: p.field("yfov").as_f32().unwrap_or(0.8),
+                    z_near: p.field("znear").as_f32().unwrap_or(0.1),
+                    z_far: p.field("zfar").as_f32(),
+                }
+            },
+        };
+        model.cameras.push(camera);
+    }
+
+    for light in doc.field("extensions").field("KHR_lights_punctual").field("lights").items() {
+        let kind = match light.field("type").as_str() {
+            Some("directional") => LightKind::Directional,
+            Some("spot") => {
+                let spot = light.field("spot");
+                LightKind::Spot {
+                    inner_cone_angle: spot.field("innerConeAngle").as_f32().unwrap_or(0.0),
+                    outer_cone_angle: spot.field("outerConeAngle").as_f32().unwrap_or(std::f32::consts::FRAC_PI_4),
+                }
+            },
+            _ => LightKind::Point,
+        };
+        model.lights.push(GltfLight {
+            name: string_field(light, "name"),
+            kind,
+            color: vec3_field(light, "color", Vec3::ONE),
+            intensity: light.field("intensity").as_f32().unwrap_or(1.0),
+            range: light.field("range").as_f32(),
+        });
+    }
+
+    for node in doc.field("nodes").items() {
+        let rotation = node.field("rotation").as_f32_vec()
+            .filter(|r| r.len() == 4)
+            .map(|r| Quat::new(r[0], r[1], r[2], r[3]))
+            .unwrap_or(Quat::IDENTITY);
+        let matrix = node.field("matrix").as_f32_vec()
+            .filter(|m| m.len() == 16)
+            .map(|m| Mat4 { cols: [
+                [m[0], m[1], m[2], m[3]],
+                [m[4], m[5], m[6], m[7]],
+                [m[8], m[9], m[10], m[11]],
+                [m[12], m[13], m[14], m[15]],
+            ] });
+        model.nodes.push(GltfNode {
+            name: string_field(node, "name"),
+            parent: None,
+            children: node.field("children").items().iter().filter_map(JsonValue::as_usize).collect(),
+            mesh: node.field("mesh").as_usize(),
+            skin: node.field("skin").as_usize(),
+            camera: node.field("camera").as_usize(),
+            light: node.field("extensions").field("KHR_lights_punctual").field("light").as_usize(),
+            matrix,
+            translation: vec3_field(node, "translation", Vec3::ZERO),
+            rotation,
+            scale: vec3_field(node, "scale", Vec3::ONE),
+        });
+    }
+    for scene in doc.field("scenes").items() {
+        model.scenes.push(scene.field("nodes").items().iter().filter_map(JsonValue::as_usize).collect());
+    }
+    check_node_references(&mut model).map_err(error)?;
+
+    for (i, skin) in doc.field("skins").items().iter().enumerate() {
+        let inverse_bind_matrices = match skin.field("inverseBindMatrices").as_usize() {
+            Some(accessor) => {
+                let (components, values) = reader.read_f32(accessor).map_err(|e| error(format!("skin {}: {}", i, e)))?;
+                if components != 16 {
+                    return Err(error(format!("skin {}: inverse bind matrices must be MAT4s", i)));
+                }
+                values.chunks_exact(16)
+                    .map(|m| Mat4 { cols: [
+                        [m[0], m[1], m[2], m[3]],
+                        [m[4], m[5], m[6], m[7]],
+                        [m[8], m[9], m[10], m[11]],
+                        [m[12], m[13], m[14], m[15]],
+                    ] })
+                    .collect()
+            },
+            None => Vec::new(),  // all identity
+        };
+        let joints: Vec<usize> = skin.field("joints").items().iter().filter_map(JsonValue::as_usize).collect();
+        if joints.iter().any(|&j| j >= model.nodes.len()) {
+            return Err(error(format!("skin {} refers to a node that doesn't exist", i)));
+        }
+        let skeleton = skin.field("skeleton").as_usize();
+        if skeleton.is_some_and(|s| s >= model.nodes.len()) {
+            return Err(error(format!("skin {} has a skeleton root that doesn't exist", i)));
+        }
+        if joints.len() > MAX_JOINTS {
+            let warning = format!("skin {} has {} joints, but only {} are supported", i, joints.len(), MAX_JOINTS);
+            model.warnings.push(warning);
+        }
+        model.skins.push(GltfSkin {
+            name: string_field(skin, "name"),
+            joints,
+            inverse_bind_matrices,
+            skeleton,
+        });
+    }
+    for (i, node) in model.nodes.iter().enumerate() {
+        if node.skin.is_some_and(|s| s >= model.skins.len()) {
+            return Err(error(format!("node {} uses skin {}, which doesn't exist", i, node.skin.unwrap())));
+        }
+    }
+
+    for (i, animation) in doc.field("animations").items().iter().enumerate() {
+        let animation = parse_animation(&reader, animation, model.nodes.len())
+            .map_err(|e| error(format!("animation {}: {}", i, e)))?;
+        model.animations.push(animation);
+    }
+
+    Ok(model)
+}
+
+/// Fills in every node's parent, and makes sure the hierarchy really is a tree (no node with two parents, no loops)
+fn check_node_references(model: &mut GltfModel) -> Result<(), String> {
+    let node_count = model.nodes.len();
+    for parent in 0..node_count {
+        for child in model.nodes[parent].children.clone() {
+            if child >= node_count {
+                return Err(format!("node {} has a child {} that doesn't exist", parent, child));
+            }
+            if child == parent {
+                return Err(format!("node {} is its own child", child));
+            }
+            if model.nodes[child].parent.is_some() {
+                return Err(format!("node {} has more than one parent", child));
+            }
+            model.nodes[child].parent = Some(parent);
+        }
+    }
+    for (i, node) in model.nodes.iter().enumerate() {
+        // Every node's chain of parents has to reach a root within `node_count` steps, or there's a loop
+        let mut current = node.parent;
+        let mut steps = 0;
+        while let Some(parent) = current {
+            steps += 1;
+            if steps > node_count {
+                return Err(format!("node {} is part of a loop in the node hierarchy", i));
+            }
+            current = model.nodes[parent].parent;
+        }
+        if node.mesh.is_some_and(|m| m >= model.meshes.len()) {
+            return Err(format!("node {} uses mesh {}, which doesn't exist", i, node.mesh.unwrap()));
+        }
+        if node.camera.is_some_and(|c| c >= model.cameras.len()) {
+            return Err(format!("node {} uses camera {}, which doesn't exist", i, node.camera.unwrap()));
+        }
+        if node.light.is_some_and(|l| l >= model.lights.len()) {
+            return Err(format!("node {} uses light {}, which doesn't exist", i, node.light.unwrap()));
+        }
+    }
+    for (i, roots) in model.scenes.iter().enumerate() {
+        if let Some(root) = roots.iter().find(|&&root| root >= node_count) {
+            return Err(format!("scene {} has a root node {} that doesn't exist", i, root));
+        }
+    }
+    Ok(())
+}
+
+fn string_field(value: &JsonValue, key: &str) -> String {
+    value.field(key).as_str().unwrap_or("").to_string()
+}
+
+fn vec3_field(value: &JsonValue, key: &str, default: Vec3) -> Vec3 {
+    match value.field(key).as_f32_vec() {
+        Some(ref v) if v.len() == 3 => Vec3::new(v[0], v[1], v[2]),
+        _ => default,
+    }
+}
+
+fn texture_ref(value: &JsonValue) -> Option<TextureRef> {
+    Some(TextureRef {
+        texture: value.field("index").as_usize()?,
+        tex_coord: value.field("texCoord").as_usize().unwrap_or(0),
+    })
+}
+
+fn parse_material(material: &JsonValue) -> GltfMaterial {
+    let default = GltfMaterial::default();
+    let pbr = material.field("pbrMetallicRoughness");
+    GltfMaterial {
+        name: string_field(material, "name"),
+        base_color_factor: match pbr.field("baseColorFactor").as_f32_vec() {
+            Some(ref c) if c.len() == 4 => Vec4::new(c[0], c[1], c[2], c[3]),
+            _ => default.base_color_factor,
+        },
+        base_color_texture: texture_ref(pbr.field("baseColorTexture")),
+        metallic_factor: pbr.field("metallicFactor").as_f32().unwrap_or(default.metallic_factor),
+        roughness_factor: pbr.field("roughnessFactor").as_f32().unwrap_or(default.roughness_factor),
+        metallic_roughness_texture: texture_ref(pbr.field("metallicRoughnessTexture")),
+        normal_texture: texture_ref(material.field("normalTexture")),
+        normal_scale: material.field("normalTexture").field("scale").as_f32().unwrap_or(default.normal_scale),
+        occlusion_texture: texture_ref(material.field("occlusionTexture")),
+        occlusion_strength: material.field("occlusionTexture").field("strength").as_f32()
+            .unwrap_or(default.occlusion_strength),
+        emissive_texture: texture_ref(material.field("emissiveTexture")),
+        emissive_factor: vec3_field(material, "emissiveFactor", default.emissive_factor),
+        alpha_mode: match material.field("alphaMode").as_str() {
+            Some("MASK") => AlphaMode::Mask,
+            Some("BLEND") => AlphaMode::Blend,
+            _ => AlphaMode::Opaque,
+        },
+        alpha_cutoff: material.field("alphaCutoff").as_f32().unwrap_or(default.alpha_cutoff),
+        double_sided: material.field("doubleSided").as_bool().unwrap_or(false),
+    }
+}
+
+fn parse_primitive(reader: &AccessorReader, primitive: &JsonValue, materials: &[GltfMaterial])
+    -> Result<GltfPrimitive, String>
+{
+    let attributes = primitive.field("attributes");
+    let attribute = |key: &str, allowed_components: &[usize]| -> Result<Option<Vec<f32>>, String> {
+        match attributes.field(key).as_usize() {
+            Some(accessor) => {
+                let (components, values) = reader.read_f32(accessor).map_err(|e| format!("{}: {}", key, e))?;
+                if !allowed_components.contains(&components) {
+                    return Err(format!("{} has {} components per vertex", key, components));
+                }
+                // Widen everything to 4 components (w = 1), so callers don't have to care what the file used
+                Ok(Some(values.chunks_exact(components)
+                    .flat_map(|v| (0..4).map(move |i| v.get(i).cloned().unwrap_or(if i == 3 { 1.0 } else { 0.0 })))
+                    .collect()))
+            },
+            None => Ok(None),
+        }
+    };
+
+    let positions = attribute("POSITION", &[3])?.ok_or("primitive has no POSITION attribute")?;
+    let vertex_count = positions.len() / 4;
+    let normals = attribute("NORMAL", &[3])?;
+    let tex_coords = attribute("TEXCOORD_0", &[2])?;
+    let tangents = attribute("TANGENT", &[4])?;
+    let colors = attribute("COLOR_0", &[3, 4])?;
+    let joints = attribute("JOINTS_0", &[4])?;
+    let weights = attribute("WEIGHTS_0", &[4])?;
+    for (key, values) in &[("NORMAL", &normals), ("TEXCOORD_0", &tex_coords), ("TANGENT", &tangents),
+                           ("COLOR_0", &colors), ("JOINTS_0", &joints), ("WEIGHTS_0", &weights)] {
+        if values.as_ref().is_some_and(|v| v.len() / 4 != vertex_count) {
+            return Err(format!("{} has a different number of vertices than POSITION", key));
+        }
+    }
+
+    let at = |values: &Option<Vec<f32>>, i: usize| -> [f32; 4] {
+        values.as_ref().map_or([0.0; 4], |v| [v[i * 4], v[i * 4 + 1], v[i * 4 + 2], v[i * 4 + 3]])
+    };
+    let mut data = MeshData::new();
+    for (i, p) in positions.chunks_exact(4).enumerate() {
+        let (n, uv, t) = (at(&normals, i), at(&tex_coords, i), at(&tangents, i));
+        // Note that we drop the tangent's w, which says whether the bitangent is flipped (for mirrored UVs), since
+        // `Vertex` doesn't have room for it
+        data.vertices.push(Vertex {
+            position: Vec3::new(p[0], p[1], p[2]),
+            normal: Vec3::new(n[0], n[1], n[2]),
+            tex_coord: Vec2::new(uv[0], uv[1]),
+            tangent: Vec3::new(t[0], t[1], t[2]),
+        });
+    }
+
+    data.indices = match primitive.field("indices").as_usize() {
+        Some(accessor) => reader.read_u32(accessor).map_err(|e| format!("indices: {}", e))?,
+        None => (0..vertex_count as u32).collect(),
+    };
+    if let Some(&bad) = data.indices.iter().find(|&&index| index as usize >= vertex_count) {
+        return Err(format!("index {} is out of range for {} vertices", bad, vertex_count));
+    }
+
+    let mode = primitive.field("mode").as_usize().unwrap_or(4) as gl::types::GLenum;  // 4 is `gl::TRIANGLES`
+    let material = primitive.field("material").as_usize();
+    if material.is_some_and(|m| m >= materials.len()) {
+        return Err(format!("material {} doesn't exist", material.unwrap()));
+    }
+
+    // Fill in anything the lighting needs but the file left out
+    if mode == gl::TRIANGLES {
+        if normals.is_none() {
+            data.compute_normals();
+        }
+        let has_normal_map = material.is_some_and(|m| materials[m].normal_texture.is_some());
+        if tangents.is_none() && tex_coords.is_some() && has_normal_map {
+            data.compute_tangents();
+        }
+    }
+
+    let to_vec4 = |values: Option<Vec<f32>>| -> Vec<[f32; 4]> {
+        values.map(|v| v.chunks_exact(4).map(|c| [c[0], c[1], c[2], c[3]]).collect()).unwrap_or_default()
+    };
+    Ok(GltfPrimitive {
+        data,
+        colors: to_vec4(colors).into_iter().map(|c| Vec4::new(c[0], c[1], c[2], c[3])).collect(),
+        joints: to_vec4(joints),
+        weights: to_vec4(weights),
+        material,
+        mode,
+    })
+}
+
+fn parse_animation(reader: &AccessorReader, animation: &JsonValue, node_count: usize) -> Result<GltfAnimation, String> {
+    let mut samplers = Vec::new();
+    for (i, sampler) in animation.field("samplers").items().iter().enumerate() {
+        let input = sampler.field("input").as_usize().ok_or_else(|| format!("sampler {} has no input", i))?;
+        let output = sampler.field("output").as_usize().ok_or_else(|| format!("sampler {} has no output", i))?;
+        let (_, times) = reader.read_f32(input).map_err(|e| format!("sampler {}: {}", i, e))?;
+        let (_, values) = reader.read_f32(output).map_err(|e| format!("sampler {}: {}", i, e))?;
+        if times.is_empty() {
+            return Err(format!("sampler {} has no keyframes", i));
+        }
+        let interpolation = match sampler.field("interpolation").as_str() {
+            Some("STEP") => Interpolation::Step,
+            Some("CUBICSPLINE") => Interpolation::CubicSpline,
+            _ => Interpolation::Linear,
+        };
+        samplers.push(AnimationSampler { times, values, interpolation });
+    }
+
+    let mut channels = Vec::new();
+    for (i, channel) in animation.field("channels").items().iter().enumerate() {
+        let target = channel.field("target");
+        let node = match target.field("node").as_usize() {
+            Some(node) if node < node_count => node,
+            Some(node) => return Err(format!("channel {} targets node {}, which doesn't exist", i, node)),
+            None => continue,  // channels without a node are for extensions to fill in
+        };
+        let (path, components) = match target.field("path").as_str() {
+            Some("translation") => (AnimationPath::Translation, 3),
+            Some("rotation") => (AnimationPath::Rotation, 4),
+            Some("scale") => (AnimationPath::Scale, 3),
+            _ => continue,  // "weights", for morph targets, which we don't import
+        };
+        let sampler = channel.field("sampler").as_usize()
+            .filter(|&s| s < samplers.len())
+            .ok_or_else(|| format!("channel {} has no valid sampler", i))?;
+
+        let s = &samplers[sampler];
+        let per_keyframe = if s.interpolation == Interpolation::CubicSpline { 3 * components } else { components };
+        if s.values.len() != s.times.len() * per_keyframe {
+            return Err(format!("channel {}'s sampler has the wrong number of output values", i));
+        }
+        channels.push(AnimationChannel { node, path, sampler });
+    }
+
+    let duration = samplers.iter().map(|s| *s.times.last().unwrap()).fold(0.0, f32::max);
+    Ok(GltfAnimation { name: string_field(animation, "name"), channels, samplers, duration })
+}
+
+/// Reads accessors out of the buffers.  Everything gets converted to `f32` (or `u32` for indices), whatever type the
+/// file stored it as
+struct AccessorReader<'a> {
+    doc: &'a JsonValue,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> AccessorReader<'a> {
+    fn buffer_view(&self, index: usize) -> Result<&[u8], String> {
+        let view = self.doc.field("bufferViews").items().get(index)
+            .ok_or_else(|| format!("buffer view {} doesn't exist", index))?;
+        let buffer = view.field("buffer").as_usize()
+            .and_then(|b| self.buffers.get(b))
+            .ok_or_else(|| format!("buffer view {} refers to a buffer that doesn't exist", index))?;
+        let offset = view.field("byteOffset").as_usize().unwrap_or(0);
+        let length = view.field("byteLength").as_usize().unwrap_or(0);
+        offset.checked_add(length)
+            .and_then(|end| buffer.get(offset..end))
+            .ok_or_else(|| format!("buffer view {} runs past the end of its buffer", index))
+    }
+
+    /// Returns the number of components per element (3 for a VEC3, 16 for a MAT4, ...) and all the components one
+    /// after another.  Values stay exactly as stored (just widened to `f64`, which can hold any of them exactly) along
+    /// with the component type, so the callers can decide how to convert them
+    fn read_raw(&self, index: usize) -> Result<(usize, Vec<f64>, u32, bool), String> {
+        let accessor = self.doc.field("accessors").items().get(index)
+            .ok_or_else(|| format!("accessor {} doesn't exist", index))?;
+        let component_type = accessor.field("componentType").as_usize().unwrap_or(0) as u32;
+        let component_size = component_type_size(component_type)
+            .ok_or_else(|| format!("accessor {} has an invalid component type {}", index, component_type))?;
+        let components = match accessor.field("type").as_str().unwrap_or("") {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" | "MAT2" => 4,
+            "MAT3" => 9,
+            "MAT4" => 16,
+            other => return Err(format!("accessor {} has an invalid type '{}'", index, other)),
+        };
+        let count = accessor.field("count").as_usize().unwrap_or(0);
+        let normalized = accessor.field("normalized").as_bool().unwrap_or(false);
+
+        // `count` comes straight from the file, so it has to be checked against the data before anything gets allocated
+        // for it.  Accessors without a buffer view are all zeros (plus whatever sparse values get patched in below), so
+        // there's nothing to check them against; they just can't have more elements than all the buffers have bytes
+        let element_size = component_size * components;
+        let view = match accessor.field("bufferView").as_usize() {
+            Some(view_index) => {
+                let view = self.buffer_view(view_index)?;
+                let offset = accessor.field("byteOffset").as_usize().unwrap_or(0);
+                let stride = self.doc.field("bufferViews").items()[view_index].field("byteStride").as_usize()
+                    .unwrap_or(element_size);
+                if stride < element_size {
+                    return Err(format!("accessor {} has elements bigger than its buffer view's stride", index));
+                }
+                let end = match count.checked_sub(1) {
+                    Some(last) => stride.checked_mul(last)
+                        .and_then(|start| start.checked_add(offset))
+                        .and_then(|start| start.checked_add(element_size)),
+                    None => Some(0),
+                };
+                match end {
+                    Some(end) if end <= view.len() => {},
+                    _ => return Err(format!("accessor {} runs past the end of its buffer view", index)),
+                }
+                Some((view, offset, stride))
+            },
+            None => {
+                let total: usize = self.buffers.iter().map(Vec::len).sum();
+                if count > total {
+                    return Err(format!("accessor {} has {} elements, more than its file has bytes", index, count));
+                }
+                None
+            },
+        };
+
+        let mut values = vec![0.0f64; count * components];
+        if let Some((view, offset, stride)) = view {
+            for i in 0..count {
+                for c in 0..components {
+                    let at = offset + i * stride + c * component_size;
+                    values[i * components + c] = read_component(view, at, component_type);
+                }
+            }
+        }
+
+        // Sparse accessors replace a handful of elements, which is a cheap way to store e.g. a morph target that only
+        // moves a few vertices
+        let sparse = accessor.field("sparse");
+        if !sparse.is_null() {
+            let sparse_count = sparse.field("count").as_usize().unwrap_or(0);
+            let indices = sparse.field("indices");
+            let indices_view = indices.field("bufferView").as_usize().ok_or("sparse indices have no buffer view")?;
+            let indices_view = self.buffer_view(indices_view)?;
+            let indices_offset = indices.field("byteOffset").as_usize().unwrap_or(0);
+            let indices_type = indices.field("componentType").as_usize().unwrap_or(0) as u32;
+            let indices_size = component_type_size(indices_type)
+                .ok_or("sparse indices have an invalid component type")?;
+            let sparse_values = sparse.field("values");
+            let values_view = sparse_values.field("bufferView").as_usize().ok_or("sparse values have no buffer view")?;
+            let values_view = self.buffer_view(values_view)?;
+            let values_offset = sparse_values.field("byteOffset").as_usize().unwrap_or(0);
+
+            let fits = |offset: usize, element_size: usize, view: &[u8]| {
+                sparse_count.checked_mul(element_size)
+                    .and_then(|size| size.checked_add(offset))
+                    .is_some_and(|end| end <= view.len())
+            };
+            if !fits(indices_offset, indices_size, indices_view) || !fits(values_offset, element_size, values_view) {
+                return Err(format!("accessor {}'s sparse data runs past the end of its buffer view", index));
+            }
+            for i in 0..sparse_count {
+                let element = read_component(indices_view, indices_offset + i * indices_size, indices_type) as usize;
+                if element >= count {
+                    return Err(format!("accessor {} has a sparse index {} past its end", index, element));
+                }
+                for c in 0..components {
+                    let at = values_offset + (i * components + c) * component_size;
+                    values[element * components + c] = read_component(values_view, at, component_type);
+                }
+            }
+        }
+
+        Ok((components, values, component_type, normalized))
+    }
+
+    fn read_f32(&self, index: usize) -> Result<(usize, Vec<f32>), String> {
+        let (components, values, component_type, normalized) = self.read_raw(index)?;
+        // Normalized integers map their whole range onto 0..1 (or -1..1 for signed types)
+        let scale = match component_type {
+            5120 => 127.0,
+            5121 => 255.0,
+            5122 => 32767.0,
+            5123 => 65535.0,
+            _ => 1.0,
+        };
+        let values = if normalized {
+            // The most negative value of a signed type would come out a little below -1, so clamp it
+            values.into_iter().map(|v| ((v / scale) as f32).max(-1.0)).collect()
+        } else {
+            values.into_iter().map(|v| v as f32).collect()
+        };
+        Ok((components, values))
+    }
+
+    fn read_u32(&self, index: usize) -> Result<Vec<u32>, String> {
+        let (components, values, component_type, _) = self.read_raw(index)?;
+        if components != 1 || ![5121, 5123, 5125].contains(&component_type) {
+            return Err(format!("accessor {} should be unsigned integer scalars", index));
+        }
+        Ok(values.into_iter().map(|v| v as u32).collect())
+    }
+}
+
+fn component_type_size(component_type: u32) -> Option<usize> {
+    match component_type {
+        5120 | 5121 => Some(1),  // byte, unsigned byte
+        5122 | 5123 => Some(2),  // short, unsigned short
+        5125 | 5126 => Some(4),  // unsigned int, float
+        _ => None,
+    }
+}
+
+/// Everything in glTF's binary data is little-endian
+fn read_component(bytes: &[u8], at: usize, component_type: u32) -> f64 {
+    match component_type {
+        5120 => bytes[at] as i8 as f64,
+        5121 => bytes[at] as f64,
+        5122 => i16::from_le_bytes([bytes[at], bytes[at + 1]]) as f64,
+        5123 => u16::from_le_bytes([bytes[at], bytes[at + 1]]) as f64,
+        5125 => u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as f64,
+        _ => f32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as f64,
+    }
+}
+
+/// URIs are either `data:` URIs with the bytes base64-encoded right there, or paths relative to the glTF file
+fn load_uri<F>(uri: &str, load_external: &mut F) -> Result<Vec<u8>, String>
+    where F: FnMut(&str) -> Result<Vec<u8>, String>
+{
+    if uri.starts_with("data:") {
+        let comma = uri.find(',').ok_or("malformed data URI")?;
+        if !uri[..comma].ends_with(";base64") {
+            return Err("only base64 data URIs are supported".to_string());
+        }
+        decode_base64(&uri[comma + 1..])
+    } else {
+        load_external(uri)
+    }
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    for byte in text.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return Err(format!("invalid base64 character '{}'", byte as char)),
+        };
+        // Every character is 6 bits; whenever we've got a whole byte's worth, move it to the output
+        bits = (bits << 6) | value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            out.push((bits >> bit_count) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// Relative URIs can have things like spaces encoded as `%20`
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(value)) => {
+                out.push(value);
+                i += 3;
+            },
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// What glTF meshes get uploaded as: our usual vertex plus vertex colors and skinning data.  Unskinned meshes just
+/// have all-zero weights, so that one shader can draw both
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GltfVertex {
+    vertex: Vertex,
+    color: Vec4,
+    joints: [f32; 4],  // joint indices, as floats since that's simpler than integer attributes and exact up to 2^24
+    weights: [f32; 4],
+}
+
+impl GltfVertex {
+    fn layout() -> VertexLayout {
+        let f = std::mem::size_of::<f32>();
+        let mut layout = Vertex::layout();
+        layout.stride = std::mem::size_of::<GltfVertex>();
+        layout
+            .float(attrib::COLOR, 4, 11 * f)
+            .float(attrib::JOINTS, 4, 15 * f)
+            .float(attrib::WEIGHTS, 4, 19 * f)
+    }
+}
+
+/// The GPU side of a `GltfModel`: one `Mesh` per primitive and one `Texture` per glTF texture
+pub struct GltfGpuData {
+    pub meshes: Vec<Vec<Mesh>>,
+    pub textures: Vec<Option<Texture>>,
+    white: Texture,
+    flat_normal: Texture,
+}
+
+#[allow(dead_code)]
+impl GltfModel {
+    /// Root nodes of the scene to show.  Files without any scenes just get every node that has no parent
+    pub fn scene_roots(&self) -> Vec<usize> {
+        match self.scenes.get(self.scene.unwrap_or(0)) {
+            Some(roots) => roots.clone(),
+            None => (0..self.nodes.len()).filter(|&i| self.nodes[i].parent.is_none()).collect(),
+        }
+    }
+
+    /// Every node's transform relative to the world, indexed the same as `nodes`
+    pub fn world_transforms(&self) -> Vec<Mat4> {
+        let mut world = vec![Mat4::identity(); self.nodes.len()];
+        let mut stack: Vec<(usize, Mat4)> = (0..self.nodes.len())
+            .filter(|&i| self.nodes[i].parent.is_none())
+            .map(|i| (i, Mat4::identity()))
+            .collect();
+        while let Some((node, parent_world)) = stack.pop() {
+            world[node] = parent_world * self.nodes[node].local_transform();
+            for &child in &self.nodes[node].children {
+                stack.push((child, world[node]));
+            }
+        }
+        world
+    }
+
+    /// Poses the model with animation `index` at `time` seconds, looping it
+    pub fn animate(&mut self, index: usize, time: f32) {
+        if let Some(animation) = self.animations.get(index) {
+            let time = if animation.duration > 0.0 { time % animation.duration } else { 0.0 };
+            animation.apply(&mut self.nodes, time);
+        }
+    }
+
+    /// Smallest box (min corner, max corner) containing every mesh in the scene, as posed right now (but ignoring
+    /// skinning)
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        let world = self.world_transforms();
+        let mut min = Vec3::splat(f32::INFINITY);
+        let mut max = Vec3::splat(f32::NEG_INFINITY);
+        for node in self.scene_nodes() {
+            if let Some(mesh) = self.nodes[node].mesh {
+                for primitive in &self.meshes[mesh].primitives {
+                    for vertex in &primitive.data.vertices {
+                        let p = world[node].transform_point(vertex.position);
+                        min = min.min(p);
+                        max = max.max(p);
+                    }
+                }
+            }
+        }
+        (min, max)
+    }
+
+    /// Every node in the scene, parents before children
+    pub fn scene_nodes(&self) -> Vec<usize> {
+        let mut nodes = Vec::new();
+        let mut stack = self.scene_roots();
+        stack.reverse();
+        while let Some(node) = stack.pop() {
+            nodes.push(node);
+            stack.extend(self.nodes[node].children.iter().rev());
+        }
+        nodes
+    }
+
+    /// Uploads all the meshes and textures.  Images that failed to decode become plain white textures
+    pub fn upload(&self) -> GltfGpuData {
+        let meshes = self.meshes.iter()
+            .map(|mesh| mesh.primitives.iter()
+                .map(|primitive| {
+                    let vertices: Vec<GltfVertex> = primitive.data.vertices.iter().enumerate()
+                        .map(|(i, &vertex)| GltfVertex {
+                            vertex,
+                            color: primitive.colors.get(i).cloned().unwrap_or(Vec4::new(1.0, 1.0, 1.0, 1.0)),
+                            joints: primitive.joints.get(i).cloned().unwrap_or([0.0; 4]),
+                            weights: primitive.weights.get(i).cloned().unwrap_or([0.0; 4]),
+                        })
+                        .collect();
+                    Mesh::new(&vertices, &GltfVertex::layout(), Some(&primitive.data.indices), primitive.mode)
+                })
+                .collect())
+            .collect();
+
+        // Color textures are stored in sRGB, while everything else (normals, roughness, ...) is plain data
+        let is_color = |index: usize| self.materials.iter().any(|m| {
+            let uses = |texture: Option<TextureRef>| texture.map(|t| t.texture) == Some(index);
+            uses(m.base_color_texture) || uses(m.emissive_texture)
+        });
+        let textures = self.textures.iter().enumerate()
+            .map(|(i, texture)| {
+                let image = texture.image.and_then(|image| self.images.get(image)).and_then(Option::as_ref)?;
+                let gpu_texture = Texture::from_image(image, is_color(i));
+                gpu_texture.set_filter(texture.sampler.min_filter, texture.sampler.mag_filter);
+                gpu_texture.set_wrap(texture.sampler.wrap_s, texture.sampler.wrap_t);
+                Some(gpu_texture)
+            })
+            .collect();
+
+        GltfGpuData {
+            meshes,
+            textures,
+            white: Texture::solid([255, 255, 255, 255]),
+            flat_normal: Texture::solid([128, 128, 255, 255]),
+        }
+    }
+
+    /// Draws the scene with `program` (which should be in use, with `View` and `Projection` already set).  Sets the
+    /// `Model` matrix, the skinning uniforms and the material uniforms that `gltf.frag` uses; programs that don't have
+    /// some of those uniforms (like the debug view ones) just ignore them
+    pub fn draw(&self, gpu: &GltfGpuData, program: &Program) {
+        let world = self.world_transforms();
+        let default_material = GltfMaterial::default();
+
+        program.set_uniform_i32("BaseColorTexture", 0);
+        program.set_uniform_i32("MetallicRoughnessTexture", 1);
+        program.set_uniform_i32("NormalTexture", 2);
+        program.set_uniform_i32("OcclusionTexture", 3);
+        program.set_uniform_i32("EmissiveTexture", 4);
+        program.set_uniform_i32("VertexColors", 1);  // for `pbr.vert`; every glTF vertex has a color, white if unset
+
+        for node_index in self.scene_nodes() {
+            let node = &self.nodes[node_index];
+            let mesh = match node.mesh {
+                Some(mesh) => mesh,
+                None => continue,
+            };
+
+            program.set_uniform_mat4("Model", &world[node_index]);
+            match node.skin.and_then(|skin| self.skins.get(skin)) {
+                Some(skin) => {
+                    let joint_matrices = skin.joint_matrices(&world, node_index);
+                    let count = joint_matrices.len().min(MAX_JOINTS);
+                    program.set_uniform_mat4_array("Joints", &joint_matrices[..count]);
+                    program.set_uniform_i32("Skinned", 1);
+                },
+                None => program.set_uniform_i32("Skinned", 0),
+            }
+
+            for (primitive, gpu_mesh) in self.meshes[mesh].primitives.iter().zip(&gpu.meshes[mesh]) {
+                let material = primitive.material.map_or(&default_material, |m| &self.materials[m]);
+                program.set_uniform_vec4("BaseColorFactor", material.base_color_factor);
+                program.set_uniform_f32("MetallicFactor", material.metallic_factor);
+                program.set_uniform_f32("RoughnessFactor", material.roughness_factor);
+                program.set_uniform_f32("NormalScale", material.normal_scale);
+                program.set_uniform_f32("OcclusionStrength", material.occlusion_strength);
+                program.set_uniform_vec3("EmissiveFactor", material.emissive_factor);
+                program.set_uniform_f32(
+                    "AlphaCutoff",
+                    if material.alpha_mode == AlphaMode::Mask { material.alpha_cutoff } else { -1.0 },
+                );
+
+                let bind = |texture: Option<TextureRef>, unit: u32, fallback: &Texture| {
+                    texture.and_then(|t| gpu.textures.get(t.texture)).and_then(Option::as_ref)
+                        .unwrap_or(fallback)
+                        .bind(unit);
+                };
+                bind(material.base_color_texture, 0, &gpu.white);
+                bind(material.metallic_roughness_texture, 1, &gpu.white);
+                bind(material.normal_texture, 2, &gpu.flat_normal);
+                bind(material.occlusion_texture, 3, &gpu.white);
+                bind(material.emissive_texture, 4, &gpu.white);
+
+                gpu_mesh.draw();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<GltfModel, String> {
+        parse_gltf(json, None, "test.gltf", |uri| Err(format!("no file called '{}'", uri)))
+    }
+
+    /// A document with three nodes (0 has 1 and 2 as children), a camera and a light, with `extra` added to the end
+    fn document(extra: &str) -> String {
+        format!(r#"{{
+            "asset": {{ "version": "2.0" }},
+            "cameras": [{{ "type": "perspective", "perspective": {{ "yfov": 1.0, "znear": 0.1 }} }}],
+            "extensions": {{ "KHR_lights_punctual": {{ "lights": [{{ "type": "point" }}] }} }},
+            "nodes": [
+                {{ "children": [2, 1] }},
+                {{ "camera": 0 }},
+                {{ "extensions": {{ "KHR_lights_punctual": {{ "light": 0 }} }} }}
+            ]{}
+        }}"#, extra)
+    }
+
+    #[test]
+    fn builds_the_hierarchy() {
+        let model = parse(&document(r#", "scenes": [{ "nodes": [0] }]"#)).unwrap();
+        assert_eq!(model.nodes[1].parent, Some(0));
+        assert_eq!(model.nodes[2].parent, Some(0));
+        assert_eq!(model.scene_nodes(), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        let cases = [
+            (r#", "scenes": [{ "nodes": [99] }]"#, "scene 0 has a root node 99 that doesn't exist"),
+            (r#", "skins": [{ "joints": [0, 7] }]"#, "skin 0 refers to a node that doesn't exist"),
+            (r#", "skins": [{ "joints": [1], "skeleton": 5 }]"#, "skin 0 has a skeleton root that doesn't exist"),
+        ];
+        for (extra, message) in &cases {
+            assert_eq!(parse(&document(extra)).unwrap_err(), format!("test.gltf: {}", message));
+        }
+
+        let node_cases = [
+            (r#"{ "children": [9] }"#, "node 3 has a child 9 that doesn't exist"),
+            (r#"{ "mesh": 0 }"#, "node 3 uses mesh 0, which doesn't exist"),
+            (r#"{ "camera": 1 }"#, "node 3 uses camera 1, which doesn't exist"),
+            (
+                r#"{ "extensions": { "KHR_lights_punctual": { "light": 2 } } }"#,
+                "node 3 uses light 2, which doesn't exist",
+            ),
+            (r#"{ "skin": 0 }"#, "node 3 uses skin 0, which doesn't exist"),
+        ];
+        for (node, message) in &node_cases {
+            let json = document("").replace("\"light\": 0 } } }\n", &format!("\"light\": 0 }} }} }}, {}\n", node));
+            assert_eq!(parse(&json).unwrap_err(), format!("test.gltf: {}", message));
+        }
+    }
+
+    /// A triangle whose positions are interleaved with normalized byte colors (so the view has a byte stride), plus
+    /// u16 indices and one sparse replacement position:
+    ///
+    ///     0..48   position (3 floats) and color (4 normalized bytes) per vertex, 16 bytes apart
+    ///     48..54  indices
+    ///     56..58  the sparse index (vertex 1)
+    ///     60..72  the sparse value
+    fn triangle_buffer() -> Vec<u8> {
+        let positions = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let colors = [[255u8, 0, 0, 255], [0, 128, 0, 255], [0, 0, 255, 51]];
+        let mut bytes = Vec::new();
+        for (position, color) in positions.iter().zip(&colors) {
+            for c in position {
+                bytes.extend_from_slice(&c.to_le_bytes());
+            }
+            bytes.extend_from_slice(color);
+        }
+        for index in &[0u16, 1, 2, 0, 1] {  // the indices, then padding up to 4 bytes, then the sparse index
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0, 0]);
+        for c in &[9.0f32, 9.0, 9.0] {
+            bytes.extend_from_slice(&c.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Three meshes over `triangle_buffer()`: the colored triangle, the triangle with vertex 1 moved by a sparse
+    /// accessor, and a sparse accessor with no buffer view at all (so zeros, apart from vertex 1)
+    fn triangle_document(buffer: &str) -> String {
+        let sparse = r#""sparse": { "count": 1, "indices": { "bufferView": 2, "componentType": 5123 },
+            "values": { "bufferView": 3 } }"#;
+        format!(r#"{{
+            "asset": {{ "version": "2.0" }},
+            "buffers": [{{ {}"byteLength": 72 }}],
+            "bufferViews": [
+                {{ "buffer": 0, "byteLength": 48, "byteStride": 16 }},
+                {{ "buffer": 0, "byteOffset": 48, "byteLength": 6 }},
+                {{ "buffer": 0, "byteOffset": 56, "byteLength": 2 }},
+                {{ "buffer": 0, "byteOffset": 60, "byteLength": 12 }}
+            ],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 3 }},
+                {{ "bufferView": 0, "byteOffset": 12, "componentType": 5121, "normalized": true, "type": "VEC4",
+                   "count": 3 }},
+                {{ "bufferView": 1, "componentType": 5123, "type": "SCALAR", "count": 3 }},
+                {{ "bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 3, {} }},
+                {{ "componentType": 5126, "type": "VEC3", "count": 3, {} }}
+            ],
+            "meshes": [
+                {{ "primitives": [{{ "attributes": {{ "POSITION": 0, "COLOR_0": 1 }}, "indices": 2 }}] }},
+                {{ "primitives": [{{ "attributes": {{ "POSITION": 3 }} }}] }},
+                {{ "primitives": [{{ "attributes": {{ "POSITION": 4 }} }}] }}
+            ]
+        }}"#, buffer, sparse, sparse)
+    }
+
+    fn check_triangle(model: &GltfModel) {
+        let positions = |mesh: usize| -> Vec<Vec3> {
+            model.meshes[mesh].primitives[0].data.vertices.iter().map(|v| v.position).collect()
+        };
+        let triangle = &model.meshes[0].primitives[0];
+        assert_eq!(positions(0), vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)]);
+        assert_eq!(triangle.data.indices, vec![0, 1, 2]);
+        assert_eq!(triangle.colors[1], Vec4::new(0.0, 128.0 / 255.0, 0.0, 1.0));
+        assert_eq!(triangle.colors[2], Vec4::new(0.0, 0.0, 1.0, 0.2));
+        assert_eq!(positions(1)[1], Vec3::splat(9.0));
+        assert_eq!(positions(2), vec![Vec3::ZERO, Vec3::splat(9.0), Vec3::ZERO]);
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut text = String::new();
+        for chunk in bytes.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                let c = if i <= chunk.len() { alphabet[(n >> (18 - 6 * i) & 63) as usize] } else { b'=' };
+                text.push(c as char);
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn reads_embedded_buffers() {
+        let uri = format!(r#""uri": "data:application/octet-stream;base64,{}", "#, base64(&triangle_buffer()));
+        check_triangle(&parse(&triangle_document(&uri)).unwrap());
+    }
+
+    #[test]
+    fn reads_external_buffers() {
+        let json = triangle_document(r#""uri": "triangle.bin", "#);
+        let model = parse_gltf(&json, None, "test.gltf", |uri| {
+            assert_eq!(uri, "triangle.bin");
+            Ok(triangle_buffer())
+        });
+        check_triangle(&model.unwrap());
+
+        let error = parse(&json).unwrap_err();
+        assert_eq!(error, "test.gltf: buffer 0: no file called 'triangle.bin'");
+    }
+
+    #[test]
+    fn reads_glb() {
+        let mut json = triangle_document("").into_bytes();
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+        let bin = triangle_buffer();
+        let mut glb = b"glTF".to_vec();
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        for (kind, data) in &[(b"JSON", &json), (b"BIN\0", &bin)] {
+            glb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            glb.extend_from_slice(*kind);
+            glb.extend_from_slice(data);
+        }
+
+        let (json_source, bin) = split_glb(&glb).unwrap();
+        assert_eq!(bin.as_deref(), Some(&triangle_buffer()[..]));
+        check_triangle(&parse_gltf(&json_source, bin, "test.glb", |_| Err("no files".to_string())).unwrap());
+
+        assert_eq!(split_glb(&glb[..30]).unwrap_err(), "GLB chunk runs past the end of the file");
+    }
+
+    #[test]
+    fn rejects_accessors_past_their_data() {
+        let uri = format!(r#""uri": "data:application/octet-stream;base64,{}", "#, base64(&triangle_buffer()));
+        let json = triangle_document(&uri);
+        let cases = [
+            (r#""count": 3 },"#, r#""count": 4 },"#, "mesh 0 primitive 0: POSITION: accessor 0 runs past the end"),
+            (r#""count": 3 },"#, r#""count": 1152921504606846976 },"#, "accessor 0 runs past the end"),  // 2^60
+            (r#""byteStride": 16"#, r#""byteStride": 4"#, "has elements bigger than its buffer view's stride"),
+        ];
+        for (from, to, message) in &cases {
+            let error = parse(&json.replacen(from, to, 1)).unwrap_err();
+            assert!(error.contains(message), "{}", error);
+        }
+
+        // Without a buffer view there's nothing to check against, but it still can't be bigger than the file
+        let json = json.replace(r#"{ "componentType": 5126, "type": "VEC3", "count": 3,"#,
+                                r#"{ "componentType": 5126, "type": "VEC3", "count": 1000000,"#);
+        assert!(parse(&json).unwrap_err().contains("accessor 4 has 1000000 elements, more than its file has bytes"));
+    }
+
+    #[test]
+    fn samples_animations() {
+        let sampler = AnimationSampler {
+            times: vec![0.0, 1.0, 2.0],
+            values: vec![0.0, 10.0, 30.0],
+            interpolation: Interpolation::Linear,
+        };
+        assert_eq!(sampler.sample(-1.0, 1), vec![0.0]);
+        assert_eq!(sampler.sample(1.5, 1), vec![20.0]);
+        assert_eq!(sampler.sample(5.0, 1), vec![30.0]);
+        assert_eq!(sampler.sample(f32::NAN, 1), vec![30.0]);  // no keyframe comes after NaN
+    }
+
+    #[test]
+    fn rejects_loops() {
+        let json = document("").replace(r#"{ "camera": 0 }"#, r#"{ "camera": 0, "children": [0] }"#);
+        assert_eq!(parse(&json).unwrap_err(), "test.gltf: node 0 is part of a loop in the node hierarchy");
+    }
+}
@@ -0,0 +1,137 @@
+use std::ffi::CString;
+use std::path::Path;
+
+use crate::render_gl;
+use crate::program;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::debug_view::DebugViews;
+use crate::gltf;
+use crate::math::Vec3;
+use crate::render_state::{CullMode, RenderState, RenderStateCache};
+use crate::resources::Resources;
+
+/// Loads a glTF scene (textured, with a node hierarchy, a skinned mesh and an animation) and plays it.  Drag to orbit,
+/// Space pauses the animation, and F1-F7 switch debug views
+pub fn gltf_viewer() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.5, 0.3, 0.3, 1.0);  // Color that window will default to when everything is cleared
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let mut timer = _sdl.timer().unwrap();
+
+    let res = Resources::from_crate_dir(Path::new("assets"));
+
+    let vert_shader = render_gl::Shader::from_vert_source(
+        &CString::new(
+            include_str!("gltf.vert")
+        ).unwrap()
+    ).unwrap();
+
+    let frag_shader = render_gl::Shader::from_frag_source(
+        &CString::new(
+            include_str!("gltf.frag")
+        ).unwrap()
+    ).unwrap();
+
+    let shader_program = program::Program::from_shaders(
+        &[vert_shader, frag_shader]
+    ).unwrap();
+
+    let mut model = match gltf::load(&res, "models/animated_scene.gltf") {
+        Ok(model) => model,
+        Err(e) => {
+            println!("Failed to load model: {}", e);
+            return;
+        },
+    };
+    for warning in &model.warnings {
+        println!("Warning: {}", warning);
+    }
+    println!(
+        "Loaded {} nodes, {} meshes, {} materials, {} textures, {} skins, {} animations, {} cameras and {} lights",
+        model.nodes.len(),
+        model.meshes.len(),
+        model.materials.len(),
+        model.textures.len(),
+        model.skins.len(),
+        model.animations.len(),
+        model.cameras.len(),
+        model.lights.len(),
+    );
+    let gpu_data = model.upload();
+
+    // Frame the whole scene
+    let (min, max) = model.bounds();
+    let center = (min + max) * 0.5;
+    let radius = (max - min).length() * 0.5;
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(center + Vec3::new(0.0, radius * 0.5, radius * 2.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(center, radius * 2.0);
+    controller.attach(&mut camera);
+
+    // Some of the materials are double-sided, and we draw everything in one go, so don't cull anything
+    let render_state = RenderState { cull_mode: CullMode::None, ..RenderState::opaque() };
+    let mut render_state_cache = RenderStateCache::new();
+    let mut debug_views = DebugViews::new().unwrap();
+
+    let mut playing = true;
+    let mut animation_time = 0.0f32;
+    let mut last_ticks = timer.ticks();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Space), .. } => {
+                    playing = !playing;
+                },
+                _ if debug_views.handle_event(&event) => {},
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let ticks = timer.ticks();
+        if playing {
+            animation_time += (ticks - last_ticks) as f32 / 1000.0;
+        }
+        last_ticks = ticks;
+        model.animate(0, animation_time);
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        debug_views.near = camera.near;
+        debug_views.far = camera.far;
+        debug_views.draw(&mut render_state_cache, &render_state, &shader_program, |program| {
+            program.set_uniform_mat4("View", &camera.view_matrix());
+            program.set_uniform_mat4("Projection", &camera.projection_matrix());
+            program.set_uniform_vec3("CameraPosition", camera.position);
+            model.draw(&gpu_data, program);
+        });
+
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,608 @@
+// A small immediate-mode GUI for tweaking things while a demo runs.  "Immediate mode" means there are no widget
+// objects to create, keep and wire up: every frame, the code just says what's on screen, and each widget call draws
+// itself and reports whether the user changed it, straight away.
+//
+//     if gui.begin_panel("Lights", 10.0, 10.0, 240.0) {
+//         gui.slider_f32("Brightness", &mut brightness, 0.0, 10.0);
+//         if gui.button("Reset") { ... }
+//         gui.end_panel();
+//     }
+//
+// The only things remembered between frames are where each panel is, whether it's collapsed, and which widget the
+// mouse is holding onto, so a widget is known by its panel's name plus its own label.  Labels need to be unique within
+// a panel.
+//
+// Everything is drawn as rectangles and text through one `SpriteBatch`, each panel on its own layers, so that the
+// panel in front covers up the ones behind it.  Panels react to the mouse using where they were last frame, which is
+// the usual immediate-mode trick for knowing what's under the mouse before anything has been laid out this frame.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::math::{Vec2, Vec3, Vec4};
+use crate::program::{glsl_type_name, Program};
+use crate::render_state::{BlendState, CullMode, PolygonMode, RenderState, RenderStateCache};
+use crate::sprite::{Camera2D, Region, Sprite, SpriteBatch};
+use crate::text::{Align, FontAtlas, TextStyle};
+use crate::texture::Texture;
+
+const TITLE_HEIGHT: f32 = 22.0;
+const ROW_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 6.0;   // between a panel's edge and its widgets
+const SPACING: f32 = 4.0;   // between rows
+const LABEL_WIDTH: f32 = 0.4;  // how much of a row the label takes, for widgets with a label beside them
+
+const PANEL_COLOR: Vec4 = Vec4 { x: 0.11, y: 0.11, z: 0.14, w: 0.92 };
+const TITLE_COLOR: Vec4 = Vec4 { x: 0.22, y: 0.27, z: 0.42, w: 1.0 };
+const WIDGET_COLOR: Vec4 = Vec4 { x: 0.21, y: 0.21, z: 0.26, w: 1.0 };
+const HOVER_COLOR: Vec4 = Vec4 { x: 0.28, y: 0.28, z: 0.36, w: 1.0 };
+const HELD_COLOR: Vec4 = Vec4 { x: 0.36, y: 0.40, z: 0.56, w: 1.0 };
+const FILL_COLOR: Vec4 = Vec4 { x: 0.33, y: 0.48, z: 0.80, w: 1.0 };
+const TEXT_COLOR: Vec4 = Vec4 { x: 0.92, y: 0.92, z: 0.95, w: 1.0 };
+const DIM_TEXT_COLOR: Vec4 = Vec4 { x: 0.6, y: 0.6, z: 0.66, w: 1.0 };
+
+/// A rectangle in window pixels, from its top-left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rect {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl Rect {
+    fn new(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    fn contains(&self, p: Vec2) -> bool {
+        p.x >= self.x && p.x < self.x + self.width && p.y >= self.y && p.y < self.y + self.height
+    }
+
+    /// Splits off the first `fraction` of the width, returning both halves
+    fn split(&self, fraction: f32) -> (Rect, Rect) {
+        let left = (self.width * fraction).round();
+        (
+            Rect::new(self.x, self.y, left, self.height),
+            Rect::new(self.x + left, self.y, self.width - left, self.height),
+        )
+    }
+}
+
+struct Panel {
+    name: String,
+    position: Vec2,
+    width: f32,
+    height: f32,       // as big as it was last frame
+    collapsed: bool,
+    visible: bool,     // whether it was drawn last frame, so hidden panels don't catch the mouse
+    drawn: bool,       // whether it's been drawn so far this frame
+    z: u32,            // higher is in front
+}
+
+/// All the state the GUI keeps between frames, and everything it needs to draw itself
+pub struct Gui {
+    batch: SpriteBatch,
+    white: Texture,
+    font: FontAtlas,
+    panels: Vec<Panel>,
+    next_z: u32,
+    current: Option<usize>,  // the panel widgets are going into
+    cursor: Vec2,            // where the next row goes
+    hovered: Option<usize>,  // the front-most panel under the mouse
+    active: Option<u64>,     // the widget the mouse is holding, if any
+    drag_offset: Vec2,       // where in the title bar a panel being dragged was grabbed
+    mouse: Vec2,
+    mouse_delta: Vec2,       // how far the mouse moved since the last frame
+    mouse_down: bool,
+    mouse_pressed: bool,     // went down since the last frame
+    mouse_released: bool,    // came up since the last frame
+    drag_remainder: f32,     // the part of a whole number `drag_i32()` hasn't moved by yet
+}
+
+#[allow(dead_code)]
+impl Gui {
+    /// `font` should be a bitmap atlas at the size the GUI text is drawn at; 14 pixels fits the rows nicely
+    pub fn new(font: FontAtlas) -> Result<Gui, String> {
+        Ok(Gui {
+            batch: SpriteBatch::new()?,
+            white: Texture::solid([255, 255, 255, 255]),
+            font,
+            panels: Vec::new(),
+            next_z: 0,
+            current: None,
+            cursor: Vec2::new(0.0, 0.0),
+            hovered: None,
+            active: None,
+            drag_offset: Vec2::new(0.0, 0.0),
+            mouse: Vec2::new(0.0, 0.0),
+            mouse_delta: Vec2::new(0.0, 0.0),
+            mouse_down: false,
+            mouse_pressed: false,
+            mouse_released: false,
+            drag_remainder: 0.0,
+        })
+    }
+
+    /// Feeds the GUI the mouse.  Returns true if the GUI is using the event (the mouse is over a panel, or dragging
+    /// something in one), in which case the rest of the program should probably ignore it
+    pub fn handle_event(&mut self, event: &sdl2::event::Event) -> bool {
+        use sdl2::event::Event;
+        use sdl2::mouse::MouseButton;
+        match *event {
+            Event::MouseMotion { x, y, xrel, yrel, .. } => {
+                self.mouse = Vec2::new(x as f32, y as f32);
+                self.mouse_delta += Vec2::new(xrel as f32, yrel as f32);
+            },
+            Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
+                self.mouse = Vec2::new(x as f32, y as f32);
+                self.mouse_down = true;
+                self.mouse_pressed = true;
+            },
+            Event::MouseButtonUp { mouse_btn: MouseButton::Left, x, y, .. } => {
+                self.mouse = Vec2::new(x as f32, y as f32);
+                self.mouse_down = false;
+                self.mouse_released = true;
+            },
+            Event::MouseButtonDown { .. } | Event::MouseButtonUp { .. } | Event::MouseWheel { .. } => {},
+            _ => return false,
+        }
+        self.wants_mouse()
+    }
+
+    /// Whether the mouse is over a panel or busy with a widget
+    pub fn wants_mouse(&self) -> bool {
+        self.active.is_some() || self.panel_at(self.mouse).is_some()
+    }
+
+    fn panel_at(&self, p: Vec2) -> Option<usize> {
+        self.panels.iter()
+            .enumerate()
+            .filter(|(_, panel)| {
+                panel.visible && Rect::new(panel.position.x, panel.position.y, panel.width, panel.height).contains(p)
+            })
+            .max_by_key(|(_, panel)| panel.z)
+            .map(|(i, _)| i)
+    }
+
+    /// Starts a frame of GUI, in a window `width` by `height` pixels
+    pub fn begin_frame(&mut self, width: u32, height: u32) {
+        self.hovered = self.panel_at(self.mouse);
+        if let (true, Some(hovered)) = (self.mouse_pressed, self.hovered) {
+            // Clicking anywhere on a panel brings it to the front
+            self.next_z += 1;
+            self.panels[hovered].z = self.next_z;
+        }
+        for panel in &mut self.panels {
+            panel.drawn = false;
+        }
+        self.batch.begin(&Camera2D::new(width, height));
+    }
+
+    /// Draws the frame's GUI over whatever's already been drawn
+    pub fn end_frame(&mut self, cache: &mut RenderStateCache) {
+        self.batch.end(cache);
+        for panel in &mut self.panels {
+            panel.visible = panel.drawn;
+        }
+        if !self.mouse_down {
+            self.active = None;
+        }
+        self.mouse_pressed = false;
+        self.mouse_released = false;
+        self.mouse_delta = Vec2::new(0.0, 0.0);
+    }
+
+    /// Starts a panel, which is placed at (`x`, `y`) the first time and can then be dragged around by its title.
+    /// Returns false if it's collapsed, in which case skip its widgets and don't call `end_panel()`
+    pub fn begin_panel(&mut self, name: &str, x: f32, y: f32, width: f32) -> bool {
+        let index = match self.panels.iter().position(|panel| panel.name == name) {
+            Some(index) => index,
+            None => {
+                self.next_z += 1;
+                self.panels.push(Panel {
+                    name: name.to_string(),
+                    position: Vec2::new(x, y),
+                    width,
+                    height: TITLE_HEIGHT,
+                    collapsed: false,
+                    visible: false,
+                    drawn: false,
+                    z: self.next_z,
+                });
+                self.panels.len() - 1
+            },
+        };
+        self.current = Some(index);
+        self.panels[index].drawn = true;
+        self.panels[index].width = width;
+
+        // Grabbing the title drags the panel around, and grabbing the arrow at its left end folds it up or opens it
+        let id = self.id("#title");
+        let position = self.panels[index].position;
+        let title = Rect::new(position.x, position.y, width, TITLE_HEIGHT);
+        let arrow = Rect::new(position.x, position.y, TITLE_HEIGHT, TITLE_HEIGHT);
+        let (_, pressed, held) = self.interact(id, title);
+        if pressed {
+            self.drag_offset = self.mouse - position;
+            if arrow.contains(self.mouse) {
+                self.panels[index].collapsed = !self.panels[index].collapsed;
+            }
+        } else if held {
+            self.panels[index].position = self.mouse - self.drag_offset;
+        }
+
+        let position = self.panels[index].position;
+        let title = Rect::new(position.x, position.y, width, TITLE_HEIGHT);
+        let collapsed = self.panels[index].collapsed;
+        self.fill(title, TITLE_COLOR, 1);
+        let (arrow, label) = title.split(TITLE_HEIGHT / width);
+        self.text(if collapsed { ">" } else { "v" }, arrow, Align::Center, DIM_TEXT_COLOR);
+        self.text(name, label, Align::Left, TEXT_COLOR);
+
+        if collapsed {
+            self.panels[index].height = TITLE_HEIGHT;
+            self.current = None;
+            return false;
+        }
+        self.cursor = Vec2::new(position.x + PADDING, position.y + TITLE_HEIGHT + PADDING);
+        true
+    }
+
+    /// Finishes the panel started by `begin_panel()`, once all its widgets are in
+    pub fn end_panel(&mut self) {
+        let index = match self.current.take() {
+            Some(index) => index,
+            None => return,
+        };
+        let panel = &mut self.panels[index];
+        panel.height = self.cursor.y - SPACING + PADDING - panel.position.y;
+        let background = Rect::new(panel.position.x, panel.position.y, panel.width, panel.height);
+        // The background goes in last but on the panel's lowest layer, now that it's known how tall it is
+        self.current = Some(index);
+        self.fill(background, PANEL_COLOR, 0);
+        self.current = None;
+    }
+
+    /// A widget's id, from its panel and label
+    fn id(&self, label: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.current.map(|index| &self.panels[index].name).hash(&mut hasher);
+        label.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The next row of the current panel
+    fn row(&mut self) -> Rect {
+        let width = self.current.map_or(0.0, |index| self.panels[index].width) - PADDING * 2.0;
+        let rect = Rect::new(self.cursor.x, self.cursor.y, width, ROW_HEIGHT);
+        self.cursor.y += ROW_HEIGHT + SPACING;
+        rect
+    }
+
+    /// What the mouse is doing to the widget `id` covering `rect`: whether it's over it, whether it was just pressed
+    /// on it, and whether it's being held down on it (having been pressed on it, wherever the mouse has gone since)
+    fn interact(&mut self, id: u64, rect: Rect) -> (bool, bool, bool) {
+        let over = self.hovered.is_some() && self.hovered == self.current && rect.contains(self.mouse);
+        let pressed = over && self.mouse_pressed && self.active.is_none();
+        if pressed {
+            self.active = Some(id);
+        }
+        let held = self.active == Some(id) && (self.mouse_down || self.mouse_released);
+        (over && self.active.is_none_or(|active| active == id), pressed, held && !pressed)
+    }
+
+    fn layer(&self, offset: i32) -> i32 {
+        self.current.map_or(0, |index| self.panels[index].z as i32 * 4) + offset
+    }
+
+    /// A solid rectangle on one of the current panel's layers (0 for its background, 1 for widgets)
+    fn fill(&mut self, rect: Rect, color: Vec4, layer: i32) {
+        let sprite = Sprite {
+            size: Vec2::new(rect.width, rect.height),
+            origin: Vec2::new(0.0, 0.0),
+            color,
+            layer: self.layer(layer),
+            ..Sprite::new(Region::whole(&self.white), Vec2::new(rect.x, rect.y))
+        };
+        self.batch.draw(&self.white, &sprite);
+    }
+
+    /// One line of text, lined up inside `rect`
+    fn text(&mut self, text: &str, rect: Rect, align: Align, color: Vec4) {
+        let style = TextStyle { color, ..TextStyle::new(self.font.pixel_size()) };
+        let layout = self.font.layout(text, &style);
+        let x = match align {
+            Align::Left => rect.x + 4.0,
+            Align::Center => rect.x + (rect.width - layout.size.x) * 0.5,
+            Align::Right => rect.x + rect.width - layout.size.x - 4.0,
+        };
+        let origin = Vec2::new(x.round(), (rect.y + (rect.height - layout.size.y) * 0.5).round());
+        let layer = self.layer(2);
+        for glyph in &layout.glyphs {
+            let sprite = Sprite {
+                size: glyph.size,
+                origin: Vec2::new(0.0, 0.0),
+                color,
+                layer,
+                ..Sprite::new(glyph.region, origin + glyph.position)
+            };
+            self.batch.draw(self.font.texture(), &sprite);
+        }
+    }
+
+    fn widget_color(over: bool, held: bool) -> Vec4 {
+        if held {
+            HELD_COLOR
+        } else if over {
+            HOVER_COLOR
+        } else {
+            WIDGET_COLOR
+        }
+    }
+
+    /// A line of text
+    pub fn label(&mut self, text: &str) {
+        let rect = self.row();
+        self.text(text, rect, Align::Left, TEXT_COLOR);
+    }
+
+    /// A label on the left and a value on the right, for showing numbers that can't be changed
+    pub fn value(&mut self, label: &str, value: &str) {
+        let (left, right) = self.row().split(LABEL_WIDTH);
+        self.text(label, left, Align::Left, DIM_TEXT_COLOR);
+        self.text(value, right, Align::Left, TEXT_COLOR);
+    }
+
+    /// A thin line across the panel
+    pub fn separator(&mut self) {
+        let width = self.current.map_or(0.0, |index| self.panels[index].width) - PADDING * 2.0;
+        let line = Rect::new(self.cursor.x, self.cursor.y + 2.0, width, 1.0);
+        self.fill(line, HOVER_COLOR, 1);
+        self.cursor.y += 5.0 + SPACING;
+    }
+
+    /// Returns true on the frame it's clicked
+    pub fn button(&mut self, label: &str) -> bool {
+        let rect = self.row();
+        let id = self.id(label);
+        let (over, _, held) = self.interact(id, rect);
+        self.fill(rect, Gui::widget_color(over, held && self.mouse_down), 1);
+        self.text(label, rect, Align::Center, TEXT_COLOR);
+        held && self.mouse_released && over
+    }
+
+    /// Returns true if it was changed
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let rect = self.row();
+        let id = self.id(label);
+        let (over, pressed, _) = self.interact(id, rect);
+        if pressed {
+            *value = !*value;
+        }
+        let outer = Rect::new(rect.x, rect.y + 2.0, rect.height - 4.0, rect.height - 4.0);
+        self.fill(outer, Gui::widget_color(over, false), 1);
+        if *value {
+            let inner = Rect::new(outer.x + 4.0, outer.y + 4.0, outer.width - 8.0, outer.height - 8.0);
+            self.fill(inner, FILL_COLOR, 2);
+        }
+        let text = Rect::new(rect.x + rect.height, rect.y, rect.width - rect.height, rect.height);
+        self.text(label, text, Align::Left, TEXT_COLOR);
+        pressed
+    }
+
+    /// Dragging across it picks a value between `min` and `max`.  Returns true if it was changed
+    pub fn slider_f32(&mut self, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let (left, right) = self.row().split(LABEL_WIDTH);
+        self.text(label, left, Align::Left, DIM_TEXT_COLOR);
+        let changed = self.slider_in(label, right, value, min, max);
+        self.text(&format!("{:.3}", value), right, Align::Center, TEXT_COLOR);
+        changed
+    }
+
+    /// Same as `slider_f32()`, for whole numbers
+    pub fn slider_i32(&mut self, label: &str, value: &mut i32, min: i32, max: i32) -> bool {
+        let (left, right) = self.row().split(LABEL_WIDTH);
+        self.text(label, left, Align::Left, DIM_TEXT_COLOR);
+        let mut float = *value as f32;
+        self.slider_in(label, right, &mut float, min as f32, max as f32);
+        let old = *value;
+        *value = float.round() as i32;
+        self.text(&value.to_string(), right, Align::Center, TEXT_COLOR);
+        *value != old
+    }
+
+    fn slider_in(&mut self, id_label: &str, rect: Rect, value: &mut f32, min: f32, max: f32) -> bool {
+        let id = self.id(id_label);
+        let (over, pressed, held) = self.interact(id, rect);
+        let old = *value;
+        if pressed || held {
+            let t = ((self.mouse.x - rect.x) / rect.width).clamp(0.0, 1.0);
+            *value = min + (max - min) * t;
+        }
+        self.fill(rect, Gui::widget_color(over, pressed || held), 1);
+        let t = if max > min { ((*value - min) / (max - min)).clamp(0.0, 1.0) } else { 0.0 };
+        self.fill(Rect::new(rect.x, rect.y, (rect.width * t).round(), rect.height), FILL_COLOR, 1);
+        *value != old
+    }
+
+    /// A number that changes by `speed` per pixel the mouse is dragged sideways, for values without an obvious range.
+    /// Returns true if it was changed
+    pub fn drag_f32(&mut self, label: &str, value: &mut f32, speed: f32) -> bool {
+        self.drag_vector(label, std::slice::from_mut(value), speed)
+    }
+
+    /// Same as `drag_f32()`, with a box per component
+    pub fn drag_vector(&mut self, label: &str, values: &mut [f32], speed: f32) -> bool {
+        let (left, right) = self.row().split(LABEL_WIDTH);
+        self.text(label, left, Align::Left, DIM_TEXT_COLOR);
+        let width = right.width / values.len().max(1) as f32;
+        let mut changed = false;
+        for (i, value) in values.iter_mut().enumerate() {
+            let rect = Rect::new(right.x + i as f32 * width, right.y, width - 2.0, right.height);
+            let id = self.id(&format!("{}#{}", label, i));
+            let (over, pressed, held) = self.interact(id, rect);
+            if held && self.mouse_delta.x != 0.0 {
+                *value += self.mouse_delta.x * speed;
+                changed = true;
+            }
+            self.fill(rect, Gui::widget_color(over, pressed || held), 1);
+            self.text(&format!("{:.3}", value), rect, Align::Center, TEXT_COLOR);
+        }
+        changed
+    }
+
+    /// Same as `drag_f32()`, for whole numbers
+    pub fn drag_i32(&mut self, label: &str, value: &mut i32, speed: f32) -> bool {
+        let (left, right) = self.row().split(LABEL_WIDTH);
+        self.text(label, left, Align::Left, DIM_TEXT_COLOR);
+        let id = self.id(label);
+        let (over, pressed, held) = self.interact(id, right);
+        let old = *value;
+        if pressed {
+            self.drag_remainder = 0.0;
+        } else if held {
+            // Slow drags move less than one per frame, so the leftovers are kept until they add up
+            self.drag_remainder += self.mouse_delta.x * speed;
+            let steps = self.drag_remainder.trunc();
+            self.drag_remainder -= steps;
+            *value += steps as i32;
+        }
+        self.fill(right, Gui::widget_color(over, pressed || held), 1);
+        self.text(&value.to_string(), right, Align::Center, TEXT_COLOR);
+        *value != old
+    }
+
+    /// A swatch of the color and a slider for each of its 3 (RGB) or 4 (RGBA) channels.  Returns true if it was
+    /// changed
+    pub fn color_edit(&mut self, label: &str, color: &mut [f32]) -> bool {
+        let (left, right) = self.row().split(LABEL_WIDTH);
+        self.text(label, left, Align::Left, DIM_TEXT_COLOR);
+        let swatch = Vec4::new(
+            color.first().copied().unwrap_or(0.0),
+            color.get(1).copied().unwrap_or(0.0),
+            color.get(2).copied().unwrap_or(0.0),
+            1.0,
+        );
+        self.fill(right, swatch, 1);
+
+        let mut changed = false;
+        for (channel, value) in ["r", "g", "b", "a"].iter().zip(color.iter_mut()) {
+            let (_, slider) = self.row().split(LABEL_WIDTH);
+            let name = Rect::new(slider.x - 16.0, slider.y, 16.0, slider.height);
+            self.text(channel, name, Align::Left, DIM_TEXT_COLOR);
+            changed |= self.slider_in(&format!("{}#{}", label, channel), slider, value, 0.0, 1.0);
+            self.text(&format!("{:.3}", value), slider, Align::Center, TEXT_COLOR);
+        }
+        changed
+    }
+
+    /// A list of options, one of which is picked.  Returns true if the pick changed
+    pub fn radio(&mut self, label: &str, selected: &mut usize, options: &[&str]) -> bool {
+        self.label(label);
+        let mut changed = false;
+        for (i, option) in options.iter().enumerate() {
+            let rect = self.row();
+            let rect = Rect::new(rect.x + 10.0, rect.y, rect.width - 10.0, rect.height);
+            let id = self.id(&format!("{}#{}", label, option));
+            let (over, pressed, _) = self.interact(id, rect);
+            if pressed && *selected != i {
+                *selected = i;
+                changed = true;
+            }
+            let dot = Rect::new(rect.x + 3.0, rect.y + 5.0, rect.height - 10.0, rect.height - 10.0);
+            self.fill(dot, if *selected == i { FILL_COLOR } else { Gui::widget_color(over, false) }, 1);
+            let text = Rect::new(rect.x + rect.height, rect.y, rect.width - rect.height, rect.height);
+            self.text(option, text, Align::Left, TEXT_COLOR);
+        }
+        changed
+    }
+
+    /// Checkboxes and choices for the parts of a `RenderState` that are worth flipping while something's running.
+    /// Returns true if any of it changed
+    pub fn render_state(&mut self, state: &mut RenderState) -> bool {
+        let mut changed = false;
+        changed |= self.checkbox("Depth test", &mut state.depth.test);
+        changed |= self.checkbox("Depth write", &mut state.depth.write);
+        let mut blending = state.blend.enabled;
+        if self.checkbox("Alpha blending", &mut blending) {
+            state.blend = if blending { BlendState::alpha() } else { BlendState::default() };
+            changed = true;
+        }
+
+        const CULL_MODES: [CullMode; 3] = [CullMode::None, CullMode::Back, CullMode::Front];
+        let mut cull = CULL_MODES.iter().position(|&mode| mode == state.cull_mode).unwrap_or(0);
+        if self.radio("Cull faces", &mut cull, &["None", "Back", "Front"]) {
+            state.cull_mode = CULL_MODES[cull];
+            changed = true;
+        }
+
+        const POLYGON_MODES: [PolygonMode; 3] = [PolygonMode::Fill, PolygonMode::Line, PolygonMode::Point];
+        let mut polygons = POLYGON_MODES.iter().position(|&mode| mode == state.polygon_mode).unwrap_or(0);
+        if self.radio("Polygons", &mut polygons, &["Fill", "Wireframe", "Points"]) {
+            state.polygon_mode = POLYGON_MODES[polygons];
+            changed = true;
+        }
+        changed
+    }
+
+    /// A widget for every uniform `program` has, worked out from what GL says about it: sliders for colors, draggable
+    /// numbers for other floats and vectors, checkboxes for bools, and plain labels for matrices and samplers (which
+    /// are better set from code).  Values are read back from GL every frame, so uniforms the program sets itself
+    /// show their latest value, but anything changed here gets overwritten the next time the program sets it.  Arrays
+    /// only show their first element
+    pub fn uniforms(&mut self, program: &Program) {
+        for uniform in program.active_uniforms() {
+            let floats = program.uniform_f32_values(uniform.location);
+            let name = uniform.name.as_str();
+            let components = match uniform.gl_type {
+                gl::FLOAT => 1,
+                gl::FLOAT_VEC2 => 2,
+                gl::FLOAT_VEC3 => 3,
+                gl::FLOAT_VEC4 => 4,
+                _ => 0,
+            };
+
+            if components > 0 {
+                let mut values = floats[..components].to_vec();
+                let changed = if components >= 3 && name.to_lowercase().contains("color") {
+                    self.color_edit(name, &mut values)
+                } else {
+                    self.drag_vector(name, &mut values, 0.01)
+                };
+                if changed {
+                    program.set_used();
+                    match values.len() {
+                        1 => program.set_uniform_f32(name, values[0]),
+                        2 => program.set_uniform_vec2(name, Vec2::new(values[0], values[1])),
+                        3 => program.set_uniform_vec3(name, Vec3::new(values[0], values[1], values[2])),
+                        _ => program.set_uniform_vec4(name, Vec4::new(values[0], values[1], values[2], values[3])),
+                    }
+                }
+                continue;
+            }
+
+            let mut int = program.uniform_i32_values(uniform.location)[0];
+            match uniform.gl_type {
+                gl::BOOL => {
+                    let mut value = int != 0;
+                    if self.checkbox(name, &mut value) {
+                        program.set_used();
+                        program.set_uniform_i32(name, value as i32);
+                    }
+                },
+                gl::INT => {
+                    if self.drag_i32(name, &mut int, 0.1) {
+                        program.set_used();
+                        program.set_uniform_i32(name, int);
+                    }
+                },
+                gl::SAMPLER_2D | gl::SAMPLER_3D | gl::SAMPLER_CUBE | gl::SAMPLER_2D_ARRAY | gl::SAMPLER_2D_SHADOW
+                | gl::SAMPLER_2D_ARRAY_SHADOW | gl::SAMPLER_CUBE_SHADOW => {
+                    self.value(name, &format!("{}, unit {}", glsl_type_name(uniform.gl_type), int));
+                },
+                gl_type => self.value(name, glsl_type_name(gl_type)),
+            }
+        }
+    }
+}
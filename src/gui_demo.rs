@@ -0,0 +1,211 @@
+use std::ffi::CString;
+use std::path::Path;
+
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::gui::Gui;
+use crate::math::{Mat4, Quat, Vec3, Vec4};
+use crate::mesh::Mesh;
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::resources::Resources;
+use crate::shapes;
+use crate::text::{self, AtlasKind, Font, FontAtlas};
+use crate::vertex::Vertex;
+
+/// Something to look at, and the program it's drawn with, whose uniforms get a panel of their own
+struct Scene {
+    name: &'static str,
+    program: Program,
+    mesh: Mesh,
+    is_3d: bool,  // whether it wants Model, View and Projection matrices
+}
+
+fn load_program(vert: &str, frag: &str) -> Result<Program, String> {
+    let vert_shader = Shader::from_vert_source(&CString::new(vert).unwrap())?;
+    let frag_shader = Shader::from_frag_source(&CString::new(frag).unwrap())?;
+    Program::from_shaders(&[vert_shader, frag_shader])
+}
+
+/// The GUI overlay, on top of a few of the earlier scenes: the very first colored triangle, a shaded torus, and a
+/// sphere with the UV checker on it.  The panels pick the scene, the clear color and some render state, show frame
+/// stats, and list every uniform the scene's program has, with widgets to change them.  Uniforms the demo sets itself
+/// every frame (the matrices, and the triangle's color while it's animating) just show what they currently are.
+///
+/// Panels can be dragged by their titles and folded up with the arrow.  Dragging anywhere else orbits the camera
+pub fn gui_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+
+    let res = Resources::from_crate_dir(Path::new("assets"));
+    let setup = || -> Result<(Gui, Vec<Scene>), String> {
+        let font = Font::load(&res, "fonts/DejaVuSans.ttf")?;
+        let gui = Gui::new(FontAtlas::bake(&font, 14.0, AtlasKind::Bitmap, text::ASCII)?)?;
+
+        let corners = [Vec3::new(-0.5, -0.5, 0.0), Vec3::new(0.5, -0.5, 0.0), Vec3::new(0.0, 0.5, 0.0)];
+        let triangle: Vec<Vertex> = corners.iter().map(|&position| Vertex { position, ..Vertex::default() }).collect();
+        let scenes = vec![
+            Scene {
+                name: "Triangle",
+                program: load_program(
+                    include_str!("coloring_with_uniforms.vert"),
+                    include_str!("coloring_with_uniforms.frag"),
+                )?,
+                mesh: Mesh::new(&triangle, &Vertex::layout(), None, gl::TRIANGLES),
+                is_3d: false,
+            },
+            Scene {
+                name: "Shaded torus",
+                program: load_program(include_str!("mesh.vert"), include_str!("mesh.frag"))?,
+                mesh: shapes::torus(1.5, 0.6, 48, 24).upload(),
+                is_3d: true,
+            },
+            Scene {
+                name: "UV checker",
+                program: load_program(include_str!("debug_view.vert"), include_str!("debug_uv_checker.frag"))?,
+                mesh: shapes::uv_sphere(1.8, 32, 16).upload(),
+                is_3d: true,
+            },
+        ];
+        // Starting values for the uniforms nothing else sets
+        scenes[1].program.set_used();
+        scenes[1].program.set_uniform_vec3("DiffuseColor", Vec3::new(0.9, 0.55, 0.3));
+        scenes[2].program.set_used();
+        scenes[2].program.set_uniform_f32("CheckerScale", 8.0);
+        Ok((gui, scenes))
+    };
+    let (mut gui, scenes) = match setup() {
+        Ok(setup) => setup,
+        Err(e) => {
+            println!("Failed to set up the GUI demo: {}", e);
+            return;
+        },
+    };
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 2.0, 6.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 6.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut render_state = RenderState::opaque();
+    let mut scene = 0;
+    let mut clear_color = [0.5, 0.3, 0.3];  // the same as the very first triangle's
+    let mut animate = true;
+    let mut spin = true;
+    let mut spin_speed = 0.5;
+    let mut angle = 0.0f32;
+    let mut time = 0.0f32;
+    let mut frame_ms = 0.0f64;
+    let frequency = timer.performance_frequency() as f64;
+    let mut last_counter = timer.performance_counter();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                _ => {
+                    camera.handle_resize(&event);
+                    // Clicks and drags on the panels are the GUI's, so they shouldn't move the camera as well
+                    if !gui.handle_event(&event) {
+                        controller.handle_event(&mut camera, &event);
+                    }
+                },
+            }
+        }
+
+        let counter = timer.performance_counter();
+        let dt = (counter - last_counter) as f64 / frequency;
+        last_counter = counter;
+        frame_ms = frame_ms * 0.95 + dt * 1000.0 * 0.05;  // smoothed, so the numbers are readable
+        time += dt as f32;
+        if spin {
+            angle += dt as f32 * spin_speed;
+        }
+
+        unsafe {
+            gl::ClearColor(clear_color[0], clear_color[1], clear_color[2], 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let current = &scenes[scene];
+        render_state_cache.apply(&render_state);
+        current.program.set_used();
+        if current.is_3d {
+            let model = Mat4::from_trs(Vec3::ZERO, Quat::from_axis_angle(Vec3::new(0.3, 1.0, 0.2).normalize(), angle),
+                                       Vec3::ONE);
+            current.program.set_uniform_mat4("Model", &model);
+            current.program.set_uniform_mat4("View", &camera.view_matrix());
+            current.program.set_uniform_mat4("Projection", &camera.projection_matrix());
+        } else if animate {
+            // The green pulsing from the very first demo
+            let green = time.sin() / 2.0 + 0.5;
+            current.program.set_uniform_vec4("ourColor", Vec4::new(0.0, green, 0.0, 1.0));
+        }
+        current.mesh.draw();
+
+        let (width, height) = window.size();
+        gui.begin_frame(width, height);
+        if gui.begin_panel("Scene", 10.0, 10.0, 220.0) {
+            let names: Vec<&str> = scenes.iter().map(|scene| scene.name).collect();
+            gui.radio("Showing", &mut scene, &names);
+            gui.separator();
+            gui.checkbox("Spin", &mut spin);
+            gui.slider_f32("Speed", &mut spin_speed, -3.0, 3.0);
+            if gui.button("Reset camera") {
+                camera = Camera::new(Vec3::new(0.0, 2.0, 6.0), width as f32 / height as f32);
+                controller = OrbitController::new(Vec3::ZERO, 6.0);
+                controller.attach(&mut camera);
+            }
+            gui.end_panel();
+        }
+        if gui.begin_panel("Clear color", 10.0, 220.0, 220.0) {
+            gui.color_edit("Color", &mut clear_color);
+            gui.end_panel();
+        }
+        if gui.begin_panel("Render state", 10.0, 350.0, 220.0) {
+            gui.render_state(&mut render_state);
+            gui.end_panel();
+        }
+        if gui.begin_panel("Frame stats", width as f32 - 230.0, 10.0, 220.0) {
+            let (applies, skipped) = render_state_cache.stats();
+            gui.value("Frame", &format!("{:.2} ms", frame_ms));
+            gui.value("FPS", &format!("{:.0}", 1000.0 / frame_ms.max(0.001)));
+            gui.value("Triangles", &(scenes[scene].mesh.count() / 3).to_string());
+            gui.value("State sets", &format!("{} ({} skipped)", applies, skipped));
+            gui.end_panel();
+        }
+        if gui.begin_panel("Uniforms", width as f32 - 330.0, 150.0, 320.0) {
+            gui.label(scenes[scene].name);
+            if !scenes[scene].is_3d {
+                gui.checkbox("Animate ourColor", &mut animate);
+            }
+            gui.separator();
+            gui.uniforms(&scenes[scene].program);
+            gui.end_panel();
+        }
+        gui.end_frame(&mut render_state_cache);
+
+        window.gl_swap_window();
+    }
+}
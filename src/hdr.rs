@@ -0,0 +1,171 @@
+// Decodes Radiance `.hdr` files, the usual format for the HDR environment maps (panoramas of a whole scene, brighter
+// than white wherever there's a sun or a lamp) that image-based lighting is built from.
+//
+// The file starts with a text header, ending in a blank line and then a line giving the size, like `-Y 512 +X 1024`
+// (512 rows from the top down, each 1024 pixels from left to right).  Each pixel is four bytes, RGBE: a mantissa for
+// each of red, green and blue that all share the exponent in the fourth byte, which gives a huge range of brightness in
+// not much space.  Rows are usually run-length encoded one channel at a time, since neighbouring pixels tend to have
+// the same exponent.
+
+use crate::resources::Resources;
+
+/// Floating point RGB pixels, starting at the top-left corner and going row by row.  Unlike `Image`, the values aren't
+/// limited to 0 to 1
+#[derive(Debug, Clone)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<f32>,  // three per pixel
+}
+
+#[allow(dead_code)]
+impl HdrImage {
+    pub fn load(res: &Resources, resource_name: &str) -> Result<HdrImage, String> {
+        let bytes = res.load_buffer(resource_name).map_err(|e| format!("{}: {}", resource_name, e))?;
+        HdrImage::decode(&bytes).map_err(|e| format!("{}: {}", resource_name, e))
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<HdrImage, String> {
+        if !bytes.starts_with(b"#?") {
+            return Err("not a Radiance HDR file".to_string());
+        }
+
+        // The header is lines of text up to the first empty one, then one more line with the size
+        let mut pos = 0;
+        let next_line = |pos: &mut usize| -> Result<String, String> {
+            let end = bytes[*pos..].iter().position(|&b| b == b'\n').ok_or("HDR header is truncated")?;
+            let line = String::from_utf8_lossy(&bytes[*pos..*pos + end]).trim_end().to_string();
+            *pos += end + 1;
+            Ok(line)
+        };
+        loop {
+            let line = next_line(&mut pos)?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(format!("unsupported HDR pixel format '{}'", format));
+                }
+            }
+        }
+
+        let size_line = next_line(&mut pos)?;
+        let parts: Vec<&str> = size_line.split_whitespace().collect();
+        let (flipped, height, width) = match parts.as_slice() {
+            ["-Y", height, "+X", width] => (false, *height, *width),
+            ["+Y", height, "+X", width] => (true, *height, *width),
+            _ => return Err(format!("unsupported HDR orientation '{}'", size_line)),
+        };
+        let parse_size = |s: &str| s.parse::<u32>().map_err(|_| format!("invalid HDR size '{}'", size_line));
+        let (width, height) = (parse_size(width)?, parse_size(height)?);
+
+        let row_len = width as usize;
+        let mut rgbe = vec![0u8; row_len * 4];
+        // A bogus size in the header shouldn't get to reserve gigabytes, so don't reserve more than the bytes left
+        let pixel_count = row_len.saturating_mul(height as usize).min(bytes.len() - pos);
+        let mut pixels = Vec::with_capacity(pixel_count * 3);
+        for _ in 0..height {
+            pos = read_scanline(bytes, pos, &mut rgbe)?;
+            for pixel in rgbe.chunks_exact(4) {
+                pixels.extend_from_slice(&rgbe_to_rgb(pixel));
+            }
+        }
+
+        let mut image = HdrImage { width, height, pixels };
+        if flipped {
+            image.flip_vertically();
+        }
+        Ok(image)
+    }
+
+    pub fn flip_vertically(&mut self) {
+        let row_len = (self.width * 3) as usize;
+        let height = self.height as usize;
+        for y in 0..height / 2 {
+            let (top, bottom) = self.pixels.split_at_mut((height - 1 - y) * row_len);
+            top[y * row_len..(y + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+        }
+    }
+}
+
+/// The shared exponent is stored offset by 128, and the mantissas are 0 to 255 rather than 0 to 1, hence the 136
+fn rgbe_to_rgb(rgbe: &[u8]) -> [f32; 3] {
+    if rgbe[3] == 0 {
+        return [0.0; 3];
+    }
+    let scale = 2f32.powi(rgbe[3] as i32 - 136);
+    [rgbe[0] as f32 * scale, rgbe[1] as f32 * scale, rgbe[2] as f32 * scale]
+}
+
+/// Reads one row of RGBE pixels starting at `pos` into `row`, returning where the next row starts
+fn read_scanline(bytes: &[u8], mut pos: usize, row: &mut [u8]) -> Result<usize, String> {
+    let width = row.len() / 4;
+    let byte = |pos: &mut usize| -> Result<u8, String> {
+        let b = *bytes.get(*pos).ok_or("HDR pixel data is truncated")?;
+        *pos += 1;
+        Ok(b)
+    };
+
+    // Run-length encoded rows start with 2, 2 and then the row width, which can't be the start of a sensible pixel.
+    // Very narrow and very wide rows can't be encoded this way, so they're always flat
+    let encoded = (8..0x8000).contains(&width)
+        && matches!(bytes.get(pos..pos + 4), Some(b) if b[0] == 2 && b[1] == 2 && b[2] & 0x80 == 0);
+    if !encoded {
+        return read_flat_scanline(bytes, pos, row);
+    }
+    if ((bytes[pos + 2] as usize) << 8 | bytes[pos + 3] as usize) != width {
+        return Err("HDR scanline width doesn't match the image width".to_string());
+    }
+    pos += 4;
+
+    // Each channel is stored separately, as a list of runs: a count above 128 means repeat the next byte (count - 128)
+    // times, anything else means that many bytes copied as they are
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = byte(&mut pos)? as usize;
+            let (run, repeat) = if count > 128 { (count - 128, true) } else { (count, false) };
+            if run == 0 || x + run > width {
+                return Err("HDR scanline has a bad run length".to_string());
+            }
+            let value = if repeat { byte(&mut pos)? } else { 0 };
+            for _ in 0..run {
+                row[x * 4 + channel] = if repeat { value } else { byte(&mut pos)? };
+                x += 1;
+            }
+        }
+    }
+    Ok(pos)
+}
+
+/// The older format: plain RGBE pixels, where a pixel of 1, 1, 1 means "repeat the last pixel", with the count in the
+/// exponent byte (and consecutive repeats counting in bigger and bigger steps)
+fn read_flat_scanline(bytes: &[u8], mut pos: usize, row: &mut [u8]) -> Result<usize, String> {
+    let width = row.len() / 4;
+    let mut x = 0;
+    let mut shift = 0;
+    while x < width {
+        let pixel = bytes.get(pos..pos + 4).ok_or("HDR pixel data is truncated")?;
+        pos += 4;
+        if pixel[0] == 1 && pixel[1] == 1 && pixel[2] == 1 {
+            if x == 0 {
+                return Err("HDR scanline starts with a repeat".to_string());
+            }
+            let count = (pixel[3] as usize) << shift;
+            if count == 0 || x + count > width {
+                return Err("HDR scanline has a bad run length".to_string());
+            }
+            for _ in 0..count {
+                row.copy_within((x - 1) * 4..x * 4, x * 4);
+                x += 1;
+            }
+            shift += 8;
+        } else {
+            row[x * 4..x * 4 + 4].copy_from_slice(pixel);
+            x += 1;
+            shift = 0;
+        }
+    }
+    Ok(pos)
+}
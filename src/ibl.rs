@@ -0,0 +1,358 @@
+// Image-based lighting: lighting a scene with a whole HDR panorama of its surroundings rather than a handful of point
+// lights.  In principle every pixel would integrate the light arriving from every direction, which is far too slow to
+// do per frame, so `Environment::load()` precomputes it on the GPU into three pieces that shading just looks up:
+//
+// - the irradiance cubemap, the cosine-weighted average of the light around each direction, for diffuse lighting
+// - the prefiltered cubemap, the environment blurred by a GGX lobe of more and more roughness down its mip levels, for
+//   specular reflections
+// - the BRDF lookup table, how much of that reflection a surface sends back depending on viewing angle and roughness
+//
+// Precomputing takes a noticeable moment, so the results are cached to disk next to the other resources (under
+// `cache/`) and reused as long as the HDR file and the settings haven't changed.  Cache files are a small header
+// followed by raw little-endian floats:
+//
+//     "IBLC", version (u32), key (u64), size (u32), faces (u32), channels (u32), levels (u32), pixels...
+//
+// where the key is a hash of everything the maps were computed from, and the pixels go level by level, with each
+// level being every face one after another.
+
+use crate::framebuffer::{self, Framebuffer, FullscreenTriangle};
+use crate::hdr::HdrImage;
+use crate::program::Program;
+use crate::render_gl;
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::resources::{self, Resources};
+use crate::texture::{Cubemap, Texture};
+
+/// Texture units `Environment::bind()` uses, kept well clear of the ones materials use (which count up from 0)
+pub const IRRADIANCE_UNIT: u32 = 13;
+pub const PREFILTERED_UNIT: u32 = 14;
+pub const BRDF_LUT_UNIT: u32 = 15;
+
+const CACHE_MAGIC: &[u8; 4] = b"IBLC";
+const CACHE_VERSION: u32 = 1;
+const CACHE_HEADER_LEN: usize = 32;
+
+/// How big each precomputed map is, and how hard to work on them.  Changing any of these recomputes the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IblSettings {
+    pub environment_size: u32,  // the cubemap the panorama is copied onto, which is also what the skybox shows
+    pub irradiance_size: u32,   // irradiance is very blurry, so this can be tiny
+    pub prefiltered_size: u32,
+    pub prefiltered_levels: u32,  // from perfectly smooth at the top level to fully rough at the bottom
+    pub brdf_lut_size: u32,
+    pub sample_count: u32,  // per pixel, for all three
+}
+
+impl Default for IblSettings {
+    fn default() -> IblSettings {
+        IblSettings {
+            environment_size: 512,
+            irradiance_size: 32,
+            prefiltered_size: 128,
+            prefiltered_levels: 5,
+            brdf_lut_size: 256,
+            sample_count: 1024,
+        }
+    }
+}
+
+/// The floats making up a cubemap (6 faces) or a plain texture (1 face), the way the cache stores them
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedMaps {
+    pub size: u32,
+    pub faces: u32,
+    pub channels: u32,
+    pub levels: Vec<Vec<f32>>,  // each one half the size of the last, with every face one after another
+}
+
+#[allow(dead_code)]
+impl CachedMaps {
+    /// How many floats level `level` should have
+    pub fn level_len(&self, level: usize) -> usize {
+        let level_size = (self.size >> level).max(1) as usize;
+        level_size * level_size * (self.faces * self.channels) as usize
+    }
+
+    pub fn encode(&self, key: u64) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(CACHE_HEADER_LEN + self.levels.iter().map(Vec::len).sum::<usize>() * 4);
+        bytes.extend_from_slice(CACHE_MAGIC);
+        bytes.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&key.to_le_bytes());
+        for value in &[self.size, self.faces, self.channels, self.levels.len() as u32] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for level in &self.levels {
+            for value in level {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Returns the key the maps were saved with along with the maps themselves
+    pub fn decode(bytes: &[u8]) -> Result<(u64, CachedMaps), String> {
+        if bytes.len() < CACHE_HEADER_LEN || &bytes[..4] != CACHE_MAGIC {
+            return Err("not an IBL cache file".to_string());
+        }
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+        };
+        if u32_at(4) != CACHE_VERSION {
+            return Err(format!("IBL cache file is version {}, expected {}", u32_at(4), CACHE_VERSION));
+        }
+        let mut key_bytes = [0u8; 8];
+        key_bytes.copy_from_slice(&bytes[8..16]);
+        let key = u64::from_le_bytes(key_bytes);
+
+        let mut maps = CachedMaps { size: u32_at(16), faces: u32_at(20), channels: u32_at(24), levels: Vec::new() };
+        let level_count = u32_at(28) as usize;
+        if maps.size == 0 || maps.size > 1 << 16 || maps.faces == 0 || maps.faces > 6 || maps.channels > 4
+            || level_count == 0 || level_count > 32 {
+            return Err("IBL cache file has a bad header".to_string());
+        }
+        let expected: usize = (0..level_count).map(|level| maps.level_len(level)).sum();
+        if bytes.len() != CACHE_HEADER_LEN + expected * 4 {
+            return Err("IBL cache file is the wrong length".to_string());
+        }
+
+        let mut floats = bytes[CACHE_HEADER_LEN..].chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+        for level in 0..level_count {
+            let level_floats = floats.by_ref().take(maps.level_len(level)).collect();
+            maps.levels.push(level_floats);
+        }
+        Ok((key, maps))
+    }
+}
+
+/// The cached maps in `resource_name`, if there are any, they were made with `key`, and they have the number of faces
+/// and channels we're after
+fn load_cached(
+    res: &Resources,
+    resource_name: &str,
+    key: u64,
+    (faces, channels): (u32, u32),
+    warnings: &mut Vec<String>,
+) -> Option<CachedMaps> {
+    let bytes = res.load_buffer(resource_name).ok()?;  // not cached yet
+    match CachedMaps::decode(&bytes) {
+        Ok((cached_key, maps)) if cached_key == key => {
+            if maps.faces == faces && maps.channels == channels {
+                Some(maps)
+            } else {
+                warnings.push(format!("{}: holds the wrong kind of map, recomputing it", resource_name));
+                None
+            }
+        },
+        Ok(_) => None,  // out of date
+        Err(e) => {
+            warnings.push(format!("{}: {}, recomputing it", resource_name, e));
+            None
+        },
+    }
+}
+
+fn save_cached(res: &Resources, resource_name: &str, key: u64, maps: &CachedMaps, warnings: &mut Vec<String>) {
+    if let Err(e) = res.save_buffer(resource_name, &maps.encode(key)) {
+        warnings.push(format!("{}: failed to save: {}", resource_name, e));
+    }
+}
+
+/// `fullscreen.vert` with one of the IBL fragment shaders, with `ibl_common.glsl` pasted into the latter
+fn ibl_program(fragment_source: &str) -> Result<Program, String> {
+    framebuffer::fullscreen_program(&render_gl::insert_after_version(fragment_source, include_str!("ibl_common.glsl")))
+}
+
+/// Draws `program` over every face of mip level `level` of `target`
+fn render_faces(
+    framebuffer: &mut Framebuffer,
+    fullscreen: &FullscreenTriangle,
+    program: &Program,
+    target: &Cubemap,
+    level: u32,
+) -> Result<(), String> {
+    for face in 0..6 {
+        framebuffer.attach_cubemap_face(target, face, level)?;
+        framebuffer.bind();
+        program.set_uniform_i32("Face", face as i32);
+        fullscreen.draw();
+    }
+    Ok(())
+}
+
+/// Everything image-based lighting needs from one HDR panorama, ready to bind
+pub struct Environment {
+    pub skybox: Cubemap,  // the panorama itself, as a mipmapped cubemap
+    pub irradiance: Cubemap,
+    pub prefiltered: Cubemap,
+    pub brdf_lut: Texture,
+    pub from_cache: bool,  // whether all of the precomputed maps came from the cache
+    pub warnings: Vec<String>,
+}
+
+#[allow(dead_code)]
+impl Environment {
+    /// Loads the equirectangular `.hdr` panorama `resource_name` and builds (or loads from the cache) everything from
+    /// it.  Draws with its own render state, which goes through `cache` so that stays in step, and puts the
+    /// framebuffer and viewport back how it found them
+    pub fn load(
+        res: &Resources,
+        resource_name: &str,
+        settings: &IblSettings,
+        cache: &mut RenderStateCache,
+    ) -> Result<Environment, String> {
+        let source = res.load_buffer(resource_name).map_err(|e| format!("{}: {}", resource_name, e))?;
+        let image = HdrImage::decode(&source).map_err(|e| format!("{}: {}", resource_name, e))?;
+
+        let mut viewport = [0 as gl::types::GLint; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            // Without this, filtering stops at the edge of each face and the seams between them show up as lines,
+            // especially in the blurry mip levels
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+        }
+        cache.apply(&RenderState::default());
+
+        let result = Environment::build(res, resource_name, &source, &image, settings);
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        result
+    }
+
+    fn build(
+        res: &Resources,
+        resource_name: &str,
+        source: &[u8],
+        image: &HdrImage,
+        settings: &IblSettings,
+    ) -> Result<Environment, String> {
+        let mut warnings = Vec::new();
+        let mut framebuffer = Framebuffer::empty();
+        let fullscreen = FullscreenTriangle::new();
+        let setting_bytes: Vec<u8> = [
+            settings.environment_size,
+            settings.irradiance_size,
+            settings.prefiltered_size,
+            settings.prefiltered_levels,
+            settings.brdf_lut_size,
+            settings.sample_count,
+            CACHE_VERSION,
+        ].iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+        let key = resources::cache_key(&[source, &setting_bytes]);
+        let cache_name = |map: &str| format!("cache/{}.{}", resource_name.replace('/', "_"), map);
+
+        // Copy the panorama onto a cubemap, which is what the other passes (and the skybox) sample.  Its mip levels
+        // let the convolutions read pre-averaged versions of it
+        let equirectangular = Texture::from_f32(image.width, image.height, 3, &image.pixels, gl::RGB32F);
+        equirectangular.set_wrap(gl::REPEAT, gl::CLAMP_TO_EDGE);  // longitude wraps around
+        let environment_levels = 32 - settings.environment_size.max(1).leading_zeros();
+        let skybox = Cubemap::new(settings.environment_size, environment_levels, gl::RGB16F);
+        let equirect_program = ibl_program(include_str!("ibl_equirect.frag"))?;
+        equirect_program.set_used();
+        equirectangular.bind(0);
+        equirect_program.set_uniform_i32("Equirectangular", 0);
+        render_faces(&mut framebuffer, &fullscreen, &equirect_program, &skybox, 0)?;
+        skybox.generate_mipmaps();
+        skybox.bind(0);
+
+        let irradiance_name = cache_name("irradiance");
+        let (irradiance, irradiance_cached) = match load_cached(res, &irradiance_name, key, (6, 3), &mut warnings) {
+            Some(maps) => (Cubemap::from_f32(maps.size, 3, &maps.levels, gl::RGB16F), true),
+            None => {
+                let irradiance = Cubemap::new(settings.irradiance_size, 1, gl::RGB16F);
+                let program = ibl_program(include_str!("ibl_irradiance.frag"))?;
+                program.set_used();
+                program.set_uniform_i32("Environment", 0);
+                program.set_uniform_f32("EnvironmentSize", settings.environment_size as f32);
+                program.set_uniform_i32("SampleCount", settings.sample_count as i32);
+                render_faces(&mut framebuffer, &fullscreen, &program, &irradiance, 0)?;
+                let maps = CachedMaps {
+                    size: settings.irradiance_size,
+                    faces: 6,
+                    channels: 3,
+                    levels: vec![irradiance.read_f32(0, 3)],
+                };
+                save_cached(res, &irradiance_name, key, &maps, &mut warnings);
+                (irradiance, false)
+            },
+        };
+
+        let prefiltered_name = cache_name("prefiltered");
+        let (prefiltered, prefiltered_cached) = match load_cached(res, &prefiltered_name, key, (6, 3), &mut warnings) {
+            Some(maps) => (Cubemap::from_f32(maps.size, 3, &maps.levels, gl::RGB16F), true),
+            None => {
+                let levels = settings.prefiltered_levels.max(1);
+                let prefiltered = Cubemap::new(settings.prefiltered_size, levels, gl::RGB16F);
+                let program = ibl_program(include_str!("ibl_prefilter.frag"))?;
+                program.set_used();
+                program.set_uniform_i32("Environment", 0);
+                program.set_uniform_f32("EnvironmentSize", settings.environment_size as f32);
+                program.set_uniform_i32("SampleCount", settings.sample_count as i32);
+                for level in 0..levels {
+                    let roughness = if levels > 1 { level as f32 / (levels - 1) as f32 } else { 0.0 };
+                    program.set_uniform_f32("Roughness", roughness);
+                    render_faces(&mut framebuffer, &fullscreen, &program, &prefiltered, level)?;
+                }
+                let maps = CachedMaps {
+                    size: settings.prefiltered_size,
+                    faces: 6,
+                    channels: 3,
+                    levels: (0..levels).map(|level| prefiltered.read_f32(level, 3)).collect(),
+                };
+                save_cached(res, &prefiltered_name, key, &maps, &mut warnings);
+                (prefiltered, false)
+            },
+        };
+
+        // The lookup table doesn't depend on the environment, so it's shared between them all
+        let brdf_name = "cache/brdf_lut";
+        let brdf_key = resources::cache_key(&[
+            b"brdf",
+            &settings.brdf_lut_size.to_le_bytes(),
+            &settings.sample_count.to_le_bytes(),
+            &CACHE_VERSION.to_le_bytes(),
+        ]);
+        let (brdf_lut, brdf_cached) = match load_cached(res, brdf_name, brdf_key, (1, 2), &mut warnings) {
+            Some(maps) => (Texture::from_f32(maps.size, maps.size, 2, &maps.levels[0], gl::RG16F), true),
+            None => {
+                let size = settings.brdf_lut_size;
+                let brdf_lut = Texture::empty(size, size, gl::RG16F);
+                let program = ibl_program(include_str!("ibl_brdf.frag"))?;
+                program.set_used();
+                program.set_uniform_i32("SampleCount", settings.sample_count as i32);
+                framebuffer.attach_texture(&brdf_lut)?;
+                framebuffer.bind();
+                fullscreen.draw();
+                let maps = CachedMaps { size, faces: 1, channels: 2, levels: vec![brdf_lut.read_f32(2)] };
+                save_cached(res, brdf_name, brdf_key, &maps, &mut warnings);
+                (brdf_lut, false)
+            },
+        };
+
+        Ok(Environment {
+            skybox,
+            irradiance,
+            prefiltered,
+            brdf_lut,
+            from_cache: irradiance_cached && prefiltered_cached && brdf_cached,
+            warnings,
+        })
+    }
+
+    /// Binds the maps to their texture units and points `program`'s samplers (`IrradianceMap`, `PrefilteredMap` and
+    /// `BrdfLut`, as in `pbr.frag`) at them.  `program` needs to be in use
+    pub fn bind(&self, program: &Program, intensity: f32) {
+        self.irradiance.bind(IRRADIANCE_UNIT);
+        self.prefiltered.bind(PREFILTERED_UNIT);
+        self.brdf_lut.bind(BRDF_LUT_UNIT);
+        program.set_uniform_i32("IrradianceMap", IRRADIANCE_UNIT as i32);
+        program.set_uniform_i32("PrefilteredMap", PREFILTERED_UNIT as i32);
+        program.set_uniform_i32("BrdfLut", BRDF_LUT_UNIT as i32);
+        program.set_uniform_f32("PrefilteredLevels", self.prefiltered.levels() as f32);
+        program.set_uniform_f32("EnvironmentIntensity", intensity);
+    }
+}
@@ -0,0 +1,44 @@
+#version 330 core
+
+// The second half of the split sum: how much of the prefiltered environment a surface reflects, as a scale and a bias
+// to apply to its base reflectivity (F0), for every viewing angle (x) and roughness (y).  It doesn't depend on the
+// environment at all, so one table does for every environment map
+
+uniform int SampleCount;
+
+out vec2 Color;
+
+// Smith's shadowing term for one direction, with the `k` that image-based lighting uses
+float geometry_schlick_ggx(float n_dot_x, float roughness)
+{
+    float k = roughness * roughness / 2.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+void main()
+{
+    float n_dot_v = max(TexCoord.x, 0.0001);
+    float roughness = TexCoord.y;
+    vec3 n = vec3(0.0, 0.0, 1.0);
+    vec3 v = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+
+    uint count = uint(SampleCount);
+    float scale = 0.0;
+    float bias = 0.0;
+    for (uint i = 0u; i < count; i++) {
+        vec3 h = importance_sample_ggx(hammersley(i, count), n, roughness);
+        vec3 l = 2.0 * dot(v, h) * h - v;
+        float n_dot_l = max(l.z, 0.0);
+        if (n_dot_l > 0.0) {
+            float n_dot_h = max(h.z, 0.0);
+            float v_dot_h = max(dot(v, h), 0.0);
+            float g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+            float g_visible = g * v_dot_h / (n_dot_h * n_dot_v);
+            float fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fresnel) * g_visible;
+            bias += fresnel * g_visible;
+        }
+    }
+
+    Color = vec2(scale, bias) / float(count);
+}
@@ -0,0 +1,79 @@
+// Shared by the image-based lighting shaders; `ibl.rs` pastes it in after the `#version` line
+
+const float PI = 3.14159265359;
+
+in vec2 TexCoord;
+uniform int Face;  // which face of the cubemap is being drawn, in GL's order: +X, -X, +Y, -Y, +Z, -Z
+
+// The direction through a point on a cubemap face, with `uv` going 0 to 1 across the face the way it's drawn into.
+// Each face's axes follow the table in the GL spec, which has them pointing in some surprising directions
+vec3 cube_face_direction(int face, vec2 uv)
+{
+    vec2 p = uv * 2.0 - 1.0;
+    vec3 direction;
+    if (face == 0) {
+        direction = vec3(1.0, -p.y, -p.x);
+    } else if (face == 1) {
+        direction = vec3(-1.0, -p.y, p.x);
+    } else if (face == 2) {
+        direction = vec3(p.x, 1.0, p.y);
+    } else if (face == 3) {
+        direction = vec3(p.x, -1.0, -p.y);
+    } else if (face == 4) {
+        direction = vec3(p.x, -p.y, 1.0);
+    } else {
+        direction = vec3(-p.x, -p.y, -1.0);
+    }
+    return normalize(direction);
+}
+
+// Points spread evenly over the unit square, without the clumps and gaps random ones would have, so fewer samples
+// give a smooth result.  The second coordinate is the sample number with its bits mirrored
+vec2 hammersley(uint i, uint count)
+{
+    uint bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10);
+}
+
+// Turns a direction around +Z into the same direction around `n`
+vec3 around_normal(vec3 direction, vec3 n)
+{
+    vec3 up = abs(n.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, n));
+    vec3 bitangent = cross(n, tangent);
+    return tangent * direction.x + bitangent * direction.y + n * direction.z;
+}
+
+// GGX normal distribution: how many microscopic bumps on the surface face along a halfway vector `n_dot_h` away from
+// the normal
+float distribution_ggx(float n_dot_h, float roughness)
+{
+    float a2 = roughness * roughness * roughness * roughness;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Picks a halfway vector with the GGX distribution, so samples bunch up where the BRDF is big instead of being wasted
+vec3 importance_sample_ggx(vec2 xi, vec3 n, float roughness)
+{
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    return around_normal(vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta), n);
+}
+
+// Which mip level of a `size` by `size` cubemap to read a sample from, so that each sample averages over roughly the
+// patch of the environment it stands for.  Reading the top level for every sample instead would need thousands more
+// of them before small bright spots (like the sun) stop showing up as speckles
+float sample_level(float pdf, uint count, float size)
+{
+    float sample_solid_angle = 1.0 / (float(count) * pdf + 0.0001);
+    float texel_solid_angle = 4.0 * PI / (6.0 * size * size);
+    return max(0.5 * log2(sample_solid_angle / texel_solid_angle) + 1.0, 0.0);
+}
@@ -0,0 +1,14 @@
+#version 330 core
+
+// Copies an equirectangular panorama (longitude across, latitude down, like a world map) onto one face of a cubemap
+
+uniform sampler2D Equirectangular;
+
+out vec4 Color;
+
+void main()
+{
+    vec3 direction = cube_face_direction(Face, TexCoord);
+    vec2 uv = vec2(atan(direction.z, direction.x) / (2.0 * PI) + 0.5, 0.5 - asin(clamp(direction.y, -1.0, 1.0)) / PI);
+    Color = vec4(texture(Equirectangular, uv).rgb, 1.0);
+}
@@ -0,0 +1,32 @@
+#version 330 core
+
+// For each direction a surface could face, the average light arriving over the half of the environment in front of
+// it, weighted by how squarely it hits (the cosine in Lambert's law).  That's all diffuse lighting needs, so the
+// expensive averaging happens once here rather than every frame.
+//
+// The samples are spread out in proportion to the cosine, which takes care of the weighting
+
+uniform samplerCube Environment;
+uniform float EnvironmentSize;
+uniform int SampleCount;
+
+out vec4 Color;
+
+void main()
+{
+    vec3 n = cube_face_direction(Face, TexCoord);
+    uint count = uint(SampleCount);
+
+    vec3 sum = vec3(0.0);
+    for (uint i = 0u; i < count; i++) {
+        vec2 xi = hammersley(i, count);
+        float phi = 2.0 * PI * xi.x;
+        float cos_theta = sqrt(1.0 - xi.y);
+        float sin_theta = sqrt(xi.y);
+        vec3 l = around_normal(vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta), n);
+        float pdf = cos_theta / PI;
+        sum += textureLod(Environment, l, sample_level(pdf, count, EnvironmentSize)).rgb;
+    }
+
+    Color = vec4(sum / float(count), 1.0);
+}
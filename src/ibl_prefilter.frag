@@ -0,0 +1,43 @@
+#version 330 core
+
+// Blurs the environment the way a surface with the given roughness would blur its reflection, using the GGX
+// distribution.  Each mip level of the prefiltered cubemap gets a rougher version, so shading just picks the level
+// matching a surface's roughness.
+//
+// This is the first half of the "split sum" approximation: it pretends the surface is being looked at straight on
+// (view direction = normal = reflection direction), which loses the stretched-out reflections you get at grazing angles
+// but makes the result depend on the direction alone
+
+uniform samplerCube Environment;
+uniform float EnvironmentSize;
+uniform int SampleCount;
+uniform float Roughness;
+
+out vec4 Color;
+
+void main()
+{
+    vec3 n = cube_face_direction(Face, TexCoord);
+    vec3 v = n;
+    if (Roughness == 0.0) {
+        Color = vec4(textureLod(Environment, n, 0.0).rgb, 1.0);  // a perfect mirror, so there's nothing to blur
+        return;
+    }
+
+    uint count = uint(SampleCount);
+    vec3 sum = vec3(0.0);
+    float total_weight = 0.0;
+    for (uint i = 0u; i < count; i++) {
+        vec3 h = importance_sample_ggx(hammersley(i, count), n, Roughness);
+        vec3 l = 2.0 * dot(v, h) * h - v;
+        float n_dot_l = dot(n, l);
+        if (n_dot_l > 0.0) {
+            // With v = n, the usual D * n_dot_h / (4 * v_dot_h) simplifies to this
+            float pdf = distribution_ggx(max(dot(n, h), 0.0), Roughness) / 4.0;
+            sum += textureLod(Environment, l, sample_level(pdf, count, EnvironmentSize)).rgb * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+
+    Color = vec4(sum / max(total_weight, 0.0001), 1.0);
+}
@@ -0,0 +1,303 @@
+// Decodes image files into plain RGBA pixels that can be handed to `Texture`.  Only PNG is supported for now, since
+// it's lossless, supports alpha, and is what most of our assets (and most glTF exports) use.
+//
+// A PNG file is a signature followed by a list of chunks.  The pixels live in one or more `IDAT` chunks, which
+// together are a zlib stream.  After decompressing, every row of pixels starts with a byte saying which "filter" was
+// used on that row (each filter predicts a byte from its neighbours, and only the difference gets stored), so the last
+// step is undoing those filters.
+
+use miniz_oxide::inflate::decompress_to_vec_zlib;
+
+use crate::resources::Resources;
+
+/// 8 bits per channel RGBA pixels, starting at the top-left corner and going row by row
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+#[allow(dead_code)]
+impl Image {
+    /// Makes an image that's all one color
+    pub fn solid(width: u32, height: u32, rgba: [u8; 4]) -> Result<Image, String> {
+        let pixels = rgba.iter().cloned().cycle().take(byte_size(width, height)?).collect();
+        Ok(Image { width, height, pixels })
+    }
+
+    pub fn load(res: &Resources, resource_name: &str) -> Result<Image, String> {
+        let bytes = res.load_buffer(resource_name).map_err(|e| format!("{}: {}", resource_name, e))?;
+        Image::decode(&bytes).map_err(|e| format!("{}: {}", resource_name, e))
+    }
+
+    /// Works out the format from the first few bytes of the file
+    pub fn decode(bytes: &[u8]) -> Result<Image, String> {
+        if bytes.starts_with(&PNG_SIGNATURE) {
+            decode_png(bytes)
+        } else if bytes.starts_with(&[0xFF, 0xD8]) {
+            Err("JPEG images aren't supported yet, convert it to a PNG".to_string())
+        } else {
+            Err("unrecognized image format".to_string())
+        }
+    }
+
+    /// GL's texture coordinates start at the bottom-left, while images are stored starting at the top-left, so things
+    /// like sprite sheets need flipping before upload
+    pub fn flip_vertically(&mut self) {
+        let row_len = self.width as usize * 4;
+        let height = self.height as usize;
+        for y in 0..height / 2 {
+            let (top, bottom) = self.pixels.split_at_mut((height - 1 - y) * row_len);
+            top[y * row_len..(y + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+        }
+    }
+}
+
+/// How many bytes `width` by `height` RGBA8 pixels take up.  The sizes often come straight from a file, so this fails
+/// rather than overflowing
+pub fn byte_size(width: u32, height: u32) -> Result<usize, String> {
+    (width as usize).checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .ok_or_else(|| format!("a {}x{} image is too big", width, height))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn decode_png(bytes: &[u8]) -> Result<Image, String> {
+    let mut pos = PNG_SIGNATURE.len();
+    let mut header: Option<(u32, u32, u8, u8, u8)> = None;  // width, height, bit depth, color type, interlace
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut transparency: Option<Vec<u8>> = None;
+    let mut compressed: Vec<u8> = Vec::new();
+
+    while pos + 8 <= bytes.len() {
+        let length = read_u32(bytes, pos) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let data = bytes.get(pos + 8..pos + 8 + length).ok_or("PNG chunk runs past the end of the file")?;
+        pos += 12 + length;  // length, type, data, CRC
+
+        match kind {
+            b"IHDR" => {
+                if data.len() < 13 {
+                    return Err("PNG header is too short".to_string());
+                }
+                header = Some((read_u32(data, 0), read_u32(data, 4), data[8], data[9], data[12]));
+            },
+            b"PLTE" => palette = data.chunks_exact(3).map(|c| [c[0], c[1], c[2], 255]).collect(),
+            b"tRNS" => transparency = Some(data.to_vec()),
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {},  // ancillary chunks like gamma and text, which we don't need
+        }
+    }
+
+    let (width, height, bit_depth, color_type, interlace) = header.ok_or("PNG is missing its header")?;
+    if interlace != 0 {
+        return Err("interlaced PNGs aren't supported".to_string());
+    }
+
+    // Samples per pixel for each color type: grayscale, RGB, palette, grayscale + alpha, RGBA
+    let channels = match color_type {
+        0 => 1,
+        2 => 3,
+        3 => 1,
+        4 => 2,
+        6 => 4,
+        _ => return Err(format!("invalid PNG color type {}", color_type)),
+    };
+    if ![1, 2, 4, 8, 16].contains(&bit_depth) || (bit_depth < 8 && color_type != 0 && color_type != 3) {
+        return Err(format!("unsupported PNG bit depth {} for color type {}", bit_depth, color_type));
+    }
+    let size = byte_size(width, height)?;
+
+    let raw = decompress_to_vec_zlib(&compressed).map_err(|e| format!("PNG data failed to decompress: {:?}", e))?;
+
+    let bits_per_pixel = channels * bit_depth as usize;
+    let bytes_per_pixel = bits_per_pixel.div_ceil(8).max(1);  // filters work on whole bytes
+    let stride = (width as usize * bits_per_pixel).div_ceil(8);
+    if (stride + 1).checked_mul(height as usize).is_none_or(|needed| raw.len() < needed) {
+        return Err("PNG image data is truncated".to_string());
+    }
+
+    // Undo the per-row filters
+    let mut unfiltered = vec![0u8; stride * height as usize];
+    for y in 0..height as usize {
+        let filter = raw[y * (stride + 1)];
+        let src = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (previous_rows, current_rows) = unfiltered.split_at_mut(y * stride);
+        let prior: &[u8] = if y > 0 { &previous_rows[(y - 1) * stride..] } else { &[] };
+        let row = &mut current_rows[..stride];
+
+        for x in 0..stride {
+            let a = if x >= bytes_per_pixel { row[x - bytes_per_pixel] as i16 } else { 0 };
+            let b = if y > 0 { prior[x] as i16 } else { 0 };
+            let c = if y > 0 && x >= bytes_per_pixel { prior[x - bytes_per_pixel] as i16 } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => (a + b) / 2,
+                4 => {
+                    // Paeth: whichever of left, up and up-left is closest to left + up - up-left
+                    let p = a + b - c;
+                    let (pa, pb, pc) = ((p - a).abs(), (p - b).abs(), (p - c).abs());
+                    if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+                },
+                _ => return Err(format!("invalid PNG filter type {}", filter)),
+            };
+            row[x] = src[x].wrapping_add(predicted as u8);
+        }
+    }
+
+    // Now expand whatever format it was into RGBA8
+    let sample = |row: &[u8], index: usize| -> u16 {
+        match bit_depth {
+            16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+            8 => row[index] as u16,
+            _ => {
+                let per_byte = 8 / bit_depth as usize;
+                let byte = row[index / per_byte];
+                let shift = 8 - bit_depth as usize * (index % per_byte + 1);
+                ((byte >> shift) & ((1u16 << bit_depth) - 1) as u8) as u16
+            },
+        }
+    };
+    // Scales a sample up (or down, for 16 bit) to fit in 0..255
+    let to_u8 = |value: u16| -> u8 {
+        match bit_depth {
+            16 => (value >> 8) as u8,
+            8 => value as u8,
+            _ => (value as u32 * 255 / ((1u32 << bit_depth) - 1)) as u8,
+        }
+    };
+    let transparent_gray = transparency.as_ref().filter(|t| color_type == 0 && t.len() >= 2)
+        .map(|t| u16::from_be_bytes([t[0], t[1]]));
+    let transparent_rgb = transparency.as_ref().filter(|t| color_type == 2 && t.len() >= 6)
+        .map(|t| [0, 2, 4].map(|i| u16::from_be_bytes([t[i], t[i + 1]])));
+    if color_type == 3 {
+        if let Some(ref alphas) = transparency {
+            for (entry, &alpha) in palette.iter_mut().zip(alphas) {
+                entry[3] = alpha;
+            }
+        }
+    }
+
+    let mut pixels = Vec::with_capacity(size);
+    for y in 0..height as usize {
+        let row = &unfiltered[y * stride..(y + 1) * stride];
+        for x in 0..width as usize {
+            let i = x * channels;
+            let rgba = match color_type {
+                0 => {
+                    let g = sample(row, i);
+                    let alpha = if Some(g) == transparent_gray { 0 } else { 255 };
+                    [to_u8(g), to_u8(g), to_u8(g), alpha]
+                },
+                2 => {
+                    let rgb = [sample(row, i), sample(row, i + 1), sample(row, i + 2)];
+                    let alpha = if Some(rgb) == transparent_rgb { 0 } else { 255 };
+                    [to_u8(rgb[0]), to_u8(rgb[1]), to_u8(rgb[2]), alpha]
+                },
+                3 => {
+                    let index = sample(row, i) as usize;
+                    *palette.get(index).ok_or("PNG palette index out of range")?
+                },
+                4 => {
+                    let g = to_u8(sample(row, i));
+                    [g, g, g, to_u8(sample(row, i + 1))]
+                },
+                _ => [
+                    to_u8(sample(row, i)),
+                    to_u8(sample(row, i + 1)),
+                    to_u8(sample(row, i + 2)),
+                    to_u8(sample(row, i + 3)),
+                ],
+            };
+            pixels.extend_from_slice(&rgba);
+        }
+    }
+
+    Ok(Image { width, height, pixels })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps a zlib stream up as a PNG.  The decoder doesn't check CRCs, so they're left as zeroes
+    fn png(width: u32, height: u32, color_type: u8, zlib: &[u8]) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+        let mut bytes = PNG_SIGNATURE.to_vec();
+        for (kind, data) in &[(b"IHDR", &header[..]), (b"IDAT", zlib), (b"IEND", &[][..])] {
+            bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(*kind);
+            bytes.extend_from_slice(data);
+            bytes.extend_from_slice(&[0; 4]);
+        }
+        bytes
+    }
+
+    /// A zlib stream holding `data` in a single stored (uncompressed) block
+    fn stored_zlib(data: &[u8]) -> Vec<u8> {
+        let mut zlib = vec![0x78, 0x01, 0x01];  // header, then a final block of type 0
+        zlib.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(data.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(data);
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in data {
+            a = (a + byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        zlib.extend_from_slice(&((b << 16) | a).to_be_bytes());
+        zlib
+    }
+
+    #[test]
+    fn decodes_stored_block() {
+        let rows = [0, 255, 0, 0, 128, 0, 0, 255, 64];  // one unfiltered row of two RGBA pixels
+        let image = Image::decode(&png(2, 1, 6, &stored_zlib(&rows))).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, vec![255, 0, 0, 128, 0, 0, 255, 64]);
+    }
+
+    #[test]
+    fn decodes_fixed_huffman_block() {
+        // A 3x2 RGB image compressed with fixed Huffman codes.  The first row uses the "sub" filter, and the second
+        // uses "up":
+        //     1, [10 20 30] [5 5 5] [5 5 5]
+        //     2, [1 1 1] [2 2 2] [3 3 3]
+        let zlib = [
+            0x78, 0x01, 0x63, 0xe4, 0x12, 0x91, 0x63, 0x05, 0x03, 0x26, 0x46, 0x46, 0x46, 0x26, 0x26, 0x26, 0x66, 0x66,
+            0x66, 0x00, 0x06, 0x3d, 0x00, 0x70,
+        ];
+        assert_eq!((zlib[2] >> 1) & 3, 1, "the test data should use fixed Huffman codes");
+        let image = Image::decode(&png(3, 2, 2, &zlib)).unwrap();
+        assert_eq!(image.pixels, vec![
+            10, 20, 30, 255, 15, 25, 35, 255, 20, 30, 40, 255,
+            11, 21, 31, 255, 17, 27, 37, 255, 23, 33, 43, 255,
+        ]);
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let error = Image::decode(&png(2, 2, 6, &stored_zlib(&[0, 1, 2, 3, 4, 5, 6, 7, 8]))).unwrap_err();
+        assert_eq!(error, "PNG image data is truncated");
+    }
+
+    #[test]
+    fn rejects_sizes_that_overflow() {
+        let error = Image::decode(&png(u32::MAX, u32::MAX, 6, &stored_zlib(&[]))).unwrap_err();
+        assert_eq!(error, "a 4294967295x4294967295 image is too big");
+        assert!(Image::solid(u32::MAX, u32::MAX, [0; 4]).is_err());
+        assert_eq!(Image::solid(2, 3, [1, 2, 3, 4]).unwrap().pixels.len(), 24);
+    }
+}
@@ -0,0 +1,14 @@
+#version 330 core
+
+// Shows a texture across the whole screen, with `fullscreen.vert`
+
+in vec2 TexCoord;
+
+uniform sampler2D Image;
+
+out vec4 Color;
+
+void main()
+{
+    Color = vec4(texture(Image, TexCoord).rgb, 1.0);
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec3 Normal;
+    vec4 Color;
+} IN;
+
+out vec4 Color;
+
+void main()
+{
+    // The same fixed light as mesh.frag; the point here is how many things get drawn, not how they're lit
+    vec3 light_direction = normalize(vec3(0.4, 1.0, 0.7));
+    float shade = 0.3 + 0.7 * max(dot(normalize(IN.Normal), light_direction), 0.0);
+    Color = vec4(IN.Color.rgb * shade, IN.Color.a);
+}
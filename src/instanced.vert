@@ -0,0 +1,29 @@
+#version 330 core
+
+// Like mesh.vert, but each copy of the mesh gets its transform and color from per-instance attributes (see
+// `vertex::Instance`), so one draw call can put thousands of them in different places.  With `Instanced` off it falls
+// back to the `Model` and `Tint` uniforms, for drawing copies one at a time to compare against
+
+layout (location = 0) in vec3 Position;
+layout (location = 2) in vec3 Normal;
+layout (location = 7) in vec4 InstanceColor;
+layout (location = 8) in mat4 InstanceTransform;  // takes up locations 8 to 11
+
+uniform bool Instanced = true;
+uniform mat4 Model;
+uniform vec4 Tint;
+uniform mat4 View;
+uniform mat4 Projection;
+
+out VS_OUTPUT {
+    vec3 Normal;
+    vec4 Color;
+} OUT;
+
+void main()
+{
+    mat4 model = Instanced ? InstanceTransform : Model;
+    gl_Position = Projection * View * model * vec4(Position, 1.0);
+    OUT.Normal = mat3(model) * Normal;
+    OUT.Color = Instanced ? InstanceColor : Tint;
+}
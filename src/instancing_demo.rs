@@ -0,0 +1,166 @@
+use std::ffi::CString;
+
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::math::{Mat4, Quat, Vec3, Vec4};
+use crate::mesh::InstanceBuffer;
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::shapes;
+use crate::vertex::Instance;
+
+/// The benchmark's cubes sit in a block this many across, high and deep, which is 100,000 of them
+const GRID: (u32, u32, u32) = (50, 40, 50);
+const SPACING: f32 = 1.5;
+
+/// Same tiny hash as the solar system's asteroids, so every cube gets the same spin and color every run
+fn hash(n: u32) -> f32 {
+    let n = n.wrapping_mul(0x9E37_79B9) ^ (n >> 15);
+    (n.wrapping_mul(0x85EB_CA6B) >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Where cube `i` sits, which way it spins, and what color it is
+fn cube_instance(i: u32, time: f32) -> Instance {
+    let (x, y, z) = (i % GRID.0, i / GRID.0 % GRID.1, i / (GRID.0 * GRID.1));
+    let center = Vec3::new(GRID.0 as f32 - 1.0, GRID.1 as f32 - 1.0, GRID.2 as f32 - 1.0) * 0.5;
+    let position = (Vec3::new(x as f32, y as f32, z as f32) - center) * SPACING;
+    let axis = Vec3::new(hash(i * 3) - 0.5, hash(i * 3 + 1) - 0.5, hash(i * 3 + 2) - 0.5);
+    let axis = if axis.length() > 0.0 { axis.normalize() } else { Vec3::Y };
+    let rotation = Quat::from_axis_angle(axis, time * (0.5 + hash(i * 7)) * 2.0);
+    let color = Vec4::new(x as f32 / GRID.0 as f32, y as f32 / GRID.1 as f32, z as f32 / GRID.2 as f32, 1.0);
+    Instance::new(Mat4::from_trs(position, rotation, Vec3::ONE), color)
+}
+
+/// A block of 100,000 spinning cubes, drawn with one instanced draw call.  The title shows how long each frame takes.
+///
+/// I switches to drawing the cubes one at a time (one draw call each, with a uniform set in between) to see how much
+/// instancing saves, and Up and Down change how many cubes there are.  Space stops the cubes spinning, which also
+/// stops their transforms being worked out and uploaded again every frame.  Dragging orbits the camera
+pub fn instancing_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    // Don't wait for the screen to refresh before swapping, or every frame time would just be the refresh rate
+    if video_subsystem.gl_set_swap_interval(0).is_err() {
+        println!("Couldn't turn vsync off, so frame times won't go below the screen's refresh rate");
+    }
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+
+    let setup = || -> Result<Program, String> {
+        let vert_shader = Shader::from_vert_source(&CString::new(include_str!("instanced.vert")).unwrap())?;
+        let frag_shader = Shader::from_frag_source(&CString::new(include_str!("instanced.frag")).unwrap())?;
+        Program::from_shaders(&[vert_shader, frag_shader])
+    };
+    let program = match setup() {
+        Ok(program) => program,
+        Err(e) => {
+            println!("Failed to set up instancing: {}", e);
+            return;
+        },
+    };
+
+    let cube = shapes::cube(1.0, 1).upload();
+    let mut instances = InstanceBuffer::new(Instance::layout());
+    cube.attach_instances(&instances);
+
+    let max_count = GRID.0 * GRID.1 * GRID.2;
+    let mut count = max_count;
+    let mut cubes: Vec<Instance> = (0..count).map(|i| cube_instance(i, 0.0)).collect();
+    instances.update(&cubes);
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 30.0, 110.0), width as f32 / height as f32);
+    camera.far = 500.0;
+    let mut controller = OrbitController::new(Vec3::ZERO, 110.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut instanced = true;
+    let mut spinning = true;
+    let mut time = 0.0f32;
+    let mut frame_ms = 0.0f64;
+    let frequency = timer.performance_frequency() as f64;
+    let mut last_counter = timer.performance_counter();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::I), .. } => instanced = !instanced,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Space), .. } => spinning = !spinning,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Up), .. } => count = (count * 2).min(max_count),
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Down), .. } => count = (count / 2).max(1),
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        // Time the whole frame, including waiting for the previous one to finish drawing, which is where the GPU's
+        // share of the work shows up
+        let counter = timer.performance_counter();
+        let dt = (counter - last_counter) as f64 / frequency;
+        last_counter = counter;
+        frame_ms = frame_ms * 0.95 + dt * 1000.0 * 0.05;  // smoothed, so the title is readable
+
+        if spinning || cubes.len() != count as usize {
+            if spinning {
+                time += dt as f32;
+            }
+            cubes = (0..count).map(|i| cube_instance(i, time)).collect();
+            instances.update(&cubes);
+        }
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        render_state_cache.apply(&RenderState::opaque());
+        program.set_used();
+        program.set_uniform_mat4("View", &camera.view_matrix());
+        program.set_uniform_mat4("Projection", &camera.projection_matrix());
+        program.set_uniform_i32("Instanced", instanced as i32);
+        let draw_calls = if instanced {
+            cube.draw_instanced(instances.count());
+            1
+        } else {
+            for instance in &cubes {
+                program.set_uniform_mat4("Model", &instance.transform);
+                program.set_uniform_vec4("Tint", instance.color);
+                cube.draw();
+            }
+            cubes.len()
+        };
+
+        window.set_title(&format!(
+            "{} cubes, {} draw call{}, {:.2} ms per frame ({:.0} fps)",
+            count,
+            draw_calls,
+            if draw_calls == 1 { "" } else { "s" },
+            frame_ms,
+            1000.0 / frame_ms.max(0.001),
+        )).unwrap();
+        window.gl_swap_window();
+    }
+}
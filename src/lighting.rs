@@ -0,0 +1,172 @@
+// Phong/Blinn-Phong lighting for directional, point and spot lights, using `lit.vert` and `lit.frag`.
+//
+// Rather than setting a handful of uniforms per light, every light goes into one uniform buffer, which the shader sees
+// as the `Lights` uniform block.  That's one upload per frame no matter how many lights or programs there are: any
+// program that declares the block and is pointed at `LIGHTS_BINDING` reads the same buffer.
+//
+// The buffer has room for a fixed number of lights, which has to match the `MAX_LIGHTS` the shader was compiled with,
+// so `create_program()` and `LightBuffer::new()` both take it.
+//
+// The block itself lives in lights.glsl, along with the shadow lookups, and gets pasted into any shader that wants it
+// by `with_lights()`.  Lights that cast shadows also get told which of a `ShadowRenderer`'s maps are theirs, with the
+// matrices for those maps at the end of the block.
+
+use std::ffi::CString;
+
+use crate::buffer::UniformBuffer;
+use crate::math::{Mat4, Vec3};
+use crate::program::Program;
+use crate::render_gl::{self, Shader};
+use crate::scene::{LightKind, WorldLight};
+use crate::shadow::{self, LightShadow, ShadowRenderer};
+
+/// The uniform buffer binding point the `Lights` block is read from
+pub const LIGHTS_BINDING: gl::types::GLuint = 0;
+
+/// Matches `LIGHT_DIRECTIONAL`, `LIGHT_POINT` and `LIGHT_SPOT` in lights.glsl
+fn light_type(kind: LightKind) -> f32 {
+    match kind {
+        LightKind::Directional => 0.0,
+        LightKind::Point => 1.0,
+        LightKind::Spot { .. } => 2.0,
+    }
+}
+
+/// One light, laid out the way std140 lays out `struct Light` in lights.glsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct GpuLight {
+    position_type: [f32; 4],
+    direction_range: [f32; 4],
+    color_intensity: [f32; 4],
+    cone: [f32; 4],
+    shadow: [f32; 4],
+    shadow_range: [f32; 4],
+}
+
+/// Everything in the `Lights` block before the array of lights
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GpuLightsHeader {
+    ambient: [f32; 4],
+    count: [i32; 4],
+}
+
+impl GpuLight {
+    fn from_world_light(light: &WorldLight, shadow: Option<&LightShadow>) -> GpuLight {
+        let (inner, outer) = match light.light.kind {
+            LightKind::Spot { inner_angle, outer_angle } => (inner_angle, outer_angle),
+            _ => (0.0, 0.0),
+        };
+        let (shadow, shadow_range) = match shadow {
+            Some(s) => (
+                [s.first_map as f32, s.map_count as f32, s.settings.normal_offset, s.settings.filter_radius as f32],
+                s.range,
+            ),
+            None => ([-1.0, 0.0, 0.0, 0.0], [0.0; 4]),
+        };
+        let (p, d, c) = (light.position, light.direction, light.light.color);
+        GpuLight {
+            position_type: [p.x, p.y, p.z, light_type(light.light.kind)],
+            direction_range: [d.x, d.y, d.z, light.light.range],
+            color_intensity: [c.x, c.y, c.z, light.light.intensity],
+            cone: [inner.cos(), outer.cos(), 0.0, 0.0],
+            shadow,
+            shadow_range,
+        }
+    }
+}
+
+/// The uniform buffer behind the `Lights` block
+pub struct LightBuffer {
+    buffer: UniformBuffer,
+    max_lights: usize,
+}
+
+#[allow(dead_code)]
+impl LightBuffer {
+    /// Makes room for `max_lights` lights and attaches the buffer to `LIGHTS_BINDING`
+    pub fn new(max_lights: usize) -> LightBuffer {
+        let mut buffer = UniformBuffer::new();
+        let size = std::mem::size_of::<GpuLightsHeader>()
+            + max_lights * std::mem::size_of::<GpuLight>()
+            + shadow::MAX_SHADOW_MAPS * std::mem::size_of::<Mat4>();
+        buffer.bind();
+        buffer.dynamic_draw_data(&vec![0u8; size]);
+        buffer.unbind();
+        buffer.bind_base(LIGHTS_BINDING);
+        LightBuffer { buffer, max_lights }
+    }
+
+    pub fn max_lights(&self) -> usize {
+        self.max_lights
+    }
+
+    /// Uploads `lights` (like the ones from `Scene::lights()`) along with the ambient light that lights everything a
+    /// little.  Any lights past `max_lights()` are left out, and none of them cast shadows
+    pub fn update(&self, ambient: Vec3, lights: &[WorldLight]) {
+        self.upload(ambient, lights, &[], &[]);
+    }
+
+    /// Like `update()`, but with the shadows that `shadows` drew for the same `lights`.  The `ShadowRenderer` needs
+    /// binding as well, for the shaders to see the maps
+    pub fn update_with_shadows(&self, ambient: Vec3, lights: &[WorldLight], shadows: &ShadowRenderer) {
+        self.upload(ambient, lights, shadows.shadows(), shadows.matrices());
+    }
+
+    fn upload(&self, ambient: Vec3, lights: &[WorldLight], shadows: &[Option<LightShadow>], matrices: &[Mat4]) {
+        let count = lights.len().min(self.max_lights);
+        let header = GpuLightsHeader {
+            ambient: [ambient.x, ambient.y, ambient.z, 0.0],
+            count: [count as i32, 0, 0, 0],
+        };
+        let gpu_lights: Vec<GpuLight> = lights[..count].iter().enumerate()
+            .map(|(i, light)| GpuLight::from_world_light(light, shadows.get(i).and_then(|s| s.as_ref())))
+            .collect();
+        let lights_size = std::mem::size_of::<GpuLightsHeader>() + self.max_lights * std::mem::size_of::<GpuLight>();
+
+        self.buffer.bind();
+        self.buffer.sub_data(0, &[header]);
+        self.buffer.sub_data(std::mem::size_of::<GpuLightsHeader>(), &gpu_lights);
+        if !matrices.is_empty() {
+            self.buffer.sub_data(lights_size, &matrices[..matrices.len().min(shadow::MAX_SHADOW_MAPS)]);
+        }
+        self.buffer.unbind();
+        // Binding a buffer to `gl::UNIFORM_BUFFER` to upload to it doesn't disturb the indexed binding the shaders read
+        // from, so there's no need to call `bind_base()` again
+    }
+}
+
+/// Pastes lights.glsl into a fragment shader, for up to `max_lights` lights (which has to match the `LightBuffer` it
+/// reads)
+#[allow(dead_code)]
+pub fn with_lights(source: &str, max_lights: usize) -> String {
+    let lights = format!("#define MAX_LIGHTS {}\n{}", max_lights.max(1), include_str!("lights.glsl"));
+    render_gl::insert_after_version(source, &lights)
+}
+
+/// Points a program built with `with_lights()` at `LIGHTS_BINDING`, and its shadow samplers at the texture units
+/// `ShadowRenderer::bind()` uses.  The samplers need their own units even when nothing casts shadows, since GL refuses
+/// to draw with two different kinds of sampler on the same unit
+#[allow(dead_code)]
+pub fn prepare_program(program: &Program) {
+    program.bind_uniform_block("Lights", LIGHTS_BINDING);
+    program.set_used();
+    program.set_uniform_i32("ShadowMaps", shadow::SHADOW_MAPS_UNIT as i32);
+    for i in 0..shadow::MAX_POINT_SHADOWS {
+        let unit = shadow::POINT_SHADOW_MAPS_UNIT + i as u32;
+        program.set_uniform_i32(&format!("PointShadowMaps[{}]", i), unit as i32);
+    }
+}
+
+/// Compiles `lit.vert` and `lit.frag` for up to `max_lights` lights, set up with `prepare_program()`
+#[allow(dead_code)]
+pub fn create_program(max_lights: usize) -> Result<Program, String> {
+    let vert_shader = Shader::from_vert_source(&CString::new(include_str!("lit.vert")).unwrap())?;
+    let frag_source = with_lights(include_str!("lit.frag"), max_lights);
+    let frag_shader = Shader::from_frag_source(&CString::new(frag_source).unwrap())?;
+
+    let program = Program::from_shaders(&[vert_shader, frag_shader])?;
+    prepare_program(&program);
+    Ok(program)
+}
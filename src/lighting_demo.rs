@@ -0,0 +1,282 @@
+use crate::bounds::Aabb;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::lighting::{self, LightBuffer};
+use crate::material::{MaterialLibrary, MaterialValue};
+use crate::math::{radians, Quat, Vec2, Vec3};
+use crate::mesh::MeshData;
+use crate::render_state::RenderStateCache;
+use crate::scene::{Light, LightKind, Renderable, Scene};
+use crate::shapes;
+use crate::texture::Texture;
+
+const MAX_LIGHTS: usize = 8;
+const TILE_TEXTURE_SIZE: u32 = 64;
+const TILES_PER_TEXTURE: u32 = 4;
+
+/// How far into a tile (0 to 1 across it) the bevel around its edge goes
+const BEVEL: f32 = 0.12;
+
+/// Which way the surface of a beveled tile faces at `uv` (0 to 1 across the tile), in tangent space.  The bevel slopes
+/// up from the grout towards the middle of the tile, so on the left edge the surface leans towards -U, and so on
+fn tile_normal(uv: Vec2) -> Vec3 {
+    let slope = |t: f32| if t < BEVEL { -1.0 } else if t > 1.0 - BEVEL { 1.0 } else { 0.0 };
+    Vec3::new(slope(uv.x) * 0.8, slope(uv.y) * 0.8, 1.0).normalize()
+}
+
+/// Builds a square texture with `pixel(uv)` giving the color at each point of a tile, repeated `TILES_PER_TEXTURE`
+/// times in each direction
+fn tile_texture<F: Fn(Vec2) -> [u8; 4]>(srgb: bool, pixel: F) -> Texture {
+    let size = TILE_TEXTURE_SIZE;
+    let tile_size = (size / TILES_PER_TEXTURE) as f32;
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let uv = Vec2::new((x as f32 + 0.5) % tile_size / tile_size, (y as f32 + 0.5) % tile_size / tile_size);
+            pixels.extend_from_slice(&pixel(uv));
+        }
+    }
+    Texture::from_rgba8(size, size, &pixels, srgb)
+}
+
+fn add_material(library: &mut MaterialLibrary, program: usize, name: &str, params: &[(&str, MaterialValue)]) -> usize {
+    let mut material = library.create_material(name, program);
+    for &(param, value) in params {
+        material.set(param, value);
+    }
+    library.add_material(material)
+}
+
+/// A few shapes on a tiled floor, lit by a directional light, three colored point lights and a spot light, with
+/// Blinn-Phong shading.  The floor and the cube have normal and specular maps, so the grout between their tiles
+/// catches the light differently from the tiles.
+///
+/// Tab picks a light, the arrow keys move it around (or turn it, for the directional light), Page Up/Down move it up
+/// and down, and L switches it on and off.  B switches between Blinn-Phong and Phong highlights, N turns the normal
+/// maps off and on, and dragging orbits the camera
+pub fn lighting_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.05, 0.05, 0.08, 1.0);  // Dark, so the lights stand out
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let mut timer = _sdl.timer().unwrap();
+
+    let lit_program = match lighting::create_program(MAX_LIGHTS) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("Failed to build the lighting shaders: {}", e);
+            return;
+        },
+    };
+    let light_buffer = LightBuffer::new(MAX_LIGHTS);
+
+    let mut scene = Scene::new();
+    let program = scene.materials.add_program(lit_program);
+
+    // Tiles are shiny and the grout between them isn't
+    let normal_map = scene.materials.add_texture(tile_texture(false, |uv| {
+        let n = tile_normal(uv);
+        let encode = |x: f32| ((x * 0.5 + 0.5) * 255.0).round() as u8;
+        [encode(n.x), encode(n.y), encode(n.z), 255]
+    }));
+    let specular_map = scene.materials.add_texture(tile_texture(false, |uv| {
+        let grout = uv.x < BEVEL / 2.0 || uv.x > 1.0 - BEVEL / 2.0 || uv.y < BEVEL / 2.0 || uv.y > 1.0 - BEVEL / 2.0;
+        if grout { [20, 20, 20, 255] } else { [255, 255, 255, 255] }
+    }));
+
+    let tiled = |color: Vec3, scale: f32| vec![
+        ("DiffuseColor", MaterialValue::Vec3(color)),
+        ("NormalTexture", MaterialValue::Texture(Some(normal_map))),
+        ("UseNormalTexture", MaterialValue::Int(1)),
+        ("SpecularTexture", MaterialValue::Texture(Some(specular_map))),
+        ("SpecularColor", MaterialValue::Vec3(Vec3::splat(0.8))),
+        ("Shininess", MaterialValue::Float(64.0)),
+        ("TextureScale", MaterialValue::Vec2(Vec2::new(scale, scale))),
+    ];
+    let floor_material = add_material(&mut scene.materials, program, "floor", &tiled(Vec3::splat(0.7), 3.0));
+    let tile_material = add_material(&mut scene.materials, program, "tiles", &tiled(Vec3::new(0.3, 0.5, 0.7), 1.0));
+    let plastic_material = add_material(&mut scene.materials, program, "red plastic", &[
+        ("DiffuseColor", MaterialValue::Vec3(Vec3::new(0.8, 0.1, 0.1))),
+        ("SpecularColor", MaterialValue::Vec3(Vec3::splat(0.6))),
+        ("Shininess", MaterialValue::Float(96.0)),
+    ]);
+    let gold_material = add_material(&mut scene.materials, program, "gold", &[
+        ("DiffuseColor", MaterialValue::Vec3(Vec3::new(0.6, 0.45, 0.1))),
+        ("SpecularColor", MaterialValue::Vec3(Vec3::new(1.0, 0.85, 0.4))),
+        ("Shininess", MaterialValue::Float(24.0)),
+    ]);
+    let matte_material = add_material(&mut scene.materials, program, "matte", &[
+        ("DiffuseColor", MaterialValue::Vec3(Vec3::new(0.5, 0.7, 0.4))),
+        ("SpecularColor", MaterialValue::Vec3(Vec3::ZERO)),
+    ]);
+
+    let add_object = |scene: &mut Scene, name: &str, data: &MeshData, material: usize, position: Vec3| {
+        let mesh = scene.add_mesh_data(data);
+        let (min, max) = data.bounds();
+        let node = scene.add_node(name, None);
+        scene.node_mut(node).set_translation(position);
+        scene.node_mut(node).renderable = Some(Renderable::new(mesh, material, Aabb::new(min, max)));
+        node
+    };
+    add_object(&mut scene, "floor", &shapes::plane_grid(12.0, 12.0, 1, 1), floor_material, Vec3::ZERO);
+    add_object(&mut scene, "sphere", &shapes::uv_sphere(0.8, 48, 24), plastic_material, Vec3::new(-2.0, 0.8, 0.0));
+    add_object(&mut scene, "cube", &shapes::cube(1.4, 1), tile_material, Vec3::new(2.0, 0.7, 0.0));
+    add_object(&mut scene, "torus", &shapes::torus(0.7, 0.25, 48, 24), gold_material, Vec3::new(0.0, 0.35, 2.2));
+    add_object(&mut scene, "cylinder", &shapes::cylinder(0.5, 1.6, 32, 1), matte_material, Vec3::new(0.0, 0.8, -2.2));
+
+    // Each light that has a position gets a small glowing ball so you can see where it is.  The ball's a child of the
+    // light, so it follows it around
+    let marker_data = shapes::uv_sphere(0.08, 12, 6);
+    let marker_mesh = scene.add_mesh_data(&marker_data);
+    let add_light = |scene: &mut Scene, name: &str, light: Light, position: Vec3, rotation: Quat| {
+        let node = scene.add_node(name, None);
+        scene.node_mut(node).set_translation(position);
+        scene.node_mut(node).set_rotation(rotation);
+        scene.node_mut(node).light = Some(light);
+        if light.kind != LightKind::Directional {
+            let marker = add_material(&mut scene.materials, program, name, &[
+                ("DiffuseColor", MaterialValue::Vec3(Vec3::ZERO)),
+                ("SpecularColor", MaterialValue::Vec3(Vec3::ZERO)),
+                ("EmissiveColor", MaterialValue::Vec3(light.color)),
+            ]);
+            let marker_node = scene.add_node(name, Some(node));
+            let (min, max) = marker_data.bounds();
+            scene.node_mut(marker_node).renderable = Some(Renderable::new(marker_mesh, marker, Aabb::new(min, max)));
+        }
+        (node, light)
+    };
+    let point = |color: Vec3| Light { kind: LightKind::Point, color, intensity: 6.0, range: 8.0, shadow: None };
+    let lights = [
+        add_light(
+            &mut scene,
+            "sun",
+            Light {
+                kind: LightKind::Directional,
+                color: Vec3::new(1.0, 0.95, 0.8),
+                intensity: 0.3,
+                range: 0.0,
+                shadow: None,
+            },
+            Vec3::ZERO,
+            Quat::from_axis_angle(Vec3::Y, radians(30.0)) * Quat::from_axis_angle(Vec3::X, radians(-50.0)),
+        ),
+        add_light(&mut scene, "red light", point(Vec3::new(1.0, 0.2, 0.2)), Vec3::new(-3.0, 1.5, 2.0), Quat::IDENTITY),
+        add_light(&mut scene, "green light", point(Vec3::new(0.2, 1.0, 0.2)), Vec3::new(3.0, 1.5, 2.0), Quat::IDENTITY),
+        add_light(&mut scene, "blue light", point(Vec3::new(0.3, 0.4, 1.0)), Vec3::new(0.0, 1.5, -3.5), Quat::IDENTITY),
+        add_light(
+            &mut scene,
+            "spot light",
+            Light {
+                kind: LightKind::Spot { inner_angle: radians(15.0), outer_angle: radians(25.0) },
+                color: Vec3::splat(1.0),
+                intensity: 20.0,
+                range: 12.0,
+                shadow: None,
+            },
+            Vec3::new(0.0, 4.0, 0.0),
+            Quat::from_axis_angle(Vec3::X, radians(-90.0)),  // lights shine down -Z, so tip it over to face down
+        ),
+    ];
+    let mut selected = 0;
+    println!("Selected {}", scene.node(lights[selected].0).name);
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 5.0, 9.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::new(0.0, 0.5, 0.0), 9.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut blinn_phong = true;
+    let mut normal_maps = true;
+    let mut last_ticks = timer.ticks();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Tab), .. } => {
+                    selected = (selected + 1) % lights.len();
+                    println!("Selected {}", scene.node(lights[selected].0).name);
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::L), .. } => {
+                    let (node, light) = lights[selected];
+                    let node = scene.node_mut(node);
+                    node.light = if node.light.is_some() { None } else { Some(light) };
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::B), .. } => {
+                    blinn_phong = !blinn_phong;
+                    println!("{} highlights", if blinn_phong { "Blinn-Phong" } else { "Phong" });
+                    for material in &mut scene.materials.materials {
+                        material.set("BlinnPhong", MaterialValue::Int(blinn_phong as i32));
+                    }
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::N), .. } => {
+                    normal_maps = !normal_maps;
+                    for &material in &[floor_material, tile_material] {
+                        let material = &mut scene.materials.materials[material];
+                        material.set("UseNormalTexture", MaterialValue::Int(normal_maps as i32));
+                    }
+                },
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let ticks = timer.ticks();
+        let dt = (ticks - last_ticks) as f32 / 1000.0;
+        last_ticks = ticks;
+
+        // Move (or turn) the selected light while the keys are held
+        {
+            use sdl2::keyboard::Scancode;
+            let keys = event_pump.keyboard_state();
+            let axis = |positive: Scancode, negative: Scancode| {
+                keys.is_scancode_pressed(positive) as i32 as f32 - keys.is_scancode_pressed(negative) as i32 as f32
+            };
+            let (x, y, z) = (
+                axis(Scancode::Right, Scancode::Left),
+                axis(Scancode::PageUp, Scancode::PageDown),
+                axis(Scancode::Down, Scancode::Up),
+            );
+            let (node, light) = lights[selected];
+            let node = scene.node_mut(node);
+            if light.kind == LightKind::Directional {
+                let turn = Quat::from_axis_angle(Vec3::Y, -x * dt) * node.rotation();
+                node.set_rotation(turn * Quat::from_axis_angle(Vec3::X, z * dt));
+            } else if x != 0.0 || y != 0.0 || z != 0.0 {
+                node.set_translation(node.translation() + Vec3::new(x, y, z) * 3.0 * dt);
+            }
+        }
+
+        let (view, projection) = (camera.view_matrix(), camera.projection_matrix());
+        let draw_list = scene.collect_draw_calls(&(projection * view), camera.position);
+        light_buffer.update(Vec3::splat(0.05), &scene.lights());
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        scene.draw(&draw_list, &mut render_state_cache, &view, &projection);
+
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,137 @@
+// The `Lights` block and the shadow lookups that go with it, pasted into lit.frag and pbr.frag by
+// `lighting::with_lights()`, which also #defines `MAX_LIGHTS` to match the `LightBuffer` the lights come from.
+// `lighting.rs` has the Rust side of the block, and the two have to match exactly
+
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+// These match `shadow::MAX_SHADOW_MAPS` and `shadow::MAX_POINT_SHADOWS`
+#define MAX_SHADOW_MAPS 8
+#define MAX_POINT_SHADOWS 4
+
+// Laid out by std140's rules, which for a struct made of vec4s is just one after another
+struct Light {
+    vec4 PositionType;    // xyz: position in world space, w: one of the LIGHT_* types
+    vec4 DirectionRange;  // xyz: the way it shines, w: distance at which it has faded out completely
+    vec4 ColorIntensity;  // rgb: color, a: brightness
+    vec4 Cone;            // spot lights only; x: cos(inner angle), y: cos(outer angle)
+    vec4 Shadow;          // x: first shadow map (or point shadow cubemap), -1 for no shadows; y: how many maps (one
+                          // per cascade); z: normal offset; w: filter radius in texels
+    vec4 ShadowRange;     // directional lights: where each cascade ends, in distance along the view direction.
+                          // Point lights: x and y are the near and far planes of the cubemap's faces
+};
+
+layout (std140) uniform Lights {
+    vec4 Ambient;      // rgb only
+    ivec4 LightCount;  // x only; the rest is padding
+    Light LightList[MAX_LIGHTS];
+    mat4 ShadowMatrices[MAX_SHADOW_MAPS];  // world space to each shadow map's clip space
+};
+
+// Set up by `lighting::prepare_program()`, and bound by `ShadowRenderer::bind()`
+uniform sampler2DArrayShadow ShadowMaps;
+uniform samplerCubeShadow PointShadowMaps[MAX_POINT_SHADOWS];
+
+uniform mat4 View;  // to pick a cascade by how far away from the camera a point is
+
+// Percentage-closer filtering: rather than one lookup (lit or not), average the lookups over a square of
+// (2 * radius + 1)^2 texels around the point, so shadow edges come out soft instead of blocky.  Each lookup is already
+// a 2x2 bilinear blend of comparisons, since the maps use linear filtering
+float filtered_shadow(int map, vec3 coords, int radius)
+{
+    vec2 texel = 1.0 / vec2(textureSize(ShadowMaps, 0).xy);
+    float lit = 0.0;
+    for (int y = -radius; y <= radius; y++) {
+        for (int x = -radius; x <= radius; x++) {
+            lit += texture(ShadowMaps, vec4(coords.xy + vec2(x, y) * texel, float(map), coords.z));
+        }
+    }
+    float side = float(2 * radius + 1);
+    return lit / (side * side);
+}
+
+// GLSL 3.30 only lets arrays of samplers be indexed by constants, so each cubemap needs spelling out
+float point_shadow_lookup(int index, vec4 coords)
+{
+    if (index == 0) {
+        return texture(PointShadowMaps[0], coords);
+    } else if (index == 1) {
+        return texture(PointShadowMaps[1], coords);
+    } else if (index == 2) {
+        return texture(PointShadowMaps[2], coords);
+    }
+    return texture(PointShadowMaps[3], coords);
+}
+
+float point_shadow(int index, vec3 from_light, vec2 near_far, int radius)
+{
+    // Each face was drawn with an ordinary 90 degree perspective projection, so the depth stored for a point is its
+    // distance along whichever axis the face looks down, pushed through that projection
+    float near = near_far.x;
+    float far = near_far.y;
+    float major = max(abs(from_light.x), max(abs(from_light.y), abs(from_light.z)));
+    float depth = ((far + near) / (far - near) - 2.0 * far * near / ((far - near) * major)) * 0.5 + 0.5;
+
+    // Filter by nudging the direction sideways, a texel at a time.  A face covers 2 * major across at that distance
+    vec3 dir = normalize(from_light);
+    vec3 side = normalize(cross(dir, abs(dir.y) < 0.99 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0)));
+    vec3 up = cross(side, dir);
+    float texel = 2.0 * major / float(textureSize(PointShadowMaps[0], 0).x);
+    float lit = 0.0;
+    for (int y = -radius; y <= radius; y++) {
+        for (int x = -radius; x <= radius; x++) {
+            vec3 offset = (side * float(x) + up * float(y)) * texel;
+            lit += point_shadow_lookup(index, vec4(from_light + offset, depth));
+        }
+    }
+    float count = float(2 * radius + 1);
+    return lit / (count * count);
+}
+
+// How much of `light` reaches `world_position`: 1 for all of it, 0 for none.  `normal` is the surface's geometric
+// normal (not a normal mapped one)
+float shadow_factor(Light light, vec3 world_position, vec3 normal)
+{
+    int first = int(light.Shadow.x);
+    if (first < 0) {
+        return 1.0;
+    }
+    int type = int(light.PositionType.w);
+    int radius = int(light.Shadow.w);
+
+    // Looking the shadow up from a little way out along the normal keeps surfaces from shadowing themselves at grazing
+    // angles, where the slope-scaled bias the maps were drawn with isn't enough on its own
+    vec3 l = type == LIGHT_DIRECTIONAL
+        ? -normalize(light.DirectionRange.xyz)
+        : normalize(light.PositionType.xyz - world_position);
+    float n_dot_l = clamp(dot(normal, l), 0.0, 1.0);
+    vec3 p = world_position + normal * light.Shadow.z * (1.0 - n_dot_l);
+
+    if (type == LIGHT_POINT) {
+        return point_shadow(first, p - light.PositionType.xyz, light.ShadowRange.xy, radius);
+    }
+
+    int map = first;
+    if (type == LIGHT_DIRECTIONAL) {
+        // Use the first cascade that reaches far enough.  Past the last one there are no shadows at all
+        float depth = -(View * vec4(world_position, 1.0)).z;
+        int count = int(light.Shadow.y);
+        if (depth > light.ShadowRange[count - 1]) {
+            return 1.0;
+        }
+        for (int cascade = 0; cascade < count; cascade++) {
+            if (depth <= light.ShadowRange[cascade]) {
+                map = first + cascade;
+                break;
+            }
+        }
+    }
+
+    vec4 clip = ShadowMatrices[map] * vec4(p, 1.0);
+    vec3 coords = clip.xyz / clip.w * 0.5 + 0.5;
+    if (coords.z > 1.0) {
+        return 1.0;  // further away than the map reaches
+    }
+    return filtered_shadow(map, coords, radius);
+}
@@ -0,0 +1,96 @@
+#version 330 core
+
+// The `Lights` block (and shadows) come from lights.glsl, pasted in by `lighting::create_program()`
+
+in VS_OUTPUT {
+    vec3 WorldPosition;
+    vec3 Normal;
+    vec3 Tangent;
+    vec2 TexCoord;
+    vec3 CameraPosition;
+} IN;
+
+// Material parameters.  Unset textures are white, so they leave the color they multiply alone
+uniform vec3 DiffuseColor = vec3(0.8);
+uniform sampler2D DiffuseTexture;
+uniform vec3 SpecularColor = vec3(0.5);
+uniform sampler2D SpecularTexture;      // how shiny each bit of the surface is
+uniform float Shininess = 32.0;         // higher means smaller, sharper highlights
+uniform sampler2D NormalTexture;        // tangent space normals, with +Z pointing out of the surface
+uniform bool UseNormalTexture = false;  // a white texture would make a terrible normal map, so this has to be asked for
+uniform vec3 EmissiveColor = vec3(0.0); // light the surface gives off itself, regardless of any lights
+uniform vec2 TextureScale = vec2(1.0);
+uniform bool BlinnPhong = true;         // false for the original Phong specular
+
+out vec4 Color;
+
+vec3 surface_normal(vec2 uv)
+{
+    vec3 n = normalize(IN.Normal);
+    if (!UseNormalTexture) {
+        return n;
+    }
+    // Build a frame with the tangent along +U, the bitangent along +V and the normal sticking out, and use it to turn
+    // the normal map's tangent space normal into a world space one.  Interpolation leaves the tangent slightly off
+    // perpendicular, so straighten it up first
+    vec3 t = normalize(IN.Tangent - n * dot(n, IN.Tangent));
+    vec3 b = cross(n, t);
+    vec3 mapped = texture(NormalTexture, uv).xyz * 2.0 - 1.0;
+    return normalize(mat3(t, b, n) * mapped);
+}
+
+void main()
+{
+    vec2 uv = IN.TexCoord * TextureScale;
+    vec3 diffuse_color = DiffuseColor * texture(DiffuseTexture, uv).rgb;
+    vec3 specular_color = SpecularColor * texture(SpecularTexture, uv).rgb;
+    vec3 n = surface_normal(uv);
+    vec3 v = normalize(IN.CameraPosition - IN.WorldPosition);
+
+    vec3 result = Ambient.rgb * diffuse_color + EmissiveColor;
+    for (int i = 0; i < min(LightCount.x, MAX_LIGHTS); i++) {
+        Light light = LightList[i];
+        int type = int(light.PositionType.w);
+
+        vec3 l;  // towards the light
+        float attenuation = 1.0;
+        if (type == LIGHT_DIRECTIONAL) {
+            l = -normalize(light.DirectionRange.xyz);
+        } else {
+            vec3 to_light = light.PositionType.xyz - IN.WorldPosition;
+            float distance = length(to_light);
+            l = to_light / distance;
+
+            // Inverse square falloff, squeezed down to exactly zero at the light's range so that lights have a hard
+            // limit on how far they reach
+            float window = clamp(1.0 - pow(distance / light.DirectionRange.w, 4.0), 0.0, 1.0);
+            attenuation = window * window / (1.0 + distance * distance);
+
+            if (type == LIGHT_SPOT) {
+                // Full brightness inside the inner cone, fading to nothing at the outer one
+                float angle_cos = dot(-l, normalize(light.DirectionRange.xyz));
+                attenuation *= smoothstep(light.Cone.y, light.Cone.x, angle_cos);
+            }
+        }
+
+        float diffuse = max(dot(n, l), 0.0);
+        float specular = 0.0;
+        if (diffuse > 0.0) {
+            if (BlinnPhong) {
+                // Compare the normal with the halfway vector between the light and the eye
+                vec3 h = normalize(l + v);
+                specular = pow(max(dot(n, h), 0.0), Shininess);
+            } else {
+                // Compare the eye direction with the light's reflection
+                vec3 r = reflect(-l, n);
+                specular = pow(max(dot(r, v), 0.0), Shininess);
+            }
+        }
+
+        attenuation *= shadow_factor(light, IN.WorldPosition, normalize(IN.Normal));
+        vec3 radiance = light.ColorIntensity.rgb * light.ColorIntensity.a * attenuation;
+        result += radiance * (diffuse_color * diffuse + specular_color * specular);
+    }
+
+    Color = vec4(result, 1.0);
+}
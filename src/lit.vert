@@ -0,0 +1,36 @@
+#version 330 core
+
+layout (location = 0) in vec3 Position;
+layout (location = 2) in vec3 Normal;
+layout (location = 3) in vec2 TexCoord;
+layout (location = 4) in vec3 Tangent;
+
+uniform mat4 Model;
+uniform mat4 View;
+uniform mat4 Projection;
+
+out VS_OUTPUT {
+    vec3 WorldPosition;
+    vec3 Normal;
+    vec3 Tangent;
+    vec2 TexCoord;
+    vec3 CameraPosition;
+} OUT;
+
+void main()
+{
+    vec4 world_position = Model * vec4(Position, 1.0);
+    gl_Position = Projection * View * world_position;
+
+    // The inverse transpose keeps normals pointing the right way even when the model is scaled unevenly, which the
+    // plain `mat3(Model)` that mesh.vert uses doesn't.  Tangents lie along the surface, so they're transformed like
+    // positions are
+    mat3 normal_matrix = transpose(inverse(mat3(Model)));
+    OUT.WorldPosition = world_position.xyz;
+    OUT.Normal = normal_matrix * Normal;
+    OUT.Tangent = mat3(Model) * Tangent;
+    OUT.TexCoord = TexCoord;
+
+    // The view matrix moves the camera to the origin, so undoing it tells us where the camera is
+    OUT.CameraPosition = inverse(View)[3].xyz;
+}
@@ -10,6 +10,64 @@ mod create_and_clear_window;
 mod render_gl;
 mod program;
 mod two_vaos_and_two_vbos;
+mod math;
+mod transformations;
+mod camera;
+mod camera_controllers;
+mod render_state;
+mod vertex;
+mod debug_view;
+mod buffer;
+mod mesh;
+mod obj;
+mod obj_viewer;
+mod json;
+mod image;
+mod texture;
+mod gltf;
+mod gltf_viewer;
+mod ply;
+mod stl;
+mod scanned_geometry;
+mod shapes;
+mod procedural_shapes;
+mod bounds;
+mod scene;
+mod solar_system;
+mod material;
+mod material_showcase;
+mod lighting;
+mod lighting_demo;
+mod hdr;
+mod framebuffer;
+mod ibl;
+mod pbr;
+mod pbr_demo;
+mod shadow;
+mod shadow_demo;
+mod deferred;
+mod deferred_demo;
+mod instancing_demo;
+mod sprite;
+mod sprite_demo;
+mod text;
+mod text_demo;
+mod debug_draw;
+mod debug_draw_demo;
+mod gui;
+mod gui_demo;
+mod uniform_block;
+mod uniform_block_demo;
+mod compute;
+mod compute_demo;
+mod tessellation;
+mod tessellation_demo;
+mod transform_feedback;
+mod geometry_demo;
+mod program_pipeline;
+mod pipeline_demo;
+mod program_cache;
+mod program_cache_demo;
 pub mod resources;
 
 fn main() {
@@ -27,6 +85,29 @@ fn main() {
     two_vaos_and_two_vbos::one_yellow_triangle();
     two_vaos_and_two_vbos::vertex_shader_coloring();
     two_vaos_and_two_vbos::coloring_with_uniforms();
+    transformations::transformations();
+    camera_controllers::camera_controllers();
+    obj_viewer::obj_viewer();
+    gltf_viewer::gltf_viewer();
+    scanned_geometry::scanned_geometry();
+    procedural_shapes::procedural_shapes();
+    solar_system::solar_system();
+    material_showcase::material_showcase();
+    lighting_demo::lighting_demo();
+    pbr_demo::pbr_demo();
+    shadow_demo::shadow_demo();
+    deferred_demo::deferred_demo();
+    instancing_demo::instancing_demo();
+    sprite_demo::sprite_demo();
+    text_demo::text_demo();
+    debug_draw_demo::debug_draw_demo();
+    gui_demo::gui_demo();
+    uniform_block_demo::uniform_block_demo();
+    compute_demo::compute_demo();
+    tessellation_demo::tessellation_demo();
+    geometry_demo::geometry_demo();
+    pipeline_demo::pipeline_demo();
+    program_cache_demo::program_cache_demo();
 
     ////////////////////////////////////////////////////////////////////////////////////////////////
     //                                    Now, the new stuff                                      //
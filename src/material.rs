@@ -0,0 +1,413 @@
+// Materials: a program plus the values to give its uniforms (colors, numbers, textures) and the render state to draw
+// with.  Drawing the same mesh with a different material gives it a different look without touching the mesh, or any
+// Rust code if the material came from a file.
+//
+// A new `Material` starts out with one parameter per uniform the program actually has (found by asking GL, see
+// `Program::active_uniforms()`), set to whatever the GLSL initializes it to.  So a shader that says
+//
+//     uniform vec3 DiffuseColor = vec3(0.8);
+//
+// gives its materials a gray `DiffuseColor` unless they say otherwise.  Uniforms that the renderer sets itself (`Model`,
+// `View` and `Projection` every draw, and the environment lighting from `Environment::bind()`) are left out.
+//
+// Material files are JSON, and look like
+//
+//     {
+//         "materials": [
+//             {
+//                 "name": "red glass",
+//                 "vertex_shader": "material.vert",
+//                 "fragment_shader": "material.frag",
+//                 "params": {
+//                     "DiffuseColor": [0.9, 0.1, 0.1],
+//                     "Opacity": 0.4,
+//                     "DiffuseTexture": "checker.png",
+//                     "NormalTexture": { "texture": "bumps.png", "srgb": false }
+//                 },
+//                 "render_state": { "preset": "transparent", "cull": "none" }
+//             }
+//         ]
+//     }
+//
+// File names are relative to the material file.  Textures named with a plain string are assumed to hold colors (so
+// are sRGB); use the object form with `"srgb": false` for ones that hold data, like normal maps.  `render_state` can
+// also just be the name of a preset: "opaque", "transparent" or "overlay".
+
+use std::ffi::CString;
+
+use crate::image::Image;
+use crate::json::{self, JsonValue};
+use crate::math::{Vec2, Vec3, Vec4};
+use crate::program::{Program, UniformInfo};
+use crate::render_gl;
+use crate::render_state::{BlendState, CullMode, PolygonMode, RenderState};
+use crate::resources::{self, Resources};
+use crate::texture::Texture;
+
+/// Uniforms that get set per draw or per frame rather than per material.  Cubemap samplers aren't in here since
+/// materials can't hold them anyway
+const RENDERER_UNIFORMS: [&str; 6] =
+    ["Model", "View", "Projection", "BrdfLut", "PrefilteredLevels", "EnvironmentIntensity"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaterialValue {
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Int(i32),  // also used for `bool` uniforms
+    Texture(Option<usize>),  // index into `MaterialLibrary::textures`; `None` means plain white
+}
+
+impl MaterialValue {
+    /// The default for a uniform of GL type `gl_type`, read from the program.  `None` for types materials can't hold
+    /// (like matrices)
+    fn from_uniform(program: &Program, uniform: &UniformInfo) -> Option<MaterialValue> {
+        let (location, gl_type) = (uniform.location, uniform.gl_type);
+        let v = program.uniform_f32_values(location);
+        match gl_type {
+            gl::FLOAT => Some(MaterialValue::Float(v[0])),
+            gl::FLOAT_VEC2 => Some(MaterialValue::Vec2(Vec2::new(v[0], v[1]))),
+            gl::FLOAT_VEC3 => Some(MaterialValue::Vec3(Vec3::new(v[0], v[1], v[2]))),
+            gl::FLOAT_VEC4 => Some(MaterialValue::Vec4(Vec4::new(v[0], v[1], v[2], v[3]))),
+            gl::INT | gl::BOOL => Some(MaterialValue::Int(program.uniform_i32_values(location)[0])),
+            gl::SAMPLER_2D => Some(MaterialValue::Texture(None)),
+            _ => None,
+        }
+    }
+
+    /// The same kind of value as `self`, made from a list of numbers, or an error saying what was expected
+    fn with_numbers(&self, n: &[f32]) -> Result<MaterialValue, String> {
+        let check = |needed: usize| {
+            if n.len() == needed { Ok(()) } else { Err(format!("expected {} number(s), got {}", needed, n.len())) }
+        };
+        match self {
+            MaterialValue::Float(_) => check(1).map(|_| MaterialValue::Float(n[0])),
+            MaterialValue::Vec2(_) => check(2).map(|_| MaterialValue::Vec2(Vec2::new(n[0], n[1]))),
+            MaterialValue::Vec3(_) => check(3).map(|_| MaterialValue::Vec3(Vec3::new(n[0], n[1], n[2]))),
+            MaterialValue::Vec4(_) => check(4).map(|_| MaterialValue::Vec4(Vec4::new(n[0], n[1], n[2], n[3]))),
+            MaterialValue::Int(_) => check(1).map(|_| MaterialValue::Int(n[0] as i32)),
+            MaterialValue::Texture(_) => Err("expected a texture file name, not numbers".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    pub program: usize,  // index into `MaterialLibrary::programs`
+    pub params: Vec<(String, MaterialValue)>,  // kept in the order the program listed its uniforms
+    pub render_state: RenderState,
+}
+
+#[allow(dead_code)]
+impl Material {
+    /// A material using `program` (which lives at index `program_index` of the library), with every parameter set to
+    /// the program's defaults
+    pub fn new(name: &str, program_index: usize, program: &Program) -> Material {
+        let params = program.active_uniforms().into_iter()
+            .filter(|uniform| uniform.size == 1 && !RENDERER_UNIFORMS.contains(&uniform.name.as_str()))
+            .filter_map(|uniform| {
+                let value = MaterialValue::from_uniform(program, &uniform)?;
+                Some((uniform.name, value))
+            })
+            .collect();
+        Material { name: name.to_string(), program: program_index, params, render_state: RenderState::opaque() }
+    }
+
+    pub fn get(&self, name: &str) -> Option<MaterialValue> {
+        self.params.iter().find(|(n, _)| n == name).map(|&(_, value)| value)
+    }
+
+    /// Sets a parameter.  Names the program doesn't have are added anyway, and just don't do anything
+    pub fn set(&mut self, name: &str, value: MaterialValue) {
+        match self.params.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.params.push((name.to_string(), value)),
+        }
+    }
+
+    /// Sets every parameter on `program`, which needs to be the material's program and already in use.  Textures get
+    /// bound to units 0, 1, 2, ... in the order they appear
+    pub fn apply(&self, program: &Program, textures: &[Texture], white: &Texture) {
+        let mut unit = 0;
+        for (name, value) in &self.params {
+            match *value {
+                MaterialValue::Float(x) => program.set_uniform_f32(name, x),
+                MaterialValue::Vec2(v) => program.set_uniform_vec2(name, v),
+                MaterialValue::Vec3(v) => program.set_uniform_vec3(name, v),
+                MaterialValue::Vec4(v) => program.set_uniform_vec4(name, v),
+                MaterialValue::Int(x) => program.set_uniform_i32(name, x),
+                MaterialValue::Texture(texture) => {
+                    texture.and_then(|index| textures.get(index)).unwrap_or(white).bind(unit);
+                    program.set_uniform_i32(name, unit as i32);
+                    unit += 1;
+                },
+            }
+        }
+    }
+}
+
+/// What a material file asks for a parameter to be set to, before we know what type the uniform is
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamDesc {
+    Numbers(Vec<f32>),
+    Texture { file: String, srgb: bool },
+}
+
+/// One material from a material file, with file names already made relative to the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialDesc {
+    pub name: String,
+    pub vertex_shader: String,
+    pub fragment_shader: String,
+    pub params: Vec<(String, ParamDesc)>,
+    pub render_state: RenderState,
+}
+
+/// Parses a material file without touching GL.  `resource_name` is used for error messages and for working out where
+/// the files it mentions live
+pub fn parse_material_file(source: &str, resource_name: &str) -> Result<Vec<MaterialDesc>, String> {
+    let root = json::parse(source).map_err(|e| format!("{}: {}", resource_name, e))?;
+    let materials = root.field("materials").as_array()
+        .ok_or_else(|| format!("{}: expected a \"materials\" array", resource_name))?;
+
+    materials.iter().enumerate()
+        .map(|(index, material)| {
+            let name = material.field("name").as_str().map(str::to_string).unwrap_or_else(|| format!("#{}", index));
+            let error = |message: String| format!("{}: material '{}': {}", resource_name, name, message);
+            let file = |key: &str| {
+                material.field(key).as_str()
+                    .map(|file| resources::relative_to(resource_name, file))
+                    .ok_or_else(|| error(format!("missing \"{}\"", key)))
+            };
+            let vertex_shader = file("vertex_shader")?;
+            let fragment_shader = file("fragment_shader")?;
+
+            let mut params = Vec::new();
+            for (param, value) in material.field("params").as_object().unwrap_or(&[]) {
+                let desc = parse_param(value, resource_name)
+                    .ok_or_else(|| error(format!("don't know what to make of the value for '{}'", param)))?;
+                params.push((param.clone(), desc));
+            }
+
+            let render_state = parse_render_state(material.field("render_state")).map_err(error)?;
+            Ok(MaterialDesc { name: name.clone(), vertex_shader, fragment_shader, params, render_state })
+        })
+        .collect()
+}
+
+fn parse_param(value: &JsonValue, resource_name: &str) -> Option<ParamDesc> {
+    match value {
+        JsonValue::Number(x) => Some(ParamDesc::Numbers(vec![*x as f32])),
+        JsonValue::Bool(b) => Some(ParamDesc::Numbers(vec![if *b { 1.0 } else { 0.0 }])),
+        JsonValue::Array(_) => value.as_f32_vec().map(ParamDesc::Numbers),
+        JsonValue::String(file) => {
+            Some(ParamDesc::Texture { file: resources::relative_to(resource_name, file), srgb: true })
+        },
+        JsonValue::Object(_) => Some(ParamDesc::Texture {
+            file: resources::relative_to(resource_name, value.field("texture").as_str()?),
+            srgb: value.field("srgb").as_bool().unwrap_or(true),
+        }),
+        JsonValue::Null => None,
+    }
+}
+
+/// Either a preset's name, or an object with a `preset` to start from plus any of `cull`, `depth_test`,
+/// `depth_write`, `blend`, `polygon_mode` and `point_size` to change.  Missing entirely means opaque
+fn parse_render_state(value: &JsonValue) -> Result<RenderState, String> {
+    let preset = |name: &str| match name {
+        "opaque" => Ok(RenderState::opaque()),
+        "transparent" => Ok(RenderState::transparent()),
+        "overlay" => Ok(RenderState::overlay()),
+        _ => Err(format!("unknown render state preset '{}'", name)),
+    };
+    let mut state = match value {
+        JsonValue::Null => return Ok(RenderState::opaque()),
+        JsonValue::String(name) => return preset(name),
+        JsonValue::Object(_) => preset(value.field("preset").as_str().unwrap_or("opaque"))?,
+        _ => return Err("\"render_state\" should be a preset name or an object".to_string()),
+    };
+
+    let option = |key: &str| value.get(key).filter(|v| !v.is_null());
+    let expect_str = |key: &str| -> Result<Option<&str>, String> {
+        option(key).map(|v| v.as_str().ok_or_else(|| format!("\"{}\" should be a string", key))).transpose()
+    };
+    let expect_bool = |key: &str| -> Result<Option<bool>, String> {
+        option(key).map(|v| v.as_bool().ok_or_else(|| format!("\"{}\" should be true or false", key))).transpose()
+    };
+
+    if let Some(cull) = expect_str("cull")? {
+        state.cull_mode = match cull {
+            "none" => CullMode::None,
+            "back" => CullMode::Back,
+            "front" => CullMode::Front,
+            _ => return Err(format!("unknown cull mode '{}'", cull)),
+        };
+    }
+    if let Some(test) = expect_bool("depth_test")? {
+        state.depth.test = test;
+    }
+    if let Some(write) = expect_bool("depth_write")? {
+        state.depth.write = write;
+    }
+    if let Some(blend) = expect_str("blend")? {
+        state.blend = match blend {
+            "none" => BlendState::default(),
+            "alpha" => BlendState::alpha(),
+            "additive" => BlendState::additive(),
+            _ => return Err(format!("unknown blend mode '{}'", blend)),
+        };
+    }
+    if let Some(mode) = expect_str("polygon_mode")? {
+        state.polygon_mode = match mode {
+            "fill" => PolygonMode::Fill,
+            "line" => PolygonMode::Line,
+            "point" => PolygonMode::Point,
+            _ => return Err(format!("unknown polygon mode '{}'", mode)),
+        };
+    }
+    if let Some(size) = option("point_size") {
+        state.point_size = size.as_f32().ok_or("\"point_size\" should be a number")?;
+    }
+    Ok(state)
+}
+
+/// Owns the programs and textures that materials refer to, along with the materials themselves.  Programs and
+/// textures loaded from files are shared between every material that names the same files
+#[derive(Default)]
+pub struct MaterialLibrary {
+    pub programs: Vec<Program>,
+    pub textures: Vec<Texture>,
+    pub materials: Vec<Material>,
+    pub warnings: Vec<String>,  // problems that didn't stop anything loading, like parameters the shader doesn't have
+    program_files: Vec<Option<(String, String)>>,  // which files each program came from, if any
+    texture_files: Vec<Option<(String, bool)>>,
+    white: Option<Texture>,  // what unset texture parameters get; made the first time it's needed
+}
+
+#[allow(dead_code)]
+impl MaterialLibrary {
+    pub fn new() -> MaterialLibrary {
+        MaterialLibrary::default()
+    }
+
+    pub fn add_program(&mut self, program: Program) -> usize {
+        self.programs.push(program);
+        self.program_files.push(None);
+        self.programs.len() - 1
+    }
+
+    pub fn add_texture(&mut self, texture: Texture) -> usize {
+        self.textures.push(texture);
+        self.texture_files.push(None);
+        self.textures.len() - 1
+    }
+
+    /// A material using program `program`, with the program's default parameters.  It isn't added to the library
+    /// until it's passed to `add_material()`, so there's a chance to change it first
+    pub fn create_material(&self, name: &str, program: usize) -> Material {
+        Material::new(name, program, &self.programs[program])
+    }
+
+    pub fn add_material(&mut self, material: Material) -> usize {
+        if self.white.is_none() {
+            self.white = Some(Texture::solid([255, 255, 255, 255]));
+        }
+        self.materials.push(material);
+        self.materials.len() - 1
+    }
+
+    /// First material called `name`
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.materials.iter().position(|material| material.name == name)
+    }
+
+    /// Switches to material `index`'s program and sets all its parameters, returning the program so the caller can set
+    /// any per-draw uniforms.  Doesn't touch the render state, since that's better done through a `RenderStateCache`
+    pub fn bind(&self, index: usize) -> &Program {
+        let material = &self.materials[index];
+        let program = &self.programs[material.program];
+        program.set_used();
+        material.apply(program, &self.textures, self.white.as_ref().expect("materials are added with add_material()"));
+        program
+    }
+
+    /// Sets material `index`'s parameters on `program` instead of the material's own, for passes that draw the same
+    /// materials with a different shader (like filling a G-buffer).  `program` needs to be in use already, and any
+    /// parameters it doesn't have are skipped
+    pub fn apply_to(&self, index: usize, program: &Program) {
+        let white = self.white.as_ref().expect("materials are added with add_material()");
+        self.materials[index].apply(program, &self.textures, white);
+    }
+
+    /// Loads every material in a material file, compiling programs and loading textures as needed, and returns the
+    /// new materials' indices
+    pub fn load(&mut self, res: &Resources, resource_name: &str) -> Result<Vec<usize>, String> {
+        let source = res.load_string(resource_name).map_err(|e| format!("{}: {}", resource_name, e))?;
+        let descs = parse_material_file(&source, resource_name)?;
+
+        let mut loaded = Vec::new();
+        for desc in &descs {
+            let error = |message: String| format!("{}: material '{}': {}", resource_name, desc.name, message);
+            let program = self.load_program(res, &desc.vertex_shader, &desc.fragment_shader).map_err(error)?;
+            let mut material = self.create_material(&desc.name, program);
+            material.render_state = desc.render_state;
+
+            for (name, param) in &desc.params {
+                let default = match material.get(name) {
+                    Some(default) => default,
+                    None => {
+                        self.warnings.push(error(format!("the program has no uniform '{}' for it to set", name)));
+                        continue;
+                    },
+                };
+                let value = match (default, param) {
+                    (MaterialValue::Texture(_), ParamDesc::Texture { file, srgb }) => {
+                        MaterialValue::Texture(Some(self.load_texture(res, file, *srgb).map_err(error)?))
+                    },
+                    (_, ParamDesc::Texture { .. }) => {
+                        return Err(error(format!("'{}' isn't a texture, so it can't be set to a file", name)));
+                    },
+                    (_, ParamDesc::Numbers(numbers)) => {
+                        default.with_numbers(numbers).map_err(|e| error(format!("'{}': {}", name, e)))?
+                    },
+                };
+                material.set(name, value);
+            }
+            loaded.push(self.add_material(material));
+        }
+        Ok(loaded)
+    }
+
+    fn load_program(&mut self, res: &Resources, vertex_shader: &str, fragment_shader: &str) -> Result<usize, String> {
+        let files = Some((vertex_shader.to_string(), fragment_shader.to_string()));
+        if let Some(index) = self.program_files.iter().position(|f| *f == files) {
+            return Ok(index);
+        }
+
+        let load = |name: &str| -> Result<CString, String> {
+            res.load_cstring(name).map_err(|e| format!("{}: {}", name, e))
+        };
+        let vert_shader = render_gl::Shader::from_vert_source(&load(vertex_shader)?)
+            .map_err(|e| format!("{}: {}", vertex_shader, e))?;
+        let frag_shader = render_gl::Shader::from_frag_source(&load(fragment_shader)?)
+            .map_err(|e| format!("{}: {}", fragment_shader, e))?;
+        let program = Program::from_shaders(&[vert_shader, frag_shader])?;
+
+        let index = self.add_program(program);
+        self.program_files[index] = files;
+        Ok(index)
+    }
+
+    fn load_texture(&mut self, res: &Resources, file: &str, srgb: bool) -> Result<usize, String> {
+        let key = Some((file.to_string(), srgb));
+        if let Some(index) = self.texture_files.iter().position(|f| *f == key) {
+            return Ok(index);
+        }
+        let image = Image::load(res, file)?;
+        let index = self.add_texture(Texture::from_image(&image, srgb));
+        self.texture_files[index] = key;
+        Ok(index)
+    }
+}
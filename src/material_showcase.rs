@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use crate::bounds::Aabb;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::material::MaterialLibrary;
+use crate::math::{Quat, Vec3};
+use crate::render_state::RenderStateCache;
+use crate::resources::Resources;
+use crate::scene::{Renderable, Scene};
+use crate::shapes;
+
+const MATERIAL_FILE: &str = "materials/showcase.json";
+
+/// Loads every material in the material file, printing what each ended up with.  Errors are printed rather than
+/// returned, since the point is to be able to fix the file and try again
+fn load_materials(res: &Resources) -> Option<MaterialLibrary> {
+    let mut library = MaterialLibrary::new();
+    if let Err(e) = library.load(res, MATERIAL_FILE) {
+        println!("Failed to load materials: {}", e);
+        return None;
+    }
+    for warning in &library.warnings {
+        println!("Warning: {}", warning);
+    }
+    if library.materials.is_empty() {
+        println!("{} doesn't have any materials in it", MATERIAL_FILE);
+        return None;
+    }
+    for material in &library.materials {
+        println!("Material '{}':", material.name);
+        for (name, value) in &material.params {
+            println!("    {} = {:?}", name, value);
+        }
+    }
+    Some(library)
+}
+
+/// The same mesh drawn once with each material from `assets/materials/showcase.json`.  Nothing about how they look is
+/// in the Rust code: edit the file (or the shaders next to it) and press R to reload it.  Drag to orbit
+pub fn material_showcase() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.5, 0.3, 0.3, 1.0);  // Color that window will default to when everything is cleared
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+
+    let res = Resources::from_crate_dir(Path::new("assets"));
+
+    let mut scene = Scene::new();
+    scene.materials = match load_materials(&res) {
+        Some(library) => library,
+        None => return,
+    };
+
+    // One slot per material in a row.  If a reload changes how many materials there are, the slots just go round the
+    // list again
+    let torus_data = shapes::torus(0.6, 0.25, 48, 24);
+    let torus = scene.add_mesh_data(&torus_data);
+    let (min, max) = torus_data.bounds();
+    let slot_count = 5;
+    let slots: Vec<usize> = (0..slot_count)
+        .map(|i| {
+            let node = scene.add_node("slot", None);
+            scene.node_mut(node).set_translation(Vec3::new((i as f32 - (slot_count - 1) as f32 / 2.0) * 1.8, 0.0, 0.0));
+            scene.node_mut(node).set_rotation(Quat::from_axis_angle(Vec3::X, 1.0));
+            node
+        })
+        .collect();
+    let assign_materials = |scene: &mut Scene| {
+        let material_count = scene.materials.materials.len();
+        for (i, &node) in slots.iter().enumerate() {
+            scene.node_mut(node).renderable = Some(Renderable::new(torus, i % material_count, Aabb::new(min, max)));
+        }
+    };
+    assign_materials(&mut scene);
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 2.0, 7.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 7.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::R), .. } => {
+                    // Keep the old materials if the new ones are broken
+                    if let Some(library) = load_materials(&res) {
+                        scene.materials = library;
+                        assign_materials(&mut scene);
+                    }
+                },
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let (view, projection) = (camera.view_matrix(), camera.projection_matrix());
+        let draw_list = scene.collect_draw_calls(&(projection * view), camera.position);
+        scene.draw(&draw_list, &mut render_state_cache, &view, &projection);
+
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,603 @@
+// Our own little linear algebra module.  We could pull in something like `cgmath` or `nalgebra`, but writing the
+// handful of types we actually need is a good way to understand what the transform matrices are really doing.
+//
+// Everything here follows OpenGL's conventions: right-handed coordinates, column vectors (so `a * b * v` applies `b`
+// first, then `a`), and matrices stored column-major so they can be handed straight to `glUniformMatrix4fv` without
+// transposing.  The types are all `#[repr(C)]` so that they can be dropped straight into vertex and uniform buffers.
+
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+/// A 4x4 matrix stored as four columns, which is the memory layout that OpenGL expects for a `mat4` uniform
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub cols: [[f32; 4]; 4],
+}
+
+/// A unit quaternion representing a rotation.  These avoid gimbal lock and interpolate nicely, which is why we use
+/// them for orientations rather than storing Euler angles everywhere
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+#[allow(dead_code)]
+impl Vec2 {
+    pub const fn new(x: f32, y: f32) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    pub fn dot(self, other: Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+}
+
+#[allow(dead_code)]
+impl Vec3 {
+    pub const ZERO: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+    pub const ONE: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+    pub const X: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+    pub const Y: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    pub const Z: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, z: f32) -> Vec3 {
+        Vec3 { x, y, z }
+    }
+
+    pub fn splat(v: f32) -> Vec3 {
+        Vec3::new(v, v, v)
+    }
+
+    pub fn extend(self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
+
+    pub fn dot(self, other: Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// Right-handed cross product, so `X.cross(Y) == Z`
+    pub fn cross(self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Returns a vector pointing the same way with a length of one.  A zero vector is returned unchanged rather than
+    /// turning into a vector full of NaNs
+    pub fn normalize(self) -> Vec3 {
+        let len = self.length();
+        if len > 0.0 { self / len } else { self }
+    }
+
+    pub fn lerp(self, other: Vec3, t: f32) -> Vec3 {
+        self + (other - self) * t
+    }
+
+    /// Component-wise multiplication, mostly useful for scaling and for multiplying colors together
+    pub fn mul_elem(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x * other.x, self.y * other.y, self.z * other.z)
+    }
+
+    pub fn min(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+
+    pub fn max(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+
+    pub fn to_array(self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+#[allow(dead_code)]
+impl Vec4 {
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Vec4 {
+        Vec4 { x, y, z, w }
+    }
+
+    /// Drops the `w` component
+    pub fn truncate(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    pub fn dot(self, other: Vec4) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        [self.x, self.y, self.z, self.w]
+    }
+}
+
+#[allow(dead_code)]
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        cols: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn identity() -> Mat4 {
+        Mat4::IDENTITY
+    }
+
+    pub fn from_cols(c0: Vec4, c1: Vec4, c2: Vec4, c3: Vec4) -> Mat4 {
+        Mat4 { cols: [c0.to_array(), c1.to_array(), c2.to_array(), c3.to_array()] }
+    }
+
+    pub fn col(&self, i: usize) -> Vec4 {
+        let c = self.cols[i];
+        Vec4::new(c[0], c[1], c[2], c[3])
+    }
+
+    pub fn row(&self, i: usize) -> Vec4 {
+        Vec4::new(self.cols[0][i], self.cols[1][i], self.cols[2][i], self.cols[3][i])
+    }
+
+    pub fn translation(t: Vec3) -> Mat4 {
+        let mut m = Mat4::IDENTITY;
+        m.cols[3] = [t.x, t.y, t.z, 1.0];
+        m
+    }
+
+    pub fn scale(s: Vec3) -> Mat4 {
+        let mut m = Mat4::IDENTITY;
+        m.cols[0][0] = s.x;
+        m.cols[1][1] = s.y;
+        m.cols[2][2] = s.z;
+        m
+    }
+
+    /// Rotation of `angle` radians around `axis` (which doesn't need to be normalized already)
+    pub fn rotation(axis: Vec3, angle: f32) -> Mat4 {
+        Quat::from_axis_angle(axis, angle).to_mat4()
+    }
+
+    pub fn rotation_x(angle: f32) -> Mat4 {
+        Mat4::rotation(Vec3::X, angle)
+    }
+
+    pub fn rotation_y(angle: f32) -> Mat4 {
+        Mat4::rotation(Vec3::Y, angle)
+    }
+
+    pub fn rotation_z(angle: f32) -> Mat4 {
+        Mat4::rotation(Vec3::Z, angle)
+    }
+
+    /// Builds a model matrix out of a translation, rotation and scale.  The order is scale first, then rotate, then
+    /// translate, which is nearly always what you want for placing an object in the world
+    pub fn from_trs(translation: Vec3, rotation: Quat, scale: Vec3) -> Mat4 {
+        let r = rotation.to_mat4();
+        Mat4::from_cols(
+            r.col(0) * scale.x,
+            r.col(1) * scale.y,
+            r.col(2) * scale.z,
+            translation.extend(1.0),
+        )
+    }
+
+    /// Same projection that `gluPerspective` gives.  `fov_y` is the vertical field of view in radians, and the
+    /// resulting clip-space depth goes from -1 at `near` to 1 at `far`
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let f = 1.0 / (fov_y / 2.0).tan();
+        let mut m = Mat4 { cols: [[0.0; 4]; 4] };
+        m.cols[0][0] = f / aspect;
+        m.cols[1][1] = f;
+        m.cols[2][2] = (far + near) / (near - far);
+        m.cols[2][3] = -1.0;
+        m.cols[3][2] = (2.0 * far * near) / (near - far);
+        m
+    }
+
+    /// Same projection that `glOrtho` gives.  Handy for 2D stuff, or for the light's view in directional shadows
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+        let mut m = Mat4::IDENTITY;
+        m.cols[0][0] = 2.0 / (right - left);
+        m.cols[1][1] = 2.0 / (top - bottom);
+        m.cols[2][2] = -2.0 / (far - near);
+        m.cols[3][0] = -(right + left) / (right - left);
+        m.cols[3][1] = -(top + bottom) / (top - bottom);
+        m.cols[3][2] = -(far + near) / (far - near);
+        m
+    }
+
+    /// View matrix for a camera sitting at `eye` and looking at `target`.  OpenGL cameras look down their own -Z axis
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+        let forward = (target - eye).normalize();
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+        Mat4::from_cols(
+            Vec4::new(right.x, up.x, -forward.x, 0.0),
+            Vec4::new(right.y, up.y, -forward.y, 0.0),
+            Vec4::new(right.z, up.z, -forward.z, 0.0),
+            Vec4::new(-right.dot(eye), -up.dot(eye), forward.dot(eye), 1.0),
+        )
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        Mat4::from_cols(self.row(0), self.row(1), self.row(2), self.row(3))
+    }
+
+    /// General 4x4 inverse via cofactors (this is the same expansion MESA uses for `gluInvertMatrix`).  Since the
+    /// inverse of a transpose is the transpose of the inverse, it doesn't matter that we store things column-major.
+    /// Returns `None` if the matrix is singular.  Only an exact zero counts: the determinant scales with the cube of
+    /// the matrix's scale, so any fixed cutoff would also turn away perfectly good small-scale transforms
+    pub fn inverse(&self) -> Option<Mat4> {
+        let m = self.to_cols_array();
+        let mut inv = [0.0f32; 16];
+
+        inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+            + m[9] * m[7] * m[14] + m[13] * m[6] * m[11] - m[13] * m[7] * m[10];
+        inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+            - m[8] * m[7] * m[14] - m[12] * m[6] * m[11] + m[12] * m[7] * m[10];
+        inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+            + m[8] * m[7] * m[13] + m[12] * m[5] * m[11] - m[12] * m[7] * m[9];
+        inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+            - m[8] * m[6] * m[13] - m[12] * m[5] * m[10] + m[12] * m[6] * m[9];
+        inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+            - m[9] * m[3] * m[14] - m[13] * m[2] * m[11] + m[13] * m[3] * m[10];
+        inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+            + m[8] * m[3] * m[14] + m[12] * m[2] * m[11] - m[12] * m[3] * m[10];
+        inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+            - m[8] * m[3] * m[13] - m[12] * m[1] * m[11] + m[12] * m[3] * m[9];
+        inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+            + m[8] * m[2] * m[13] + m[12] * m[1] * m[10] - m[12] * m[2] * m[9];
+        inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+            + m[5] * m[3] * m[14] + m[13] * m[2] * m[7] - m[13] * m[3] * m[6];
+        inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+            - m[4] * m[3] * m[14] - m[12] * m[2] * m[7] + m[12] * m[3] * m[6];
+        inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+            + m[4] * m[3] * m[13] + m[12] * m[1] * m[7] - m[12] * m[3] * m[5];
+        inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+            - m[4] * m[2] * m[13] - m[12] * m[1] * m[6] + m[12] * m[2] * m[5];
+        inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+            - m[5] * m[3] * m[10] - m[9] * m[2] * m[7] + m[9] * m[3] * m[6];
+        inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+            + m[4] * m[3] * m[10] + m[8] * m[2] * m[7] - m[8] * m[3] * m[6];
+        inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+            - m[4] * m[3] * m[9] - m[8] * m[1] * m[7] + m[8] * m[3] * m[5];
+        inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+            + m[4] * m[2] * m[9] + m[8] * m[1] * m[6] - m[8] * m[2] * m[5];
+
+        let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let mut cols = [[0.0; 4]; 4];
+        for (i, v) in inv.iter().enumerate() {
+            cols[i / 4][i % 4] = v * inv_det;
+        }
+        Some(Mat4 { cols })
+    }
+
+    /// Transforms a point (`w = 1`), so translation applies
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let v = *self * p.extend(1.0);
+        if v.w != 0.0 && v.w != 1.0 { v.truncate() / v.w } else { v.truncate() }
+    }
+
+    /// Transforms a direction (`w = 0`), so translation doesn't apply
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        (*self * v.extend(0.0)).truncate()
+    }
+
+    /// Pointer to the first element, for handing off to `glUniformMatrix4fv`
+    pub fn as_ptr(&self) -> *const f32 {
+        self.cols.as_ptr() as *const f32
+    }
+
+    pub fn to_cols_array(self) -> [f32; 16] {
+        let mut out = [0.0; 16];
+        for (i, col) in self.cols.iter().enumerate() {
+            out[i * 4..i * 4 + 4].copy_from_slice(col);
+        }
+        out
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Mat4 {
+        Mat4::IDENTITY
+    }
+}
+
+#[allow(dead_code)]
+impl Quat {
+    pub const IDENTITY: Quat = Quat { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Quat {
+        Quat { x, y, z, w }
+    }
+
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let axis = axis.normalize();
+        let (s, c) = (angle / 2.0).sin_cos();
+        Quat::new(axis.x * s, axis.y * s, axis.z * s, c)
+    }
+
+    /// Yaw around Y, then pitch around X, then roll around Z.  This is the order a first-person camera wants
+    pub fn from_euler(yaw: f32, pitch: f32, roll: f32) -> Quat {
+        Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch) * Quat::from_axis_angle(Vec3::Z, roll)
+    }
+
+    pub fn dot(self, other: Quat) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn normalize(self) -> Quat {
+        let len = self.dot(self).sqrt();
+        if len > 0.0 {
+            Quat::new(self.x / len, self.y / len, self.z / len, self.w / len)
+        } else {
+            Quat::IDENTITY
+        }
+    }
+
+    /// For a unit quaternion the conjugate is also the inverse rotation
+    pub fn conjugate(self) -> Quat {
+        Quat::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        // Optimized form of `q * v * q^-1`
+        let q = Vec3::new(self.x, self.y, self.z);
+        let t = q.cross(v) * 2.0;
+        v + t * self.w + q.cross(t)
+    }
+
+    /// Spherical linear interpolation, which keeps a constant angular speed.  Used for blending animation keyframes
+    pub fn slerp(self, other: Quat, t: f32) -> Quat {
+        let mut other = other;
+        let mut cos_theta = self.dot(other);
+
+        // Take the short way around
+        if cos_theta < 0.0 {
+            other = Quat::new(-other.x, -other.y, -other.z, -other.w);
+            cos_theta = -cos_theta;
+        }
+
+        // When the two are really close together just lerp, otherwise `sin_theta` gets too close to zero
+        if cos_theta > 0.9995 {
+            return Quat::new(
+                self.x + (other.x - self.x) * t,
+                self.y + (other.y - self.y) * t,
+                self.z + (other.z - self.z) * t,
+                self.w + (other.w - self.w) * t,
+            ).normalize();
+        }
+
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+        Quat::new(
+            self.x * a + other.x * b,
+            self.y * a + other.y * b,
+            self.z * a + other.z * b,
+            self.w * a + other.w * b,
+        )
+    }
+
+    pub fn to_mat4(self) -> Mat4 {
+        let Quat { x, y, z, w } = self;
+        let (xx, yy, zz) = (x * x, y * y, z * z);
+        let (xy, xz, yz) = (x * y, x * z, y * z);
+        let (wx, wy, wz) = (w * x, w * y, w * z);
+        Mat4::from_cols(
+            Vec4::new(1.0 - 2.0 * (yy + zz), 2.0 * (xy + wz), 2.0 * (xz - wy), 0.0),
+            Vec4::new(2.0 * (xy - wz), 1.0 - 2.0 * (xx + zz), 2.0 * (yz + wx), 0.0),
+            Vec4::new(2.0 * (xz + wy), 2.0 * (yz - wx), 1.0 - 2.0 * (xx + yy), 0.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+        )
+    }
+}
+
+impl Default for Quat {
+    fn default() -> Quat {
+        Quat::IDENTITY
+    }
+}
+
+/// Converts degrees to radians, since all of the functions above take radians
+#[allow(dead_code)]
+pub fn radians(degrees: f32) -> f32 {
+    degrees * std::f32::consts::PI / 180.0
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//                                       Operator overloads for the types above                                       //
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+macro_rules! impl_vector_ops {
+    ($t:ident { $($field:ident),+ }) => {
+        impl Add for $t {
+            type Output = $t;
+            fn add(self, o: $t) -> $t { $t { $($field: self.$field + o.$field),+ } }
+        }
+
+        impl Sub for $t {
+            type Output = $t;
+            fn sub(self, o: $t) -> $t { $t { $($field: self.$field - o.$field),+ } }
+        }
+
+        impl Mul<f32> for $t {
+            type Output = $t;
+            fn mul(self, s: f32) -> $t { $t { $($field: self.$field * s),+ } }
+        }
+
+        impl Div<f32> for $t {
+            type Output = $t;
+            fn div(self, s: f32) -> $t { $t { $($field: self.$field / s),+ } }
+        }
+
+        impl Neg for $t {
+            type Output = $t;
+            fn neg(self) -> $t { $t { $($field: -self.$field),+ } }
+        }
+
+        impl AddAssign for $t {
+            fn add_assign(&mut self, o: $t) { $(self.$field += o.$field;)+ }
+        }
+
+        impl SubAssign for $t {
+            fn sub_assign(&mut self, o: $t) { $(self.$field -= o.$field;)+ }
+        }
+
+        impl MulAssign<f32> for $t {
+            fn mul_assign(&mut self, s: f32) { $(self.$field *= s;)+ }
+        }
+    };
+}
+
+impl_vector_ops!(Vec2 { x, y });
+impl_vector_ops!(Vec3 { x, y, z });
+impl_vector_ops!(Vec4 { x, y, z, w });
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut out = [[0.0; 4]; 4];
+        for (c, out_col) in out.iter_mut().enumerate() {
+            for (r, out_val) in out_col.iter_mut().enumerate() {
+                *out_val = self.row(r).dot(rhs.col(c));
+            }
+        }
+        Mat4 { cols: out }
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+
+    fn mul(self, v: Vec4) -> Vec4 {
+        Vec4::new(self.row(0).dot(v), self.row(1).dot(v), self.row(2).dot(v), self.row(3).dot(v))
+    }
+}
+
+impl Mul for Quat {
+    type Output = Quat;
+
+    /// Combines two rotations; `a * b` rotates by `b` first and then by `a`, same as with matrices
+    fn mul(self, o: Quat) -> Quat {
+        Quat::new(
+            self.w * o.x + self.x * o.w + self.y * o.z - self.z * o.y,
+            self.w * o.y - self.x * o.z + self.y * o.w + self.z * o.x,
+            self.w * o.z + self.x * o.y - self.y * o.x + self.z * o.w,
+            self.w * o.w - self.x * o.x - self.y * o.y - self.z * o.z,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec4, b: Vec4) {
+        assert!((a - b).to_array().iter().all(|d| d.abs() < 1e-4), "{:?} != {:?}", a, b);
+    }
+
+    fn assert_mat_close(a: Mat4, b: Mat4) {
+        for i in 0..4 {
+            assert_close(a.col(i), b.col(i));
+        }
+    }
+
+    #[test]
+    fn inverse_round_trips() {
+        let rotation = Quat::from_axis_angle(Vec3::new(1.0, 2.0, 3.0), 0.7);
+        for &scale in &[1.0, 0.001, 250.0] {
+            // The translation scales too, or f32 can't hold the inverse's translation precisely enough to compare
+            let m = Mat4::from_trs(Vec3::new(3.0, -2.0, 5.0) * scale, rotation, Vec3::new(scale, scale * 2.0, scale));
+            let inverse = m.inverse().unwrap_or_else(|| panic!("scale {} wasn't invertible", scale));
+            assert_mat_close(m * inverse, Mat4::IDENTITY);
+            assert_mat_close(inverse * m, Mat4::IDENTITY);
+        }
+
+        let flattened = Mat4::scale(Vec3::new(1.0, 0.0, 1.0));
+        assert!(flattened.inverse().is_none());
+    }
+
+    #[test]
+    fn look_at_faces_down_negative_z() {
+        let eye = Vec3::new(1.0, 2.0, 3.0);
+        let view = Mat4::look_at(eye, Vec3::new(1.0, 2.0, -7.0), Vec3::Y);
+        assert_close(view.transform_point(eye).extend(1.0), Vec4::new(0.0, 0.0, 0.0, 1.0));
+        assert_close(view.transform_point(Vec3::new(1.0, 2.0, -7.0)).extend(1.0), Vec4::new(0.0, 0.0, -10.0, 1.0));
+        assert_close(view.transform_vector(Vec3::X).extend(0.0), Vec4::new(1.0, 0.0, 0.0, 0.0));
+        assert_close(view.transform_vector(Vec3::Y).extend(0.0), Vec4::new(0.0, 1.0, 0.0, 0.0));
+
+        // Looking along +X instead, so +X ends up straight ahead and +Z off to the right
+        let view = Mat4::look_at(Vec3::ZERO, Vec3::X, Vec3::Y);
+        assert_close(view.transform_vector(Vec3::X).extend(0.0), Vec4::new(0.0, 0.0, -1.0, 0.0));
+        assert_close(view.transform_vector(Vec3::Z).extend(0.0), Vec4::new(1.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_to_the_depth_range() {
+        let projection = Mat4::perspective(radians(60.0), 16.0 / 9.0, 0.1, 100.0);
+        let depth = |distance: f32| projection.transform_point(Vec3::new(0.0, 0.0, -distance)).z;
+        assert!((depth(0.1) + 1.0).abs() < 1e-4, "{}", depth(0.1));
+        assert!((depth(100.0) - 1.0).abs() < 1e-4, "{}", depth(100.0));
+        assert!(depth(1.0) > depth(0.1) && depth(1.0) < depth(100.0));
+    }
+
+    #[test]
+    fn slerp_hits_both_ends() {
+        let a = Quat::from_axis_angle(Vec3::Y, 0.3);
+        let b = Quat::from_axis_angle(Vec3::X, 2.0);
+        let as_vec4 = |q: Quat| Vec4::new(q.x, q.y, q.z, q.w);
+        assert_close(as_vec4(a.slerp(b, 0.0)), as_vec4(a));
+        assert_close(as_vec4(a.slerp(b, 1.0)), as_vec4(b));
+
+        // Halfway between two rotations about the same axis is the rotation by the average angle
+        let halfway = Quat::from_axis_angle(Vec3::Z, 0.2).slerp(Quat::from_axis_angle(Vec3::Z, 1.0), 0.5);
+        assert_close(as_vec4(halfway), as_vec4(Quat::from_axis_angle(Vec3::Z, 0.6)));
+    }
+}
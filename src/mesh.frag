@@ -0,0 +1,18 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec3 Normal;
+    vec2 TexCoord;
+} IN;
+
+uniform vec3 DiffuseColor;
+
+out vec4 Color;
+
+void main()
+{
+    // No real lighting yet, just enough shading from a fixed direction that we can tell the faces of a shape apart
+    vec3 light_direction = normalize(vec3(0.4, 1.0, 0.7));
+    float shade = 0.3 + 0.7 * max(dot(normalize(IN.Normal), light_direction), 0.0);
+    Color = vec4(DiffuseColor * shade, 1.0);
+}
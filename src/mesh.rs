@@ -0,0 +1,332 @@
+// A mesh on the CPU side is just a list of vertices plus a list of indices saying which vertices make up each triangle.
+// Indexing means a vertex shared by several triangles only gets stored (and run through the vertex shader) once.
+// `Mesh` is the GPU side: the same data living in buffers, with a VAO that knows how to read it.
+
+use std::collections::HashMap;
+
+use crate::buffer::{ArrayBuffer, ElementArrayBuffer, VertexArray};
+use crate::math::{Vec2, Vec3};
+use crate::tessellation;
+use crate::vertex::{Vertex, VertexLayout};
+
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,  // three per triangle
+}
+
+#[allow(dead_code)]
+impl MeshData {
+    pub fn new() -> MeshData {
+        MeshData::default()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Smooth normals: each vertex gets the average of the normals of the triangles it's part of.  Bigger triangles
+    /// count for more, since we don't normalize the cross products before adding them up
+    pub fn compute_normals(&mut self) {
+        for vertex in &mut self.vertices {
+            vertex.normal = Vec3::ZERO;
+        }
+        for triangle in self.indices.chunks_exact(3) {
+            let (a, b, c) = (triangle[0] as usize, triangle[1] as usize, triangle[2] as usize);
+            let p0 = self.vertices[a].position;
+            let face_normal = (self.vertices[b].position - p0).cross(self.vertices[c].position - p0);
+            self.vertices[a].normal += face_normal;
+            self.vertices[b].normal += face_normal;
+            self.vertices[c].normal += face_normal;
+        }
+        for vertex in &mut self.vertices {
+            vertex.normal = vertex.normal.normalize();
+        }
+    }
+
+    /// Tangents point along the direction that the U texture coordinate increases in, which is what normal mapping
+    /// needs to make sense of a normal map.  Needs normals and texture coordinates to already be filled in
+    pub fn compute_tangents(&mut self) {
+        let mut tangents = vec![Vec3::ZERO; self.vertices.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let (a, b, c) = (triangle[0] as usize, triangle[1] as usize, triangle[2] as usize);
+            let (v0, v1, v2) = (&self.vertices[a], &self.vertices[b], &self.vertices[c]);
+
+            let edge_1 = v1.position - v0.position;
+            let edge_2 = v2.position - v0.position;
+            let duv_1: Vec2 = v1.tex_coord - v0.tex_coord;
+            let duv_2: Vec2 = v2.tex_coord - v0.tex_coord;
+
+            let det = duv_1.x * duv_2.y - duv_2.x * duv_1.y;
+            if det.abs() < 1e-8 {
+                continue;  // Degenerate UVs, so this triangle can't tell us anything
+            }
+            let tangent = (edge_1 * duv_2.y - edge_2 * duv_1.y) / det;
+
+            tangents[a] += tangent;
+            tangents[b] += tangent;
+            tangents[c] += tangent;
+        }
+
+        for (vertex, tangent) in self.vertices.iter_mut().zip(tangents) {
+            // Gram-Schmidt: make the tangent exactly perpendicular to the normal
+            let n = vertex.normal;
+            vertex.tangent = (tangent - n * n.dot(tangent)).normalize();
+        }
+    }
+
+    /// Smallest box (min corner, max corner) that contains every vertex
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        let mut min = Vec3::splat(f32::INFINITY);
+        let mut max = Vec3::splat(f32::NEG_INFINITY);
+        for vertex in &self.vertices {
+            min = min.min(vertex.position);
+            max = max.max(vertex.position);
+        }
+        (min, max)
+    }
+
+    pub fn upload(&self) -> Mesh {
+        Mesh::new(&self.vertices, &Vertex::layout(), Some(&self.indices), gl::TRIANGLES)
+    }
+
+    /// Indices for drawing with `gl::TRIANGLES_ADJACENCY`: six per triangle, going corner, then the far corner of the
+    /// triangle on the other side of the edge to the next corner, then the next corner, and so on, which is what a
+    /// `triangles_adjacency` geometry shader gets as `gl_in[0]` to `gl_in[5]`.  Vertices are matched up by position,
+    /// since meshes duplicate them along texture seams.  An edge with nothing on the other side gets the triangle's
+    /// own far corner, so the "neighbour" is the triangle itself turned around, and always faces the other way
+    pub fn adjacency_indices(&self) -> Vec<u32> {
+        // The same position always maps to the first vertex that had it.  Positions are rounded first, since the two
+        // copies of a seam vertex often come from different angles (like 0 and 2 pi) and don't quite agree
+        let mut welded: HashMap<[i64; 3], u32> = HashMap::new();
+        let key = |p: Vec3| [p.x, p.y, p.z].map(|c| (c * 1e4).round() as i64);
+        let canonical: Vec<u32> = self.vertices.iter().enumerate()
+            .map(|(i, vertex)| *welded.entry(key(vertex.position)).or_insert(i as u32))
+            .collect();
+
+        // Each triangle's edges, going round the way it winds, mapped to the corner opposite them
+        let mut opposite: HashMap<(u32, u32), u32> = HashMap::new();
+        for triangle in self.indices.chunks_exact(3) {
+            for k in 0..3 {
+                let (a, b, c) = (triangle[k], triangle[(k + 1) % 3], triangle[(k + 2) % 3]);
+                opposite.insert((canonical[a as usize], canonical[b as usize]), c);
+            }
+        }
+
+        let mut indices = Vec::with_capacity(self.indices.len() * 2);
+        for triangle in self.indices.chunks_exact(3) {
+            for k in 0..3 {
+                let (a, b, c) = (triangle[k], triangle[(k + 1) % 3], triangle[(k + 2) % 3]);
+                // A neighbour that winds the same way goes along the shared edge backwards
+                let neighbour = opposite.get(&(canonical[b as usize], canonical[a as usize])).copied().unwrap_or(c);
+                indices.extend_from_slice(&[a, neighbour]);
+            }
+        }
+        indices
+    }
+
+    /// Uploads the mesh to be drawn with `gl::TRIANGLES_ADJACENCY`, for geometry shaders that need to know about
+    /// neighbouring triangles (like ones that find silhouette edges)
+    pub fn upload_with_adjacency(&self) -> Mesh {
+        Mesh::new(&self.vertices, &Vertex::layout(), Some(&self.adjacency_indices()), gl::TRIANGLES_ADJACENCY)
+    }
+}
+
+/// Vertices (and optionally indices) living on the GPU, ready to draw
+pub struct Mesh {
+    vao: VertexArray,
+    _vbo: ArrayBuffer,  // never used directly again, but has to stay alive as long as the VAO points at it
+    ebo: Option<ElementArrayBuffer>,
+    count: gl::types::GLsizei,  // number of indices if we have them, otherwise number of vertices
+    pub mode: gl::types::GLenum,  // `gl::TRIANGLES`, `gl::LINES`, `gl::POINTS`, ...
+}
+
+#[allow(dead_code)]
+impl Mesh {
+    /// Uploads any kind of vertex, as long as `layout` correctly describes it
+    pub fn new<V>(vertices: &[V], layout: &VertexLayout, indices: Option<&[u32]>, mode: gl::types::GLenum) -> Mesh {
+        let vao = VertexArray::new();
+        let mut vbo = ArrayBuffer::new();
+
+        vao.bind();
+        vbo.bind();
+        vbo.static_draw_data(vertices);
+        layout.apply();
+
+        // The element buffer binding is part of the VAO's state, so it has to be bound while the VAO is, and must not
+        // be unbound until the VAO is
+        let ebo = indices.map(|indices| {
+            let mut ebo = ElementArrayBuffer::new();
+            ebo.bind();
+            ebo.static_draw_data(indices);
+            ebo
+        });
+
+        vao.unbind();
+        vbo.unbind();
+
+        let count = match indices {
+            Some(indices) => indices.len(),
+            None => vertices.len(),
+        };
+
+        Mesh { vao, _vbo: vbo, ebo, count: count as gl::types::GLsizei, mode }
+    }
+
+    pub fn vertex_array(&self) -> &VertexArray {
+        &self.vao
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.ebo.is_some()
+    }
+
+    pub fn count(&self) -> gl::types::GLsizei {
+        self.count
+    }
+
+    pub fn draw(&self) {
+        self.vao.bind();
+        unsafe {
+            if self.ebo.is_some() {
+                gl::DrawElements(self.mode, self.count, gl::UNSIGNED_INT, std::ptr::null());
+            } else {
+                gl::DrawArrays(self.mode, 0, self.count);
+            }
+        }
+        self.vao.unbind();
+    }
+
+    /// Draws the mesh as `gl::PATCHES` for a tessellating program, whatever its `mode` says, taking every
+    /// `vertices_per_patch` vertices (or indices) as one patch.  The count has to be what the shaders expect to be
+    /// given: the control shader's input arrays, or the evaluation shader's if there's no control shader.  Fails,
+    /// drawing nothing, if the driver doesn't allow patches that size or the mesh isn't a whole number of them
+    pub fn draw_patches(&self, vertices_per_patch: u32) -> Result<(), String> {
+        if !(1..=tessellation::max_patch_vertices()).contains(&vertices_per_patch) {
+            return Err(format!("{} vertices per patch isn't allowed", vertices_per_patch));
+        }
+        if self.count as u32 % vertices_per_patch != 0 {
+            return Err(format!(
+                "{} vertices isn't a whole number of {}-vertex patches",
+                self.count, vertices_per_patch
+            ));
+        }
+        tessellation::set_patch_vertices(vertices_per_patch);
+        self.vao.bind();
+        unsafe {
+            if self.ebo.is_some() {
+                gl::DrawElements(gl::PATCHES, self.count, gl::UNSIGNED_INT, std::ptr::null());
+            } else {
+                gl::DrawArrays(gl::PATCHES, 0, self.count);
+            }
+        }
+        self.vao.unbind();
+        Ok(())
+    }
+
+    /// Points the mesh's VAO at `instances` as well as its vertices, so that `draw_instanced()` can read them.  Only
+    /// needs doing once per buffer, even if the buffer's contents change later.  The buffer has to outlive any draws
+    pub fn attach_instances(&self, instances: &InstanceBuffer) {
+        self.vao.bind();
+        instances.buffer.bind();
+        instances.layout.apply();
+        self.vao.unbind();
+        instances.buffer.unbind();
+    }
+
+    /// Draws `count` copies of the mesh in one call.  Attributes from an attached `InstanceBuffer` step once per copy,
+    /// and shaders can also tell the copies apart by `gl_InstanceID`
+    pub fn draw_instanced(&self, count: usize) {
+        self.vao.bind();
+        unsafe {
+            if self.ebo.is_some() {
+                gl::DrawElementsInstanced(
+                    self.mode,
+                    self.count,
+                    gl::UNSIGNED_INT,
+                    std::ptr::null(),
+                    count as gl::types::GLsizei,
+                );
+            } else {
+                gl::DrawArraysInstanced(self.mode, 0, self.count, count as gl::types::GLsizei);
+            }
+        }
+        self.vao.unbind();
+    }
+}
+
+/// Per-instance data for instanced draws (like `vertex::Instance`s), in a buffer that can be attached to any number
+/// of meshes with `Mesh::attach_instances()`
+pub struct InstanceBuffer {
+    buffer: ArrayBuffer,
+    layout: VertexLayout,
+    count: usize,
+}
+
+#[allow(dead_code)]
+impl InstanceBuffer {
+    /// An empty buffer for instances laid out like `layout`, which should be per-instance (see
+    /// `VertexLayout::per_instance()`)
+    pub fn new(layout: VertexLayout) -> InstanceBuffer {
+        InstanceBuffer { buffer: ArrayBuffer::new(), layout, count: 0 }
+    }
+
+    /// Replaces the instances.  When there are no more of them than last time, the existing storage gets overwritten
+    /// rather than reallocated
+    pub fn update<I>(&mut self, instances: &[I]) {
+        assert_eq!(std::mem::size_of::<I>(), self.layout.stride, "instances don't match the buffer's layout");
+        self.buffer.bind();
+        if std::mem::size_of_val(instances) <= self.buffer.size() && !instances.is_empty() {
+            self.buffer.sub_data(0, instances);
+        } else {
+            self.buffer.dynamic_draw_data(instances);
+        }
+        self.buffer.unbind();
+        self.count = instances.len();
+    }
+
+    /// How many instances the last `update()` uploaded
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes;
+
+    /// For each edge of each triangle: whether the adjacency indices found a real neighbour, rather than falling back
+    /// on the triangle's own far corner
+    fn neighbours_found(data: &MeshData) -> Vec<bool> {
+        let adjacency = data.adjacency_indices();
+        assert_eq!(adjacency.len(), data.indices.len() * 2);
+        let position = |index: u32| data.vertices[index as usize].position;
+        let mut found = Vec::new();
+        for (triangle, six) in data.indices.chunks(3).zip(adjacency.chunks(6)) {
+            for k in 0..3 {
+                let (a, b, c) = (triangle[k], triangle[(k + 1) % 3], triangle[(k + 2) % 3]);
+                assert_eq!([six[2 * k], six[(2 * k + 2) % 6]], [a, b], "corners have to be in the even slots");
+                found.push(position(six[2 * k + 1]) != position(c));
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn adjacency_finds_neighbours() {
+        // The quad's two triangles only share the diagonal, so each has one neighbour and two open edges
+        let found = neighbours_found(&shapes::quad(1.0, 1.0));
+        assert_eq!(found.iter().filter(|&&found| found).count(), 2);
+
+        // A cube's faces all have their own vertices, and a sphere's wrap round a texture seam, but both are closed
+        // once vertices are matched by position, so every edge has a neighbour
+        assert!(neighbours_found(&shapes::cube(1.0, 2)).iter().all(|&found| found));
+        assert!(neighbours_found(&shapes::uv_sphere(1.0, 16, 8)).iter().all(|&found| found));
+    }
+}
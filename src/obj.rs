@@ -0,0 +1,444 @@
+// Loader for Wavefront OBJ files (and the MTL material files they refer to).  OBJ is a plain text format with one
+// thing per line, like:
+//
+//     v 1.0 2.0 3.0          <- a position
+//     vt 0.5 0.5             <- a texture coordinate
+//     vn 0.0 1.0 0.0         <- a normal
+//     f 1/1/1 2/2/1 3/3/1    <- a face, made of position/texcoord/normal indices (which start at 1, not 0!)
+//
+// The catch is that OBJ indexes positions, texture coordinates and normals separately, while GL only has a single
+// index per vertex.  So every unique combination of the three becomes one of our vertices, and faces with more than
+// three corners get split up into triangles.
+
+use std::collections::HashMap;
+
+use crate::math::{Vec2, Vec3};
+use crate::mesh::{Mesh, MeshData};
+use crate::resources::{self, Resources};
+use crate::vertex::Vertex;
+
+#[derive(Debug, Clone)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub ambient: Vec3,   // Ka
+    pub diffuse: Vec3,   // Kd
+    pub specular: Vec3,  // Ks
+    pub emissive: Vec3,  // Ke
+    pub shininess: f32,  // Ns
+    pub dissolve: f32,   // d (or 1 - Tr), i.e. opacity
+    pub illum: u32,      // illumination model
+    // Texture maps, as resource names relative to the resource root (so they can be loaded just like the OBJ was)
+    pub diffuse_map: Option<String>,   // map_Kd
+    pub specular_map: Option<String>,  // map_Ks
+    pub normal_map: Option<String>,    // map_Bump, bump or norm
+    pub alpha_map: Option<String>,     // map_d
+}
+
+impl Default for ObjMaterial {
+    fn default() -> ObjMaterial {
+        ObjMaterial {
+            name: String::new(),
+            ambient: Vec3::ZERO,
+            diffuse: Vec3::splat(0.8),
+            specular: Vec3::ZERO,
+            emissive: Vec3::ZERO,
+            shininess: 0.0,
+            dissolve: 1.0,
+            illum: 1,
+            diffuse_map: None,
+            specular_map: None,
+            normal_map: None,
+            alpha_map: None,
+        }
+    }
+}
+
+/// One chunk of the model that shares an object, group and material, and so can be drawn with a single draw call
+#[derive(Debug, Clone)]
+pub struct ObjMesh {
+    pub object: String,             // from `o`
+    pub group: String,              // from `g`
+    pub material: Option<usize>,    // index into `ObjModel::materials`
+    pub data: MeshData,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ObjModel {
+    pub meshes: Vec<ObjMesh>,
+    pub materials: Vec<ObjMaterial>,
+}
+
+#[allow(dead_code)]
+impl ObjModel {
+    /// Sends every mesh off to the GPU.  The returned meshes are in the same order as `self.meshes`, so use that to
+    /// look up which material goes with each one
+    pub fn upload(&self) -> Vec<Mesh> {
+        self.meshes.iter().map(|mesh| mesh.data.upload()).collect()
+    }
+
+    pub fn material(&self, mesh: &ObjMesh) -> Option<&ObjMaterial> {
+        mesh.material.map(|index| &self.materials[index])
+    }
+}
+
+/// Loads an OBJ file, and any MTL files it mentions, through the resource loader
+#[allow(dead_code)]
+pub fn load(res: &Resources, resource_name: &str) -> Result<ObjModel, String> {
+    let source = res.load_string(resource_name).map_err(|e| format!("{}: {}", resource_name, e))?;
+
+    parse_obj(&source, resource_name, |mtl_name| {
+        let source = res.load_string(mtl_name).map_err(|e| format!("{}: {}", mtl_name, e))?;
+        parse_mtl(&source, mtl_name)
+    })
+}
+
+/// Builds up one `ObjMesh`, remembering which (position, texcoord, normal) combinations we've already turned into
+/// vertices so we can reuse them
+struct MeshBuilder {
+    object: String,
+    group: String,
+    material_name: Option<(String, usize)>,  // name, and the line it was set on (for error messages)
+    data: MeshData,
+    has_normals: bool,
+    has_tex_coords: bool,
+    vertex_lookup: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+}
+
+impl MeshBuilder {
+    fn new(object: &str, group: &str, material_name: Option<(String, usize)>) -> MeshBuilder {
+        MeshBuilder {
+            object: object.to_string(),
+            group: group.to_string(),
+            material_name,
+            data: MeshData::new(),
+            has_normals: true,
+            has_tex_coords: true,
+            vertex_lookup: HashMap::new(),
+        }
+    }
+}
+
+/// Parses OBJ source.  `resource_name` is only used for error messages and for finding MTL files, which get loaded by
+/// calling `load_mtl` with their resource name
+pub fn parse_obj<F>(source: &str, resource_name: &str, mut load_mtl: F) -> Result<ObjModel, String>
+    where F: FnMut(&str) -> Result<Vec<ObjMaterial>, String>
+{
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut tex_coords: Vec<Vec2> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    let mut finished: Vec<MeshBuilder> = Vec::new();
+    let mut current = MeshBuilder::new("", "", None);
+
+    for (line_index, raw_line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let error = |message: String| format!("{}:{}: {}", resource_name, line_number, message);
+
+        let mut parts = strip_comment(raw_line).split_whitespace();
+        let keyword = match parts.next() {
+            Some(keyword) => keyword,
+            None => continue,  // blank line or just a comment
+        };
+        let args: Vec<&str> = parts.collect();
+
+        match keyword {
+            "v" => {
+                let v = parse_floats(&args, 3, 3).map_err(error)?;
+                positions.push(Vec3::new(v[0], v[1], v[2]));
+            },
+            "vt" => {
+                // The V coordinate is optional, and there might be a W that we don't care about
+                let v = parse_floats(&args, 1, 3).map_err(error)?;
+                tex_coords.push(Vec2::new(v[0], v.get(1).cloned().unwrap_or(0.0)));
+            },
+            "vn" => {
+                let v = parse_floats(&args, 3, 3).map_err(error)?;
+                normals.push(Vec3::new(v[0], v[1], v[2]));
+            },
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error(format!("a face needs at least 3 vertices, but this one has {}", args.len())));
+                }
+
+                let mut corners = Vec::with_capacity(args.len());
+                for arg in &args {
+                    let key = parse_face_vertex(arg, positions.len(), tex_coords.len(), normals.len())
+                        .map_err(error)?;
+                    corners.push(add_vertex(&mut current, key, &positions, &tex_coords, &normals));
+                }
+
+                // Split the polygon into a fan of triangles around its first corner.  This is only correct for convex
+                // polygons, but that's what basically every exporter writes
+                for i in 1..corners.len() - 1 {
+                    current.data.indices.extend_from_slice(&[corners[0], corners[i], corners[i + 1]]);
+                }
+            },
+            "o" | "g" | "usemtl" => {
+                let name = args.join(" ");
+                let (object, group, material_name) = match keyword {
+                    "o" => (name, String::new(), current.material_name.clone()),
+                    "g" => (current.object.clone(), name, current.material_name.clone()),
+                    _ => {
+                        if name.is_empty() {
+                            return Err(error("`usemtl` needs a material name".to_string()));
+                        }
+                        (current.object.clone(), current.group.clone(), Some((name, line_number)))
+                    },
+                };
+
+                // Anything drawn from here on goes into a new mesh, since it's a different object, group or material
+                let next = MeshBuilder::new(&object, &group, material_name);
+                finished.push(std::mem::replace(&mut current, next));
+            },
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(error("`mtllib` needs at least one file name".to_string()));
+                }
+                for mtl_file in &args {
+                    let mtl_name = resources::relative_to(resource_name, mtl_file);
+                    let loaded = load_mtl(&mtl_name).map_err(|e| error(format!("couldn't load material library: {}", e)))?;
+                    materials.extend(loaded);
+                }
+            },
+            // Smoothing groups, lines, points and the various curve and surface keywords aren't something we can use,
+            // but they're still valid OBJ, so just skip them
+            _ => {},
+        }
+    }
+    finished.push(current);
+
+    let mut meshes = Vec::new();
+    for mut builder in finished.into_iter().filter(|builder| !builder.data.indices.is_empty()) {
+        let material = match builder.material_name {
+            Some((ref name, line_number)) => match materials.iter().position(|m| &m.name == name) {
+                Some(index) => Some(index),
+                None => return Err(format!("{}:{}: unknown material '{}'", resource_name, line_number, name)),
+            },
+            None => None,
+        };
+
+        if !builder.has_normals {
+            builder.data.compute_normals();
+        }
+        if builder.has_tex_coords {
+            builder.data.compute_tangents();
+        }
+
+        meshes.push(ObjMesh { object: builder.object, group: builder.group, material, data: builder.data });
+    }
+
+    Ok(ObjModel { meshes, materials })
+}
+
+/// Parses MTL source into a list of materials
+pub fn parse_mtl(source: &str, resource_name: &str) -> Result<Vec<ObjMaterial>, String> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (line_index, raw_line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let error = |message: String| format!("{}:{}: {}", resource_name, line_number, message);
+
+        let mut parts = strip_comment(raw_line).split_whitespace();
+        let keyword = match parts.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = parts.collect();
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(error("`newmtl` needs a material name".to_string()));
+            }
+            materials.push(ObjMaterial { name: args.join(" "), ..ObjMaterial::default() });
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => return Err(error(format!("`{}` came before any `newmtl`", keyword))),
+        };
+
+        let color = |args: &[&str]| parse_floats(args, 3, 3).map(|v| Vec3::new(v[0], v[1], v[2]));
+        let scalar = |args: &[&str]| parse_floats(args, 1, 1).map(|v| v[0]);
+
+        // Texture maps can have options like `-bm 1.0` before the file name, so the file name is always the last part
+        let map = |args: &[&str]| match args.last() {
+            Some(file) => Ok(Some(resources::relative_to(resource_name, file))),
+            None => Err(format!("`{}` needs a file name", keyword)),
+        };
+
+        match keyword {
+            "Ka" => material.ambient = color(&args).map_err(error)?,
+            "Kd" => material.diffuse = color(&args).map_err(error)?,
+            "Ks" => material.specular = color(&args).map_err(error)?,
+            "Ke" => material.emissive = color(&args).map_err(error)?,
+            "Ns" => material.shininess = scalar(&args).map_err(error)?,
+            "d" => material.dissolve = scalar(&args).map_err(error)?,
+            "Tr" => material.dissolve = 1.0 - scalar(&args).map_err(error)?,
+            "illum" => {
+                material.illum = args.first()
+                    .and_then(|arg| arg.parse().ok())
+                    .ok_or_else(|| error("`illum` needs a whole number".to_string()))?;
+            },
+            "map_Kd" => material.diffuse_map = map(&args).map_err(error)?,
+            "map_Ks" => material.specular_map = map(&args).map_err(error)?,
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_map = map(&args).map_err(error)?,
+            "map_d" => material.alpha_map = map(&args).map_err(error)?,
+            _ => {},  // plenty of other settings we don't use
+        }
+    }
+
+    Ok(materials)
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+fn parse_floats(args: &[&str], min: usize, max: usize) -> Result<Vec<f32>, String> {
+    if args.len() < min {
+        return Err(format!("expected at least {} numbers, found {}", min, args.len()));
+    }
+    args.iter()
+        .take(max)
+        .map(|arg| arg.parse::<f32>().map_err(|_| format!("expected a number, found '{}'", arg)))
+        .collect()
+}
+
+/// Turns an OBJ index (which starts at 1, or counts backwards from the end if it's negative) into a normal 0-based
+/// index, checking that it actually refers to something
+fn resolve_index(text: &str, count: usize, what: &str) -> Result<usize, String> {
+    let index: i64 = text.parse().map_err(|_| format!("expected a {} index, found '{}'", what, text))?;
+    let resolved = if index > 0 { index - 1 } else { count as i64 + index };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{} index {} is out of range (there are {} so far)", what, index, count));
+    }
+    Ok(resolved as usize)
+}
+
+/// Parses one corner of a face: `v`, `v/vt`, `v//vn` or `v/vt/vn`
+fn parse_face_vertex(text: &str, num_positions: usize, num_tex_coords: usize, num_normals: usize)
+    -> Result<(usize, Option<usize>, Option<usize>), String>
+{
+    let mut parts = text.split('/');
+    let position = resolve_index(parts.next().unwrap_or(""), num_positions, "position")?;
+    let tex_coord = match parts.next() {
+        Some("") | None => None,
+        Some(part) => Some(resolve_index(part, num_tex_coords, "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(part) => Some(resolve_index(part, num_normals, "normal")?),
+    };
+    if parts.next().is_some() {
+        return Err(format!("'{}' has too many parts for a face vertex", text));
+    }
+    Ok((position, tex_coord, normal))
+}
+
+fn add_vertex(
+    builder: &mut MeshBuilder,
+    key: (usize, Option<usize>, Option<usize>),
+    positions: &[Vec3],
+    tex_coords: &[Vec2],
+    normals: &[Vec3],
+) -> u32 {
+    if let Some(&index) = builder.vertex_lookup.get(&key) {
+        return index;
+    }
+
+    let (position, tex_coord, normal) = key;
+    builder.has_tex_coords &= tex_coord.is_some();
+    builder.has_normals &= normal.is_some();
+
+    let index = builder.data.vertices.len() as u32;
+    builder.data.vertices.push(Vertex::new(
+        positions[position],
+        normal.map(|i| normals[i]).unwrap_or(Vec3::ZERO),
+        tex_coord.map(|i| tex_coords[i]).unwrap_or_default(),
+    ));
+    builder.vertex_lookup.insert(key, index);
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "\
+# a unit quad, split into two materials
+mtllib quad.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vn 0 0 1
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/3/1
+g back
+usemtl blue
+f -4//1 -2//1 -1//1
+";
+
+    const MTL: &str = "\
+newmtl red
+Kd 1 0 0
+map_Kd textures/red.png
+newmtl blue
+Kd 0 0 1
+Tr 0.25
+";
+
+    fn load_quad(source: &str) -> Result<ObjModel, String> {
+        parse_obj(source, "models/quad.obj", |name| {
+            assert_eq!(name, "models/quad.mtl");
+            parse_mtl(MTL, name)
+        })
+    }
+
+    #[test]
+    fn splits_meshes_by_material() {
+        let model = load_quad(QUAD).unwrap();
+        assert_eq!(model.meshes.len(), 2);
+
+        let red = &model.meshes[0];
+        assert_eq!(red.data.vertices.len(), 4);
+        assert_eq!(red.data.indices, vec![0, 1, 2, 0, 2, 3]);  // the quad got split into two triangles
+        assert_eq!(model.material(red).unwrap().name, "red");
+
+        let blue = &model.meshes[1];
+        assert_eq!(blue.group, "back");
+        assert_eq!(blue.data.vertices[0].position, Vec3::new(0.0, 0.0, 0.0));  // -4 counts back from the end
+        assert_eq!(model.material(blue).unwrap().name, "blue");
+    }
+
+    #[test]
+    fn reads_materials() {
+        let materials = parse_mtl(MTL, "models/quad.mtl").unwrap();
+        assert_eq!(materials[0].diffuse, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(materials[0].diffuse_map.as_deref(), Some("models/textures/red.png"));
+        assert_eq!(materials[1].dissolve, 0.75);
+    }
+
+    #[test]
+    fn errors_give_the_line() {
+        let error = load_quad("v 0 0 0\nv 1 0 0\n\nf 1 2 3\n").unwrap_err();
+        assert_eq!(error, "models/quad.obj:4: position index 3 is out of range (there are 2 so far)");
+
+        let error = load_quad(&QUAD.replace("usemtl blue", "usemtl green")).unwrap_err();
+        assert_eq!(error, "models/quad.obj:14: unknown material 'green'");
+
+        let error = parse_mtl("newmtl red\nKd 1 0\n", "quad.mtl").unwrap_err();
+        assert_eq!(error, "quad.mtl:2: expected at least 3 numbers, found 2");
+
+        let error = parse_mtl("Kd 1 0 0\n", "quad.mtl").unwrap_err();
+        assert_eq!(error, "quad.mtl:1: `Kd` came before any `newmtl`");
+    }
+}
@@ -0,0 +1,118 @@
+use std::ffi::CString;
+use std::path::Path;
+
+use crate::render_gl;
+use crate::program;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::debug_view::DebugViews;
+use crate::math::{Mat4, Vec3};
+use crate::obj;
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::resources::Resources;
+
+/// Loads a model from an OBJ file instead of typing the vertices in by hand.  Drag to orbit around it, and use F1-F7
+/// for the debug views
+pub fn obj_viewer() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.5, 0.3, 0.3, 1.0);  // Color that window will default to when everything is cleared
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+
+    let res = Resources::from_crate_dir(Path::new("assets"));
+
+    let vert_shader = render_gl::Shader::from_vert_source(
+        &CString::new(
+            include_str!("mesh.vert")
+        ).unwrap()
+    ).unwrap();
+
+    let frag_shader = render_gl::Shader::from_frag_source(
+        &CString::new(
+            include_str!("mesh.frag")
+        ).unwrap()
+    ).unwrap();
+
+    let shader_program = program::Program::from_shaders(
+        &[vert_shader, frag_shader]
+    ).unwrap();
+
+    // Print the error rather than panicking on it, since a broken model file is an easy mistake to make
+    let model = match obj::load(&res, "models/cube.obj") {
+        Ok(model) => model,
+        Err(e) => {
+            println!("Failed to load model: {}", e);
+            return;
+        },
+    };
+    for mesh in &model.meshes {
+        println!(
+            "Loaded '{}/{}': {} vertices, {} triangles, material {:?}",
+            mesh.object,
+            mesh.group,
+            mesh.data.vertices.len(),
+            mesh.data.triangle_count(),
+            model.material(mesh).map(|m| &m.name),
+        );
+    }
+    let gpu_meshes = model.upload();
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(2.0, 1.5, 3.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 4.0);
+    controller.attach(&mut camera);
+
+    let render_state = RenderState::opaque();
+    let mut render_state_cache = RenderStateCache::new();
+    let mut debug_views = DebugViews::new().unwrap();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Escape), .. } => break 'main,
+                _ if debug_views.handle_event(&event) => {},
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        debug_views.near = camera.near;
+        debug_views.far = camera.far;
+        debug_views.draw(&mut render_state_cache, &render_state, &shader_program, |program| {
+            program.set_uniform_mat4("Model", &Mat4::identity());
+            program.set_uniform_mat4("View", &camera.view_matrix());
+            program.set_uniform_mat4("Projection", &camera.projection_matrix());
+
+            for (mesh, gpu_mesh) in model.meshes.iter().zip(&gpu_meshes) {
+                let diffuse = model.material(mesh).map(|m| m.diffuse).unwrap_or(Vec3::splat(0.8));
+                program.set_uniform_vec3("DiffuseColor", diffuse);
+                gpu_mesh.draw();
+            }
+        });
+
+        window.gl_swap_window();
+    }
+}
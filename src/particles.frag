@@ -0,0 +1,15 @@
+#version 430 core
+
+in vec4 ParticleColor;
+
+out vec4 Color;
+
+void main()
+{
+    // Round points rather than squares, fading out towards the edge
+    float distance = length(gl_PointCoord * 2.0 - 1.0);
+    if (distance > 1.0) {
+        discard;
+    }
+    Color = vec4(ParticleColor.rgb, ParticleColor.a * (1.0 - distance));
+}
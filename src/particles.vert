@@ -0,0 +1,22 @@
+#version 430 core
+
+// Draws the particles straight out of the storage buffer the compute shaders work on, one point per vertex, so there's
+// no vertex buffer: `gl_VertexID` picks the particle
+uniform mat4 ViewProjection;
+
+out vec4 ParticleColor;
+
+void main()
+{
+    Particle particle = ParticleList[gl_VertexID];
+    if (particle.Life <= 0.0) {
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);  // outside clip space, so it gets thrown away
+        ParticleColor = vec4(0.0);
+        return;
+    }
+    gl_Position = ViewProjection * vec4(particle.Position, 1.0);
+
+    // White hot when they're new, cooling to a dim red as they run out of life
+    float heat = clamp(particle.Life / 4.0, 0.0, 1.0);
+    ParticleColor = vec4(mix(vec3(0.8, 0.15, 0.05), vec3(1.0, 0.9, 0.6), heat), 0.3 + 0.7 * heat);
+}
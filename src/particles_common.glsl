@@ -0,0 +1,23 @@
+// The buffers the particle compute shaders share, pasted in after `#version` by `compute_demo.rs`.  The Rust side of
+// `Particle` is `compute_demo::Particle`, laid out by std430's rules
+
+struct Particle {
+    vec3 Position;
+    float Life;  // seconds left to live; 0 or less is dead
+    vec3 Velocity;
+    float Seed;  // a random number of its own, so that particles respawning together don't all do the same thing
+};
+
+layout (std430) buffer Particles {
+    Particle ParticleList[];
+};
+
+layout (std430) buffer Counters {
+    uint DispatchArgs[3];  // work groups for the emit pass, which is dispatched indirectly
+    uint DeadCount;        // how many particles the update pass found dead
+    uint EmitCount;        // how many of those the emit pass brings back
+};
+
+layout (std430) buffer DeadList {
+    uint DeadIndices[];
+};
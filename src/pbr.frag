@@ -0,0 +1,149 @@
+#version 330 core
+
+// Physically based shading with glTF's metallic-roughness material: the Cook-Torrance BRDF (GGX distribution, Smith
+// shadowing, Schlick's Fresnel) for the lights in the `Lights` block, plus image-based lighting from an `ibl::Environment`
+// for everything else around.  The result is linear HDR color, which still needs tone mapping before it's shown.
+//
+// The `Lights` block (and shadows) come from lights.glsl, pasted in by `pbr::create_program()`
+
+const float PI = 3.14159265359;
+
+in VS_OUTPUT {
+    vec3 WorldPosition;
+    vec3 Normal;
+    vec3 Tangent;
+    vec2 TexCoord;
+    vec4 Color;
+    vec3 CameraPosition;
+} IN;
+
+// The material, with the same names `GltfModel::draw()` sets for `gltf.frag`.  The defaults are for meshes drawn
+// through materials, where unset textures are plain white: a white normal map only works out flat with a
+// `NormalScale` of 0, and a white occlusion map needs an `OcclusionStrength` of 0
+uniform vec4 BaseColorFactor = vec4(1.0);
+uniform float MetallicFactor = 0.0;
+uniform float RoughnessFactor = 0.5;
+uniform float NormalScale = 0.0;
+uniform float OcclusionStrength = 0.0;
+uniform vec3 EmissiveFactor = vec3(0.0);
+uniform float AlphaCutoff = -1.0;
+
+uniform sampler2D BaseColorTexture;
+uniform sampler2D MetallicRoughnessTexture;
+uniform sampler2D NormalTexture;
+uniform sampler2D OcclusionTexture;
+uniform sampler2D EmissiveTexture;
+
+// Set by `Environment::bind()`
+uniform samplerCube IrradianceMap;
+uniform samplerCube PrefilteredMap;
+uniform sampler2D BrdfLut;
+uniform float PrefilteredLevels;
+uniform float EnvironmentIntensity;
+
+out vec4 Color;
+
+float distribution_ggx(float n_dot_h, float roughness)
+{
+    float a2 = roughness * roughness * roughness * roughness;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Smith's shadowing and masking, with the `k` for direct lights
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness)
+{
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0)
+{
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Rough surfaces don't brighten up as much at grazing angles, which matters for the environment's reflections
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness)
+{
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+void main()
+{
+    vec4 base_color = BaseColorFactor * IN.Color * texture(BaseColorTexture, IN.TexCoord);
+    if (base_color.a < AlphaCutoff) {
+        discard;
+    }
+    vec3 albedo = base_color.rgb;
+
+    vec3 n = normalize(IN.Normal);
+    if (length(IN.Tangent) > 0.0) {
+        vec3 t = normalize(IN.Tangent - n * dot(n, IN.Tangent));
+        vec3 mapped = texture(NormalTexture, IN.TexCoord).xyz * 2.0 - 1.0;
+        mapped.xy *= NormalScale;
+        n = normalize(mat3(t, cross(n, t), n) * mapped);
+    }
+
+    vec2 metallic_roughness = texture(MetallicRoughnessTexture, IN.TexCoord).bg;
+    float metallic = clamp(MetallicFactor * metallic_roughness.x, 0.0, 1.0);
+    // Perfectly smooth surfaces would turn lights into infinitely small, infinitely bright points
+    float roughness = clamp(RoughnessFactor * metallic_roughness.y, 0.04, 1.0);
+    float occlusion = mix(1.0, texture(OcclusionTexture, IN.TexCoord).r, OcclusionStrength);
+
+    vec3 v = normalize(IN.CameraPosition - IN.WorldPosition);
+    float n_dot_v = max(dot(n, v), 0.0001);
+
+    // How much light bounces straight back when looking straight on.  Around 4% for nearly every non-metal, and the
+    // base color for metals, which have no diffuse at all
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+
+    vec3 direct = vec3(0.0);
+    for (int i = 0; i < min(LightCount.x, MAX_LIGHTS); i++) {
+        Light light = LightList[i];
+        int type = int(light.PositionType.w);
+
+        vec3 l;
+        float attenuation = 1.0;
+        if (type == LIGHT_DIRECTIONAL) {
+            l = -normalize(light.DirectionRange.xyz);
+        } else {
+            vec3 to_light = light.PositionType.xyz - IN.WorldPosition;
+            float distance = length(to_light);
+            l = to_light / distance;
+            float window = clamp(1.0 - pow(distance / light.DirectionRange.w, 4.0), 0.0, 1.0);
+            attenuation = window * window / (1.0 + distance * distance);
+            if (type == LIGHT_SPOT) {
+                float angle_cos = dot(-l, normalize(light.DirectionRange.xyz));
+                attenuation *= smoothstep(light.Cone.y, light.Cone.x, angle_cos);
+            }
+        }
+
+        float n_dot_l = dot(n, l);
+        if (n_dot_l <= 0.0) {
+            continue;
+        }
+        attenuation *= shadow_factor(light, IN.WorldPosition, normalize(IN.Normal));
+        vec3 h = normalize(v + l);
+        vec3 fresnel = fresnel_schlick(max(dot(h, v), 0.0), f0);
+        float d = distribution_ggx(max(dot(n, h), 0.0), roughness);
+        float g = geometry_smith(n_dot_v, n_dot_l, roughness);
+        vec3 specular = d * g * fresnel / (4.0 * n_dot_v * n_dot_l + 0.0001);
+        vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo / PI;  // whatever isn't reflected goes in
+
+        vec3 radiance = light.ColorIntensity.rgb * light.ColorIntensity.a * attenuation;
+        direct += (diffuse + specular) * radiance * n_dot_l;
+    }
+
+    // The environment: diffuse from the irradiance map, and specular from the prefiltered map (the level blurred for
+    // this roughness) scaled by the BRDF lookup table
+    vec3 fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    vec3 irradiance = texture(IrradianceMap, n).rgb;
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * irradiance * albedo;
+    vec3 prefiltered = textureLod(PrefilteredMap, reflect(-v, n), roughness * (PrefilteredLevels - 1.0)).rgb;
+    vec2 brdf = texture(BrdfLut, vec2(n_dot_v, roughness)).rg;
+    vec3 specular = prefiltered * (fresnel * brdf.x + brdf.y);
+    vec3 ambient = ((diffuse + specular) * EnvironmentIntensity + Ambient.rgb * albedo) * occlusion;
+
+    vec3 emissive = EmissiveFactor * texture(EmissiveTexture, IN.TexCoord).rgb;
+    Color = vec4(direct + ambient + emissive, base_color.a);
+}
@@ -0,0 +1,166 @@
+// Physically based rendering: `pbr.vert` and `pbr.frag` shade glTF's metallic-roughness materials with lights from a
+// `LightBuffer` and the surroundings from an `ibl::Environment`, and everything else needed to get that on screen.
+//
+// Physically based lighting comes out in real-world-ish units, where a sunlit wall can easily be ten times brighter
+// than "white".  So the scene is drawn into a floating point framebuffer first, and a `ToneMapper` then squeezes that
+// down into what a screen can show, like a camera's exposure does.  Color textures are stored as sRGB and turned into
+// linear values when sampled, all the lighting math is linear, and the tone mapper converts back to sRGB at the very
+// end.
+
+use std::ffi::CString;
+
+use crate::framebuffer::{self, DepthAttachment, Framebuffer, FullscreenTriangle};
+use crate::lighting;
+use crate::math::{Mat4, Vec4};
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{CompareFunc, DepthState, RenderState, RenderStateCache};
+use crate::texture::Cubemap;
+
+/// Compiles `pbr.vert` and `pbr.frag` for up to `max_lights` lights, set up with `lighting::prepare_program()`
+#[allow(dead_code)]
+pub fn create_program(max_lights: usize) -> Result<Program, String> {
+    let vert_shader = Shader::from_vert_source(&CString::new(include_str!("pbr.vert")).unwrap())?;
+    let frag_source = lighting::with_lights(include_str!("pbr.frag"), max_lights);
+    let frag_shader = Shader::from_frag_source(&CString::new(frag_source).unwrap())?;
+
+    let program = Program::from_shaders(&[vert_shader, frag_shader])?;
+    lighting::prepare_program(&program);
+    Ok(program)
+}
+
+/// The curve HDR colors get squeezed through, numbered the same as in `tonemap.frag`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum ToneMapping {
+    Clamp,      // no curve at all, so anything too bright just turns white
+    Reinhard,   // x / (1 + x): simple, but washes out the brights
+    Aces,       // the filmic curve from the Academy Color Encoding System, punchier and more contrasty
+    Uncharted,  // John Hable's filmic curve from Uncharted 2
+}
+
+#[allow(dead_code)]
+impl ToneMapping {
+    pub const ALL: [ToneMapping; 4] =
+        [ToneMapping::Clamp, ToneMapping::Reinhard, ToneMapping::Aces, ToneMapping::Uncharted];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapping::Clamp => "clamp",
+            ToneMapping::Reinhard => "Reinhard",
+            ToneMapping::Aces => "ACES",
+            ToneMapping::Uncharted => "Uncharted 2",
+        }
+    }
+
+    pub fn next(self) -> ToneMapping {
+        let index = ToneMapping::ALL.iter().position(|&t| t == self).unwrap();
+        ToneMapping::ALL[(index + 1) % ToneMapping::ALL.len()]
+    }
+}
+
+/// Owns the floating point framebuffer the scene gets drawn into, and draws it to the window.  Anything between
+/// `begin()` and `end()` ends up tone mapped
+pub struct ToneMapper {
+    target: Framebuffer,
+    program: Program,
+    fullscreen: FullscreenTriangle,
+    pub exposure: f32,  // in stops: each +1 doubles the brightness
+    pub operator: ToneMapping,
+}
+
+#[allow(dead_code)]
+impl ToneMapper {
+    pub fn new(width: u32, height: u32) -> Result<ToneMapper, String> {
+        Ok(ToneMapper {
+            target: ToneMapper::create_target(width, height)?,
+            program: framebuffer::fullscreen_program(include_str!("tonemap.frag"))?,
+            fullscreen: FullscreenTriangle::new(),
+            exposure: 0.0,
+            operator: ToneMapping::Aces,
+        })
+    }
+
+    fn create_target(width: u32, height: u32) -> Result<Framebuffer, String> {
+        // 16 bit floats go up to 65504, which is plenty, at half the memory of 32 bit ones
+        Framebuffer::new(width.max(1), height.max(1), &[gl::RGBA16F], DepthAttachment::Renderbuffer)
+    }
+
+    /// Call when the window changes size
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), String> {
+        if (width, height) != (self.target.width(), self.target.height()) {
+            self.target = ToneMapper::create_target(width, height)?;
+        }
+        Ok(())
+    }
+
+    /// Starts drawing into the HDR framebuffer (and clears it)
+    pub fn begin(&self) {
+        self.target.bind();
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    /// Tone maps what was drawn onto the window
+    pub fn end(&self, cache: &mut RenderStateCache) {
+        Framebuffer::bind_default(self.target.width(), self.target.height());
+        cache.apply(&RenderState::default());
+        self.program.set_used();
+        self.target.color(0).bind(0);
+        self.program.set_uniform_i32("HdrColor", 0);
+        self.program.set_uniform_f32("Exposure", 2f32.powf(self.exposure));
+        self.program.set_uniform_i32("Operator", self.operator as i32);
+        self.fullscreen.draw();
+    }
+}
+
+/// Draws a cubemap behind everything else.  Draw it after the opaque geometry, so that the only pixels it has to
+/// shade are the ones nothing else covered
+pub struct Skybox {
+    program: Program,
+    fullscreen: FullscreenTriangle,
+    render_state: RenderState,
+}
+
+#[allow(dead_code)]
+impl Skybox {
+    pub fn new() -> Result<Skybox, String> {
+        Ok(Skybox {
+            program: framebuffer::fullscreen_program(include_str!("skybox.frag"))?,
+            fullscreen: FullscreenTriangle::new(),
+            // The sky sits exactly on the far plane, so it needs "less or equal" to pass where nothing was drawn
+            render_state: RenderState {
+                depth: DepthState { test: true, write: false, func: CompareFunc::LessEqual },
+                ..RenderState::default()
+            },
+        })
+    }
+
+    /// `level` picks a blurrier mip level of `cubemap` (0 for the sharpest), and `intensity` scales its brightness
+    pub fn draw(
+        &self,
+        cubemap: &Cubemap,
+        level: f32,
+        intensity: f32,
+        view: &Mat4,
+        projection: &Mat4,
+        cache: &mut RenderStateCache,
+    ) {
+        // Only the camera's rotation matters, since the sky is infinitely far away
+        let rotation = Mat4::from_cols(view.col(0), view.col(1), view.col(2), Vec4::new(0.0, 0.0, 0.0, 1.0));
+        let inverse = match (*projection * rotation).inverse() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+
+        cache.apply(&self.render_state);
+        self.program.set_used();
+        cubemap.bind(0);
+        self.program.set_uniform_i32("Environment", 0);
+        self.program.set_uniform_mat4("InverseViewProjection", &inverse);
+        self.program.set_uniform_f32("Level", level);
+        self.program.set_uniform_f32("Intensity", intensity);
+        self.fullscreen.draw();
+    }
+}
@@ -0,0 +1,55 @@
+#version 330 core
+
+// `gltf.vert` plus the camera position, so it works for glTF models (through `GltfModel::draw()`) and for plain meshes
+// drawn through materials alike
+
+#define MAX_JOINTS 64
+
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec4 Color;
+layout (location = 2) in vec3 Normal;
+layout (location = 3) in vec2 TexCoord;
+layout (location = 4) in vec3 Tangent;
+layout (location = 5) in vec4 JointIndices;
+layout (location = 6) in vec4 JointWeights;
+
+uniform mat4 Model;
+uniform mat4 View;
+uniform mat4 Projection;
+
+uniform bool Skinned = false;
+uniform mat4 Joints[MAX_JOINTS];
+
+// Only glTF meshes have vertex colors.  Anything else leaves the attribute turned off, and turned off attributes read
+// as black, so colors have to be asked for
+uniform bool VertexColors = false;
+
+out VS_OUTPUT {
+    vec3 WorldPosition;
+    vec3 Normal;
+    vec3 Tangent;
+    vec2 TexCoord;
+    vec4 Color;
+    vec3 CameraPosition;
+} OUT;
+
+void main()
+{
+    mat4 skin = mat4(1.0);
+    if (Skinned) {
+        skin = JointWeights.x * Joints[int(JointIndices.x)]
+             + JointWeights.y * Joints[int(JointIndices.y)]
+             + JointWeights.z * Joints[int(JointIndices.z)]
+             + JointWeights.w * Joints[int(JointIndices.w)];
+    }
+    mat4 model = Model * skin;
+
+    vec4 world_position = model * vec4(Position, 1.0);
+    gl_Position = Projection * View * world_position;
+    OUT.WorldPosition = world_position.xyz;
+    OUT.Normal = transpose(inverse(mat3(model))) * Normal;
+    OUT.Tangent = mat3(model) * Tangent;
+    OUT.TexCoord = TexCoord;
+    OUT.Color = VertexColors ? Color : vec4(1.0);
+    OUT.CameraPosition = inverse(View)[3].xyz;
+}
@@ -0,0 +1,244 @@
+use std::path::Path;
+
+use crate::bounds::Aabb;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::gltf;
+use crate::ibl::{Environment, IblSettings};
+use crate::lighting::LightBuffer;
+use crate::material::MaterialValue;
+use crate::math::{Vec3, Vec4};
+use crate::pbr::{self, Skybox, ToneMapper};
+use crate::program::Program;
+use crate::render_state::{CullMode, RenderState, RenderStateCache};
+use crate::resources::Resources;
+use crate::scene::{Light, LightKind, Renderable, Scene};
+use crate::shapes;
+
+const MAX_LIGHTS: usize = 4;
+const ENVIRONMENT: &str = "environments/sky.hdr";
+const GRID_SIZE: usize = 7;
+
+/// What's drawn behind the scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Background {
+    Sharp,
+    Blurred,
+    Irradiance,
+}
+
+/// Physically based shading lit by an HDR environment map.  By default it's a grid of spheres, getting more metallic
+/// from bottom to top and rougher from left to right; M swaps that for the animated glTF scene.  The irradiance and
+/// reflection maps are worked out from `assets/environments/sky.hdr` the first time, and cached in `assets/cache`
+/// after that.
+///
+/// T cycles the tone mapping curve, +/- change the exposure, L switches the four point lights on and off, B changes
+/// the background (the environment, blurred, or the irradiance map), Space pauses the animation, and dragging orbits
+/// the camera
+pub fn pbr_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.0, 0.0, 0.0, 1.0);  // Always covered by the sky anyway
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let mut timer = _sdl.timer().unwrap();
+
+    let res = Resources::from_crate_dir(Path::new("assets"));
+    let mut render_state_cache = RenderStateCache::new();
+
+    let start = timer.ticks();
+    let environment = match Environment::load(&res, ENVIRONMENT, &IblSettings::default(), &mut render_state_cache) {
+        Ok(environment) => environment,
+        Err(e) => {
+            println!("Failed to load the environment: {}", e);
+            return;
+        },
+    };
+    for warning in &environment.warnings {
+        println!("Warning: {}", warning);
+    }
+    println!(
+        "Environment took {} ms ({})",
+        timer.ticks() - start,
+        if environment.from_cache { "from the cache" } else { "precomputed" },
+    );
+
+    let (width, height) = window.size();
+    let setup = || -> Result<(ToneMapper, Skybox, Program), String> {
+        Ok((ToneMapper::new(width, height)?, Skybox::new()?, pbr::create_program(MAX_LIGHTS)?))
+    };
+    let (mut tone_mapper, skybox, pbr_program) = match setup() {
+        Ok(setup) => setup,
+        Err(e) => {
+            println!("Failed to set up rendering: {}", e);
+            return;
+        },
+    };
+    let light_buffer = LightBuffer::new(MAX_LIGHTS);
+
+    // Any mesh works with the PBR program through a material; here it's spheres with every combination of metallic
+    // and roughness
+    let mut scene = Scene::new();
+    let program = scene.materials.add_program(pbr_program);
+    let sphere_data = shapes::uv_sphere(0.4, 48, 24);
+    let sphere = scene.add_mesh_data(&sphere_data);
+    let (min, max) = sphere_data.bounds();
+    let spacing = 1.1;
+    let offset = (GRID_SIZE - 1) as f32 * spacing / 2.0;
+    for row in 0..GRID_SIZE {
+        for column in 0..GRID_SIZE {
+            let metallic = row as f32 / (GRID_SIZE - 1) as f32;
+            let roughness = column as f32 / (GRID_SIZE - 1) as f32;
+            let mut material = scene.materials.create_material("sphere", program);
+            material.set("BaseColorFactor", MaterialValue::Vec4(Vec4::new(0.95, 0.64, 0.54, 1.0)));  // copper
+            material.set("MetallicFactor", MaterialValue::Float(metallic));
+            material.set("RoughnessFactor", MaterialValue::Float(roughness));
+            let material = scene.materials.add_material(material);
+
+            let node = scene.add_node("sphere", None);
+            scene.node_mut(node).set_translation(Vec3::new(
+                column as f32 * spacing - offset,
+                row as f32 * spacing - offset,
+                0.0,
+            ));
+            scene.node_mut(node).renderable = Some(Renderable::new(sphere, material, Aabb::new(min, max)));
+        }
+    }
+
+    let light = Light { kind: LightKind::Point, color: Vec3::ONE, intensity: 60.0, range: 20.0, shadow: None };
+    let light_nodes: Vec<usize> = [(-1.0, 1.0), (1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)].iter()
+        .map(|&(x, y)| {
+            let node = scene.add_node("light", None);
+            scene.node_mut(node).set_translation(Vec3::new(x * 4.0, y * 4.0, 4.0));
+            scene.node_mut(node).light = Some(light);
+            node
+        })
+        .collect();
+
+    let mut model = match gltf::load(&res, "models/animated_scene.gltf") {
+        Ok(model) => model,
+        Err(e) => {
+            println!("Failed to load model: {}", e);
+            return;
+        },
+    };
+    let model_gpu_data = model.upload();
+    let model_state = RenderState { cull_mode: CullMode::None, ..RenderState::opaque() };
+    let (model_min, model_max) = model.bounds();
+    let model_center = (model_min + model_max) * 0.5;
+    let model_radius = (model_max - model_min).length() * 0.5;
+
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 11.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 11.0);
+    controller.attach(&mut camera);
+
+    let mut show_model = false;
+    let mut lights_on = true;
+    let mut background = Background::Sharp;
+    let mut playing = true;
+    let mut animation_time = 0.0f32;
+    let mut last_ticks = timer.ticks();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::M), .. } => {
+                    show_model = !show_model;
+                    let (target, distance) = if show_model {
+                        (model_center, model_radius * 2.5)
+                    } else {
+                        (Vec3::ZERO, 11.0)
+                    };
+                    camera.position = target + Vec3::new(0.0, 0.0, distance);
+                    controller = OrbitController::new(target, distance);
+                    controller.attach(&mut camera);
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::T), .. } => {
+                    tone_mapper.operator = tone_mapper.operator.next();
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Equals), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(Keycode::KpPlus), .. } => tone_mapper.exposure += 0.5,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Minus), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(Keycode::KpMinus), .. } => tone_mapper.exposure -= 0.5,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::L), .. } => {
+                    lights_on = !lights_on;
+                    for &node in &light_nodes {
+                        scene.node_mut(node).light = if lights_on { Some(light) } else { None };
+                    }
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::B), .. } => {
+                    background = match background {
+                        Background::Sharp => Background::Blurred,
+                        Background::Blurred => Background::Irradiance,
+                        Background::Irradiance => Background::Sharp,
+                    };
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Space), .. } => playing = !playing,
+                sdl2::event::Event::Window { win_event: sdl2::event::WindowEvent::Resized(w, h), .. } => {
+                    camera.handle_resize(&event);
+                    if let Err(e) = tone_mapper.resize(w as u32, h as u32) {
+                        println!("Failed to resize the HDR framebuffer: {}", e);
+                        break 'main;
+                    }
+                },
+                _ => controller.handle_event(&mut camera, &event),
+            }
+        }
+
+        let ticks = timer.ticks();
+        if playing {
+            animation_time += (ticks - last_ticks) as f32 / 1000.0;
+        }
+        last_ticks = ticks;
+        model.animate(0, animation_time);
+
+        let (view, projection) = (camera.view_matrix(), camera.projection_matrix());
+        let draw_list = scene.collect_draw_calls(&(projection * view), camera.position);
+        light_buffer.update(Vec3::ZERO, &scene.lights());
+
+        tone_mapper.begin();
+        let pbr_program = &scene.materials.programs[program];
+        pbr_program.set_used();
+        environment.bind(pbr_program, 1.0);
+        if show_model {
+            render_state_cache.apply(&model_state);
+            pbr_program.set_uniform_mat4("View", &view);
+            pbr_program.set_uniform_mat4("Projection", &projection);
+            model.draw(&model_gpu_data, pbr_program);
+        } else {
+            scene.draw(&draw_list, &mut render_state_cache, &view, &projection);
+        }
+        let (background_map, level) = match background {
+            Background::Sharp => (&environment.skybox, 0.0),
+            Background::Blurred => (&environment.skybox, 4.0),
+            Background::Irradiance => (&environment.irradiance, 0.0),
+        };
+        skybox.draw(background_map, level, 1.0, &view, &projection, &mut render_state_cache);
+        tone_mapper.end(&mut render_state_cache);
+
+        window.set_title(&format!(
+            "{} tone mapping, exposure {:+.1}",
+            tone_mapper.operator.name(),
+            tone_mapper.exposure,
+        )).unwrap();
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,26 @@
+#version 410 core
+
+// mesh.vert, as a separable vertex stage.  Its outputs have explicit locations, since the fragment stage comes from a
+// different program, and the pipeline matches them up by location
+layout (location = 0) in vec3 Position;
+layout (location = 2) in vec3 Normal;
+layout (location = 3) in vec2 TexCoord;
+
+uniform mat4 Model;
+uniform mat4 View;
+uniform mat4 Projection;
+
+// Separable programs are meant to say which built-in outputs they write
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+
+layout (location = 0) out vec3 WorldNormal;
+layout (location = 1) out vec2 SurfaceTexCoord;
+
+void main()
+{
+    gl_Position = Projection * View * Model * vec4(Position, 1.0);
+    WorldNormal = mat3(Model) * Normal;
+    SurfaceTexCoord = TexCoord;
+}
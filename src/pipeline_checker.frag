@@ -0,0 +1,16 @@
+#version 410 core
+
+// debug_uv_checker.frag, as a separable fragment stage
+layout (location = 1) in vec2 SurfaceTexCoord;
+
+uniform float CheckerScale;
+
+out vec4 Color;
+
+void main()
+{
+    vec2 cell = floor(SurfaceTexCoord * CheckerScale);
+    float checker = mod(cell.x + cell.y, 2.0);
+    vec3 tint = vec3(fract(SurfaceTexCoord), 0.5);
+    Color = vec4(mix(tint * 0.4, tint, checker), 1.0);
+}
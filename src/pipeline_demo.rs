@@ -0,0 +1,167 @@
+use std::ffi::CString;
+
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::math::{Mat4, Quat, Vec3};
+use crate::program::Program;
+use crate::program_pipeline::{self, ProgramPipeline};
+use crate::render_gl::Shader;
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::shapes;
+
+/// A separable program with just the one stage
+fn stage_program(source: &str, shader_type: gl::types::GLenum) -> Result<Program, String> {
+    let shader = Shader::from_source(&CString::new(source).unwrap(), shader_type)?;
+    Program::from_shaders_separable(&[shader])
+}
+
+/// Two vertex stages and three fragment stages, each linked once on its own and mixed and matched in one pipeline,
+/// which makes all six combinations out of five programs (linking them the usual way would take six programs, and
+/// compile each vertex shader three times).  The vertex stages are a plain one and one that ripples the surface; the
+/// fragment stages shade the torus, show its normals, or put the UV checker on it.  Before anything's drawn, a
+/// fragment stage that doesn't fit is put in the pipeline, to show what `ProgramPipeline::validate()` says about it.
+///
+/// V and F switch vertex and fragment stages.  Dragging orbits the camera.  This needs OpenGL 4.1, so it asks for a
+/// newer context than most of the other lessons do
+pub fn pipeline_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here.  Pipelines need 4.1, rather than the 3.3 everything else asks for
+    program_pipeline::request_context(&video_subsystem);
+    video_subsystem.gl_attr().set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+
+    if !program_pipeline::is_supported() {
+        println!("Failed to set up program pipelines: this needs OpenGL {}.{}", program_pipeline::MIN_GL_VERSION.0,
+                 program_pipeline::MIN_GL_VERSION.1);
+        return;
+    }
+    type Stages = Vec<(&'static str, Program)>;
+    let setup = || -> Result<(Stages, Stages, Program), String> {
+        let vertex_stages = vec![
+            ("plain", stage_program(include_str!("pipeline.vert"), gl::VERTEX_SHADER)?),
+            ("rippling", stage_program(include_str!("pipeline_wave.vert"), gl::VERTEX_SHADER)?),
+        ];
+        let fragment_stages = vec![
+            ("shaded", stage_program(include_str!("pipeline_shaded.frag"), gl::FRAGMENT_SHADER)?),
+            ("normals", stage_program(include_str!("pipeline_normals.frag"), gl::FRAGMENT_SHADER)?),
+            ("UV checker", stage_program(include_str!("pipeline_checker.frag"), gl::FRAGMENT_SHADER)?),
+        ];
+        let mismatched = stage_program(include_str!("pipeline_mismatched.frag"), gl::FRAGMENT_SHADER)?;
+        Ok((vertex_stages, fragment_stages, mismatched))
+    };
+    let (vertex_stages, fragment_stages, mismatched) = match setup() {
+        Ok(setup) => setup,
+        Err(e) => {
+            println!("Failed to set up program pipelines: {}", e);
+            return;
+        },
+    };
+
+    let mut pipeline = ProgramPipeline::new();
+    let check = pipeline.use_program(&vertex_stages[0].1)
+        .and_then(|_| pipeline.use_program(&mismatched))
+        .and_then(|_| pipeline.validate());
+    match check {
+        Ok(()) => println!("The mismatched fragment stage somehow fits"),
+        Err(e) => println!("As expected, the mismatched fragment stage doesn't fit. {}", e),
+    }
+
+    fragment_stages[0].1.set_used();
+    fragment_stages[0].1.set_uniform_vec3("DiffuseColor", Vec3::new(0.9, 0.55, 0.3));
+    fragment_stages[2].1.set_used();
+    fragment_stages[2].1.set_uniform_f32("CheckerScale", 8.0);
+    let mesh = shapes::torus(1.5, 0.6, 96, 48).upload();
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 2.0, 6.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 6.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut vertex_stage = 0;
+    let mut fragment_stage = 0;
+    let mut changed = true;
+    let mut time = 0.0f32;
+    let frequency = timer.performance_frequency() as f64;
+    let mut last_counter = timer.performance_counter();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::V), .. } => {
+                    vertex_stage = (vertex_stage + 1) % vertex_stages.len();
+                    changed = true;
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::F), .. } => {
+                    fragment_stage = (fragment_stage + 1) % fragment_stages.len();
+                    changed = true;
+                },
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let counter = timer.performance_counter();
+        time += ((counter - last_counter) as f64 / frequency) as f32;
+        last_counter = counter;
+
+        let (vertex_name, vertex_program) = &vertex_stages[vertex_stage];
+        let (fragment_name, fragment_program) = &fragment_stages[fragment_stage];
+        if changed {
+            // Swapping a stage is all it takes: nothing gets linked
+            let swapped = pipeline.use_program(vertex_program)
+                .and_then(|_| pipeline.use_program(fragment_program))
+                .and_then(|_| pipeline.validate());
+            if let Err(e) = swapped {
+                println!("Failed to put the pipeline together: {}", e);
+                return;
+            }
+            window.set_title(&format!(
+                "{} vertex stage, {} fragment stage ({} programs for {} combinations)",
+                vertex_name,
+                fragment_name,
+                vertex_stages.len() + fragment_stages.len(),
+                vertex_stages.len() * fragment_stages.len(),
+            )).unwrap();
+            changed = false;
+        }
+
+        // Each stage's uniforms belong to its own program, so they're set through that, before binding the pipeline
+        let model = Mat4::from_trs(Vec3::ZERO, Quat::from_axis_angle(Vec3::new(0.3, 1.0, 0.2).normalize(), time * 0.4),
+                                   Vec3::ONE);
+        vertex_program.set_used();
+        vertex_program.set_uniform_mat4("Model", &model);
+        vertex_program.set_uniform_mat4("View", &camera.view_matrix());
+        vertex_program.set_uniform_mat4("Projection", &camera.projection_matrix());
+        vertex_program.set_uniform_f32("Time", time);  // only the rippling one has it, and the other ignores it
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        render_state_cache.apply(&RenderState::opaque());
+        pipeline.bind();
+        mesh.draw();
+        pipeline.unbind();
+
+        window.gl_swap_window();
+    }
+}
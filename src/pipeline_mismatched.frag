@@ -0,0 +1,13 @@
+#version 410 core
+
+// Doesn't fit the vertex stages on purpose, to show what `ProgramPipeline::validate()` reports: it reads the texture
+// coordinates as the wrong type, and reads a `Fade` that no vertex stage writes
+layout (location = 1) in vec3 SurfaceTexCoord;
+layout (location = 2) in float Fade;
+
+out vec4 Color;
+
+void main()
+{
+    Color = vec4(SurfaceTexCoord * Fade, 1.0);
+}
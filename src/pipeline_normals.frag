@@ -0,0 +1,11 @@
+#version 410 core
+
+// Shows the normals as colors, mapped from -1..1 into 0..1
+layout (location = 0) in vec3 WorldNormal;
+
+out vec4 Color;
+
+void main()
+{
+    Color = vec4(normalize(WorldNormal) * 0.5 + 0.5, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 410 core
+
+// mesh.frag, as a separable fragment stage
+layout (location = 0) in vec3 WorldNormal;
+
+uniform vec3 DiffuseColor;
+
+out vec4 Color;
+
+void main()
+{
+    vec3 light_direction = normalize(vec3(0.4, 1.0, 0.7));
+    float shade = 0.3 + 0.7 * max(dot(normalize(WorldNormal), light_direction), 0.0);
+    Color = vec4(DiffuseColor * shade, 1.0);
+}
@@ -0,0 +1,28 @@
+#version 410 core
+
+// A different vertex stage with the same outputs, so it works with every fragment stage pipeline.vert does: pushes the
+// surface in and out along its normals in ripples that travel around the texture
+layout (location = 0) in vec3 Position;
+layout (location = 2) in vec3 Normal;
+layout (location = 3) in vec2 TexCoord;
+
+uniform mat4 Model;
+uniform mat4 View;
+uniform mat4 Projection;
+uniform float Time;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+
+layout (location = 0) out vec3 WorldNormal;
+layout (location = 1) out vec2 SurfaceTexCoord;
+
+void main()
+{
+    float wave = sin(TexCoord.x * 25.0 + Time * 3.0) * sin(TexCoord.y * 12.0 + Time * 2.0);
+    vec3 position = Position + Normal * wave * 0.12;
+    gl_Position = Projection * View * Model * vec4(position, 1.0);
+    WorldNormal = mat3(Model) * Normal;
+    SurfaceTexCoord = TexCoord;
+}
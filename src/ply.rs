@@ -0,0 +1,548 @@
+// Loader for PLY ("Polygon File Format", also known as the Stanford format) files, which is what 3D scanners and
+// point cloud tools tend to spit out.  A PLY file starts with a text header describing its elements:
+//
+//     ply
+//     format binary_little_endian 1.0    <- or ascii, or binary_big_endian
+//     element vertex 1000                <- 1000 vertices, each made of the properties below
+//     property float x
+//     property float y
+//     property float z
+//     property uchar red
+//     property float intensity           <- files can have any properties they like
+//     element face 1800
+//     property list uchar int vertex_indices
+//     end_header
+//
+// followed by the data for every element, in the order the header listed them.  Since the properties are whatever
+// the file says they are, we keep every vertex property as its own column of numbers, and leave it to
+// `PlyModel::standard_attributes()` (or the caller) to decide which columns end up in which vertex attributes.
+
+use crate::math::{Vec2, Vec3};
+use crate::mesh::{Mesh, MeshData};
+use crate::resources::Resources;
+use crate::vertex::{attrib, Vertex, VertexLayout};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlyType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl PlyType {
+    fn from_name(name: &str) -> Option<PlyType> {
+        // Both the old names (`uchar`) and the newer ones (`uint8`) turn up in the wild
+        Some(match name {
+            "char" | "int8" => PlyType::Int8,
+            "uchar" | "uint8" => PlyType::UInt8,
+            "short" | "int16" => PlyType::Int16,
+            "ushort" | "uint16" => PlyType::UInt16,
+            "int" | "int32" => PlyType::Int32,
+            "uint" | "uint32" => PlyType::UInt32,
+            "float" | "float32" => PlyType::Float32,
+            "double" | "float64" => PlyType::Float64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            PlyType::Int8 | PlyType::UInt8 => 1,
+            PlyType::Int16 | PlyType::UInt16 => 2,
+            PlyType::Int32 | PlyType::UInt32 | PlyType::Float32 => 4,
+            PlyType::Float64 => 8,
+        }
+    }
+
+    /// Biggest value of an unsigned integer type, which is what colors stored as integers get divided by to map them
+    /// onto 0..1.  `None` for types that are already in 0..1 (or can't be sensibly normalized)
+    fn normalize_scale(self) -> Option<f32> {
+        match self {
+            PlyType::UInt8 => Some(255.0),
+            PlyType::UInt16 => Some(65535.0),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone)]
+struct PlyProperty {
+    name: String,
+    data_type: PlyType,
+    list_count_type: Option<PlyType>,  // `Some` for `property list <count type> <item type> <name>`
+}
+
+#[derive(Debug, Clone)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+/// Every vertex's value for one property, as floats
+#[derive(Debug, Clone)]
+pub struct PlyColumn {
+    pub name: String,
+    pub data_type: PlyType,  // what the file stored it as
+    pub values: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PlyModel {
+    pub vertex_count: usize,
+    pub columns: Vec<PlyColumn>,  // one per scalar vertex property, in the order the file listed them
+    pub faces: Vec<Vec<u32>>,     // empty for point clouds
+    pub comments: Vec<String>,
+}
+
+/// Maps one or more PLY vertex properties onto a vertex attribute, e.g. `["red", "green", "blue"]` onto
+/// `attrib::COLOR`.  With `normalize`, integer properties get mapped onto 0..1 (the usual way colors are stored)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyAttribute {
+    pub location: gl::types::GLuint,
+    pub properties: Vec<String>,
+    pub normalize: bool,
+}
+
+#[allow(dead_code)]
+impl PlyAttribute {
+    pub fn new(location: gl::types::GLuint, properties: &[&str], normalize: bool) -> PlyAttribute {
+        PlyAttribute { location, properties: properties.iter().map(|p| p.to_string()).collect(), normalize }
+    }
+}
+
+#[allow(dead_code)]
+impl PlyModel {
+    pub fn column(&self, name: &str) -> Option<&PlyColumn> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    pub fn is_point_cloud(&self) -> bool {
+        self.faces.is_empty()
+    }
+
+    /// The standard attributes, for whichever of them this file has: position (`x y z`), normal (`nx ny nz`), color
+    /// (`red green blue [alpha]`, or the `r g b` and `diffuse_*` spellings) and texture coordinates (`u v`, `s t` or
+    /// `texture_u texture_v`).  Add your own `PlyAttribute`s on to the end for any custom properties
+    pub fn standard_attributes(&self) -> Vec<PlyAttribute> {
+        let candidates: &[(gl::types::GLuint, bool, &[&[&str]])] = &[
+            (attrib::POSITION, false, &[&["x", "y", "z"]]),
+            (attrib::NORMAL, false, &[&["nx", "ny", "nz"]]),
+            (attrib::COLOR, true, &[
+                &["red", "green", "blue", "alpha"],
+                &["red", "green", "blue"],
+                &["r", "g", "b", "a"],
+                &["r", "g", "b"],
+                &["diffuse_red", "diffuse_green", "diffuse_blue"],
+            ]),
+            (attrib::TEX_COORD, false, &[&["u", "v"], &["s", "t"], &["texture_u", "texture_v"]]),
+        ];
+
+        candidates.iter()
+            .filter_map(|&(location, normalize, spellings)| {
+                spellings.iter()
+                    .find(|names| names.iter().all(|name| self.column(name).is_some()))
+                    .map(|names| PlyAttribute::new(location, names, normalize))
+            })
+            .collect()
+    }
+
+    /// Interleaves the properties that `attributes` asks for into one float per property per vertex, and describes
+    /// the result.  Fails if any of the properties don't exist
+    pub fn interleave(&self, attributes: &[PlyAttribute]) -> Result<(Vec<f32>, VertexLayout), String> {
+        let f = std::mem::size_of::<f32>();
+        let mut columns = Vec::new();
+        let mut layout = VertexLayout::new(0);
+        for attribute in attributes {
+            let components = attribute.properties.len() as gl::types::GLint;
+            layout = layout.float(attribute.location, components, columns.len() * f);
+            for name in &attribute.properties {
+                let column = self.column(name).ok_or_else(|| format!("no vertex property called '{}'", name))?;
+                let scale = if attribute.normalize { column.data_type.normalize_scale().unwrap_or(1.0) } else { 1.0 };
+                columns.push((column, scale));
+            }
+        }
+        layout.stride = columns.len() * f;
+
+        let mut vertices = Vec::with_capacity(columns.iter().map(|(column, _)| column.values.len()).sum());
+        for i in 0..self.vertex_count {
+            vertices.extend(columns.iter().map(|(column, scale)| column.values[i] / scale));
+        }
+        Ok((vertices, layout))
+    }
+
+    /// Uploads the vertices with the given attributes.  Files with faces become triangles, and point clouds become
+    /// `gl::POINTS`
+    pub fn upload_with(&self, attributes: &[PlyAttribute]) -> Result<Mesh, String> {
+        let (vertices, layout) = self.interleave(attributes)?;
+        if self.is_point_cloud() {
+            Ok(Mesh::new(&vertices, &layout, None, gl::POINTS))
+        } else {
+            Ok(Mesh::new(&vertices, &layout, Some(&self.triangle_indices()), gl::TRIANGLES))
+        }
+    }
+
+    pub fn upload(&self) -> Result<Mesh, String> {
+        self.upload_with(&self.standard_attributes())
+    }
+
+    /// Faces split up into triangles (as fans, so this assumes they're convex)
+    pub fn triangle_indices(&self) -> Vec<u32> {
+        let mut indices = Vec::new();
+        for face in &self.faces {
+            for i in 1..face.len().saturating_sub(1) {
+                indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+            }
+        }
+        indices
+    }
+
+    /// Converts to our usual vertex format, so the rest of the crate (normals, tangents, ...) can work with it.  Only
+    /// position, normal and texture coordinates make it across; normals get computed if the file didn't have any
+    pub fn to_mesh_data(&self) -> Result<MeshData, String> {
+        let get = |names: &[&str], i: usize| -> Option<Vec<f32>> {
+            names.iter().map(|name| self.column(name).map(|column| column.values[i])).collect()
+        };
+        let uv_names = self.standard_attributes().into_iter()
+            .find(|attribute| attribute.location == attrib::TEX_COORD)
+            .map(|attribute| attribute.properties);
+
+        let mut data = MeshData::new();
+        for i in 0..self.vertex_count {
+            let p = get(&["x", "y", "z"], i).ok_or("vertices have no x, y and z properties")?;
+            let n = get(&["nx", "ny", "nz"], i).unwrap_or_else(|| vec![0.0; 3]);
+            let uv = uv_names.as_ref()
+                .map(|names| names.iter().map(|name| self.column(name).unwrap().values[i]).collect())
+                .unwrap_or_else(|| vec![0.0; 2]);
+            let (position, normal) = (Vec3::new(p[0], p[1], p[2]), Vec3::new(n[0], n[1], n[2]));
+            data.vertices.push(Vertex::new(position, normal, Vec2::new(uv[0], uv[1])));
+        }
+        data.indices = self.triangle_indices();
+        if self.column("nx").is_none() && !data.indices.is_empty() {
+            data.compute_normals();
+        }
+        Ok(data)
+    }
+}
+
+#[allow(dead_code)]
+pub fn load(res: &Resources, resource_name: &str) -> Result<PlyModel, String> {
+    let bytes = res.load_buffer(resource_name).map_err(|e| format!("{}: {}", resource_name, e))?;
+    parse_ply(&bytes, resource_name)
+}
+
+/// Parses a whole PLY file.  `resource_name` is only used for error messages
+pub fn parse_ply(bytes: &[u8], resource_name: &str) -> Result<PlyModel, String> {
+    let error = |message: String| format!("{}: {}", resource_name, message);
+
+    // The header is always text, and ends at the first line that says `end_header`
+    let header_end = find_subslice(bytes, b"end_header").ok_or_else(|| error("no end_header found".to_string()))?;
+    let body_start = match bytes[header_end..].iter().position(|&b| b == b'\n') {
+        Some(newline) => header_end + newline + 1,
+        None => bytes.len(),
+    };
+    let header = std::str::from_utf8(&bytes[..header_end]).map_err(|_| error("header is not valid text".to_string()))?;
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    let mut model = PlyModel::default();
+    for (line_index, line) in header.lines().enumerate() {
+        let line_number = line_index + 1;
+        let error = |message: &str| format!("{}:{}: {}", resource_name, line_number, message);
+        let parse_type = |name: &str| {
+            PlyType::from_name(name).ok_or_else(|| error(&format!("unknown type '{}'", name)))
+        };
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts.as_slice() {
+            ["ply"] if line_number == 1 => {},
+            _ if line_number == 1 => return Err(error("not a PLY file")),
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(error(&format!("unknown format '{}'", name))),
+                });
+            },
+            ["comment", ..] | ["obj_info", ..] => {
+                model.comments.push(line.trim().split_once(' ').map_or("", |(_, rest)| rest).trim().to_string());
+            },
+            ["element", name, count] => {
+                let count = count.parse().map_err(|_| error(&format!("invalid element count '{}'", count)))?;
+                elements.push(PlyElement { name: name.to_string(), count, properties: Vec::new() });
+            },
+            ["property", "list", count_type, item_type, name] => {
+                let element = elements.last_mut().ok_or_else(|| error("property before any element"))?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    data_type: parse_type(item_type)?,
+                    list_count_type: Some(parse_type(count_type)?),
+                });
+            },
+            ["property", data_type, name] => {
+                let element = elements.last_mut().ok_or_else(|| error("property before any element"))?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    data_type: parse_type(data_type)?,
+                    list_count_type: None,
+                });
+            },
+            [] => {},
+            _ => return Err(error(&format!("can't make sense of '{}'", line.trim()))),
+        }
+    }
+    let format = format.ok_or_else(|| error("header has no format line".to_string()))?;
+
+    let mut reader = match format {
+        PlyFormat::Ascii => {
+            let body = std::str::from_utf8(&bytes[body_start..])
+                .map_err(|_| error("body is not valid text".to_string()))?;
+            BodyReader::Ascii(body)
+        },
+        _ => {
+            let big_endian = format == PlyFormat::BinaryBigEndian;
+            BodyReader::Binary { bytes: &bytes[body_start..], pos: 0, big_endian }
+        },
+    };
+
+    for element in &elements {
+        let is_vertex = element.name == "vertex";
+        let is_face = element.name == "face";
+        if is_vertex {
+            model.vertex_count = element.count;
+            model.columns = element.properties.iter()
+                .filter(|property| property.list_count_type.is_none())
+                .map(|property| PlyColumn {
+                    name: property.name.clone(),
+                    data_type: property.data_type,
+                    values: Vec::with_capacity(element.count.min(reader.remaining())),
+                })
+                .collect();
+        }
+
+        for index in 0..element.count {
+            let mut column = 0;
+            for property in &element.properties {
+                let error = |message: String| {
+                    error(format!("{} {}, property '{}': {}", element.name, index, property.name, message))
+                };
+                match property.list_count_type {
+                    None => {
+                        let value = reader.read(property.data_type).map_err(error)?;
+                        if is_vertex {
+                            model.columns[column].values.push(value as f32);
+                            column += 1;
+                        }
+                    },
+                    Some(count_type) => {
+                        let count = reader.read(count_type).map_err(error)? as usize;
+                        let mut items = Vec::with_capacity(count.min(reader.remaining()));
+                        for _ in 0..count {
+                            items.push(reader.read(property.data_type).map_err(error)?);
+                        }
+                        if is_face && (property.name == "vertex_indices" || property.name == "vertex_index") {
+                            // Checked before the cast, which would quietly turn -1 into 0 and 2.5 into 2
+                            let in_range = |&i: &f64| i >= 0.0 && i.fract() == 0.0 && i < model.vertex_count as f64;
+                            if let Some(&bad) = items.iter().find(|&i| !in_range(i)) {
+                                return Err(error(format!("vertex index {} is out of range", bad)));
+                            }
+                            let face: Vec<u32> = items.into_iter().map(|i| i as u32).collect();
+                            model.faces.push(face);
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    if model.columns.is_empty() {
+        return Err(error("file has no vertices".to_string()));
+    }
+    Ok(model)
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Reads values one at a time out of the body of the file, whichever format it's in
+enum BodyReader<'a> {
+    Ascii(&'a str),
+    Binary { bytes: &'a [u8], pos: usize, big_endian: bool },
+}
+
+impl<'a> BodyReader<'a> {
+    fn read(&mut self, data_type: PlyType) -> Result<f64, String> {
+        match self {
+            BodyReader::Ascii(text) => {
+                let rest = text.trim_start();
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                if end == 0 {
+                    return Err("file ends early".to_string());
+                }
+                let token = &rest[..end];
+                *text = &rest[end..];
+                token.parse::<f64>().map_err(|_| format!("invalid number '{}'", token))
+            },
+            BodyReader::Binary { bytes, pos, big_endian } => {
+                let size = data_type.size();
+                let raw = bytes.get(*pos..*pos + size).ok_or("file ends early")?;
+                *pos += size;
+
+                let mut buf = [0u8; 8];
+                buf[..size].copy_from_slice(raw);
+                if *big_endian {
+                    buf[..size].reverse();
+                }
+                Ok(match data_type {
+                    PlyType::Int8 => buf[0] as i8 as f64,
+                    PlyType::UInt8 => buf[0] as f64,
+                    PlyType::Int16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    PlyType::UInt16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    PlyType::Int32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    PlyType::UInt32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    PlyType::Float32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    PlyType::Float64 => f64::from_le_bytes(buf),
+                })
+            },
+        }
+    }
+
+    /// How many bytes of the body are left.  Every value takes at least one, so this caps how much a count from the
+    /// file is allowed to reserve
+    fn remaining(&self) -> usize {
+        match self {
+            BodyReader::Ascii(text) => text.len(),
+            BodyReader::Binary { bytes, pos, .. } => bytes.len().saturating_sub(*pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "\
+ply
+format {} 1.0
+comment made by hand
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property ushort intensity
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    /// A square with a different color at each corner, and a custom `intensity` property
+    const VERTICES: [([f32; 3], [u8; 3], u16); 4] = [
+        ([0.0, 0.0, 0.0], [255, 0, 0], 100),
+        ([1.0, 0.0, 0.0], [0, 255, 0], 200),
+        ([1.0, 1.0, 0.0], [0, 0, 255], 300),
+        ([0.0, 1.0, 0.0], [255, 255, 255], 400),
+    ];
+
+    /// Writes the square out in the given format
+    fn square(format: PlyFormat) -> Vec<u8> {
+        let name = match format {
+            PlyFormat::Ascii => "ascii",
+            PlyFormat::BinaryLittleEndian => "binary_little_endian",
+            PlyFormat::BinaryBigEndian => "binary_big_endian",
+        };
+        let mut bytes = HEADER.replace("{}", name).into_bytes();
+        if format == PlyFormat::Ascii {
+            for (p, c, intensity) in &VERTICES {
+                let line = format!("{} {} {} {} {} {} {}\n", p[0], p[1], p[2], c[0], c[1], c[2], intensity);
+                bytes.extend_from_slice(line.as_bytes());
+            }
+            bytes.extend_from_slice(b"4 0 1 2 3\n");
+            return bytes;
+        }
+
+        let big_endian = format == PlyFormat::BinaryBigEndian;
+        let mut put = |mut raw: Vec<u8>| {
+            if big_endian {
+                raw.reverse();
+            }
+            bytes.extend_from_slice(&raw);
+        };
+        for (p, c, intensity) in &VERTICES {
+            for &coordinate in p {
+                put(coordinate.to_le_bytes().to_vec());
+            }
+            for &channel in c {
+                put(vec![channel]);
+            }
+            put(intensity.to_le_bytes().to_vec());
+        }
+        put(vec![4]);
+        for index in 0..4i32 {
+            put(index.to_le_bytes().to_vec());
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_every_format() {
+        for &format in &[PlyFormat::Ascii, PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian] {
+            let model = parse_ply(&square(format), "square.ply").unwrap();
+            assert_eq!(model.vertex_count, 4);
+            assert_eq!(model.comments, vec!["made by hand"]);
+            assert_eq!(model.column("x").unwrap().values, vec![0.0, 1.0, 1.0, 0.0], "{:?}", format);
+            assert_eq!(model.column("green").unwrap().values, vec![0.0, 255.0, 0.0, 255.0], "{:?}", format);
+            assert_eq!(model.column("intensity").unwrap().values, vec![100.0, 200.0, 300.0, 400.0], "{:?}", format);
+            assert_eq!(model.faces, vec![vec![0, 1, 2, 3]], "{:?}", format);
+            assert_eq!(model.triangle_indices(), vec![0, 1, 2, 0, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn interleaves_attributes() {
+        let model = parse_ply(&square(PlyFormat::BinaryLittleEndian), "square.ply").unwrap();
+        let mut attributes = model.standard_attributes();
+        attributes.push(PlyAttribute::new(attrib::INTENSITY, &["intensity"], false));
+        let (vertices, layout) = model.interleave(&attributes).unwrap();
+        assert_eq!(layout.stride, 7 * 4);  // position, color (normalized to 0..1) and intensity
+        assert_eq!(&vertices[7..14], &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 200.0]);
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        let bytes = square(PlyFormat::BinaryLittleEndian);
+        let error = parse_ply(&bytes[..bytes.len() - 40], "square.ply").unwrap_err();  // 17 bytes per vertex
+        assert_eq!(error, "square.ply: vertex 2, property 'z': file ends early");
+
+        // A header claiming far more than the file holds has to fail, not try to reserve room for all of it
+        let huge = String::from_utf8(square(PlyFormat::Ascii)).unwrap().replace("vertex 4", "vertex 4000000000000");
+        assert!(parse_ply(huge.as_bytes(), "square.ply").is_err());
+
+        let bad_index = String::from_utf8(square(PlyFormat::Ascii)).unwrap().replace("4 0 1 2 3", "3 0 1 9");
+        let error = parse_ply(bad_index.as_bytes(), "square.ply").unwrap_err();
+        assert_eq!(error, "square.ply: face 0, property 'vertex_indices': vertex index 9 is out of range");
+
+        for bad in &["-1", "1.5"] {
+            let text = String::from_utf8(square(PlyFormat::Ascii)).unwrap();
+            let bad_index = text.replace("4 0 1 2 3", &format!("3 0 1 {}", bad));
+            let error = parse_ply(bad_index.as_bytes(), "square.ply").unwrap_err();
+            let expected = "square.ply: face 0, property 'vertex_indices': vertex index {} is out of range";
+            assert_eq!(error, expected.replace("{}", bad));
+        }
+    }
+}
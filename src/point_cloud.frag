@@ -0,0 +1,19 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec3 Normal;
+    vec4 Color;
+} IN;
+
+out vec4 Color;
+
+void main()
+{
+    // Plenty of scans don't come with normals, and those points just get their color as-is
+    float shade = 1.0;
+    if (length(IN.Normal) > 0.0) {
+        vec3 light_direction = normalize(vec3(0.4, 1.0, 0.7));
+        shade = 0.3 + 0.7 * max(dot(normalize(IN.Normal), light_direction), 0.0);
+    }
+    Color = vec4(IN.Color.rgb * shade, IN.Color.a);
+}
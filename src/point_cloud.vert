@@ -0,0 +1,32 @@
+#version 330 core
+
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec4 Color;
+layout (location = 2) in vec3 Normal;
+layout (location = 12) in float Intensity;  // a custom property from the scan, `attrib::INTENSITY` on the Rust side
+
+uniform mat4 Model;
+uniform mat4 View;
+uniform mat4 Projection;
+
+// 0 shows the scanned colors, 1 shows the intensity as a heat map
+uniform int ColorMode;
+
+out VS_OUTPUT {
+    vec3 Normal;
+    vec4 Color;
+} OUT;
+
+void main()
+{
+    gl_Position = Projection * View * Model * vec4(Position, 1.0);
+    OUT.Normal = mat3(Model) * Normal;
+
+    if (ColorMode == 1) {
+        // Blue for low intensity, through green, to red for high
+        float t = clamp(Intensity, 0.0, 1.0);
+        OUT.Color = vec4(clamp(vec3(2.0 * t - 1.0, 1.0 - abs(2.0 * t - 1.0), 1.0 - 2.0 * t), 0.0, 1.0), 1.0);
+    } else {
+        OUT.Color = Color;
+    }
+}
@@ -0,0 +1,147 @@
+use std::ffi::CString;
+
+use crate::render_gl;
+use crate::program;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::debug_view::DebugViews;
+use crate::math::{Mat4, Vec3};
+use crate::mesh::{Mesh, MeshData};
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::shapes;
+
+/// Each scene shows one shape.  `detail` goes from 1 up, and each shape turns it into sensible subdivision counts
+struct ShapeScene {
+    name: &'static str,
+    build: fn(detail: u32) -> MeshData,
+}
+
+const SCENES: [ShapeScene; 9] = [
+    ShapeScene { name: "quad", build: |_| shapes::quad(2.0, 1.5) },
+    ShapeScene { name: "cube", build: |detail| shapes::cube(1.5, detail) },
+    ShapeScene { name: "UV sphere", build: |detail| shapes::uv_sphere(1.0, 8 * detail, 4 * detail) },
+    ShapeScene { name: "icosphere", build: |detail| shapes::icosphere(1.0, detail - 1) },
+    ShapeScene { name: "cylinder", build: |detail| shapes::cylinder(0.8, 1.8, 8 * detail, detail) },
+    ShapeScene { name: "cone", build: |detail| shapes::cone(0.8, 1.8, 8 * detail, detail) },
+    ShapeScene { name: "torus", build: |detail| shapes::torus(1.0, 0.35, 12 * detail, 6 * detail) },
+    ShapeScene { name: "plane grid", build: |detail| shapes::plane_grid(3.0, 3.0, 2 * detail, 2 * detail) },
+    ShapeScene { name: "capsule", build: |detail| shapes::capsule(0.6, 1.2, 8 * detail, 2 * detail) },
+];
+
+const MAX_DETAIL: u32 = 6;
+
+fn build_scene(scene: &ShapeScene, detail: u32) -> Mesh {
+    let data = (scene.build)(detail);
+    println!(
+        "{} (detail {}): {} vertices, {} triangles",
+        scene.name,
+        detail,
+        data.vertices.len(),
+        data.triangle_count(),
+    );
+    data.upload()
+}
+
+/// Shows off the generated shapes.  Press 1-9 to pick a shape, +/- to change how finely it's subdivided, drag to orbit
+/// around it, and use F1-F7 for the debug views (F4 and F5 are good for checking the normals, tangents and UVs)
+pub fn procedural_shapes() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.5, 0.3, 0.3, 1.0);  // Color that window will default to when everything is cleared
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+
+    let vert_shader = render_gl::Shader::from_vert_source(
+        &CString::new(
+            include_str!("mesh.vert")
+        ).unwrap()
+    ).unwrap();
+
+    let frag_shader = render_gl::Shader::from_frag_source(
+        &CString::new(
+            include_str!("mesh.frag")
+        ).unwrap()
+    ).unwrap();
+
+    let shader_program = program::Program::from_shaders(
+        &[vert_shader, frag_shader]
+    ).unwrap();
+
+    let mut scene = 0;
+    let mut detail = 2;
+    let mut mesh = build_scene(&SCENES[scene], detail);
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(2.0, 1.5, 3.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 4.0);
+    controller.attach(&mut camera);
+
+    // Leave back face culling on: a shape wound the wrong way round will disappear, which makes it easy to spot
+    let render_state = RenderState::opaque();
+    let mut render_state_cache = RenderStateCache::new();
+    let mut debug_views = DebugViews::new().unwrap();
+
+    'main: loop {
+        let (mut next_scene, mut next_detail) = (scene, detail);
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(key), .. }
+                    if (Keycode::Num1 as i32..=Keycode::Num9 as i32).contains(&(key as i32)) => {
+                    next_scene = (key as i32 - Keycode::Num1 as i32) as usize;
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Equals), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(Keycode::KpPlus), .. } => {
+                    next_detail = (detail + 1).min(MAX_DETAIL);
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Minus), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(Keycode::KpMinus), .. } => {
+                    next_detail = (detail - 1).max(1);
+                },
+                _ if debug_views.handle_event(&event) => {},
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+        if (next_scene, next_detail) != (scene, detail) {
+            scene = next_scene;
+            detail = next_detail;
+            mesh = build_scene(&SCENES[scene], detail);
+        }
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        debug_views.near = camera.near;
+        debug_views.far = camera.far;
+        debug_views.draw(&mut render_state_cache, &render_state, &shader_program, |program| {
+            program.set_uniform_mat4("Model", &Mat4::identity());
+            program.set_uniform_mat4("View", &camera.view_matrix());
+            program.set_uniform_mat4("Projection", &camera.projection_matrix());
+            program.set_uniform_vec3("DiffuseColor", Vec3::new(0.7, 0.7, 0.75));
+            mesh.draw();
+        });
+
+        window.gl_swap_window();
+    }
+}
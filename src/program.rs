@@ -1,7 +1,52 @@
 use crate::render_gl;  // This is how we get access to the stuff from `render_gl.rs`
+use crate::math::{Mat4, Vec2, Vec3, Vec4};
+use std::ffi::CString;
 
 pub struct Program {
     id: gl::types::GLuint,
+    tessellated: bool,  // has a tessellation evaluation stage, so it can only draw `gl::PATCHES`
+    geometry: Option<GeometryInfo>,
+    stages: gl::types::GLbitfield,  // `gl::VERTEX_SHADER_BIT | gl::FRAGMENT_SHADER_BIT`, ...
+    separable: bool,
+}
+
+/// What a geometry shader's `layout` lines say it takes in and puts out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeometryInfo {
+    pub input: gl::types::GLenum,   // `gl::POINTS`, `gl::LINES`, `gl::LINES_ADJACENCY`, `gl::TRIANGLES` or ...
+    pub output: gl::types::GLenum,  // `gl::POINTS`, `gl::LINE_STRIP` or `gl::TRIANGLE_STRIP`
+    pub max_vertices: u32,          // the most it can emit per input primitive
+}
+
+/// An active uniform, as GL describes it.  Uniforms inside uniform blocks aren't included, since they don't have a
+/// location of their own
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformInfo {
+    pub name: String,  // arrays are reported by their name alone, without the `[0]` GL adds
+    pub location: gl::types::GLint,
+    pub gl_type: gl::types::GLenum,  // `gl::FLOAT_VEC3`, `gl::SAMPLER_2D`, ...
+    pub size: gl::types::GLint,  // number of elements for arrays, otherwise 1
+}
+
+/// A member of a uniform block, as GL laid it out.  Offsets and strides are in bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockMemberInfo {
+    pub name: String,  // arrays of scalars, vectors and matrices keep the `[0]`, the same as GL reports them
+    pub offset: usize,
+    pub gl_type: gl::types::GLenum,
+    pub array_size: usize,    // 1 if it's not an array
+    pub array_stride: usize,  // 0 if it's not an array
+    pub matrix_stride: usize,  // distance between columns, 0 if it's not a matrix
+}
+
+/// An active uniform block, as GL describes it
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformBlockInfo {
+    pub name: String,
+    pub index: gl::types::GLuint,
+    pub binding: gl::types::GLuint,  // the uniform buffer binding point it reads from
+    pub data_size: usize,            // how big a buffer it needs, in bytes
+    pub members: Vec<BlockMemberInfo>,  // in offset order
 }
 
 impl Program {
@@ -16,7 +61,413 @@ impl Program {
         }
     }
 
+    /// Looks up where a uniform lives in this program.  Returns `None` if there's no active uniform with that name,
+    /// which also happens if the GLSL compiler optimized it away because nothing used it
+    pub fn uniform_location(&self, name: &str) -> Option<gl::types::GLint> {
+        // Note that we have to keep the `CString` alive in a variable until after the call; doing
+        // `CString::new(..).unwrap().as_ptr()` in one go drops the string immediately and hands GL a dangling pointer
+        let c_name = CString::new(name).ok()?;
+        let location = unsafe { gl::GetUniformLocation(self.id, c_name.as_ptr()) };
+        if location < 0 { None } else { Some(location) }
+    }
+
+    // All of the `set_uniform_*()` methods act on whichever program is currently in use (that's how `glUniform*()`
+    // works), so call `set_used()` first.  Setting a uniform that doesn't exist is silently ignored, same as GL does
+
+    #[allow(dead_code)]
+    pub fn set_uniform_mat4(&self, name: &str, value: &Mat4) {
+        if let Some(location) = self.uniform_location(name) {
+            unsafe {
+                // Our matrices are already column-major, so no need to have GL transpose them
+                gl::UniformMatrix4fv(location, 1, gl::FALSE, value.as_ptr());
+            }
+        }
+    }
+
+    /// Sets a whole `uniform mat4 Name[N]` array at once, starting from the first element
+    #[allow(dead_code)]
+    pub fn set_uniform_mat4_array(&self, name: &str, values: &[Mat4]) {
+        if let Some(location) = self.uniform_location(name) {
+            unsafe {
+                // `Mat4` is `#[repr(C)]`, so a slice of them is just 16 floats after another 16 floats after...
+                gl::UniformMatrix4fv(
+                    location,
+                    values.len() as gl::types::GLsizei,
+                    gl::FALSE,
+                    values.as_ptr() as *const f32,
+                );
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_uniform_vec2(&self, name: &str, value: Vec2) {
+        if let Some(location) = self.uniform_location(name) {
+            unsafe { gl::Uniform2f(location, value.x, value.y); }
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_uniform_vec3(&self, name: &str, value: Vec3) {
+        if let Some(location) = self.uniform_location(name) {
+            unsafe { gl::Uniform3f(location, value.x, value.y, value.z); }
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_uniform_vec4(&self, name: &str, value: Vec4) {
+        if let Some(location) = self.uniform_location(name) {
+            unsafe { gl::Uniform4f(location, value.x, value.y, value.z, value.w); }
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_uniform_f32(&self, name: &str, value: f32) {
+        if let Some(location) = self.uniform_location(name) {
+            unsafe { gl::Uniform1f(location, value); }
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_uniform_i32(&self, name: &str, value: i32) {
+        if let Some(location) = self.uniform_location(name) {
+            unsafe { gl::Uniform1i(location, value); }
+        }
+    }
+
+    /// Finds the uniform block called `name`.  Returns `None` if there isn't one, which also happens if nothing in the
+    /// program uses it
+    #[allow(dead_code)]
+    pub fn uniform_block_index(&self, name: &str) -> Option<gl::types::GLuint> {
+        let c_name = CString::new(name).ok()?;
+        let index = unsafe { gl::GetUniformBlockIndex(self.id, c_name.as_ptr()) };
+        if index == gl::INVALID_INDEX { None } else { Some(index) }
+    }
+
+    /// Tells the program to read the uniform block called `name` from whichever buffer is attached to uniform buffer
+    /// binding point `binding`.  Returns false if the program has no such block
+    #[allow(dead_code)]
+    pub fn bind_uniform_block(&self, name: &str, binding: gl::types::GLuint) -> bool {
+        let index = match self.uniform_block_index(name) {
+            Some(index) => index,
+            None => return false,
+        };
+        unsafe {
+            gl::UniformBlockBinding(self.id, index, binding);
+        }
+        true
+    }
+
+    /// The same as `bind_uniform_block()`, for a shader storage (`buffer`) block, which reads whichever buffer is
+    /// attached to shader storage binding point `binding`.  Needs GL 4.3, as do storage blocks themselves
+    #[allow(dead_code)]
+    pub fn bind_storage_block(&self, name: &str, binding: gl::types::GLuint) -> bool {
+        let c_name = match CString::new(name) {
+            Ok(c_name) => c_name,
+            Err(_) => return false,
+        };
+        let index = unsafe { gl::GetProgramResourceIndex(self.id, gl::SHADER_STORAGE_BLOCK, c_name.as_ptr()) };
+        if index == gl::INVALID_INDEX {
+            return false;
+        }
+        unsafe {
+            gl::ShaderStorageBlockBinding(self.id, index, binding);
+        }
+        true
+    }
+
+    /// Asks GL how it laid out the uniform block called `name`: how big it is, and where each of its members went.
+    /// Members of a block with an instance name (`uniform Camera { ... } camera;`) are reported as `Camera.Member`
+    /// by GL, but as just `Member` here, so either way of declaring a block looks the same
+    #[allow(dead_code)]
+    pub fn uniform_block(&self, name: &str) -> Option<UniformBlockInfo> {
+        let index = self.uniform_block_index(name)?;
+        let block_value = |parameter: gl::types::GLenum| {
+            let mut value: gl::types::GLint = 0;
+            unsafe {
+                gl::GetActiveUniformBlockiv(self.id, index, parameter, &mut value);
+            }
+            value
+        };
+        let data_size = block_value(gl::UNIFORM_BLOCK_DATA_SIZE) as usize;
+        let binding = block_value(gl::UNIFORM_BLOCK_BINDING) as gl::types::GLuint;
+        let count = block_value(gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS);
+
+        let mut indices = vec![0 as gl::types::GLint; count.max(0) as usize];
+        if count > 0 {
+            unsafe {
+                gl::GetActiveUniformBlockiv(
+                    self.id,
+                    index,
+                    gl::UNIFORM_BLOCK_ACTIVE_UNIFORM_INDICES,
+                    indices.as_mut_ptr(),
+                );
+            }
+        }
+        let indices: Vec<gl::types::GLuint> = indices.iter().map(|&i| i as gl::types::GLuint).collect();
+        let member_values = |parameter: gl::types::GLenum| {
+            let mut values = vec![0 as gl::types::GLint; indices.len()];
+            if !indices.is_empty() {
+                unsafe {
+                    gl::GetActiveUniformsiv(
+                        self.id,
+                        indices.len() as gl::types::GLsizei,
+                        indices.as_ptr(),
+                        parameter,
+                        values.as_mut_ptr(),
+                    );
+                }
+            }
+            values
+        };
+        let offsets = member_values(gl::UNIFORM_OFFSET);
+        let types = member_values(gl::UNIFORM_TYPE);
+        let sizes = member_values(gl::UNIFORM_SIZE);
+        let array_strides = member_values(gl::UNIFORM_ARRAY_STRIDE);
+        let matrix_strides = member_values(gl::UNIFORM_MATRIX_STRIDE);
+        let mut max_name_length: gl::types::GLint = 0;
+        unsafe {
+            gl::GetProgramiv(self.id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_name_length);
+        }
+
+        let mut members: Vec<BlockMemberInfo> = indices.iter()
+            .enumerate()
+            .map(|(i, &uniform)| {
+                let mut name_buffer = vec![0u8; max_name_length.max(1) as usize];
+                let mut name_length: gl::types::GLsizei = 0;
+                unsafe {
+                    gl::GetActiveUniformName(
+                        self.id,
+                        uniform,
+                        name_buffer.len() as gl::types::GLsizei,
+                        &mut name_length,
+                        name_buffer.as_mut_ptr() as *mut gl::types::GLchar,
+                    );
+                }
+                let full_name = String::from_utf8_lossy(&name_buffer[..name_length as usize]).into_owned();
+                let member_name = full_name.strip_prefix(name).and_then(|rest| rest.strip_prefix('.'));
+                BlockMemberInfo {
+                    name: member_name.unwrap_or(&full_name).to_string(),
+                    offset: offsets[i] as usize,
+                    gl_type: types[i] as gl::types::GLenum,
+                    array_size: sizes[i] as usize,
+                    array_stride: array_strides[i] as usize,
+                    matrix_stride: matrix_strides[i] as usize,
+                }
+            })
+            .collect();
+        members.sort_by_key(|member| member.offset);
+
+        Some(UniformBlockInfo { name: name.to_string(), index, binding, data_size, members })
+    }
+
+    /// Asks GL which uniforms the linked program actually uses.  This is how materials find out what parameters a
+    /// shader has without us listing them by hand
+    #[allow(dead_code)]
+    pub fn active_uniforms(&self) -> Vec<UniformInfo> {
+        let mut count: gl::types::GLint = 0;
+        let mut max_name_length: gl::types::GLint = 0;
+        unsafe {
+            gl::GetProgramiv(self.id, gl::ACTIVE_UNIFORMS, &mut count);
+            gl::GetProgramiv(self.id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_name_length);
+        }
+
+        let mut uniforms = Vec::new();
+        for index in 0..count as gl::types::GLuint {
+            let mut name_buffer = vec![0u8; max_name_length.max(1) as usize];
+            let mut name_length: gl::types::GLsizei = 0;
+            let mut size: gl::types::GLint = 0;
+            let mut gl_type: gl::types::GLenum = 0;
+            unsafe {
+                gl::GetActiveUniform(
+                    self.id,
+                    index,
+                    name_buffer.len() as gl::types::GLsizei,
+                    &mut name_length,
+                    &mut size,
+                    &mut gl_type,
+                    name_buffer.as_mut_ptr() as *mut gl::types::GLchar,
+                );
+            }
+            let full_name = String::from_utf8_lossy(&name_buffer[..name_length as usize]).into_owned();
+            let location = match self.uniform_location(&full_name) {
+                Some(location) => location,
+                None => continue,  // part of a uniform block
+            };
+            let name = full_name.strip_suffix("[0]").unwrap_or(&full_name).to_string();
+            uniforms.push(UniformInfo { name, location, gl_type, size });
+        }
+        uniforms
+    }
+
+    /// Reads back the current value of a float, vector or matrix uniform, which before anything's been set is
+    /// whatever its initializer in the GLSL said (or zeros).  Only as many of the 16 values as the uniform's type
+    /// has get filled in
+    #[allow(dead_code)]
+    pub fn uniform_f32_values(&self, location: gl::types::GLint) -> [f32; 16] {
+        let mut values = [0.0f32; 16];
+        unsafe {
+            gl::GetUniformfv(self.id, location, values.as_mut_ptr());
+        }
+        values
+    }
+
+    /// Same as `uniform_f32_values()`, for `int`, `bool` and sampler uniforms
+    #[allow(dead_code)]
+    pub fn uniform_i32_values(&self, location: gl::types::GLint) -> [i32; 16] {
+        let mut values = [0i32; 16];
+        unsafe {
+            gl::GetUniformiv(self.id, location, values.as_mut_ptr());
+        }
+        values
+    }
+
+    /// Whether the program tessellates, in which case meshes have to be drawn with `Mesh::draw_patches()`
+    #[allow(dead_code)]
+    pub fn is_tessellated(&self) -> bool {
+        self.tessellated
+    }
+
+    /// What the geometry shader expects, if there is one
+    #[allow(dead_code)]
+    pub fn geometry(&self) -> Option<GeometryInfo> {
+        self.geometry
+    }
+
+    /// Checks that a draw with `mode` (`gl::TRIANGLES`, `gl::LINES_ADJACENCY`, ...) gives the program the kind of
+    /// primitive its first stage after the vertex shader wants.  GL just draws nothing if they don't match, and sets
+    /// `gl::INVALID_OPERATION`, which is easy to miss
+    #[allow(dead_code)]
+    pub fn check_draw_mode(&self, mode: gl::types::GLenum) -> Result<(), String> {
+        if self.tessellated {
+            return if mode == gl::PATCHES {
+                Ok(())
+            } else {
+                Err(format!("this program tessellates, so it draws `gl::PATCHES`, not {}", primitive_name(mode)))
+            };
+        }
+        match self.geometry {
+            Some(geometry) if geometry_input(mode) != Some(geometry.input) => Err(format!(
+                "the geometry shader takes {}, which a draw of {} doesn't make",
+                primitive_name(geometry.input),
+                primitive_name(mode),
+            )),
+            _ if mode == gl::PATCHES => Err("this program doesn't tessellate, so it can't draw patches".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Which stages the program has, as the bits `gl::UseProgramStages()` takes (`gl::VERTEX_SHADER_BIT`, ...)
+    #[allow(dead_code)]
+    pub fn stages(&self) -> gl::types::GLbitfield {
+        self.stages
+    }
+
+    /// Whether the program was linked to be used in a `ProgramPipeline`
+    #[allow(dead_code)]
+    pub fn is_separable(&self) -> bool {
+        self.separable
+    }
+
     pub fn from_shaders(shaders: &[render_gl::Shader]) -> Result<Program, String> {
+        Program::link(shaders, &LinkOptions::default())
+    }
+
+    /// Like `from_shaders()`, but links the program so that its stages can be mixed with other programs' in a
+    /// `ProgramPipeline` (see `program_pipeline.rs`), so it doesn't need to have every stage a draw needs.  Usually
+    /// it's just the one.  Needs GL 4.1
+    #[allow(dead_code)]
+    pub fn from_shaders_separable(shaders: &[render_gl::Shader]) -> Result<Program, String> {
+        Program::link(shaders, &LinkOptions { separable: true, ..LinkOptions::default() })
+    }
+
+    /// Like `from_shaders()`, but records the outputs named in `varyings` of the last stage before the fragment shader
+    /// into buffers while drawing (see `transform_feedback.rs`).  This has to be set up before linking, since it
+    /// changes how GL lays the outputs out.  With `gl::INTERLEAVED_ATTRIBS` they all go into one buffer, one vertex
+    /// after another; with `gl::SEPARATE_ATTRIBS` each gets a buffer of its own, at binding points 0, 1, 2, ...
+    #[allow(dead_code)]
+    pub fn from_shaders_with_feedback(
+        shaders: &[render_gl::Shader],
+        varyings: &[&str],
+        buffer_mode: gl::types::GLenum,
+    ) -> Result<Program, String> {
+        if varyings.is_empty() {
+            return Err("transform feedback needs at least one output to record".to_string());
+        }
+        Program::link(shaders, &LinkOptions { varyings, buffer_mode, ..LinkOptions::default() })
+    }
+
+    /// Like `from_shaders()`, but tells the driver `binary()` is going to be asked for, so it should keep the compiled
+    /// program around in a form it can hand back
+    #[allow(dead_code)]
+    pub fn from_shaders_retrievable(shaders: &[render_gl::Shader]) -> Result<Program, String> {
+        Program::link(shaders, &LinkOptions { retrievable: true, ..LinkOptions::default() })
+    }
+
+    /// The linked program in the driver's own format, as (format, bytes), to hand back to `from_binary()` later instead
+    /// of compiling it all again.  `None` if the driver won't say, which it's allowed to for programs that didn't come
+    /// from `from_shaders_retrievable()`.  Needs GL 4.1, or the `ARB_get_program_binary` extension
+    #[allow(dead_code)]
+    pub fn binary(&self) -> Option<(gl::types::GLenum, Vec<u8>)> {
+        if !gl::GetProgramBinary::is_loaded() {
+            return None;
+        }
+        let mut length: gl::types::GLint = 0;
+        unsafe {
+            gl::GetProgramiv(self.id, gl::PROGRAM_BINARY_LENGTH, &mut length);
+        }
+        if length <= 0 {
+            return None;
+        }
+        let mut binary = vec![0u8; length as usize];
+        let mut written: gl::types::GLsizei = 0;
+        let mut format: gl::types::GLenum = 0;
+        unsafe {
+            gl::GetProgramBinary(self.id, length, &mut written, &mut format,
+                                 binary.as_mut_ptr() as *mut gl::types::GLvoid);
+        }
+        binary.truncate(written.max(0) as usize);
+        if binary.is_empty() { None } else { Some((format, binary)) }
+    }
+
+    /// Loads a program from what `binary()` gave back, skipping compiling and linking.  `stages` are the types of the
+    /// shaders it was made from (`gl::VERTEX_SHADER`, ...), which the binary doesn't say.  The driver can refuse a
+    /// binary for any reason (it usually does after being updated), so callers need to be ready to compile from source
+    /// instead
+    #[allow(dead_code)]
+    pub fn from_binary(
+        format: gl::types::GLenum,
+        binary: &[u8],
+        stages: &[gl::types::GLenum],
+    ) -> Result<Program, String> {
+        if !gl::ProgramBinary::is_loaded() {
+            return Err("this driver can't load program binaries".to_string());
+        }
+        let program_id = unsafe { gl::CreateProgram() };
+        unsafe {
+            gl::ProgramBinary(program_id, format, binary.as_ptr() as *const gl::types::GLvoid,
+                              binary.len() as gl::types::GLsizei);
+        }
+        let mut success: gl::types::GLint = 1;
+        unsafe {
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
+        }
+        if success == 0 {
+            let error = info_log(program_id);
+            unsafe { gl::DeleteProgram(program_id); }
+            return Err(if error.trim().is_empty() { "the driver refused the binary".to_string() } else { error });
+        }
+        Ok(Program::linked(program_id, stages, false))
+    }
+
+    fn link(shaders: &[render_gl::Shader], options: &LinkOptions) -> Result<Program, String> {
+        // GL will link some combinations of stages that can't ever draw anything (and the messages for the ones it
+        // won't link vary a lot between drivers), so it's worth checking them ourselves first
+        let stages: Vec<gl::types::GLenum> = shaders.iter().map(|shader| shader.shader_type()).collect();
+        check_stages(&stages, options.separable)?;
+        let varying_names = options.varyings.iter().map(|&name| CString::new(name)).collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
         // Creates an OpenGL program object.  A program object is an object to which shader objects can be attached.  We
         // need to do this in order to link the shader objects to create the actual program
         let program_id = unsafe { gl::CreateProgram() };
@@ -27,6 +478,27 @@ impl Program {
             unsafe { gl::AttachShader(program_id, shader.id()); }
         }
 
+        // Also has to be said before linking, since a separable program keeps outputs that nothing in it reads (the next
+        // stage is in some other program, so GL can't tell they're unused)
+        if options.separable {
+            unsafe { gl::ProgramParameteri(program_id, gl::PROGRAM_SEPARABLE, gl::TRUE as gl::types::GLint); }
+        }
+        if options.retrievable {
+            unsafe {
+                gl::ProgramParameteri(program_id, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as gl::types::GLint);
+            }
+        }
+
+        // Which outputs transform feedback records is part of linking too, so it has to be said now.  The `CString`s
+        // have to stay alive until after the call, like in `uniform_location()`
+        if !varying_names.is_empty() {
+            let pointers: Vec<*const gl::types::GLchar> = varying_names.iter().map(|name| name.as_ptr()).collect();
+            unsafe {
+                gl::TransformFeedbackVaryings(program_id, pointers.len() as gl::types::GLsizei, pointers.as_ptr(),
+                                              options.buffer_mode);
+            }
+        }
+
         // This linking creates the executable that will run on the appropriate bit of the GPU, depending on whether
         // we've linked vertex, fragment, and/or geometry shaders (apparently the other types of shaders don't need to
         // be linked or something, I don't really know)
@@ -40,23 +512,10 @@ impl Program {
         }
 
         if success == 0 {
-            let mut len: gl::types::GLint = 0;
-            unsafe {
-                gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, & mut len);
-            }
-
-            let error = render_gl::create_whitespace_cstring_with_len(len as usize);
-
-            unsafe {
-                gl::GetProgramInfoLog(
-                    program_id,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut gl::types::GLchar
-                );
-            }
-
-            return Err(error.to_string_lossy().into_owned())
+            // Deleting it detaches the shaders too, so they can still be deleted by whoever owns them
+            let error = info_log(program_id);
+            unsafe { gl::DeleteProgram(program_id); }
+            return Err(error);
         }
 
         // Now that we've attached the shaders and linked things, we can detach them so that they can be deleted (this
@@ -65,7 +524,187 @@ impl Program {
             unsafe { gl::DetachShader(program_id, shader.id()); }
         }
 
-        Ok(Program { id: program_id })
+        Ok(Program::linked(program_id, &stages, options.separable))
+    }
+
+    /// Wraps up a successfully linked program, made from shaders of the types in `stages`
+    fn linked(program_id: gl::types::GLuint, stages: &[gl::types::GLenum], separable: bool) -> Program {
+        let geometry = if stages.contains(&gl::GEOMETRY_SHADER) {
+            let query = |parameter| {
+                let mut value: gl::types::GLint = 0;
+                unsafe {
+                    gl::GetProgramiv(program_id, parameter, &mut value);
+                }
+                value
+            };
+            Some(GeometryInfo {
+                input: query(gl::GEOMETRY_INPUT_TYPE) as gl::types::GLenum,
+                output: query(gl::GEOMETRY_OUTPUT_TYPE) as gl::types::GLenum,
+                max_vertices: query(gl::GEOMETRY_VERTICES_OUT) as u32,
+            })
+        } else {
+            None
+        };
+        Program {
+            id: program_id,
+            tessellated: stages.contains(&gl::TESS_EVALUATION_SHADER),
+            geometry,
+            stages: stages.iter().fold(0, |bits, &stage| bits | stage_bit(stage)),
+            separable,
+        }
+    }
+}
+
+/// What `Program::link()` should set up besides attaching the shaders
+struct LinkOptions<'a> {
+    varyings: &'a [&'a str],  // outputs for transform feedback to record, if any
+    buffer_mode: gl::types::GLenum,  // `gl::INTERLEAVED_ATTRIBS` or `gl::SEPARATE_ATTRIBS`
+    separable: bool,
+    retrievable: bool,  // whether `binary()` will be wanted
+}
+
+impl Default for LinkOptions<'_> {
+    fn default() -> Self {
+        LinkOptions { varyings: &[], buffer_mode: gl::INTERLEAVED_ATTRIBS, separable: false, retrievable: false }
+    }
+}
+
+/// Whatever the linker (or the driver, loading a binary) had to say about a program
+fn info_log(program_id: gl::types::GLuint) -> String {
+    let mut len: gl::types::GLint = 0;
+    unsafe {
+        gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, & mut len);
+    }
+
+    let error = render_gl::create_whitespace_cstring_with_len(len as usize);
+
+    unsafe {
+        gl::GetProgramInfoLog(
+            program_id,
+            len,
+            std::ptr::null_mut(),
+            error.as_ptr() as *mut gl::types::GLchar
+        );
+    }
+
+    error.to_string_lossy().into_owned()
+}
+
+/// The primitive a geometry shader gets given when drawing with `mode`, or `None` for patches, which only
+/// tessellation can take
+fn geometry_input(mode: gl::types::GLenum) -> Option<gl::types::GLenum> {
+    match mode {
+        gl::POINTS => Some(gl::POINTS),
+        gl::LINES | gl::LINE_STRIP | gl::LINE_LOOP => Some(gl::LINES),
+        gl::LINES_ADJACENCY | gl::LINE_STRIP_ADJACENCY => Some(gl::LINES_ADJACENCY),
+        gl::TRIANGLES | gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => Some(gl::TRIANGLES),
+        gl::TRIANGLES_ADJACENCY | gl::TRIANGLE_STRIP_ADJACENCY => Some(gl::TRIANGLES_ADJACENCY),
+        _ => None,
+    }
+}
+
+/// What a draw mode or primitive is called, for error messages
+fn primitive_name(mode: gl::types::GLenum) -> &'static str {
+    match mode {
+        gl::POINTS => "points",
+        gl::LINES => "lines",
+        gl::LINE_STRIP => "a line strip",
+        gl::LINE_LOOP => "a line loop",
+        gl::LINES_ADJACENCY => "lines with adjacency",
+        gl::LINE_STRIP_ADJACENCY => "a line strip with adjacency",
+        gl::TRIANGLES => "triangles",
+        gl::TRIANGLE_STRIP => "a triangle strip",
+        gl::TRIANGLE_FAN => "a triangle fan",
+        gl::TRIANGLES_ADJACENCY => "triangles with adjacency",
+        gl::TRIANGLE_STRIP_ADJACENCY => "a triangle strip with adjacency",
+        gl::PATCHES => "patches",
+        _ => "an unknown primitive",
+    }
+}
+
+/// Makes sure `stages` make a program that can run.  A tessellation control shader only decides how finely to split
+/// each patch, so it's no use without an evaluation shader to place the new vertices.  An evaluation shader on its own
+/// is fine (the levels come from `tessellation::set_default_levels()` instead), but like every other drawing stage, it
+/// needs a vertex shader to feed it.  Compute shaders don't draw, so they can't be mixed with anything.  Separable
+/// programs only have to make sense once they're put together in a pipeline, so only that last rule applies to them
+fn check_stages(stages: &[gl::types::GLenum], separable: bool) -> Result<(), String> {
+    let has = |stage| stages.contains(&stage);
+    if has(gl::COMPUTE_SHADER) {
+        if stages.iter().any(|&stage| stage != gl::COMPUTE_SHADER) {
+            return Err("a compute shader can't be linked together with drawing stages".to_string());
+        }
+        return Ok(());
+    }
+    if separable {
+        return Ok(());
+    }
+    if has(gl::TESS_CONTROL_SHADER) && !has(gl::TESS_EVALUATION_SHADER) {
+        return Err(
+            "there's a tessellation control shader but no tessellation evaluation shader to go with it".to_string()
+        );
+    }
+    let needs_vertex = [gl::TESS_CONTROL_SHADER, gl::TESS_EVALUATION_SHADER, gl::GEOMETRY_SHADER];
+    if !has(gl::VERTEX_SHADER) {
+        if let Some(&stage) = stages.iter().find(|stage| needs_vertex.contains(stage)) {
+            return Err(format!("there's a {} but no vertex shader to feed it", stage_name(stage)));
+        }
+    }
+    Ok(())
+}
+
+/// The `gl::UseProgramStages()` bit for a shader type
+fn stage_bit(stage: gl::types::GLenum) -> gl::types::GLbitfield {
+    match stage {
+        gl::VERTEX_SHADER => gl::VERTEX_SHADER_BIT,
+        gl::TESS_CONTROL_SHADER => gl::TESS_CONTROL_SHADER_BIT,
+        gl::TESS_EVALUATION_SHADER => gl::TESS_EVALUATION_SHADER_BIT,
+        gl::GEOMETRY_SHADER => gl::GEOMETRY_SHADER_BIT,
+        gl::FRAGMENT_SHADER => gl::FRAGMENT_SHADER_BIT,
+        gl::COMPUTE_SHADER => gl::COMPUTE_SHADER_BIT,
+        _ => 0,
+    }
+}
+
+/// What a uniform's (or a shader input's or output's) type is called in GLSL
+pub fn glsl_type_name(gl_type: gl::types::GLenum) -> &'static str {
+    match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::BOOL => "bool",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow",
+        gl::SAMPLER_CUBE_SHADOW => "samplerCubeShadow",
+        _ => "(other)",
+    }
+}
+
+/// What `stage` is called, for error messages
+fn stage_name(stage: gl::types::GLenum) -> &'static str {
+    match stage {
+        gl::VERTEX_SHADER => "vertex shader",
+        gl::TESS_CONTROL_SHADER => "tessellation control shader",
+        gl::TESS_EVALUATION_SHADER => "tessellation evaluation shader",
+        gl::GEOMETRY_SHADER => "geometry shader",
+        gl::FRAGMENT_SHADER => "fragment shader",
+        gl::COMPUTE_SHADER => "compute shader",
+        _ => "shader of unknown type",
     }
 }
 
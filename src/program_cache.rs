@@ -0,0 +1,214 @@
+// Caches linked programs on disk, so the next run can skip compiling and linking shaders it's already seen.  After a
+// program is linked from source, the driver is asked for its own compiled form (`Program::binary()`), which gets saved
+// under `cache/programs/` next to the other resources.  Next time, if the sources, the defines and the driver are all
+// the same, the binary is handed straight back (`Program::from_binary()`).
+//
+// The binaries only mean anything to the exact driver that made them, so the key includes the GL vendor, renderer and
+// version strings as well as the sources.  Even then a driver can turn a binary down, and some won't make any at all,
+// so anything that goes wrong just means compiling from source the way it would have been without the cache.  Cache
+// files are a small header followed by the binary:
+//
+//     "GLPB", version (u32), key (u64), binary format (u32), length (u32), bytes...
+
+use std::ffi::{CStr, CString};
+
+use crate::program::Program;
+use crate::render_gl::{self, Shader};
+use crate::resources::{self, Resources};
+
+const CACHE_MAGIC: &[u8; 4] = b"GLPB";
+const CACHE_VERSION: u32 = 1;
+const CACHE_HEADER_LEN: usize = 24;
+
+/// How the cache has done so far.  Every program is exactly one of a hit, a miss or a rejection
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u32,      // loaded from a binary
+    pub misses: u32,    // compiled from source, since there was nothing cached (or it was out of date)
+    pub rejected: u32,  // compiled from source, since the driver wouldn't take the cached binary
+    pub saved: u32,     // binaries written out after compiling
+}
+
+/// What GL says `name` (`gl::VENDOR`, ...) is, or nothing if it won't
+fn gl_string(name: gl::types::GLenum) -> String {
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(string as *const std::os::raw::c_char).to_string_lossy().into_owned()
+        }
+    }
+}
+
+/// `source` with a `#define` for each of `defines` pasted in after its `#version`
+fn with_defines(source: &str, defines: &[(&str, &str)]) -> String {
+    if defines.is_empty() {
+        return source.to_string();
+    }
+    let lines: Vec<String> = defines.iter().map(|(name, value)| format!("#define {} {}", name, value)).collect();
+    render_gl::insert_after_version(source, &lines.join("\n"))
+}
+
+fn encode(key: u64, format: gl::types::GLenum, binary: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(CACHE_HEADER_LEN + binary.len());
+    bytes.extend_from_slice(CACHE_MAGIC);
+    bytes.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&key.to_le_bytes());
+    bytes.extend_from_slice(&format.to_le_bytes());
+    bytes.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(binary);
+    bytes
+}
+
+/// Returns the key the binary was saved with, its format, and the binary itself
+fn decode(bytes: &[u8]) -> Result<(u64, gl::types::GLenum, &[u8]), String> {
+    if bytes.len() < CACHE_HEADER_LEN || &bytes[..4] != CACHE_MAGIC {
+        return Err("not a program cache file".to_string());
+    }
+    let u32_at = |offset: usize| {
+        u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+    };
+    if u32_at(4) != CACHE_VERSION {
+        return Err(format!("program cache file is version {}, expected {}", u32_at(4), CACHE_VERSION));
+    }
+    let mut key_bytes = [0u8; 8];
+    key_bytes.copy_from_slice(&bytes[8..16]);
+    if bytes.len() != CACHE_HEADER_LEN + u32_at(20) as usize {
+        return Err("program cache file is the wrong length".to_string());
+    }
+    Ok((u64::from_le_bytes(key_bytes), u32_at(16), &bytes[CACHE_HEADER_LEN..]))
+}
+
+/// Loads programs from the cache when it can, and compiles them (and adds them to the cache) when it can't
+pub struct ProgramCache<'a> {
+    res: &'a Resources,
+    driver: String,  // vendor, renderer and version, which binaries are only good for
+    enabled: bool,   // false if the driver can't make binaries at all
+    stats: CacheStats,
+    warnings: Vec<String>,
+}
+
+#[allow(dead_code)]
+impl<'a> ProgramCache<'a> {
+    /// A cache that keeps its files under `cache/programs/` in `res`.  Needs a current GL context, to find out which
+    /// driver it's caching for
+    pub fn new(res: &'a Resources) -> ProgramCache<'a> {
+        let driver = [gl::VENDOR, gl::RENDERER, gl::VERSION].iter()
+            .map(|&name| gl_string(name))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut formats: gl::types::GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+        }
+        let enabled = formats > 0 && gl::GetProgramBinary::is_loaded() && gl::ProgramBinary::is_loaded();
+        ProgramCache { res, driver, enabled, stats: CacheStats::default(), warnings: Vec::new() }
+    }
+
+    /// Whether the driver can make binaries at all.  If it can't, everything is compiled from source
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Anything that went wrong with the cache files themselves, which is never bad enough to fail over, but is worth
+    /// knowing about.  Takes them, so each is only reported once
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// The program made of `stages` (each a shader type like `gl::VERTEX_SHADER` and its source), with a `#define` for
+    /// each of `defines` pasted into every stage.  From the cache if it's there and the driver takes it, otherwise
+    /// compiled from source and added to the cache
+    pub fn program(
+        &mut self,
+        stages: &[(gl::types::GLenum, &str)],
+        defines: &[(&str, &str)],
+    ) -> Result<Program, String> {
+        let key = self.key(stages, defines);
+        let resource_name = format!("cache/programs/{:016x}", key);
+        let types: Vec<gl::types::GLenum> = stages.iter().map(|&(shader_type, _)| shader_type).collect();
+
+        let mut rejected = false;
+        if self.enabled {
+            if let Ok(bytes) = self.res.load_buffer(&resource_name) {
+                match decode(&bytes) {
+                    Ok((cached_key, format, binary)) if cached_key == key => {
+                        match Program::from_binary(format, binary, &types) {
+                            Ok(program) => {
+                                self.stats.hits += 1;
+                                return Ok(program);
+                            },
+                            Err(e) => {
+                                self.warnings.push(format!("{}: the driver refused it ({}), recompiling", resource_name,
+                                                           e.trim()));
+                                rejected = true;
+                            },
+                        }
+                    },
+                    Ok(_) => {},  // the same file name for something else, which is as good as not being cached
+                    Err(e) => self.warnings.push(format!("{}: {}, recompiling", resource_name, e)),
+                }
+            }
+        }
+        if rejected {
+            self.stats.rejected += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+
+        let shaders = stages.iter()
+            .map(|&(shader_type, source)| {
+                let source = CString::new(with_defines(source, defines)).map_err(|e| e.to_string())?;
+                Shader::from_source(&source, shader_type)
+            })
+            .collect::<Result<Vec<Shader>, String>>()?;
+        if !self.enabled {
+            return Program::from_shaders(&shaders);
+        }
+        let program = Program::from_shaders_retrievable(&shaders)?;
+        match program.binary() {
+            Some((format, binary)) => match self.res.save_buffer(&resource_name, &encode(key, format, &binary)) {
+                Ok(()) => self.stats.saved += 1,
+                Err(e) => self.warnings.push(format!("{}: failed to save: {}", resource_name, e)),
+            },
+            None => self.warnings.push(format!("{}: the driver wouldn't give a binary to save", resource_name)),
+        }
+        Ok(program)
+    }
+
+    /// Hashes everything that goes into the program, and the driver it's for.  Each part is followed by a 0 byte, so
+    /// moving text from the end of one part to the start of the next still changes the key
+    fn key(&self, stages: &[(gl::types::GLenum, &str)], defines: &[(&str, &str)]) -> u64 {
+        let mut parts: Vec<Vec<u8>> = vec![CACHE_VERSION.to_le_bytes().to_vec(), self.driver.as_bytes().to_vec()];
+        for &(shader_type, source) in stages {
+            parts.push(shader_type.to_le_bytes().to_vec());
+            parts.push(source.as_bytes().to_vec());
+        }
+        for &(name, value) in defines {
+            parts.push(name.as_bytes().to_vec());
+            parts.push(value.as_bytes().to_vec());
+        }
+        let parts: Vec<Vec<u8>> = parts.into_iter().map(|mut part| { part.push(0); part }).collect();
+        resources::cache_key(&parts.iter().map(Vec::as_slice).collect::<Vec<_>>())
+    }
+
+    /// Deletes every cached binary, for when they need making again (or to see how long that takes)
+    pub fn clear(&mut self) -> Result<(), String> {
+        let path = self.res.path("cache/programs");
+        match std::fs::remove_dir_all(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("failed to clear {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Starts the counts over
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::default();
+    }
+}
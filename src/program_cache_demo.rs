@@ -0,0 +1,164 @@
+use std::path::Path;
+
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::math::{Mat4, Quat, Vec3};
+use crate::mesh::Mesh;
+use crate::program::Program;
+use crate::program_cache::ProgramCache;
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::resources::Resources;
+use crate::shapes;
+
+/// The values of `SHADING` in cache_variants.frag: diffuse, normals, checker, bands and stripes
+const VARIANTS: [&str; 5] = ["0", "1", "2", "3", "4"];
+
+/// Gets every variant's program from the cache, and how long that took in milliseconds
+fn load_variants(cache: &mut ProgramCache, timer: &sdl2::TimerSubsystem) -> Result<(Vec<Program>, f64), String> {
+    let frequency = timer.performance_frequency() as f64;
+    let start = timer.performance_counter();
+    let mut programs = Vec::new();
+    for &shading in &VARIANTS {
+        programs.push(cache.program(
+            &[
+                (gl::VERTEX_SHADER, include_str!("mesh.vert")),
+                (gl::FRAGMENT_SHADER, include_str!("cache_variants.frag")),
+            ],
+            &[("SHADING", shading)],
+        )?);
+    }
+    let ms = (timer.performance_counter() - start) as f64 / frequency * 1000.0;
+    for warning in cache.take_warnings() {
+        println!("Program cache: {}", warning);
+    }
+    Ok((programs, ms))
+}
+
+/// Five shapes, each drawn with a different variant of the same fragment shader (picked with a `#define`), with every
+/// program coming from the on-disk program cache when it can.  The first run compiles them all and saves the binaries;
+/// the runs after that load them straight back, as long as the shaders and the driver haven't changed.  The title says
+/// how long getting the programs took, and how many came from the cache.
+///
+/// R loads them all again, which should all be cache hits.  C clears the cache first, so they all have to be compiled
+/// again.  Dragging orbits the camera
+pub fn program_cache_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+    let frequency = timer.performance_frequency() as f64;
+
+    let res = Resources::from_crate_dir(Path::new("assets"));
+    let mut cache = ProgramCache::new(&res);
+    if !cache.is_enabled() {
+        println!("This driver can't save program binaries, so everything will be compiled from source");
+    }
+    let (mut programs, mut load_ms) = match load_variants(&mut cache, &timer) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            println!("Failed to load the programs: {}", e);
+            return;
+        },
+    };
+    let meshes: Vec<Mesh> = vec![
+        shapes::uv_sphere(0.8, 32, 16).upload(),
+        shapes::torus(0.6, 0.25, 48, 24).upload(),
+        shapes::cube(1.2, 1).upload(),
+        shapes::icosphere(0.8, 3).upload(),
+        shapes::capsule(0.5, 0.8, 32, 8).upload(),
+    ];
+    let colors = [
+        Vec3::new(0.9, 0.55, 0.3),
+        Vec3::new(0.3, 0.7, 0.9),
+        Vec3::new(0.5, 0.9, 0.4),
+        Vec3::new(0.9, 0.8, 0.4),
+        Vec3::new(0.8, 0.4, 0.8),
+    ];
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 2.5, 9.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 9.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut time = 0.0f32;
+    let mut last_counter = timer.performance_counter();
+    let mut changed = true;
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(key @ (Keycode::R | Keycode::C)), .. } => {
+                    if key == Keycode::C {
+                        if let Err(e) = cache.clear() {
+                            println!("Program cache: {}", e);
+                        }
+                    }
+                    cache.reset_stats();
+                    match load_variants(&mut cache, &timer) {
+                        Ok((reloaded, ms)) => {
+                            programs = reloaded;
+                            load_ms = ms;
+                        },
+                        Err(e) => println!("Failed to reload the programs: {}", e),
+                    }
+                    changed = true;
+                },
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let counter = timer.performance_counter();
+        time += ((counter - last_counter) as f64 / frequency) as f32;
+        last_counter = counter;
+
+        if changed {
+            let stats = cache.stats();
+            window.set_title(&format!(
+                "{} programs in {:.1} ms: {} from the cache, {} compiled, {} refused by the driver, {} saved",
+                programs.len(), load_ms, stats.hits, stats.misses, stats.rejected, stats.saved,
+            )).unwrap();
+            changed = false;
+        }
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        render_state_cache.apply(&RenderState::opaque());
+        for (i, ((program, mesh), color)) in programs.iter().zip(&meshes).zip(&colors).enumerate() {
+            let position = Vec3::new((i as f32 - 2.0) * 2.2, 0.0, 0.0);
+            let rotation = Quat::from_axis_angle(Vec3::new(0.3, 1.0, 0.2).normalize(), time * 0.5 + i as f32);
+            program.set_used();
+            program.set_uniform_mat4("Model", &Mat4::from_trs(position, rotation, Vec3::ONE));
+            program.set_uniform_mat4("View", &camera.view_matrix());
+            program.set_uniform_mat4("Projection", &camera.projection_matrix());
+            program.set_uniform_vec3("DiffuseColor", *color);
+            mesh.draw();
+        }
+
+        window.gl_swap_window();
+    }
+}
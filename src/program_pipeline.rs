@@ -0,0 +1,264 @@
+// Program pipelines: instead of linking every combination of shaders into a program of its own, each stage is linked
+// on its own into a "separable" program (`Program::from_shaders_separable()`), and a pipeline object says which program
+// to take each stage from.  Switching one vertex shader between several fragment shaders, say, then just means
+// swapping the fragment stage, rather than linking (and compiling, and keeping around) the vertex shader once per
+// fragment shader.
+//
+// The catch is that nothing checks that the stages fit together until something's drawn, and then GL only says
+// something went wrong (if it says anything at all).  In a normal program the linker matches each stage's outputs to
+// the next one's inputs; here `ProgramPipeline::validate()` does it, by asking each program what its inputs and outputs
+// are, and reports whatever doesn't match up.
+//
+// Pipelines need GL 4.1, so like tessellation, a demo has to ask for it with `request_context()` before making its
+// window.  Asking programs about their inputs and outputs needs 4.3, so on older contexts `validate()` leaves the
+// matching to GL's own validation, which is much less forthcoming.
+
+use std::ffi::CStr;
+
+use crate::program::{self, Program};
+use crate::render_gl;
+
+/// The oldest GL that has separable programs and pipelines
+pub const MIN_GL_VERSION: (u8, u8) = (4, 1);
+
+/// Asks for a context new enough for pipelines.  Call this where a demo would otherwise ask for 3.3, before creating
+/// the window
+#[allow(dead_code)]
+pub fn request_context(video_subsystem: &sdl2::VideoSubsystem) {
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+    gl_attr.set_context_version(MIN_GL_VERSION.0, MIN_GL_VERSION.1);
+}
+
+/// Whether the current context has pipelines
+#[allow(dead_code)]
+pub fn is_supported() -> bool {
+    render_gl::context_version() >= (MIN_GL_VERSION.0 as i32, MIN_GL_VERSION.1 as i32)
+}
+
+/// Whether programs can be asked about their inputs and outputs (`gl::GetProgramInterfaceiv()` and friends), which
+/// came in 4.3.  Without it, `ProgramPipeline::validate()` can't match the stages up itself
+#[allow(dead_code)]
+pub fn has_program_interface_query() -> bool {
+    render_gl::context_version() >= (4, 3)
+}
+
+/// The drawing stages in the order data goes through them, with what to call them in error messages
+const STAGES: [(gl::types::GLbitfield, &str); 5] = [
+    (gl::VERTEX_SHADER_BIT, "vertex"),
+    (gl::TESS_CONTROL_SHADER_BIT, "tessellation control"),
+    (gl::TESS_EVALUATION_SHADER_BIT, "tessellation evaluation"),
+    (gl::GEOMETRY_SHADER_BIT, "geometry"),
+    (gl::FRAGMENT_SHADER_BIT, "fragment"),
+];
+
+/// An input or output of a program's first or last stage
+#[derive(Debug, Clone, PartialEq)]
+struct Variable {
+    name: String,  // members of blocks are `Block.Member`
+    gl_type: gl::types::GLenum,
+    location: gl::types::GLint,  // -1 if it doesn't say
+}
+
+/// Lists `program`'s active inputs (`gl::PROGRAM_INPUT`) or outputs (`gl::PROGRAM_OUTPUT`), leaving out the built-in
+/// ones like `gl_Position`, which GL matches up itself
+fn interface(program: gl::types::GLuint, interface: gl::types::GLenum) -> Vec<Variable> {
+    let mut count: gl::types::GLint = 0;
+    unsafe {
+        gl::GetProgramInterfaceiv(program, interface, gl::ACTIVE_RESOURCES, &mut count);
+    }
+    let mut variables = Vec::new();
+    for index in 0..count as gl::types::GLuint {
+        let properties = [gl::TYPE, gl::LOCATION];
+        let mut values = [0 as gl::types::GLint; 2];
+        let mut name_buffer = [0u8; 256];
+        unsafe {
+            gl::GetProgramResourceiv(program, interface, index, properties.len() as gl::types::GLsizei,
+                                     properties.as_ptr(), values.len() as gl::types::GLsizei, std::ptr::null_mut(),
+                                     values.as_mut_ptr());
+            gl::GetProgramResourceName(program, interface, index, name_buffer.len() as gl::types::GLsizei,
+                                       std::ptr::null_mut(), name_buffer.as_mut_ptr() as *mut gl::types::GLchar);
+        }
+        let name = CStr::from_bytes_until_nul(&name_buffer).map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if !name.starts_with("gl_") {
+            variables.push(Variable { name, gl_type: values[0] as gl::types::GLenum, location: values[1] });
+        }
+    }
+    variables
+}
+
+/// Checks that everything `consumer` reads, `producer` writes, the same way the linker would if they were in one
+/// program: by location if the input has one, otherwise by name, and as the same type either way.  Outputs nothing
+/// reads are fine.  `stages` names the two stages, for the messages
+fn match_interfaces(producer: &[Variable], consumer: &[Variable], stages: (&str, &str), problems: &mut Vec<String>) {
+    for input in consumer {
+        let output = if input.location >= 0 {
+            producer.iter().find(|output| output.location == input.location)
+        } else {
+            producer.iter().find(|output| output.name == input.name)
+        };
+        let place = if input.location >= 0 { format!(" (location {})", input.location) } else { String::new() };
+        match output {
+            None => problems.push(format!(
+                "the {} stage reads `{}`{}, which the {} stage doesn't write",
+                stages.1, input.name, place, stages.0,
+            )),
+            Some(output) if output.gl_type != input.gl_type => problems.push(format!(
+                "the {} stage reads `{}`{} as a {}, but the {} stage writes `{}` as a {}",
+                stages.1, input.name, place, program::glsl_type_name(input.gl_type),
+                stages.0, output.name, program::glsl_type_name(output.gl_type),
+            )),
+            Some(_) => {},
+        }
+    }
+}
+
+/// Which separable program each drawing stage comes from
+pub struct ProgramPipeline {
+    id: gl::types::GLuint,
+    programs: [gl::types::GLuint; 5],  // the program for each of `STAGES`, or 0 if there isn't one
+}
+
+#[allow(dead_code)]
+impl ProgramPipeline {
+    pub fn new() -> ProgramPipeline {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenProgramPipelines(1, &mut id);
+        }
+        ProgramPipeline { id, programs: [0; 5] }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    /// Takes the stages in `stages` (`gl::VERTEX_SHADER_BIT`, `gl::FRAGMENT_SHADER_BIT`, ... or'd together) from
+    /// `program`, replacing whichever programs they came from before.  The program has to be separable, and actually
+    /// have those stages.  It has to outlive any draws with the pipeline, too
+    pub fn use_stages(&mut self, program: &Program, stages: gl::types::GLbitfield) -> Result<(), String> {
+        if !program.is_separable() {
+            return Err("only programs from `Program::from_shaders_separable()` can go in a pipeline".to_string());
+        }
+        if let Some((_, name)) = STAGES.iter().find(|&&(bit, _)| stages & bit != 0 && program.stages() & bit == 0) {
+            return Err(format!("the program has no {} stage to use", name));
+        }
+        unsafe {
+            gl::UseProgramStages(self.id, stages, program.id());
+        }
+        for (i, &(bit, _)) in STAGES.iter().enumerate() {
+            if stages & bit != 0 {
+                self.programs[i] = program.id();
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes every stage `program` has
+    pub fn use_program(&mut self, program: &Program) -> Result<(), String> {
+        self.use_stages(program, program.stages())
+    }
+
+    /// Leaves the stages in `stages` out, so a draw skips them (the vertex and fragment stages have to be there to
+    /// draw anything, though)
+    pub fn clear_stages(&mut self, stages: gl::types::GLbitfield) {
+        unsafe {
+            gl::UseProgramStages(self.id, stages, 0);
+        }
+        for (i, &(bit, _)) in STAGES.iter().enumerate() {
+            if stages & bit != 0 {
+                self.programs[i] = 0;
+            }
+        }
+    }
+
+    /// Makes the pipeline what draws use.  A program made current with `Program::set_used()` takes priority over any
+    /// pipeline, so this stops using whatever program was.  That also means uniforms have to be set (each program's
+    /// own, with `set_used()` first) before binding the pipeline, not after
+    pub fn bind(&self) {
+        unsafe {
+            gl::UseProgram(0);
+            gl::BindProgramPipeline(self.id);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindProgramPipeline(0);
+        }
+    }
+
+    /// Checks that the stages fit together: that there's a vertex and a fragment stage, that each stage's inputs are
+    /// written by the stage before it, and whatever else GL's own validation finds.  Every problem gets its own line
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        for &(bit, name) in &[STAGES[0], STAGES[4]] {
+            if self.program_for(bit) == 0 {
+                problems.push(format!("there's no {} stage", name));
+            }
+        }
+
+        if has_program_interface_query() {
+            // Stages that came from the same program were matched up when it was linked
+            let present: Vec<usize> = (0..STAGES.len()).filter(|&i| self.programs[i] != 0).collect();
+            for pair in present.windows(2) {
+                let (producer, consumer) = (self.programs[pair[0]], self.programs[pair[1]]);
+                if producer != consumer {
+                    match_interfaces(
+                        &interface(producer, gl::PROGRAM_OUTPUT),
+                        &interface(consumer, gl::PROGRAM_INPUT),
+                        (STAGES[pair[0]].1, STAGES[pair[1]].1),
+                        &mut problems,
+                    );
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            let mut status: gl::types::GLint = 0;
+            unsafe {
+                gl::ValidateProgramPipeline(self.id);
+                gl::GetProgramPipelineiv(self.id, gl::VALIDATE_STATUS, &mut status);
+            }
+            if status == 0 {
+                problems.push(self.info_log());
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("the pipeline's stages don't fit together:\n    {}", problems.join("\n    ")))
+        }
+    }
+
+    fn program_for(&self, stage: gl::types::GLbitfield) -> gl::types::GLuint {
+        STAGES.iter().position(|&(bit, _)| bit == stage).map_or(0, |i| self.programs[i])
+    }
+
+    /// Whatever GL said about the last validation
+    fn info_log(&self) -> String {
+        let mut length: gl::types::GLint = 0;
+        unsafe {
+            gl::GetProgramPipelineiv(self.id, gl::INFO_LOG_LENGTH, &mut length);
+        }
+        if length <= 1 {
+            return "GL says the pipeline isn't valid, but not why".to_string();
+        }
+        let mut log = vec![0u8; length as usize];
+        unsafe {
+            gl::GetProgramPipelineInfoLog(self.id, length, std::ptr::null_mut(),
+                                          log.as_mut_ptr() as *mut gl::types::GLchar);
+        }
+        String::from_utf8_lossy(&log[..length as usize - 1]).trim().to_string()
+    }
+}
+
+impl Drop for ProgramPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgramPipelines(1, &self.id);
+        }
+    }
+}
@@ -15,6 +15,15 @@ impl Shader {
         self.id
     }
 
+    /// Which stage this is: `gl::VERTEX_SHADER`, `gl::TESS_CONTROL_SHADER`, `gl::FRAGMENT_SHADER`, ...
+    pub fn shader_type(&self) -> gl::types::GLenum {
+        let mut shader_type: gl::types::GLint = 0;
+        unsafe {
+            gl::GetShaderiv(self.id, gl::SHADER_TYPE, &mut shader_type);
+        }
+        shader_type as gl::types::GLenum
+    }
+
     /// Since this does not have a `self` parameter, this is basically a static method (doesn't act on individual objects
     /// of the `Shader` type, but rather works only with the struct itself.  This is basically a constructor method
     #[allow(dead_code)]
@@ -118,6 +127,28 @@ fn shader_from_source(source: &CStr, shader_type: gl::types::GLuint) -> Result<g
     Ok(id)
 }
 
+/// The version of the current context, as (major, minor).  Features newer than the 3.3 the lessons ask for (compute,
+/// tessellation, pipelines, ...) check this before using anything that isn't there
+#[allow(dead_code)]
+pub fn context_version() -> (i32, i32) {
+    let (mut major, mut minor) = (0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    (major, minor)
+}
+
+/// Adds `text` (like a `#define`, or some shared functions) to a shader's source.  The `#version` line has to stay
+/// first, so it goes straight after that
+#[allow(dead_code)]
+pub fn insert_after_version(source: &str, text: &str) -> String {
+    match source.find('\n') {
+        Some(end) if source.starts_with("#version") => format!("{}\n{}\n{}", &source[..end], text, &source[end + 1..]),
+        _ => format!("{}\n{}", text, source),
+    }
+}
+
 #[allow(dead_code)]
 pub fn create_whitespace_cstring_with_len(len: usize) -> CString {
     // Then we allocate a vector to act as a buffer to hold the message
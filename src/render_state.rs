@@ -0,0 +1,599 @@
+// OpenGL is a big state machine, and it's easy to end up with calls like `gl::Enable(gl::CULL_FACE)` sprinkled all
+// over the render loop where nobody can tell what state a draw call actually ends up with.  Instead, a `RenderState`
+// describes *everything* about the fixed-function part of the pipeline that we care about, and gets applied as one
+// unit.  A `RenderStateCache` remembers what we last told GL, so applying the same state twice in a row doesn't make any
+// GL calls at all.
+
+/// Comparison used by the depth and stencil tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum CompareFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunc {
+    fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            CompareFunc::Never => gl::NEVER,
+            CompareFunc::Less => gl::LESS,
+            CompareFunc::Equal => gl::EQUAL,
+            CompareFunc::LessEqual => gl::LEQUAL,
+            CompareFunc::Greater => gl::GREATER,
+            CompareFunc::NotEqual => gl::NOTEQUAL,
+            CompareFunc::GreaterEqual => gl::GEQUAL,
+            CompareFunc::Always => gl::ALWAYS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum BlendEquation {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendEquation {
+    fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            BlendEquation::Add => gl::FUNC_ADD,
+            BlendEquation::Subtract => gl::FUNC_SUBTRACT,
+            BlendEquation::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+            BlendEquation::Min => gl::MIN,
+            BlendEquation::Max => gl::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    ConstantColor,
+    OneMinusConstantColor,
+    ConstantAlpha,
+    OneMinusConstantAlpha,
+    SrcAlphaSaturate,
+}
+
+impl BlendFactor {
+    fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            BlendFactor::Zero => gl::ZERO,
+            BlendFactor::One => gl::ONE,
+            BlendFactor::SrcColor => gl::SRC_COLOR,
+            BlendFactor::OneMinusSrcColor => gl::ONE_MINUS_SRC_COLOR,
+            BlendFactor::DstColor => gl::DST_COLOR,
+            BlendFactor::OneMinusDstColor => gl::ONE_MINUS_DST_COLOR,
+            BlendFactor::SrcAlpha => gl::SRC_ALPHA,
+            BlendFactor::OneMinusSrcAlpha => gl::ONE_MINUS_SRC_ALPHA,
+            BlendFactor::DstAlpha => gl::DST_ALPHA,
+            BlendFactor::OneMinusDstAlpha => gl::ONE_MINUS_DST_ALPHA,
+            BlendFactor::ConstantColor => gl::CONSTANT_COLOR,
+            BlendFactor::OneMinusConstantColor => gl::ONE_MINUS_CONSTANT_COLOR,
+            BlendFactor::ConstantAlpha => gl::CONSTANT_ALPHA,
+            BlendFactor::OneMinusConstantAlpha => gl::ONE_MINUS_CONSTANT_ALPHA,
+            BlendFactor::SrcAlphaSaturate => gl::SRC_ALPHA_SATURATE,
+        }
+    }
+}
+
+/// Which faces get thrown away before rasterizing.  `None` means culling is disabled entirely
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+/// Which winding order counts as the front of a triangle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum FrontFace {
+    CounterClockwise,
+    Clockwise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    Increment,
+    IncrementWrap,
+    Decrement,
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOp {
+    fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            StencilOp::Keep => gl::KEEP,
+            StencilOp::Zero => gl::ZERO,
+            StencilOp::Replace => gl::REPLACE,
+            StencilOp::Increment => gl::INCR,
+            StencilOp::IncrementWrap => gl::INCR_WRAP,
+            StencilOp::Decrement => gl::DECR,
+            StencilOp::DecrementWrap => gl::DECR_WRAP,
+            StencilOp::Invert => gl::INVERT,
+        }
+    }
+}
+
+/// How polygons get rasterized.  `Line` is what people usually mean by "wireframe"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
+impl PolygonMode {
+    fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+            PolygonMode::Point => gl::POINT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthState {
+    pub test: bool,
+    pub write: bool,
+    pub func: CompareFunc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlendState {
+    pub enabled: bool,
+    pub color_equation: BlendEquation,
+    pub alpha_equation: BlendEquation,
+    pub src_color: BlendFactor,
+    pub dst_color: BlendFactor,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+    pub constant: [f32; 4],  // only matters for the `Constant*` blend factors
+}
+
+/// Stencil settings for one side (front or back facing) of the triangles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilFaceState {
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    pub write_mask: u32,
+    pub stencil_fail: StencilOp,  // stencil test failed
+    pub depth_fail: StencilOp,    // stencil test passed, but depth test failed
+    pub pass: StencilOp,          // both passed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StencilState {
+    pub enabled: bool,
+    pub front: StencilFaceState,
+    pub back: StencilFaceState,
+}
+
+/// Pushes the depth of what gets drawn further away, by `constant` units of depth precision plus `slope` times how
+/// steeply the triangle faces away from the camera.  Shadow maps need this so that surfaces don't shadow themselves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthBias {
+    pub constant: f32,
+    pub slope: f32,
+}
+
+/// Scissor rectangle in window pixels, measured from the bottom-left corner like everything else in GL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScissorRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Everything about how triangles get turned into pixels, other than the shaders themselves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderState {
+    pub depth: DepthState,
+    pub blend: BlendState,
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    pub stencil: StencilState,
+    pub color_mask: [bool; 4],
+    pub polygon_mode: PolygonMode,
+    pub point_size: f32,  // in pixels, for `PolygonMode::Point` and `gl::POINTS` draws
+    pub scissor: Option<ScissorRect>,  // `None` disables the scissor test
+    pub depth_bias: Option<DepthBias>,  // `None` disables the polygon offset
+}
+
+impl Default for DepthState {
+    fn default() -> DepthState {
+        DepthState { test: false, write: true, func: CompareFunc::Less }
+    }
+}
+
+impl Default for BlendState {
+    fn default() -> BlendState {
+        BlendState {
+            enabled: false,
+            color_equation: BlendEquation::Add,
+            alpha_equation: BlendEquation::Add,
+            src_color: BlendFactor::One,
+            dst_color: BlendFactor::Zero,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::Zero,
+            constant: [0.0; 4],
+        }
+    }
+}
+
+impl Default for StencilFaceState {
+    fn default() -> StencilFaceState {
+        StencilFaceState {
+            func: CompareFunc::Always,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            stencil_fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+/// The defaults match what a freshly created GL context starts out with, so `RenderState::default()` is "whatever GL
+/// would've done if we hadn't touched anything"
+impl Default for RenderState {
+    fn default() -> RenderState {
+        RenderState {
+            depth: DepthState::default(),
+            blend: BlendState::default(),
+            cull_mode: CullMode::None,
+            front_face: FrontFace::CounterClockwise,
+            stencil: StencilState::default(),
+            color_mask: [true; 4],
+            polygon_mode: PolygonMode::Fill,
+            point_size: 1.0,
+            scissor: None,
+            depth_bias: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl BlendState {
+    /// Classic "over" blending for things with an alpha channel
+    pub fn alpha() -> BlendState {
+        BlendState {
+            enabled: true,
+            src_color: BlendFactor::SrcAlpha,
+            dst_color: BlendFactor::OneMinusSrcAlpha,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::OneMinusSrcAlpha,
+            ..BlendState::default()
+        }
+    }
+
+    /// Just adds the new color on top of what's there, for things like light accumulation and particles
+    pub fn additive() -> BlendState {
+        BlendState {
+            enabled: true,
+            src_color: BlendFactor::One,
+            dst_color: BlendFactor::One,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::One,
+            ..BlendState::default()
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl RenderState {
+    /// What most solid 3D geometry wants: depth testing and writing, and back faces culled
+    pub fn opaque() -> RenderState {
+        RenderState {
+            depth: DepthState { test: true, write: true, func: CompareFunc::Less },
+            cull_mode: CullMode::Back,
+            ..RenderState::default()
+        }
+    }
+
+    /// See-through geometry: still depth tested against the opaque stuff, but doesn't write depth itself so that
+    /// things behind it (drawn later) aren't thrown away
+    pub fn transparent() -> RenderState {
+        RenderState {
+            depth: DepthState { test: true, write: false, func: CompareFunc::Less },
+            blend: BlendState::alpha(),
+            ..RenderState::default()
+        }
+    }
+
+    /// 2D overlays like text and UI: no depth at all, alpha blended, and nothing culled
+    pub fn overlay() -> RenderState {
+        RenderState {
+            blend: BlendState::alpha(),
+            ..RenderState::default()
+        }
+    }
+
+    /// Applies every piece of this state unconditionally.  Prefer going through a `RenderStateCache` in the render
+    /// loop, but this is handy for one-off setup code
+    pub fn apply(&self) {
+        apply_depth(&self.depth);
+        apply_blend(&self.blend);
+        apply_cull(self.cull_mode, self.front_face);
+        apply_stencil(&self.stencil);
+        apply_color_mask(self.color_mask);
+        apply_polygon_mode(self.polygon_mode);
+        apply_point_size(self.point_size);
+        apply_scissor(self.scissor);
+        apply_depth_bias(self.depth_bias);
+    }
+}
+
+/// Shadow copy of the GL state, so that we only make the GL calls for the pieces of state that actually changed
+pub struct RenderStateCache {
+    // `None` means we don't know what GL's state is (e.g. someone else has been making raw GL calls), so the next
+    // `apply()` has to set everything
+    current: Option<RenderState>,
+    applies: u64,
+    skipped: u64,
+}
+
+#[allow(dead_code)]
+impl RenderStateCache {
+    pub fn new() -> RenderStateCache {
+        RenderStateCache { current: None, applies: 0, skipped: 0 }
+    }
+
+    /// Makes `state` the current GL state, skipping anything that already matches what we last applied
+    pub fn apply(&mut self, state: &RenderState) {
+        let previous = match self.current {
+            Some(previous) => previous,
+            None => {
+                state.apply();
+                self.current = Some(*state);
+                self.applies += 1;
+                return;
+            }
+        };
+
+        if previous == *state {
+            self.skipped += 1;
+            return;
+        }
+
+        if previous.depth != state.depth {
+            apply_depth(&state.depth);
+        }
+        if previous.blend != state.blend {
+            apply_blend(&state.blend);
+        }
+        if previous.cull_mode != state.cull_mode || previous.front_face != state.front_face {
+            apply_cull(state.cull_mode, state.front_face);
+        }
+        if previous.stencil != state.stencil {
+            apply_stencil(&state.stencil);
+        }
+        if previous.color_mask != state.color_mask {
+            apply_color_mask(state.color_mask);
+        }
+        if previous.polygon_mode != state.polygon_mode {
+            apply_polygon_mode(state.polygon_mode);
+        }
+        if previous.point_size != state.point_size {
+            apply_point_size(state.point_size);
+        }
+        if previous.scissor != state.scissor {
+            apply_scissor(state.scissor);
+        }
+        if previous.depth_bias != state.depth_bias {
+            apply_depth_bias(state.depth_bias);
+        }
+
+        self.current = Some(*state);
+        self.applies += 1;
+    }
+
+    /// Forget what we think GL's state is.  Call this after any code that changes state behind the cache's back
+    pub fn invalidate(&mut self) {
+        self.current = None;
+    }
+
+    /// The state we last applied, if we know it
+    pub fn current(&self) -> Option<&RenderState> {
+        self.current.as_ref()
+    }
+
+    /// How many `apply()` calls actually changed something, and how many were skipped because nothing had changed
+    pub fn stats(&self) -> (u64, u64) {
+        (self.applies, self.skipped)
+    }
+}
+
+fn set_enabled(cap: gl::types::GLenum, enabled: bool) {
+    unsafe {
+        if enabled { gl::Enable(cap) } else { gl::Disable(cap) }
+    }
+}
+
+fn apply_depth(depth: &DepthState) {
+    set_enabled(gl::DEPTH_TEST, depth.test);
+    unsafe {
+        gl::DepthMask(if depth.write { gl::TRUE } else { gl::FALSE });
+        gl::DepthFunc(depth.func.to_gl());
+    }
+}
+
+fn apply_blend(blend: &BlendState) {
+    set_enabled(gl::BLEND, blend.enabled);
+    if !blend.enabled {
+        return;  // The rest doesn't matter while blending is off, and will get set when it's turned back on
+    }
+    unsafe {
+        gl::BlendEquationSeparate(blend.color_equation.to_gl(), blend.alpha_equation.to_gl());
+        gl::BlendFuncSeparate(
+            blend.src_color.to_gl(),
+            blend.dst_color.to_gl(),
+            blend.src_alpha.to_gl(),
+            blend.dst_alpha.to_gl(),
+        );
+        let [r, g, b, a] = blend.constant;
+        gl::BlendColor(r, g, b, a);
+    }
+}
+
+fn apply_cull(cull_mode: CullMode, front_face: FrontFace) {
+    set_enabled(gl::CULL_FACE, cull_mode != CullMode::None);
+    unsafe {
+        match cull_mode {
+            CullMode::None => {},
+            CullMode::Front => gl::CullFace(gl::FRONT),
+            CullMode::Back => gl::CullFace(gl::BACK),
+            CullMode::FrontAndBack => gl::CullFace(gl::FRONT_AND_BACK),
+        }
+        gl::FrontFace(match front_face {
+            FrontFace::CounterClockwise => gl::CCW,
+            FrontFace::Clockwise => gl::CW,
+        });
+    }
+}
+
+fn apply_stencil(stencil: &StencilState) {
+    set_enabled(gl::STENCIL_TEST, stencil.enabled);
+    if !stencil.enabled {
+        return;
+    }
+    for &(face, s) in &[(gl::FRONT, &stencil.front), (gl::BACK, &stencil.back)] {
+        unsafe {
+            gl::StencilFuncSeparate(face, s.func.to_gl(), s.reference, s.read_mask);
+            gl::StencilMaskSeparate(face, s.write_mask);
+            gl::StencilOpSeparate(face, s.stencil_fail.to_gl(), s.depth_fail.to_gl(), s.pass.to_gl());
+        }
+    }
+}
+
+fn apply_color_mask(mask: [bool; 4]) {
+    let b = |v: bool| if v { gl::TRUE } else { gl::FALSE };
+    unsafe {
+        gl::ColorMask(b(mask[0]), b(mask[1]), b(mask[2]), b(mask[3]));
+    }
+}
+
+fn apply_polygon_mode(mode: PolygonMode) {
+    // Core profile only allows `FRONT_AND_BACK` here
+    unsafe {
+        gl::PolygonMode(gl::FRONT_AND_BACK, mode.to_gl());
+    }
+}
+
+fn apply_point_size(size: f32) {
+    unsafe {
+        gl::PointSize(size);
+    }
+}
+
+fn apply_scissor(scissor: Option<ScissorRect>) {
+    set_enabled(gl::SCISSOR_TEST, scissor.is_some());
+    if let Some(rect) = scissor {
+        unsafe {
+            gl::Scissor(rect.x, rect.y, rect.width, rect.height);
+        }
+    }
+}
+
+fn apply_depth_bias(bias: Option<DepthBias>) {
+    set_enabled(gl::POLYGON_OFFSET_FILL, bias.is_some());
+    if let Some(bias) = bias {
+        unsafe {
+            gl::PolygonOffset(bias.slope, bias.constant);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gl::types::{GLboolean, GLenum, GLfloat, GLint, GLsizei, GLuint};
+    use std::sync::Mutex;
+
+    static CALLS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    /// Points the GL functions the cache uses at stand-ins that just write down their names, so the tests can see
+    /// which calls an `apply()` made without needing a context
+    macro_rules! record_calls {
+        ($($name:ident($($arg:ty),*);)*) => {$(
+            gl::$name::load_with(|_| {
+                extern "system" fn record($(_: $arg),*) {
+                    CALLS.lock().unwrap().push(stringify!($name));
+                }
+                record as *const std::os::raw::c_void
+            });
+        )*};
+    }
+
+    fn take_calls() -> Vec<&'static str> {
+        std::mem::take(&mut *CALLS.lock().unwrap())
+    }
+
+    #[test]
+    fn cache_only_makes_the_calls_that_changed() {
+        record_calls! {
+            Enable(GLenum); Disable(GLenum); DepthMask(GLboolean); DepthFunc(GLenum);
+            BlendEquationSeparate(GLenum, GLenum); BlendFuncSeparate(GLenum, GLenum, GLenum, GLenum);
+            BlendColor(GLfloat, GLfloat, GLfloat, GLfloat); CullFace(GLenum); FrontFace(GLenum);
+            StencilFuncSeparate(GLenum, GLenum, GLint, GLuint); StencilMaskSeparate(GLenum, GLuint);
+            StencilOpSeparate(GLenum, GLenum, GLenum, GLenum); ColorMask(GLboolean, GLboolean, GLboolean, GLboolean);
+            PolygonMode(GLenum, GLenum); PointSize(GLfloat); Scissor(GLint, GLint, GLsizei, GLsizei);
+            PolygonOffset(GLfloat, GLfloat);
+        }
+
+        // Nothing's known about GL's state to begin with, so the first apply sets all of it
+        let mut cache = RenderStateCache::new();
+        let opaque = RenderState::opaque();
+        cache.apply(&opaque);
+        let everything = take_calls();
+        assert!(everything.contains(&"DepthFunc") && everything.contains(&"PolygonMode"), "{:?}", everything);
+        assert_eq!(cache.current(), Some(&opaque));
+
+        cache.apply(&opaque);
+        assert!(take_calls().is_empty());
+        assert_eq!(cache.stats(), (1, 1));
+
+        cache.apply(&RenderState { cull_mode: CullMode::None, ..opaque });
+        assert_eq!(take_calls(), vec!["Disable", "FrontFace"]);
+        cache.apply(&RenderState { depth_bias: Some(DepthBias { constant: 1.0, slope: 2.0 }), ..opaque });
+        assert_eq!(take_calls(), vec!["Enable", "CullFace", "FrontFace", "Enable", "PolygonOffset"]);
+        assert_eq!(cache.stats(), (3, 1));
+
+        cache.invalidate();
+        cache.apply(&opaque);
+        assert_eq!(take_calls(), everything);
+    }
+}
@@ -1,4 +1,133 @@
-use resources::Resources;
-use std::path::Path;
+// Loads files (shaders, models, textures, ...) relative to some root folder, so the rest of the code can ask for
+// something like "models/cube.obj" without caring where the program was run from.  Resource names always use `/` as
+// the separator, and get turned into a proper path for whatever OS we're on.
 
-pub struct
\ No newline at end of file
+use std::ffi;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    FileContainsNil,
+    FileNotUtf8,
+    FailedToGetExePath,
+}
+
+impl From<io::Error> for Error {
+    fn from(other: io::Error) -> Self {
+        Error::Io(other)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::FileContainsNil => write!(f, "file contains a nil byte"),
+            Error::FileNotUtf8 => write!(f, "file is not valid UTF-8"),
+            Error::FailedToGetExePath => write!(f, "failed to get the executable's path"),
+        }
+    }
+}
+
+pub struct Resources {
+    root_path: PathBuf,
+}
+
+#[allow(dead_code)]
+impl Resources {
+    /// Resources live in a folder next to the executable (e.g. `target/debug/assets`)
+    pub fn from_relative_exe_path(rel_path: &Path) -> Result<Resources, Error> {
+        let exe_file_name = std::env::current_exe().map_err(|_| Error::FailedToGetExePath)?;
+        let exe_path = exe_file_name.parent().ok_or(Error::FailedToGetExePath)?;
+        Ok(Resources { root_path: exe_path.join(rel_path) })
+    }
+
+    /// Resources live in a folder inside the crate itself.  This is the handy one while developing with `cargo run`,
+    /// since nothing has to be copied next to the executable
+    pub fn from_crate_dir(rel_path: &Path) -> Resources {
+        Resources { root_path: Path::new(env!("CARGO_MANIFEST_DIR")).join(rel_path) }
+    }
+
+    pub fn from_path(root_path: &Path) -> Resources {
+        Resources { root_path: root_path.to_path_buf() }
+    }
+
+    /// Where a resource would live on disk
+    pub fn path(&self, resource_name: &str) -> PathBuf {
+        resource_name_to_path(&self.root_path, resource_name)
+    }
+
+    pub fn load_buffer(&self, resource_name: &str) -> Result<Vec<u8>, Error> {
+        let mut file = fs::File::open(self.path(resource_name))?;
+        let mut buffer: Vec<u8> = Vec::with_capacity(file.metadata()?.len() as usize);
+        file.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Writes a resource, making any folders it needs.  For things the program generates and wants to keep around,
+    /// like caches
+    pub fn save_buffer(&self, resource_name: &str, bytes: &[u8]) -> Result<(), Error> {
+        let path = self.path(resource_name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    pub fn load_string(&self, resource_name: &str) -> Result<String, Error> {
+        String::from_utf8(self.load_buffer(resource_name)?).map_err(|_| Error::FileNotUtf8)
+    }
+
+    /// Shader sources need to be handed to GL as C strings, so this saves converting them by hand
+    pub fn load_cstring(&self, resource_name: &str) -> Result<ffi::CString, Error> {
+        let buffer = self.load_buffer(resource_name)?;
+
+        // Check for a nil byte in the middle of the file, since C strings use that to mark the end of the string
+        if buffer.contains(&0) {
+            return Err(Error::FileContainsNil);
+        }
+
+        Ok(unsafe { ffi::CString::from_vec_unchecked(buffer) })
+    }
+}
+
+/// Resolves a name that was written inside one resource (like the `mtllib` in an OBJ file or a texture in a material
+/// file) relative to the folder that resource lives in.  `relative_to("models/cube.obj", "cube.mtl")` gives
+/// `"models/cube.mtl"`
+#[allow(dead_code)]
+pub fn relative_to(resource_name: &str, other: &str) -> String {
+    let other = other.replace('\\', "/");
+    match resource_name.rfind('/') {
+        Some(index) => format!("{}/{}", &resource_name[..index], other),
+        None => other,
+    }
+}
+
+/// FNV-1a over each of `parts` in turn, for telling whether whatever a cached file was made from has changed.  Not
+/// cryptographic, but plenty for that
+#[allow(dead_code)]
+pub fn cache_key(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for &byte in *part {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
+    let mut path: PathBuf = root_dir.into();
+
+    for part in location.split('/') {
+        path = path.join(part);
+    }
+
+    path
+}
@@ -0,0 +1,133 @@
+use std::ffi::CString;
+use std::path::Path;
+
+use crate::render_gl;
+use crate::program;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::math::{Mat4, Vec3};
+use crate::mesh::Mesh;
+use crate::ply::{self, PlyAttribute};
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::resources::Resources;
+use crate::stl;
+use crate::vertex::attrib;
+
+/// Shows the kind of geometry that comes out of scanners and CAD programs: a PLY point cloud drawn with `gl::POINTS`, a
+/// PLY mesh with vertex colors, and an STL part.  Tab switches between them, +/- change the point size, C switches the
+/// point cloud between its scanned colors and its intensity values, and dragging orbits the camera
+pub fn scanned_geometry() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.5, 0.3, 0.3, 1.0);  // Color that window will default to when everything is cleared
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+
+    let res = Resources::from_crate_dir(Path::new("assets"));
+
+    let point_cloud_program = program::Program::from_shaders(&[
+        render_gl::Shader::from_vert_source(&CString::new(include_str!("point_cloud.vert")).unwrap()).unwrap(),
+        render_gl::Shader::from_frag_source(&CString::new(include_str!("point_cloud.frag")).unwrap()).unwrap(),
+    ]).unwrap();
+    let mesh_program = program::Program::from_shaders(&[
+        render_gl::Shader::from_vert_source(&CString::new(include_str!("mesh.vert")).unwrap()).unwrap(),
+        render_gl::Shader::from_frag_source(&CString::new(include_str!("mesh.frag")).unwrap()).unwrap(),
+    ]).unwrap();
+
+    // Print the error rather than panicking on it, since a broken file is an easy mistake to make
+    let loaded = (|| -> Result<(Mesh, Mesh, Mesh), String> {
+        let points = ply::load(&res, "models/scan_points.ply")?;
+        println!("Point cloud: {} points, properties {:?}", points.vertex_count,
+                 points.columns.iter().map(|c| &c.name).collect::<Vec<_>>());
+        let mut attributes = points.standard_attributes();
+        attributes.push(PlyAttribute::new(attrib::INTENSITY, &["intensity"], false));
+        let points = points.upload_with(&attributes)?;
+
+        let pyramid = ply::load(&res, "models/pyramid.ply")?;
+        println!("PLY mesh: {} vertices, {} faces", pyramid.vertex_count, pyramid.faces.len());
+        let pyramid = pyramid.upload()?;
+
+        let bracket = stl::load(&res, "models/bracket.stl")?;
+        println!("STL mesh: {} triangles", bracket.triangle_count());
+        Ok((points, pyramid, bracket.upload()))
+    })();
+    let (points, pyramid, bracket) = match loaded {
+        Ok(meshes) => meshes,
+        Err(e) => {
+            println!("Failed to load model: {}", e);
+            return;
+        },
+    };
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 2.0, 4.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::new(0.0, 0.3, 0.0), 4.0);
+    controller.attach(&mut camera);
+
+    // The PLY pyramid and the STL part are both closed, so culling back faces is fine for everything
+    let mut render_state = RenderState::opaque();
+    render_state.point_size = 3.0;
+    let mut render_state_cache = RenderStateCache::new();
+
+    let mut showing = 0;
+    let mut color_mode = 0;
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Tab), .. } => showing = (showing + 1) % 3,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::C), .. } => color_mode = 1 - color_mode,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Equals), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(Keycode::KpPlus), .. } => {
+                    render_state.point_size = (render_state.point_size + 1.0).min(32.0);
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Minus), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(Keycode::KpMinus), .. } => {
+                    render_state.point_size = (render_state.point_size - 1.0).max(1.0);
+                },
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        render_state_cache.apply(&render_state);
+
+        let (program, mesh) = match showing {
+            0 => (&point_cloud_program, &points),
+            1 => (&point_cloud_program, &pyramid),
+            _ => (&mesh_program, &bracket),
+        };
+        program.set_used();
+        program.set_uniform_mat4("Model", &Mat4::identity());
+        program.set_uniform_mat4("View", &camera.view_matrix());
+        program.set_uniform_mat4("Projection", &camera.projection_matrix());
+        program.set_uniform_i32("ColorMode", if showing == 0 { color_mode } else { 0 });
+        program.set_uniform_vec3("DiffuseColor", Vec3::new(0.7, 0.7, 0.75));
+        mesh.draw();
+
+        window.gl_swap_window();
+    }
+}
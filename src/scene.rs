@@ -0,0 +1,368 @@
+// A scene graph: a tree of nodes, each with a transform relative to its parent, and optionally something attached to
+// it (a mesh to draw, a camera, a light).  Moving a node moves everything below it, which is how a moon can orbit a
+// planet that orbits a sun without anybody working out the combined motion by hand.
+//
+// Like `gltf`, everything refers to everything else by index: nodes by their index in the scene, and renderables by
+// the index of their mesh in `Scene::meshes` and their material in `Scene::materials`.
+//
+// World matrices are cached.  Changing a node's transform only marks it dirty, and `update_world_transforms()`
+// recomputes the dirty nodes and everything below them, leaving the rest of the tree alone.
+
+use crate::bounds::{Aabb, Frustum};
+use crate::math::{Mat4, Quat, Vec3};
+use crate::material::MaterialLibrary;
+use crate::mesh::{Mesh, MeshData};
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::shadow::ShadowSettings;
+
+/// Something to draw at a node
+#[derive(Debug, Clone)]
+pub struct Renderable {
+    pub mesh: usize,      // index into `Scene::meshes`
+    pub material: usize,  // index into `Scene::materials.materials`
+    pub bounds: Aabb,     // around the mesh, in the node's own space
+    pub casts_shadows: bool,
+}
+
+#[allow(dead_code)]
+impl Renderable {
+    pub fn new(mesh: usize, material: usize, bounds: Aabb) -> Renderable {
+        Renderable { mesh, material, bounds, casts_shadows: true }
+    }
+}
+
+/// A perspective camera that looks down its node's -Z axis, with +Y as up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneCamera {
+    pub fov_y: f32,  // radians
+    pub near: f32,
+    pub far: f32,
+}
+
+#[allow(dead_code)]
+impl SceneCamera {
+    pub fn projection_matrix(&self, aspect: f32) -> Mat4 {
+        Mat4::perspective(self.fov_y, aspect, self.near, self.far)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot { inner_angle: f32, outer_angle: f32 },  // radians from the center of the beam
+}
+
+/// A light that shines down its node's -Z axis (which only matters for directional and spot lights)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,  // how far away the light fades out to nothing; ignored by directional lights
+    pub shadow: Option<ShadowSettings>,  // `None` for a light that shines through everything
+}
+
+/// A light along with where it ended up in the world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldLight {
+    pub node: usize,
+    pub light: Light,
+    pub position: Vec3,
+    pub direction: Vec3,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    translation: Vec3,
+    rotation: Quat,
+    scale: Vec3,
+    world: Mat4,
+    dirty: bool,  // the local transform changed since `world` was last worked out
+    pub visible: bool,  // hidden nodes aren't drawn, and neither is anything below them
+    pub renderable: Option<Renderable>,
+    pub camera: Option<SceneCamera>,
+    pub light: Option<Light>,
+}
+
+#[allow(dead_code)]
+impl Node {
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[usize] {
+        &self.children
+    }
+
+    pub fn translation(&self) -> Vec3 {
+        self.translation
+    }
+
+    pub fn rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    pub fn scale(&self) -> Vec3 {
+        self.scale
+    }
+
+    pub fn set_translation(&mut self, translation: Vec3) {
+        self.translation = translation;
+        self.dirty = true;
+    }
+
+    pub fn set_rotation(&mut self, rotation: Quat) {
+        self.rotation = rotation;
+        self.dirty = true;
+    }
+
+    pub fn set_scale(&mut self, scale: Vec3) {
+        self.scale = scale;
+        self.dirty = true;
+    }
+
+    /// Transform relative to the parent node
+    pub fn local_transform(&self) -> Mat4 {
+        Mat4::from_trs(self.translation, self.rotation, self.scale)
+    }
+}
+
+/// One mesh to draw, with everything needed to draw it
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct DrawCall {
+    pub node: usize,
+    pub mesh: usize,
+    pub material: usize,
+    pub program: usize,
+    pub model: Mat4,
+    pub render_state: RenderState,
+    pub distance: f32,  // from the camera to the middle of the mesh's bounds
+}
+
+/// What a traversal of the scene decided to draw, in the order to draw it
+#[derive(Debug, Clone, Default)]
+pub struct DrawList {
+    pub calls: Vec<DrawCall>,
+    pub culled: usize,  // renderables skipped for being outside the view
+}
+
+#[derive(Default)]
+pub struct Scene {
+    nodes: Vec<Node>,
+    pub meshes: Vec<Mesh>,
+    pub materials: MaterialLibrary,
+}
+
+#[allow(dead_code)]
+impl Scene {
+    pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    /// Uploads `data`, returning the index to use in a `Renderable`
+    pub fn add_mesh_data(&mut self, data: &MeshData) -> usize {
+        self.add_mesh(data.upload())
+    }
+
+    pub fn add_mesh(&mut self, mesh: Mesh) -> usize {
+        self.meshes.push(mesh);
+        self.meshes.len() - 1
+    }
+
+    /// Adds an empty node with an identity transform.  A node with no parent is a root of the tree
+    pub fn add_node(&mut self, name: &str, parent: Option<usize>) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_string(),
+            parent,
+            children: Vec::new(),
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+            world: Mat4::identity(),
+            dirty: true,
+            visible: true,
+            renderable: None,
+            camera: None,
+            light: None,
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(index);
+        }
+        index
+    }
+
+    pub fn node(&self, index: usize) -> &Node {
+        &self.nodes[index]
+    }
+
+    pub fn node_mut(&mut self, index: usize) -> &mut Node {
+        &mut self.nodes[index]
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// First node called `name`
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    /// Moves `node` (and everything below it) under `parent`, or makes it a root if `parent` is `None`.  The node
+    /// keeps its local transform, so it will probably jump somewhere else in the world
+    pub fn set_parent(&mut self, node: usize, parent: Option<usize>) -> Result<(), String> {
+        if let Some(parent) = parent {
+            let mut ancestor = Some(parent);
+            while let Some(a) = ancestor {
+                if a == node {
+                    return Err(format!(
+                        "can't move '{}' under '{}', since that's part of its own subtree",
+                        self.nodes[node].name,
+                        self.nodes[parent].name,
+                    ));
+                }
+                ancestor = self.nodes[a].parent;
+            }
+        }
+
+        if let Some(old_parent) = self.nodes[node].parent {
+            self.nodes[old_parent].children.retain(|&child| child != node);
+        }
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(node);
+        }
+        self.nodes[node].parent = parent;
+        self.nodes[node].dirty = true;
+        Ok(())
+    }
+
+    /// Recomputes the world matrix of every node that's dirty or has a dirty ancestor, and returns how many that was
+    pub fn update_world_transforms(&mut self) -> usize {
+        let mut updated = 0;
+        let mut stack: Vec<(usize, Mat4, bool)> = (0..self.nodes.len())
+            .filter(|&i| self.nodes[i].parent.is_none())
+            .map(|i| (i, Mat4::identity(), false))
+            .collect();
+        while let Some((index, parent_world, parent_changed)) = stack.pop() {
+            let node = &mut self.nodes[index];
+            let changed = node.dirty || parent_changed;
+            if changed {
+                node.world = parent_world * node.local_transform();
+                node.dirty = false;
+                updated += 1;
+            }
+            let world = node.world;
+            for &child in &self.nodes[index].children {
+                stack.push((child, world, changed));
+            }
+        }
+        updated
+    }
+
+    /// The node's transform relative to the world, as of the last `update_world_transforms()`
+    pub fn world_transform(&self, node: usize) -> Mat4 {
+        self.nodes[node].world
+    }
+
+    /// View and projection matrices for looking through the camera at `node`, or `None` if there isn't one there
+    pub fn camera_matrices(&self, node: usize, aspect: f32) -> Option<(Mat4, Mat4)> {
+        let camera = self.nodes[node].camera?;
+        let view = self.nodes[node].world.inverse()?;
+        Some((view, camera.projection_matrix(aspect)))
+    }
+
+    /// Every light in the scene, placed in the world
+    pub fn lights(&self) -> Vec<WorldLight> {
+        self.nodes.iter().enumerate()
+            .filter_map(|(index, node)| {
+                let light = node.light?;
+                Some(WorldLight {
+                    node: index,
+                    light,
+                    position: node.world.transform_point(Vec3::ZERO),
+                    direction: node.world.transform_vector(-Vec3::Z).normalize(),
+                })
+            })
+            .collect()
+    }
+
+    /// Walks the tree and works out what needs drawing from a camera with the given `view_projection` matrix,
+    /// sitting at `eye`.  Anything whose bounds are outside the camera's view gets culled.
+    ///
+    /// Opaque things come first, grouped by program, material and mesh so that there's less switching between
+    /// them, then anything with blending turned on, furthest first so that it blends over what's behind it
+    pub fn collect_draw_calls(&mut self, view_projection: &Mat4, eye: Vec3) -> DrawList {
+        self.update_world_transforms();
+        let frustum = Frustum::from_matrix(view_projection);
+
+        let mut list = DrawList::default();
+        let mut stack: Vec<usize> = (0..self.nodes.len()).filter(|&i| self.nodes[i].parent.is_none()).collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.visible {
+                continue;
+            }
+            stack.extend_from_slice(&node.children);
+
+            let renderable = match &node.renderable {
+                Some(renderable) => renderable,
+                None => continue,
+            };
+            let bounds = renderable.bounds.transformed(&node.world);
+            if !frustum.intersects_aabb(&bounds) {
+                list.culled += 1;
+                continue;
+            }
+            let material = &self.materials.materials[renderable.material];
+            list.calls.push(DrawCall {
+                node: index,
+                mesh: renderable.mesh,
+                material: renderable.material,
+                program: material.program,
+                model: node.world,
+                render_state: material.render_state,
+                distance: (bounds.center() - eye).length(),
+            });
+        }
+
+        list.calls.sort_by(|a, b| {
+            let (a_blended, b_blended) = (a.render_state.blend.enabled, b.render_state.blend.enabled);
+            a_blended.cmp(&b_blended).then_with(|| {
+                if a_blended {
+                    b.distance.partial_cmp(&a.distance).unwrap_or(std::cmp::Ordering::Equal)
+                } else {
+                    (a.program, a.material, a.mesh).cmp(&(b.program, b.material, b.mesh))
+                }
+            })
+        });
+        list
+    }
+
+    /// Draws everything in `list`.  A program gets `View` and `Projection` set whenever it's switched to, a material
+    /// gets its parameters set whenever it's switched to, and each draw sets `Model`
+    pub fn draw(&self, list: &DrawList, cache: &mut RenderStateCache, view: &Mat4, projection: &Mat4) {
+        let (mut current_program, mut current_material) = (None, None);
+        for call in &list.calls {
+            let program = &self.materials.programs[call.program];
+            if current_material != Some(call.material) {
+                self.materials.bind(call.material);
+                current_material = Some(call.material);
+            }
+            if current_program != Some(call.program) {
+                program.set_uniform_mat4("View", view);
+                program.set_uniform_mat4("Projection", projection);
+                current_program = Some(call.program);
+            }
+            cache.apply(&call.render_state);
+            program.set_uniform_mat4("Model", &call.model);
+            self.meshes[call.mesh].draw();
+        }
+    }
+}
@@ -0,0 +1,22 @@
+#version 330 core
+
+// Text from a signed distance field atlas.  Each texel's alpha is how far it is from the glyph's outline, with 0.5 right
+// on the outline, so the edge is wherever the blended distance crosses 0.5.  `fwidth()` says how much the distance
+// changes from one pixel to the next, which makes the edge about a pixel soft however big the text is drawn
+
+in VS_OUTPUT {
+    vec2 TexCoord;
+    vec4 Color;
+} IN;
+
+uniform sampler2D SpriteTexture;
+
+out vec4 Color;
+
+void main()
+{
+    float distance = texture(SpriteTexture, IN.TexCoord).a;
+    float softness = max(fwidth(distance) * 0.7, 1e-4);
+    float coverage = smoothstep(0.5 - softness, 0.5 + softness, distance);
+    Color = vec4(IN.Color.rgb, IN.Color.a * coverage);
+}
@@ -0,0 +1,436 @@
+// Shadow maps: to find out whether a point can see a light, first draw the scene's depth from the light's point of
+// view, then compare each point's distance from the light with what the light "saw" in that direction.  Anything
+// further away than the nearest thing the light saw is behind something, so it's in shadow.
+//
+// Whether a light casts shadows, and how, is part of its definition (`Light::shadow`), and a `ShadowRenderer` takes
+// the scene's lights each frame and draws whatever maps they need:
+//
+// * Spot lights get one map, drawn with a perspective projection that covers their cone.
+// * Directional lights light everything, and one map covering the whole view would spread its texels far too thin.
+//   So the view gets cut into slices by distance ("cascades"), each with its own map: the slices near the camera are
+//   small and get lots of detail, and the ones further away cover more ground with less.
+// * Point lights shine every which way, so they get a cubemap, drawn one face at a time.
+//
+// The spot and directional maps are layers of one depth texture array, and point lights get a cubemap each, so there's
+// room for `MAX_SHADOW_MAPS` layers and `MAX_POINT_SHADOWS` cubemaps.  Lights past those limits don't cast shadows.
+// `LightBuffer::update_with_shadows()` tells the shaders which maps belong to which light, and lights.glsl looks them
+// up.
+
+use std::ffi::CString;
+
+use crate::camera::Camera;
+use crate::framebuffer::{self, Framebuffer, FullscreenTriangle};
+use crate::math::{self, Mat4, Vec3};
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{CompareFunc, DepthBias, DepthState, RenderState, RenderStateCache};
+use crate::scene::{LightKind, Scene, WorldLight};
+use crate::texture::{Cubemap, TextureArray};
+
+/// Layers in the array that spot and directional light maps share.  Has to match lights.glsl
+pub const MAX_SHADOW_MAPS: usize = 8;
+
+/// How many point lights can cast shadows at once.  Has to match lights.glsl
+pub const MAX_POINT_SHADOWS: usize = 4;
+
+/// The most slices a directional light's shadows can be cut into
+pub const MAX_CASCADES: usize = 4;
+
+/// Texture unit the shadow map array is bound to
+pub const SHADOW_MAPS_UNIT: u32 = 8;
+
+/// Texture unit the first point light cubemap is bound to; the rest follow on from it
+pub const POINT_SHADOW_MAPS_UNIT: u32 = 9;
+
+/// How close to a spot or point light something can get and still cast a shadow
+const NEAR_PLANE: f32 = 0.05;
+
+/// Which way each face of a cubemap looks, and which way is up on it, in GL's face order
+const CUBE_FACES: [(Vec3, Vec3); 6] = [
+    (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+    (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+    (Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+    (Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 0.0, -1.0)),
+    (Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, -1.0, 0.0)),
+    (Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, -1.0, 0.0)),
+];
+
+/// How a light's shadows get drawn and filtered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    pub bias: DepthBias,     // pushes shadow casters away from the light, so that surfaces don't shadow themselves
+    pub normal_offset: f32,  // world units to look shadows up from above the surface, for surfaces facing away
+    pub filter_radius: u32,  // 0 for hard edges; each step adds a ring of texels to average over
+    pub cascades: u32,       // directional lights only: how many slices to cut the view into, up to `MAX_CASCADES`
+    pub distance: f32,       // directional lights only: how far from the camera shadows reach
+    pub split_lambda: f32,   // directional lights only: 0 makes every slice equally deep, 1 makes each a multiple of
+                             // the last, which keeps the texels about the same size on screen
+}
+
+impl Default for ShadowSettings {
+    fn default() -> ShadowSettings {
+        ShadowSettings {
+            bias: DepthBias { constant: 2.0, slope: 2.5 },
+            normal_offset: 0.02,
+            filter_radius: 1,
+            cascades: 4,
+            distance: 40.0,
+            split_lambda: 0.75,
+        }
+    }
+}
+
+/// Where one light's shadows ended up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightShadow {
+    pub kind: LightKind,
+    pub first_map: usize,  // the first layer of `ShadowRenderer::maps()`, or for point lights which `point_maps()`
+    pub map_count: usize,  // one per cascade for directional lights, otherwise 1
+    pub range: [f32; 4],   // directional lights: where each cascade ends.  Others: the near and far planes
+    pub settings: ShadowSettings,
+}
+
+/// Owns the shadow maps, and draws them from the lights' definitions
+pub struct ShadowRenderer {
+    maps: TextureArray,
+    point_maps: Vec<Cubemap>,
+    framebuffer: Framebuffer,
+    program: Program,
+    shadows: Vec<Option<LightShadow>>,
+    matrices: Vec<Mat4>,
+    point_maps_used: usize,
+    casters_drawn: usize,
+}
+
+#[allow(dead_code)]
+impl ShadowRenderer {
+    /// `resolution` is the width and height of each spot and directional light map, and `point_resolution` of each
+    /// face of the point lights' cubemaps
+    pub fn new(resolution: u32, point_resolution: u32) -> Result<ShadowRenderer, String> {
+        let vert_shader = Shader::from_vert_source(&CString::new(include_str!("shadow_depth.vert")).unwrap())?;
+        let frag_shader = Shader::from_frag_source(&CString::new(include_str!("shadow_depth.frag")).unwrap())?;
+        Ok(ShadowRenderer {
+            maps: TextureArray::depth(resolution, resolution, MAX_SHADOW_MAPS as u32),
+            point_maps: (0..MAX_POINT_SHADOWS).map(|_| Cubemap::depth(point_resolution)).collect(),
+            framebuffer: Framebuffer::empty(),
+            program: Program::from_shaders(&[vert_shader, frag_shader])?,
+            shadows: Vec::new(),
+            matrices: Vec::new(),
+            point_maps_used: 0,
+            casters_drawn: 0,
+        })
+    }
+
+    /// Draws the maps for every light in `lights` (from `Scene::lights()`) that casts shadows, with directional
+    /// lights' cascades fitted to what `camera` can see.  Whatever framebuffer and viewport were in use before are put
+    /// back afterwards
+    pub fn render(
+        &mut self,
+        scene: &mut Scene,
+        lights: &[WorldLight],
+        camera: &Camera,
+        cache: &mut RenderStateCache,
+    ) -> Result<(), String> {
+        let mut previous_framebuffer: gl::types::GLint = 0;
+        let mut viewport = [0 as gl::types::GLint; 4];
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+
+        self.matrices.clear();
+        self.point_maps_used = 0;
+        self.casters_drawn = 0;
+        self.program.set_used();
+        let shadows: Result<Vec<Option<LightShadow>>, String> =
+            lights.iter().map(|light| self.render_light(scene, light, camera, cache)).collect();
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_framebuffer as gl::types::GLuint);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        self.shadows = shadows?;
+        Ok(())
+    }
+
+    fn render_light(
+        &mut self,
+        scene: &mut Scene,
+        light: &WorldLight,
+        camera: &Camera,
+        cache: &mut RenderStateCache,
+    ) -> Result<Option<LightShadow>, String> {
+        let settings = match light.light.shadow {
+            Some(settings) => settings,
+            None => return Ok(None),
+        };
+        let kind = light.light.kind;
+        let far = light.light.range.max(NEAR_PLANE * 2.0);
+
+        match kind {
+            LightKind::Point => {
+                if self.point_maps_used == MAX_POINT_SHADOWS {
+                    return Ok(None);
+                }
+                let index = self.point_maps_used;
+                self.point_maps_used += 1;
+                let projection = Mat4::perspective(math::radians(90.0), 1.0, NEAR_PLANE, far);
+                for (face, &(direction, up)) in CUBE_FACES.iter().enumerate() {
+                    let view = Mat4::look_at(light.position, light.position + direction, up);
+                    self.framebuffer.attach_depth_cubemap_face(&self.point_maps[index], face as u32)?;
+                    self.draw_casters(scene, &(projection * view), light.position, settings.bias, cache);
+                }
+                let range = [NEAR_PLANE, far, 0.0, 0.0];
+                Ok(Some(LightShadow { kind, first_map: index, map_count: 1, range, settings }))
+            },
+            LightKind::Spot { outer_angle, .. } => {
+                if self.matrices.len() == MAX_SHADOW_MAPS {
+                    return Ok(None);
+                }
+                let fov = (outer_angle * 2.0).min(math::radians(170.0));
+                let projection = Mat4::perspective(fov, 1.0, NEAR_PLANE, far);
+                let view = Mat4::look_at(light.position, light.position + light.direction, up_for(light.direction));
+                let map = self.add_map(scene, projection * view, light.position, settings.bias, cache)?;
+                let range = [NEAR_PLANE, far, 0.0, 0.0];
+                Ok(Some(LightShadow { kind, first_map: map, map_count: 1, range, settings }))
+            },
+            LightKind::Directional => {
+                let free = MAX_SHADOW_MAPS - self.matrices.len();
+                let count = (settings.cascades as usize).clamp(1, MAX_CASCADES).min(free);
+                if count == 0 {
+                    return Ok(None);
+                }
+                let distance = settings.distance.min(camera.far);
+                let splits = cascade_splits(camera.near, distance, count, settings.split_lambda);
+                let first_map = self.matrices.len();
+                let mut range = [0.0; 4];
+                let mut near = camera.near;
+                for (i, &split) in splits.iter().enumerate() {
+                    let resolution = self.maps.width();
+                    let view_projection = cascade_matrix(camera, near, split, light.direction, resolution, distance);
+                    self.add_map(scene, view_projection, camera.position, settings.bias, cache)?;
+                    range[i] = split;
+                    near = split;
+                }
+                Ok(Some(LightShadow { kind, first_map, map_count: count, range, settings }))
+            },
+        }
+    }
+
+    /// Draws the next free layer of the array, and returns which one it was
+    fn add_map(
+        &mut self,
+        scene: &mut Scene,
+        view_projection: Mat4,
+        eye: Vec3,
+        bias: DepthBias,
+        cache: &mut RenderStateCache,
+    ) -> Result<usize, String> {
+        let layer = self.matrices.len();
+        self.framebuffer.attach_depth_layer(&self.maps, layer as u32)?;
+        self.draw_casters(scene, &view_projection, eye, bias, cache);
+        self.matrices.push(view_projection);
+        Ok(layer)
+    }
+
+    /// Clears whatever's attached to the framebuffer and draws the depth of every opaque shadow caster that
+    /// `view_projection` can see into it
+    fn draw_casters(
+        &mut self,
+        scene: &mut Scene,
+        view_projection: &Mat4,
+        eye: Vec3,
+        bias: DepthBias,
+        cache: &mut RenderStateCache,
+    ) {
+        let state = RenderState {
+            depth: DepthState { test: true, write: true, func: CompareFunc::Less },
+            color_mask: [false; 4],
+            depth_bias: Some(bias),
+            ..RenderState::default()
+        };
+        self.framebuffer.bind();
+        cache.apply(&state);  // depth writes have to be on for the clear to do anything
+        unsafe {
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+        self.program.set_uniform_mat4("LightViewProjection", view_projection);
+
+        let list = scene.collect_draw_calls(view_projection, eye);
+        for call in &list.calls {
+            let casts_shadows = matches!(&scene.node(call.node).renderable, Some(r) if r.casts_shadows);
+            if !casts_shadows || call.render_state.blend.enabled {
+                continue;
+            }
+            // Keep the material's culling, so that anything double-sided shadows from both sides
+            cache.apply(&RenderState {
+                cull_mode: call.render_state.cull_mode,
+                front_face: call.render_state.front_face,
+                ..state
+            });
+            self.program.set_uniform_mat4("Model", &call.model);
+            scene.meshes[call.mesh].draw();
+            self.casters_drawn += 1;
+        }
+    }
+
+    /// What `render()` did for each of the lights it was given, in the same order
+    pub fn shadows(&self) -> &[Option<LightShadow>] {
+        &self.shadows
+    }
+
+    /// World space to clip space for each layer of `maps()` that's in use
+    pub fn matrices(&self) -> &[Mat4] {
+        &self.matrices
+    }
+
+    pub fn maps(&self) -> &TextureArray {
+        &self.maps
+    }
+
+    pub fn point_maps(&self) -> &[Cubemap] {
+        &self.point_maps
+    }
+
+    /// How many meshes the last `render()` drew, over all of the maps
+    pub fn casters_drawn(&self) -> usize {
+        self.casters_drawn
+    }
+
+    /// Binds the maps to the texture units that `lighting::prepare_program()` points programs at
+    pub fn bind(&self) {
+        self.maps.bind(SHADOW_MAPS_UNIT);
+        for (i, cubemap) in self.point_maps.iter().enumerate() {
+            cubemap.bind(POINT_SHADOW_MAPS_UNIT + i as u32);
+        }
+    }
+}
+
+/// Any vector that isn't parallel to `direction`, for building a view that looks along it
+pub fn up_for(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y }
+}
+
+/// Where each cascade ends, between evenly spaced splits and logarithmic ones
+fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let t = i as f32 / count as f32;
+            let even = near + (far - near) * t;
+            let logarithmic = near * (far / near).powf(t);
+            even + (logarithmic - even) * lambda
+        })
+        .collect()
+}
+
+/// The view and projection for a directional light's map covering what `camera` sees between `near` and `far`.
+/// Anything up to `caster_distance` further towards the light still gets drawn into it, so it can cast shadows in.
+///
+/// The map covers a sphere around that slice of the view rather than the slice itself, so it doesn't change size as
+/// the camera turns, and it only ever moves by whole texels, so shadow edges don't crawl as the camera moves
+fn cascade_matrix(
+    camera: &Camera,
+    near: f32,
+    far: f32,
+    direction: Vec3,
+    resolution: u32,
+    caster_distance: f32,
+) -> Mat4 {
+    let to_world = camera.view_matrix().inverse().unwrap_or(Mat4::IDENTITY);
+    let tan_y = (camera.fov_y / 2.0).tan();
+    let tan_x = tan_y * camera.aspect;
+    let corners: Vec<Vec3> = [near, far].iter()
+        .flat_map(|&depth| {
+            [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].iter()
+                .map(move |&(x, y)| Vec3::new(x * tan_x * depth, y * tan_y * depth, -depth))
+        })
+        .map(|corner| to_world.transform_point(corner))
+        .collect();
+    let center = corners.iter().fold(Vec3::ZERO, |sum, &corner| sum + corner) * (1.0 / corners.len() as f32);
+    let radius = corners.iter().map(|&corner| (corner - center).length()).fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;  // so floating point wobble doesn't change the size every frame
+
+    // Snap the middle of the map to the texel grid, as seen looking down the light
+    let rotation = Mat4::look_at(Vec3::ZERO, direction, up_for(direction));
+    let texel = 2.0 * radius / resolution as f32;
+    let center_in_light = rotation.transform_point(center);
+    let snapped = Vec3::new(
+        (center_in_light.x / texel).floor() * texel,
+        (center_in_light.y / texel).floor() * texel,
+        center_in_light.z,
+    );
+    let center = rotation.transpose().transform_point(snapped);
+
+    let eye = center - direction * (radius + caster_distance);
+    let view = Mat4::look_at(eye, center, up_for(direction));
+    let projection = Mat4::orthographic(-radius, radius, -radius, radius, 0.0, 2.0 * radius + caster_distance);
+    projection * view
+}
+
+/// Draws the shadow maps in a row along the bottom of the window to show what the lights see: the spot light and
+/// cascade maps first, then each point light's cubemap unwrapped around the light.  Nearer is darker
+pub struct ShadowDebugView {
+    program: Program,
+    fullscreen: FullscreenTriangle,
+}
+
+#[allow(dead_code)]
+impl ShadowDebugView {
+    pub fn new() -> Result<ShadowDebugView, String> {
+        Ok(ShadowDebugView {
+            program: framebuffer::fullscreen_program(include_str!("shadow_debug.frag"))?,
+            fullscreen: FullscreenTriangle::new(),
+        })
+    }
+
+    /// Draws over whatever's in the window, which is `width` by `height`
+    pub fn draw(&self, shadows: &ShadowRenderer, width: u32, height: u32, cache: &mut RenderStateCache) {
+        let size = (height / 5).max(1) as i32;
+        let gap = 4;
+
+        cache.apply(&RenderState::default());
+        self.program.set_used();
+        self.program.set_uniform_i32("Maps", 0);
+        self.program.set_uniform_i32("PointMap", 1);
+        shadows.maps.bind(0);
+        shadows.maps.set_depth_compare(false);
+        for cubemap in &shadows.point_maps {
+            cubemap.set_depth_compare(false);
+        }
+
+        let mut x = gap;
+        for shadow in shadows.shadows.iter().flatten() {
+            let perspective = shadow.kind != LightKind::Directional;
+            self.program.set_uniform_i32("Perspective", perspective as i32);
+            self.program.set_uniform_f32("Near", shadow.range[0]);
+            self.program.set_uniform_f32("Far", shadow.range[1]);
+            if shadow.kind == LightKind::Point {
+                shadows.point_maps[shadow.first_map].bind(1);
+                self.program.set_uniform_i32("Layer", -1);
+                self.draw_thumbnail(x, gap, size * 2, size);
+                x += size * 2 + gap;
+            } else {
+                for layer in shadow.first_map..shadow.first_map + shadow.map_count {
+                    self.program.set_uniform_i32("Layer", layer as i32);
+                    self.draw_thumbnail(x, gap, size, size);
+                    x += size + gap;
+                }
+            }
+        }
+
+        shadows.maps.set_depth_compare(true);
+        for cubemap in &shadows.point_maps {
+            cubemap.set_depth_compare(true);
+        }
+        unsafe {
+            gl::Viewport(0, 0, width as gl::types::GLsizei, height as gl::types::GLsizei);
+        }
+    }
+
+    fn draw_thumbnail(&self, x: i32, y: i32, width: i32, height: i32) {
+        unsafe {
+            gl::Viewport(x, y, width, height);
+        }
+        self.fullscreen.draw();
+    }
+}
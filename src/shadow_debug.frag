@@ -0,0 +1,37 @@
+#version 330 core
+
+// Shows one shadow map as shades of gray for `ShadowDebugView`, nearer being darker.  Perspective maps store depth
+// unevenly (most of the precision goes close to the light), so those get turned back into distances first
+
+const float PI = 3.14159265359;
+
+in vec2 TexCoord;
+
+uniform sampler2DArray Maps;
+uniform samplerCube PointMap;
+uniform int Layer;  // which layer of `Maps` to show, or -1 to show all of `PointMap` unwrapped around the light
+uniform bool Perspective;
+uniform float Near;
+uniform float Far;
+
+out vec4 Color;
+
+void main()
+{
+    float depth;
+    if (Layer >= 0) {
+        depth = texture(Maps, vec3(TexCoord, float(Layer))).r;
+    } else {
+        // Longitude across and latitude up, like a map of the world
+        float longitude = (TexCoord.x * 2.0 - 1.0) * PI;
+        float latitude = (TexCoord.y - 0.5) * PI;
+        vec3 direction = vec3(cos(latitude) * sin(longitude), sin(latitude), -cos(latitude) * cos(longitude));
+        depth = texture(PointMap, direction).r;
+    }
+    if (Perspective) {
+        float z = depth * 2.0 - 1.0;
+        float distance = 2.0 * Near * Far / (Far + Near - z * (Far - Near));
+        depth = (distance - Near) / (Far - Near);
+    }
+    Color = vec4(vec3(depth), 1.0);
+}
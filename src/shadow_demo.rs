@@ -0,0 +1,277 @@
+use crate::bounds::Aabb;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::lighting::{self, LightBuffer};
+use crate::material::{MaterialLibrary, MaterialValue};
+use crate::math::{radians, Quat, Vec3};
+use crate::mesh::MeshData;
+use crate::render_state::RenderStateCache;
+use crate::scene::{Light, LightKind, Renderable, Scene};
+use crate::shadow::{ShadowDebugView, ShadowRenderer, ShadowSettings, MAX_CASCADES};
+use crate::shapes;
+
+const MAX_LIGHTS: usize = 4;
+const SHADOW_RESOLUTION: u32 = 2048;
+const POINT_SHADOW_RESOLUTION: u32 = 512;
+
+fn add_material(library: &mut MaterialLibrary, program: usize, name: &str, params: &[(&str, MaterialValue)]) -> usize {
+    let mut material = library.create_material(name, program);
+    for &(param, value) in params {
+        material.set(param, value);
+    }
+    library.add_material(material)
+}
+
+/// Changes the shadow settings of the light at `node`, if it has a light that casts shadows
+fn change_shadow<F: Fn(&mut ShadowSettings)>(scene: &mut Scene, node: usize, change: F) {
+    if let Some(light) = &mut scene.node_mut(node).light {
+        if let Some(shadow) = &mut light.shadow {
+            change(shadow);
+        }
+    }
+}
+
+/// A courtyard of pillars lit by the sun, a spot light and a point light circling the middle, all casting shadows.
+/// The sun's shadows are cascaded, which is easiest to see in the row of pillars running off into the distance.
+///
+/// The arrow keys turn the sun, and 1, 2 and 3 switch the sun's, spot light's and point light's shadows off and on.
+/// C changes how many cascades the sun uses, F how soft the shadow edges are, and [ and ] the slope-scaled bias.  D
+/// shows the shadow maps along the bottom of the window, Space pauses, and dragging orbits the camera
+pub fn shadow_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.45, 0.6, 0.8, 1.0);  // Sky blue
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let mut timer = _sdl.timer().unwrap();
+
+    let setup = || -> Result<_, String> {
+        Ok((
+            lighting::create_program(MAX_LIGHTS)?,
+            ShadowRenderer::new(SHADOW_RESOLUTION, POINT_SHADOW_RESOLUTION)?,
+            ShadowDebugView::new()?,
+        ))
+    };
+    let (lit_program, mut shadows, debug_view) = match setup() {
+        Ok(setup) => setup,
+        Err(e) => {
+            println!("Failed to set up shadows: {}", e);
+            return;
+        },
+    };
+    let light_buffer = LightBuffer::new(MAX_LIGHTS);
+
+    let mut scene = Scene::new();
+    let program = scene.materials.add_program(lit_program);
+    let solid = |scene: &mut Scene, name: &str, color: Vec3| {
+        add_material(&mut scene.materials, program, name, &[
+            ("DiffuseColor", MaterialValue::Vec3(color)),
+            ("SpecularColor", MaterialValue::Vec3(Vec3::splat(0.3))),
+        ])
+    };
+    let ground_material = solid(&mut scene, "ground", Vec3::new(0.6, 0.58, 0.5));
+    let stone_material = solid(&mut scene, "stone", Vec3::new(0.75, 0.72, 0.68));
+    let red_material = solid(&mut scene, "red", Vec3::new(0.8, 0.2, 0.15));
+    let blue_material = solid(&mut scene, "blue", Vec3::new(0.2, 0.35, 0.8));
+
+    let add_object = |scene: &mut Scene, name: &str, data: &MeshData, material: usize, position: Vec3| {
+        let mesh = scene.add_mesh_data(data);
+        let (min, max) = data.bounds();
+        let node = scene.add_node(name, None);
+        scene.node_mut(node).set_translation(position);
+        scene.node_mut(node).renderable = Some(Renderable::new(mesh, material, Aabb::new(min, max)));
+        node
+    };
+    add_object(&mut scene, "ground", &shapes::plane_grid(120.0, 120.0, 1, 1), ground_material, Vec3::ZERO);
+
+    // A ring of pillars around the middle, and a row of them heading off into the distance
+    let pillar = shapes::cylinder(0.35, 3.0, 24, 1);
+    for i in 0..8 {
+        let angle = i as f32 / 8.0 * std::f32::consts::PI * 2.0;
+        let position = Vec3::new(angle.cos() * 5.0, 1.5, angle.sin() * 5.0);
+        add_object(&mut scene, "pillar", &pillar, stone_material, position);
+    }
+    for i in 0..12 {
+        add_object(&mut scene, "pillar", &pillar, stone_material, Vec3::new(-3.0, 1.5, -8.0 - i as f32 * 5.0));
+    }
+    let torus = shapes::torus(1.0, 0.3, 48, 24);
+    let spinner = add_object(&mut scene, "torus", &torus, red_material, Vec3::new(0.0, 1.6, 0.0));
+    add_object(&mut scene, "sphere", &shapes::uv_sphere(0.6, 32, 16), blue_material, Vec3::new(2.2, 0.6, 1.5));
+    add_object(&mut scene, "cube", &shapes::cube(1.2, 1), blue_material, Vec3::new(-2.0, 0.6, 1.8));
+
+    let sun = scene.add_node("sun", None);
+    let sun_light = Light {
+        kind: LightKind::Directional,
+        color: Vec3::new(1.0, 0.95, 0.85),
+        intensity: 0.9,
+        range: 0.0,
+        shadow: Some(ShadowSettings::default()),
+    };
+    scene.node_mut(sun).light = Some(sun_light);
+    scene.node_mut(sun).set_rotation(
+        Quat::from_axis_angle(Vec3::Y, radians(35.0)) * Quat::from_axis_angle(Vec3::X, radians(-40.0)),
+    );
+
+    let spot = scene.add_node("spot light", None);
+    let spot_light = Light {
+        kind: LightKind::Spot { inner_angle: radians(20.0), outer_angle: radians(30.0) },
+        color: Vec3::new(1.0, 0.8, 0.5),
+        intensity: 40.0,
+        range: 20.0,
+        shadow: Some(ShadowSettings { filter_radius: 2, ..ShadowSettings::default() }),
+    };
+    scene.node_mut(spot).light = Some(spot_light);
+    scene.node_mut(spot).set_translation(Vec3::new(4.0, 6.0, 6.0));
+    scene.node_mut(spot).set_rotation(
+        Quat::from_axis_angle(Vec3::Y, radians(33.0)) * Quat::from_axis_angle(Vec3::X, radians(-45.0)),
+    );
+
+    // The point light carries a glowing ball around so you can see where it is, which mustn't shadow the light inside
+    let point = scene.add_node("point light", None);
+    let point_light = Light {
+        kind: LightKind::Point,
+        color: Vec3::new(0.4, 1.0, 0.5),
+        intensity: 12.0,
+        range: 12.0,
+        shadow: Some(ShadowSettings::default()),
+    };
+    scene.node_mut(point).light = Some(point_light);
+    let marker_material = add_material(&mut scene.materials, program, "marker", &[
+        ("DiffuseColor", MaterialValue::Vec3(Vec3::ZERO)),
+        ("SpecularColor", MaterialValue::Vec3(Vec3::ZERO)),
+        ("EmissiveColor", MaterialValue::Vec3(point_light.color)),
+    ]);
+    let marker_data = shapes::uv_sphere(0.1, 12, 6);
+    let marker_mesh = scene.add_mesh_data(&marker_data);
+    let marker = scene.add_node("marker", Some(point));
+    let (min, max) = marker_data.bounds();
+    scene.node_mut(marker).renderable = Some(Renderable {
+        casts_shadows: false,
+        ..Renderable::new(marker_mesh, marker_material, Aabb::new(min, max))
+    });
+    let lights = [(sun, sun_light), (spot, spot_light), (point, point_light)];
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 6.0, 14.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::new(0.0, 1.0, 0.0), 14.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut show_maps = false;
+    let mut playing = true;
+    let mut time = 0.0f32;
+    let mut last_ticks = timer.ticks();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(key @ Keycode::Num1), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(key @ Keycode::Num2), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(key @ Keycode::Num3), .. } => {
+                    let index = match key {
+                        Keycode::Num1 => 0,
+                        Keycode::Num2 => 1,
+                        _ => 2,
+                    };
+                    let (node, original) = lights[index];
+                    if let Some(light) = &mut scene.node_mut(node).light {
+                        light.shadow = if light.shadow.is_some() { None } else { original.shadow };
+                    }
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::C), .. } => {
+                    change_shadow(&mut scene, sun, |s| s.cascades = s.cascades % MAX_CASCADES as u32 + 1);
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::F), .. } => {
+                    for &(node, _) in &lights {
+                        change_shadow(&mut scene, node, |s| s.filter_radius = (s.filter_radius + 1) % 4);
+                    }
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::LeftBracket), .. } => {
+                    for &(node, _) in &lights {
+                        change_shadow(&mut scene, node, |s| s.bias.slope = (s.bias.slope - 0.5).max(0.0));
+                    }
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::RightBracket), .. } => {
+                    for &(node, _) in &lights {
+                        change_shadow(&mut scene, node, |s| s.bias.slope += 0.5);
+                    }
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::D), .. } => show_maps = !show_maps,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Space), .. } => playing = !playing,
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let ticks = timer.ticks();
+        let dt = (ticks - last_ticks) as f32 / 1000.0;
+        last_ticks = ticks;
+        if playing {
+            time += dt;
+        }
+
+        // Turn the sun while the arrow keys are held
+        {
+            use sdl2::keyboard::Scancode;
+            let keys = event_pump.keyboard_state();
+            let axis = |positive: Scancode, negative: Scancode| {
+                keys.is_scancode_pressed(positive) as i32 as f32 - keys.is_scancode_pressed(negative) as i32 as f32
+            };
+            let (yaw, pitch) = (axis(Scancode::Left, Scancode::Right), axis(Scancode::Up, Scancode::Down));
+            let node = scene.node_mut(sun);
+            let turn = Quat::from_axis_angle(Vec3::Y, yaw * dt) * node.rotation();
+            node.set_rotation(turn * Quat::from_axis_angle(Vec3::X, pitch * dt));
+        }
+        scene.node_mut(spinner).set_rotation(Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.0).normalize(), time));
+        scene.node_mut(point).set_translation(Vec3::new((time * 0.7).cos() * 3.0, 2.0, (time * 0.7).sin() * 3.0));
+
+        let (view, projection) = (camera.view_matrix(), camera.projection_matrix());
+        let draw_list = scene.collect_draw_calls(&(projection * view), camera.position);
+        let world_lights = scene.lights();
+        if let Err(e) = shadows.render(&mut scene, &world_lights, &camera, &mut render_state_cache) {
+            println!("Failed to draw the shadow maps: {}", e);
+            break 'main;
+        }
+        light_buffer.update_with_shadows(Vec3::splat(0.15), &world_lights, &shadows);
+        shadows.bind();
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        scene.draw(&draw_list, &mut render_state_cache, &view, &projection);
+        let (width, height) = window.size();
+        if show_maps {
+            debug_view.draw(&shadows, width, height, &mut render_state_cache);
+        }
+
+        let sun_settings = scene.node(sun).light.and_then(|light| light.shadow).unwrap_or_default();
+        window.set_title(&format!(
+            "{} cascades, filter radius {}, slope bias {:.1}, {} shadow casters drawn",
+            sun_settings.cascades,
+            sun_settings.filter_radius,
+            sun_settings.bias.slope,
+            shadows.casters_drawn(),
+        )).unwrap();
+        window.gl_swap_window();
+    }
+}
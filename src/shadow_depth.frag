@@ -0,0 +1,8 @@
+#version 330 core
+
+// Shadow maps have no color to write, and the depth gets written without any help, so there's nothing to do here.
+// (GL 3.3 core technically allows leaving the fragment shader out altogether, but not every driver likes that)
+
+void main()
+{
+}
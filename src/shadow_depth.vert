@@ -0,0 +1,14 @@
+#version 330 core
+
+// Draws shadow casters from a light's point of view for `ShadowRenderer`.  Only the depth matters, so there's nothing
+// else to pass on
+
+layout (location = 0) in vec3 Position;
+
+uniform mat4 Model;
+uniform mat4 LightViewProjection;
+
+void main()
+{
+    gl_Position = LightViewProjection * Model * vec4(Position, 1.0);
+}
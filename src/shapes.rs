@@ -0,0 +1,382 @@
+// Generates meshes for common shapes, so there's something better than a triangle to test lighting and texturing on.
+// Everything comes out as `MeshData` with positions, normals, texture coordinates and tangents filled in, centered on
+// the origin, with counter-clockwise front faces.  Texture coordinates follow GL's convention of v going up.
+//
+// Most of these are built the same way: pick a function that maps a grid of (i, j) steps onto the surface, then join
+// neighbouring grid points up into triangles.  Grid points along a seam (like where a sphere's texture wraps around)
+// are duplicated, since they need different texture coordinates on either side.
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::math::{Vec2, Vec3};
+use crate::mesh::MeshData;
+use crate::vertex::Vertex;
+
+/// Builds a `(u_segments + 1) x (v_segments + 1)` grid of vertices with `vertex(i, j)`, and triangulates it.  `u`
+/// should increase to the right and `v` upwards when looking at the front of the surface.  Triangles with no area
+/// (like the ones that meet at a sphere's poles) are left out
+fn surface<F>(data: &mut MeshData, u_segments: u32, v_segments: u32, mut vertex: F)
+    where F: FnMut(u32, u32) -> Vertex
+{
+    let first = data.vertices.len() as u32;
+    for j in 0..=v_segments {
+        for i in 0..=u_segments {
+            data.vertices.push(vertex(i, j));
+        }
+    }
+
+    let row = u_segments + 1;
+    for j in 0..v_segments {
+        for i in 0..u_segments {
+            let a = first + j * row + i;       // bottom left
+            let b = a + 1;                     // bottom right
+            let c = a + row + 1;               // top right
+            let d = a + row;                   // top left
+            for &triangle in &[[a, b, c], [a, c, d]] {
+                let p = |index: u32| data.vertices[index as usize].position;
+                let area = (p(triangle[1]) - p(triangle[0])).cross(p(triangle[2]) - p(triangle[0])).length();
+                if area > 1e-10 {
+                    data.indices.extend_from_slice(&triangle);
+                }
+            }
+        }
+    }
+}
+
+/// A flat rectangle starting at `origin` and spanning `u_axis` and `v_axis` (whose cross product is the way it faces)
+fn flat_grid(data: &mut MeshData, origin: Vec3, u_axis: Vec3, v_axis: Vec3, u_segments: u32, v_segments: u32) {
+    let normal = u_axis.cross(v_axis).normalize();
+    surface(data, u_segments, v_segments, |i, j| {
+        let (u, v) = (i as f32 / u_segments as f32, j as f32 / v_segments as f32);
+        Vertex::new(origin + u_axis * u + v_axis * v, normal, Vec2::new(u, v))
+    });
+}
+
+/// A flat disc facing straight up or down, as a fan of triangles around its center
+fn disc(data: &mut MeshData, center: Vec3, radius: f32, segments: u32, facing_up: bool) {
+    let normal = if facing_up { Vec3::Y } else { -Vec3::Y };
+    let first = data.vertices.len() as u32;
+    data.vertices.push(Vertex::new(center, normal, Vec2::new(0.5, 0.5)));
+    for s in 0..=segments {
+        let angle = 2.0 * PI * s as f32 / segments as f32;
+        let (x, z) = (angle.sin(), angle.cos());
+        // Map the disc onto the texture as seen from the side it faces
+        let v = if facing_up { 0.5 - z * 0.5 } else { 0.5 + z * 0.5 };
+        let uv = Vec2::new(0.5 + x * 0.5, v);
+        data.vertices.push(Vertex::new(center + Vec3::new(x, 0.0, z) * radius, normal, uv));
+    }
+    for s in 0..segments {
+        let (a, b) = (first + 1 + s, first + 2 + s);
+        if facing_up {
+            data.indices.extend_from_slice(&[first, a, b]);
+        } else {
+            data.indices.extend_from_slice(&[first, b, a]);
+        }
+    }
+}
+
+/// Direction pointing away from the Y axis at `angle` radians around it, starting at +Z and heading towards +X
+fn around_y(angle: f32) -> Vec3 {
+    Vec3::new(angle.sin(), 0.0, angle.cos())
+}
+
+/// Drops any vertices that no triangle uses (`surface()` leaves a few behind at the poles and points, where it skips
+/// triangles), then works out the tangents
+fn finish(data: MeshData) -> MeshData {
+    let mut new_index = vec![u32::MAX; data.vertices.len()];
+    let mut compacted = MeshData::new();
+    for &index in &data.indices {
+        if new_index[index as usize] == u32::MAX {
+            new_index[index as usize] = compacted.vertices.len() as u32;
+            compacted.vertices.push(data.vertices[index as usize]);
+        }
+        compacted.indices.push(new_index[index as usize]);
+    }
+    compacted.compute_tangents();
+    compacted
+}
+
+/// A `width` by `height` rectangle in the XY plane, facing +Z
+#[allow(dead_code)]
+pub fn quad(width: f32, height: f32) -> MeshData {
+    let mut data = MeshData::new();
+    flat_grid(&mut data, Vec3::new(-width / 2.0, -height / 2.0, 0.0), Vec3::X * width, Vec3::Y * height, 1, 1);
+    finish(data)
+}
+
+/// A `width` by `depth` grid in the XZ plane, facing +Y, split into `x_segments` by `z_segments` squares.  Good as a
+/// floor, or for anything that wants lots of vertices to move around (like terrain)
+#[allow(dead_code)]
+pub fn plane_grid(width: f32, depth: f32, x_segments: u32, z_segments: u32) -> MeshData {
+    let mut data = MeshData::new();
+    let origin = Vec3::new(-width / 2.0, 0.0, depth / 2.0);
+    flat_grid(&mut data, origin, Vec3::X * width, -Vec3::Z * depth, x_segments.max(1), z_segments.max(1));
+    finish(data)
+}
+
+/// A cube with sides of `size`, each face split into `subdivisions` by `subdivisions` squares.  Every face gets the
+/// whole texture
+#[allow(dead_code)]
+pub fn cube(size: f32, subdivisions: u32) -> MeshData {
+    let half = size / 2.0;
+    let subdivisions = subdivisions.max(1);
+    // (normal, u axis, v axis) for each face, with u x v = normal so the faces wind the right way round
+    let faces = [
+        (Vec3::X, -Vec3::Z, Vec3::Y),
+        (-Vec3::X, Vec3::Z, Vec3::Y),
+        (Vec3::Y, Vec3::X, -Vec3::Z),
+        (-Vec3::Y, Vec3::X, Vec3::Z),
+        (Vec3::Z, Vec3::X, Vec3::Y),
+        (-Vec3::Z, -Vec3::X, Vec3::Y),
+    ];
+    let mut data = MeshData::new();
+    for &(normal, u, v) in &faces {
+        let origin = (normal - u - v) * half;
+        flat_grid(&mut data, origin, u * size, v * size, subdivisions, subdivisions);
+    }
+    finish(data)
+}
+
+/// The usual globe-style sphere: `segments` slices around the Y axis and `rings` bands from pole to pole.  The
+/// triangles bunch up at the poles; see `icosphere()` for one that's more even
+#[allow(dead_code)]
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    let mut data = MeshData::new();
+    surface(&mut data, segments, rings, |i, j| {
+        let (u, v) = (i as f32 / segments as f32, j as f32 / rings as f32);
+        let polar = PI * (1.0 - v);  // angle down from the north pole
+        // sin(PI) isn't quite 0 in floating point, so put the poles exactly on the axis for the triangles that meet
+        // there to come out with no area
+        let distance_from_axis = if j == 0 || j == rings { 0.0 } else { polar.sin() };
+        let normal = around_y(2.0 * PI * u) * distance_from_axis + Vec3::Y * polar.cos();
+        Vertex::new(normal * radius, normal, Vec2::new(u, v))
+    });
+    finish(data)
+}
+
+/// A sphere made by repeatedly splitting the triangles of an icosahedron into four and pushing the new corners out onto
+/// the sphere.  The triangles all end up close to the same size, unlike a UV sphere's.  Each subdivision multiplies
+/// the triangle count by four, starting from 20
+#[allow(dead_code)]
+pub fn icosphere(radius: f32, subdivisions: u32) -> MeshData {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;  // the golden ratio
+    let mut positions: Vec<Vec3> = [
+        (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+        (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+        (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+    ].iter().map(|&(x, y, z)| Vec3::new(x, y, z).normalize()).collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions.min(8) {
+        // Neighbouring triangles share edges, so remember each edge's midpoint to avoid making it twice
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, positions: &mut Vec<Vec3>| -> u32 {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                positions.push(((positions[a as usize] + positions[b as usize]) * 0.5).normalize());
+                positions.len() as u32 - 1
+            })
+        };
+        let mut split = Vec::with_capacity(triangles.len() * 4);
+        for &[a, b, c] in &triangles {
+            let ab = midpoint(a, b, &mut positions);
+            let bc = midpoint(b, c, &mut positions);
+            let ca = midpoint(c, a, &mut positions);
+            split.extend_from_slice(&[[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+        }
+        triangles = split;
+    }
+
+    // Wrap the texture around it like a UV sphere's.  Triangles that straddle the seam at the back need their own
+    // copies of the corners on the far side, with u past 1, and corners sitting right on a pole (where u means
+    // nothing) take the u of the rest of their triangle
+    let spherical_uv = |p: Vec3| Vec2::new(0.5 + p.x.atan2(p.z) / (2.0 * PI), 0.5 + p.y.asin() / PI);
+    let mut data = MeshData::new();
+    let mut made: HashMap<(u32, u32, u32), u32> = HashMap::new();
+    for triangle in &triangles {
+        let mut uvs: Vec<Vec2> = triangle.iter().map(|&i| spherical_uv(positions[i as usize])).collect();
+        let max_u = uvs.iter().map(|uv| uv.x).fold(0.0, f32::max);
+        for uv in &mut uvs {
+            if max_u - uv.x > 0.5 {
+                uv.x += 1.0;
+            }
+        }
+        for corner in 0..3 {
+            let p = positions[triangle[corner] as usize];
+            if p.x.abs() < 1e-6 && p.z.abs() < 1e-6 {
+                uvs[corner].x = (uvs[(corner + 1) % 3].x + uvs[(corner + 2) % 3].x) / 2.0;
+            }
+        }
+        for (corner, &index) in triangle.iter().enumerate() {
+            let uv = uvs[corner];
+            let vertex = *made.entry((index, uv.x.to_bits(), uv.y.to_bits())).or_insert_with(|| {
+                let p = positions[index as usize];
+                data.vertices.push(Vertex::new(p * radius, p, uv));
+                data.vertices.len() as u32 - 1
+            });
+            data.indices.push(vertex);
+        }
+    }
+    finish(data)
+}
+
+/// A cylinder along the Y axis, closed at both ends
+#[allow(dead_code)]
+pub fn cylinder(radius: f32, height: f32, segments: u32, height_segments: u32) -> MeshData {
+    frustum(radius, radius, height, segments, height_segments)
+}
+
+/// A cone along the Y axis with its point at the top, closed at the bottom
+#[allow(dead_code)]
+pub fn cone(radius: f32, height: f32, segments: u32, height_segments: u32) -> MeshData {
+    frustum(radius, 0.0, height, segments, height_segments)
+}
+
+/// A cylinder whose top and bottom can be different sizes, which covers cylinders and cones (and everything in
+/// between).  Ends with a radius of zero don't get a cap
+#[allow(dead_code)]
+pub fn frustum(bottom_radius: f32, top_radius: f32, height: f32, segments: u32, height_segments: u32) -> MeshData {
+    let (segments, height_segments) = (segments.max(3), height_segments.max(1));
+    let mut data = MeshData::new();
+    surface(&mut data, segments, height_segments, |i, j| {
+        let (u, v) = (i as f32 / segments as f32, j as f32 / height_segments as f32);
+        let out = around_y(2.0 * PI * u);
+        let radius = bottom_radius + (top_radius - bottom_radius) * v;
+        // The side slopes inwards by (bottom - top) over `height`, so the normal tips upwards by the same amount
+        let normal = (out * height + Vec3::Y * (bottom_radius - top_radius)).normalize();
+        Vertex::new(out * radius + Vec3::Y * (v - 0.5) * height, normal, Vec2::new(u, v))
+    });
+    if bottom_radius > 0.0 {
+        disc(&mut data, Vec3::Y * (-height / 2.0), bottom_radius, segments, false);
+    }
+    if top_radius > 0.0 {
+        disc(&mut data, Vec3::Y * (height / 2.0), top_radius, segments, true);
+    }
+    finish(data)
+}
+
+/// A doughnut lying flat around the Y axis.  `major_radius` is from the center to the middle of the tube, and
+/// `minor_radius` is the tube's own radius
+#[allow(dead_code)]
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> MeshData {
+    let (major_segments, minor_segments) = (major_segments.max(3), minor_segments.max(3));
+    let mut data = MeshData::new();
+    surface(&mut data, major_segments, minor_segments, |i, j| {
+        let (u, v) = (i as f32 / major_segments as f32, j as f32 / minor_segments as f32);
+        let out = around_y(2.0 * PI * u);
+        let tube_angle = 2.0 * PI * v;  // starts on the outside of the ring and goes over the top
+        let normal = out * tube_angle.cos() + Vec3::Y * tube_angle.sin();
+        Vertex::new(out * major_radius + normal * minor_radius, normal, Vec2::new(u, v))
+    });
+    finish(data)
+}
+
+/// A cylinder with a hemisphere on each end, like a pill.  `height` is just the straight middle part, so the whole
+/// thing is `height + 2 * radius` tall.  `rings` is the number of bands in each hemisphere
+#[allow(dead_code)]
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+
+    // The outline of one side, from the bottom pole to the top one: (distance from the Y axis, height, normal's
+    // elevation angle).  The two hemispheres' equators are joined by the straight part
+    let mut profile: Vec<(f32, f32, f32)> = Vec::new();
+    for k in 0..=rings {
+        let angle = -PI / 2.0 + (PI / 2.0) * k as f32 / rings as f32;
+        let distance_from_axis = if k == 0 { 0.0 } else { radius * angle.cos() };  // exactly on the axis at the pole
+        profile.push((distance_from_axis, -height / 2.0 + radius * angle.sin(), angle));
+    }
+    for k in 0..=rings {
+        let angle = (PI / 2.0) * k as f32 / rings as f32;
+        let distance_from_axis = if k == rings { 0.0 } else { radius * angle.cos() };
+        profile.push((distance_from_axis, height / 2.0 + radius * angle.sin(), angle));
+    }
+
+    // Space v out by distance along the outline, so the texture doesn't get stretched along the straight part
+    let mut distances = vec![0.0f32];
+    for pair in profile.windows(2) {
+        let step = Vec2::new(pair[1].0 - pair[0].0, pair[1].1 - pair[0].1).length();
+        distances.push(distances.last().unwrap() + step);
+    }
+    let total = *distances.last().unwrap();
+
+    let mut data = MeshData::new();
+    surface(&mut data, segments, profile.len() as u32 - 1, |i, j| {
+        let u = i as f32 / segments as f32;
+        let (distance_from_axis, y, elevation) = profile[j as usize];
+        let out = around_y(2.0 * PI * u);
+        let normal = out * elevation.cos() + Vec3::Y * elevation.sin();
+        Vertex::new(out * distance_from_axis + Vec3::Y * y, normal, Vec2::new(u, distances[j as usize] / total))
+    });
+    finish(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Volume enclosed by the triangles, which only comes out right (and positive) if the shape is closed and every
+    /// triangle winds counter-clockwise seen from outside
+    fn volume(data: &MeshData) -> f32 {
+        data.indices.chunks(3).map(|t| {
+            let p = |corner: usize| data.vertices[t[corner] as usize].position;
+            p(0).dot(p(1).cross(p(2))) / 6.0
+        }).sum()
+    }
+
+    fn check(name: &str, data: &MeshData) {
+        assert!(data.triangle_count() > 0, "{}", name);
+        assert!(data.indices.iter().all(|&i| (i as usize) < data.vertices.len()), "{}", name);
+        for t in data.indices.chunks(3) {
+            let v = |corner: usize| data.vertices[t[corner] as usize];
+            let facing = (v(1).position - v(0).position).cross(v(2).position - v(0).position);
+            for corner in 0..3 {
+                let vertex = v(corner);
+                assert!((vertex.normal.length() - 1.0).abs() < 1e-4, "{}: {:?}", name, vertex);
+                assert!(vertex.normal.dot(facing) > 0.0, "{}: triangle {:?} winds the wrong way", name, t);
+                assert!(vertex.tangent.dot(vertex.normal).abs() < 1e-3, "{}: {:?}", name, vertex);
+                assert!((0.0..=1.0).contains(&vertex.tex_coord.y), "{}: {:?}", name, vertex);
+            }
+        }
+    }
+
+    #[test]
+    fn flat_shapes() {
+        let quad = quad(2.0, 1.0);
+        check("quad", &quad);
+        assert_eq!((quad.vertices.len(), quad.triangle_count()), (4, 2));
+        assert!(quad.vertices.iter().all(|v| v.normal == Vec3::Z && v.tangent.dot(Vec3::X) > 0.99));
+
+        let grid = plane_grid(4.0, 2.0, 4, 3);
+        check("plane_grid", &grid);
+        assert_eq!((grid.vertices.len(), grid.triangle_count()), (5 * 4, 4 * 3 * 2));
+        assert!(grid.vertices.iter().all(|v| v.normal == Vec3::Y));
+    }
+
+    #[test]
+    fn closed_shapes() {
+        let sphere_volume = 4.0 / 3.0 * PI;
+        let shapes = [
+            ("cube", cube(2.0, 3), 8.0, 0.0001),
+            ("uv_sphere", uv_sphere(1.0, 64, 32), sphere_volume, 0.01),
+            ("icosphere", icosphere(1.0, 3), sphere_volume, 0.01),
+            ("cylinder", cylinder(1.0, 2.0, 64, 2), 2.0 * PI, 0.01),
+            ("cone", cone(1.0, 3.0, 64, 2), PI, 0.01),
+            ("torus", torus(2.0, 0.5, 64, 32), 2.0 * PI * PI * 2.0 * 0.25, 0.01),
+            ("capsule", capsule(0.5, 1.0, 64, 16), PI * 0.25 + sphere_volume * 0.125, 0.01),
+        ];
+        for (name, data, expected, tolerance) in &shapes {
+            check(name, data);
+            let error = (volume(data) - expected).abs() / expected;
+            assert!(error < *tolerance, "{}: volume {} rather than {}", name, volume(data), expected);
+        }
+
+        assert_eq!(icosphere(1.0, 2).triangle_count(), 20 * 4 * 4);
+        assert!(icosphere(3.0, 2).vertices.iter().all(|v| (v.position.length() - 3.0).abs() < 1e-4));
+    }
+}
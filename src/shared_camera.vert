@@ -0,0 +1,23 @@
+#version 330 core
+
+// Like mesh.vert, but the camera comes from the `Camera` block that `camera::with_camera_block()` pastes in above
+layout (location = 0) in vec3 Position;
+layout (location = 2) in vec3 Normal;
+layout (location = 3) in vec2 TexCoord;
+
+uniform mat4 Model;
+
+out VS_OUTPUT {
+    vec3 Normal;
+    vec2 TexCoord;
+} OUT;
+out vec3 WorldPosition;  // outside the block so fragment shaders that don't want it can leave it out
+
+void main()
+{
+    vec4 world_position = Model * vec4(Position, 1.0);
+    gl_Position = ViewProjection * world_position;
+    OUT.Normal = mat3(Model) * Normal;
+    OUT.TexCoord = TexCoord;
+    WorldPosition = world_position.xyz;
+}
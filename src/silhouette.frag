@@ -0,0 +1,10 @@
+#version 330 core
+
+uniform vec3 EdgeColor;
+
+out vec4 Color;
+
+void main()
+{
+    Color = vec4(EdgeColor, 1.0);
+}
@@ -0,0 +1,18 @@
+#version 330 core
+
+// Hands the geometry shader both where each corner is in the world (to tell which way triangles face) and where it
+// ends up on screen (to draw the edges)
+layout (location = 0) in vec3 Position;
+
+uniform mat4 Model;
+uniform mat4 View;
+uniform mat4 Projection;
+
+out vec3 WorldPosition;
+
+void main()
+{
+    vec4 world_position = Model * vec4(Position, 1.0);
+    WorldPosition = world_position.xyz;
+    gl_Position = Projection * View * world_position;
+}
@@ -0,0 +1,20 @@
+#version 330 core
+
+// Draws an environment cubemap behind everything, with `fullscreen.vert`.  Each pixel's direction comes from pushing
+// its screen position back through the camera, with the camera's position left out so the sky never gets any closer
+
+in vec2 TexCoord;
+
+uniform samplerCube Environment;
+uniform mat4 InverseViewProjection;  // with the view's translation removed
+uniform float Level;                 // mip level, for a blurry background
+uniform float Intensity;
+
+out vec4 Color;
+
+void main()
+{
+    vec4 far_point = InverseViewProjection * vec4(TexCoord * 2.0 - 1.0, 1.0, 1.0);
+    vec3 direction = normalize(far_point.xyz / far_point.w);
+    Color = vec4(textureLod(Environment, direction, Level).rgb * Intensity, 1.0);
+}
@@ -0,0 +1,200 @@
+use std::ffi::CString;
+
+use crate::render_gl;
+use crate::program;
+use crate::bounds::Aabb;
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::material::{MaterialLibrary, MaterialValue};
+use crate::math::{radians, Quat, Vec3};
+use crate::render_state::RenderStateCache;
+use crate::scene::{Light, LightKind, Renderable, Scene, SceneCamera};
+use crate::shapes;
+
+/// A flat-colored material for `mesh.frag`
+fn plain_material(library: &mut MaterialLibrary, program: usize, name: &str, color: Vec3) -> usize {
+    let mut material = library.create_material(name, program);
+    material.set("DiffuseColor", MaterialValue::Vec3(color));
+    library.add_material(material)
+}
+
+/// A sun, a planet orbiting it, a moon orbiting the planet and a belt of asteroids, all built out of a scene graph.
+/// Each orbit is just a spinning node with the orbiting thing as a child, so nothing has to work out where anything is
+/// by hand.  The window title shows how many things were drawn and how many were culled for being out of view.
+///
+/// Drag to orbit the camera, C to look from a camera riding along with the moon instead, B to hide the asteroid belt,
+/// and Space to pause
+pub fn solar_system() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.05, 0.05, 0.1, 1.0);  // Space is dark
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let mut timer = _sdl.timer().unwrap();
+
+    let vert_shader = render_gl::Shader::from_vert_source(
+        &CString::new(
+            include_str!("mesh.vert")
+        ).unwrap()
+    ).unwrap();
+
+    let frag_shader = render_gl::Shader::from_frag_source(
+        &CString::new(
+            include_str!("mesh.frag")
+        ).unwrap()
+    ).unwrap();
+
+    let shader_program = program::Program::from_shaders(
+        &[vert_shader, frag_shader]
+    ).unwrap();
+
+    let mut scene = Scene::new();
+    let program = scene.materials.add_program(shader_program);
+    let sun_material = plain_material(&mut scene.materials, program, "sun", Vec3::new(1.0, 0.8, 0.2));
+    let planet_material = plain_material(&mut scene.materials, program, "planet", Vec3::new(0.2, 0.4, 0.9));
+    let ring_material = plain_material(&mut scene.materials, program, "ring", Vec3::new(0.8, 0.7, 0.6));
+    let moon_material = plain_material(&mut scene.materials, program, "moon", Vec3::splat(0.7));
+    let rock_material = plain_material(&mut scene.materials, program, "rock", Vec3::new(0.5, 0.45, 0.4));
+    let sphere_data = shapes::uv_sphere(1.0, 32, 16);
+    let rock_data = shapes::icosphere(1.0, 1);
+    let ring_data = shapes::torus(1.0, 0.05, 48, 8);
+    let sphere = scene.add_mesh_data(&sphere_data);
+    let rock = scene.add_mesh_data(&rock_data);
+    let ring = scene.add_mesh_data(&ring_data);
+    let renderable = |mesh: usize, bounds: (Vec3, Vec3), material: usize| {
+        Some(Renderable::new(mesh, material, Aabb::new(bounds.0, bounds.1)))
+    };
+
+    let sun = scene.add_node("sun", None);
+    scene.node_mut(sun).set_scale(Vec3::splat(1.5));
+    scene.node_mut(sun).renderable = renderable(sphere, sphere_data.bounds(), sun_material);
+    scene.node_mut(sun).light = Some(Light {
+        kind: LightKind::Point,
+        color: Vec3::new(1.0, 0.9, 0.7),
+        intensity: 1.0,
+        range: 50.0,
+        shadow: None,
+    });
+
+    // An orbit is an empty node sitting at the middle of it, which we spin; anything that's a child of it and moved
+    // out from the middle gets carried round
+    let planet_orbit = scene.add_node("planet orbit", None);
+    let planet = scene.add_node("planet", Some(planet_orbit));
+    scene.node_mut(planet).set_translation(Vec3::new(6.0, 0.0, 0.0));
+    scene.node_mut(planet).set_scale(Vec3::splat(0.6));
+    scene.node_mut(planet).renderable = renderable(sphere, sphere_data.bounds(), planet_material);
+    let planet_ring = scene.add_node("planet ring", Some(planet));
+    scene.node_mut(planet_ring).set_rotation(Quat::from_axis_angle(Vec3::X, radians(70.0)));
+    scene.node_mut(planet_ring).set_scale(Vec3::splat(1.8));
+    scene.node_mut(planet_ring).renderable = renderable(ring, ring_data.bounds(), ring_material);
+
+    // The moon orbits the planet's position but shouldn't be squashed by the planet's scale, so its orbit hangs off
+    // the planet's orbit rather than the planet itself
+    let moon_orbit = scene.add_node("moon orbit", Some(planet_orbit));
+    scene.node_mut(moon_orbit).set_translation(Vec3::new(6.0, 0.0, 0.0));
+    let moon = scene.add_node("moon", Some(moon_orbit));
+    scene.node_mut(moon).set_translation(Vec3::new(1.8, 0.0, 0.0));
+    scene.node_mut(moon).set_scale(Vec3::splat(0.2));
+    scene.node_mut(moon).renderable = renderable(sphere, sphere_data.bounds(), moon_material);
+
+    // Cameras look down their -Z axis, so turn this one a quarter turn to face back towards the planet
+    let moon_camera = scene.add_node("moon camera", Some(moon_orbit));
+    scene.node_mut(moon_camera).set_translation(Vec3::new(2.4, 0.4, 0.0));
+    scene.node_mut(moon_camera).set_rotation(Quat::from_axis_angle(Vec3::Y, radians(90.0)));
+    scene.node_mut(moon_camera).camera = Some(SceneCamera { fov_y: radians(60.0), near: 0.1, far: 100.0 });
+
+    // Lots of small rocks, so there's plenty for culling to throw away.  They're scattered with a tiny hash rather
+    // than a random number generator so they land in the same places every run
+    let belt = scene.add_node("asteroid belt", None);
+    let hash = |n: u32| {
+        let n = n.wrapping_mul(0x9E37_79B9) ^ (n >> 15);
+        (n.wrapping_mul(0x85EB_CA6B) >> 8) as f32 / (1 << 24) as f32
+    };
+    for i in 0..2000 {
+        let angle = hash(i * 4) * std::f32::consts::PI * 2.0;
+        let distance = 10.0 + hash(i * 4 + 1) * 4.0;
+        let asteroid = scene.add_node("asteroid", Some(belt));
+        let node = scene.node_mut(asteroid);
+        node.set_translation(Vec3::new(angle.cos() * distance, (hash(i * 4 + 2) - 0.5) * 0.8, angle.sin() * distance));
+        node.set_rotation(Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.0).normalize(), angle * 7.0));
+        node.set_scale(Vec3::splat(0.05 + hash(i * 4 + 3) * 0.15));
+        node.renderable = renderable(rock, rock_data.bounds(), rock_material);
+    }
+
+    println!("Scene has {} nodes and {} light(s)", scene.nodes().len(), scene.lights().len());
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 8.0, 20.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 20.0);
+    controller.max_distance = 60.0;
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut use_moon_camera = false;
+    let mut playing = true;
+    let mut time = 0.0f32;
+    let mut last_ticks = timer.ticks();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::C), .. } => use_moon_camera = !use_moon_camera,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::B), .. } => {
+                    scene.node_mut(belt).visible = !scene.node(belt).visible;
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Space), .. } => playing = !playing,
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let ticks = timer.ticks();
+        if playing {
+            time += (ticks - last_ticks) as f32 / 1000.0;
+        }
+        last_ticks = ticks;
+
+        scene.node_mut(sun).set_rotation(Quat::from_axis_angle(Vec3::Y, time * 0.1));
+        scene.node_mut(planet_orbit).set_rotation(Quat::from_axis_angle(Vec3::Y, time * 0.3));
+        scene.node_mut(planet).set_rotation(Quat::from_axis_angle(Vec3::Y, time));
+        scene.node_mut(moon_orbit).set_rotation(Quat::from_axis_angle(Vec3::Y, time * 1.5));
+        scene.node_mut(belt).set_rotation(Quat::from_axis_angle(Vec3::Y, time * 0.05));
+        scene.update_world_transforms();
+
+        let (view, projection, eye) = if use_moon_camera {
+            let (view, projection) = scene.camera_matrices(moon_camera, camera.aspect).unwrap();
+            (view, projection, scene.world_transform(moon_camera).transform_point(Vec3::ZERO))
+        } else {
+            (camera.view_matrix(), camera.projection_matrix(), camera.position)
+        };
+        let draw_list = scene.collect_draw_calls(&(projection * view), eye);
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        scene.draw(&draw_list, &mut render_state_cache, &view, &projection);
+
+        window.set_title(&format!("{} drawn, {} culled", draw_list.calls.len(), draw_list.culled)).unwrap();
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec2 TexCoord;
+    vec4 Color;
+} IN;
+
+uniform sampler2D SpriteTexture;
+
+out vec4 Color;
+
+void main()
+{
+    Color = texture(SpriteTexture, IN.TexCoord) * IN.Color;
+}
@@ -0,0 +1,373 @@
+// 2D drawing with a `SpriteBatch`.  Giving every quad its own VAO and draw call (like `two_vaos_and_two_vbos` does
+// with its triangles) is fine for a handful of them, but each draw call costs far more CPU time than the four vertices
+// it draws, so a few thousand sprites would spend nearly all their time on overhead.
+//
+// A batch instead collects sprites over the frame as plain vertices in memory: each sprite's corners get rotated,
+// scaled and moved on the CPU, then copied into one streaming vertex buffer, and every run of sprites that share a
+// texture goes out in a single draw call.  The sprites are sorted by layer and then texture first, so that the runs are
+// as long as they can be.
+//
+// Sprites are placed in pixels, with (0, 0) at the top-left and y going down like most 2D tools expect, by a
+// `Camera2D` that can also pan and zoom.  A `TextureAtlas` names rectangles of a bigger texture, so that lots of
+// different sprites can share one texture and still be drawn together.
+
+use std::ffi::CString;
+
+use crate::buffer::{ArrayBuffer, ElementArrayBuffer, VertexArray};
+use crate::math::{Mat4, Vec2, Vec4};
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::texture::Texture;
+use crate::vertex::{attrib, VertexLayout};
+
+/// How many sprites fit in the vertex buffer at once.  A batch with more than this gets drawn in several goes
+pub const MAX_SPRITES_PER_DRAW: usize = 8192;
+
+/// A pixel camera for 2D: (0, 0) is the top-left of the window and one unit is one pixel, until it's moved or zoomed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2D {
+    pub position: Vec2,  // the world point at the top-left corner of the window
+    pub zoom: f32,       // 2 makes everything twice as big
+    pub width: f32,      // size of the window, in pixels
+    pub height: f32,
+}
+
+#[allow(dead_code)]
+impl Camera2D {
+    pub fn new(width: u32, height: u32) -> Camera2D {
+        Camera2D { position: Vec2::new(0.0, 0.0), zoom: 1.0, width: width as f32, height: height as f32 }
+    }
+
+    /// World to clip space.  The top of the window is the smaller y, so the orthographic projection is upside down
+    /// compared to GL's usual one
+    pub fn matrix(&self) -> Mat4 {
+        let (w, h) = (self.width / self.zoom, self.height / self.zoom);
+        Mat4::orthographic(self.position.x, self.position.x + w, self.position.y + h, self.position.y, -1.0, 1.0)
+    }
+
+    /// Where in the world a pixel of the window (like the mouse position) is
+    pub fn screen_to_world(&self, x: i32, y: i32) -> Vec2 {
+        Vec2::new(self.position.x + x as f32 / self.zoom, self.position.y + y as f32 / self.zoom)
+    }
+
+    /// Zooms by `factor` while keeping the world point under window pixel (`x`, `y`) where it is, which is what
+    /// zooming towards the mouse wants
+    pub fn zoom_at(&mut self, factor: f32, x: i32, y: i32) {
+        let before = self.screen_to_world(x, y);
+        self.zoom = (self.zoom * factor).clamp(0.05, 50.0);
+        let after = self.screen_to_world(x, y);
+        self.position = Vec2::new(self.position.x + before.x - after.x, self.position.y + before.y - after.y);
+    }
+
+    /// Keeps up with the window being resized, resetting the GL viewport to match like `Camera::handle_resize()` does
+    pub fn handle_resize(&mut self, event: &sdl2::event::Event) {
+        if let sdl2::event::Event::Window { win_event: sdl2::event::WindowEvent::Resized(w, h), .. } = *event {
+            self.width = w as f32;
+            self.height = h as f32;
+            unsafe { gl::Viewport(0, 0, w, h); }
+        }
+    }
+}
+
+/// A rectangle of a texture, as texture coordinates, along with its size in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub uv_min: Vec2,  // top-left corner
+    pub uv_max: Vec2,  // bottom-right corner
+    pub width: f32,
+    pub height: f32,
+}
+
+#[allow(dead_code)]
+impl Region {
+    /// All of `texture`
+    pub fn whole(texture: &Texture) -> Region {
+        Region {
+            uv_min: Vec2::new(0.0, 0.0),
+            uv_max: Vec2::new(1.0, 1.0),
+            width: texture.width() as f32,
+            height: texture.height() as f32,
+        }
+    }
+}
+
+/// A texture with named rectangles in it.  Rectangles are in pixels from the top-left of the image, for an image that
+/// was uploaded the way it was loaded (without `Image::flip_vertically()`)
+pub struct TextureAtlas {
+    texture: Texture,
+    regions: Vec<(String, Region)>,
+}
+
+#[allow(dead_code)]
+impl TextureAtlas {
+    /// Filtering is switched to plain linear, without mipmaps: the smaller mip levels blend neighbouring regions into
+    /// each other, which shows up as colored fringes around shrunk sprites
+    pub fn new(texture: Texture) -> TextureAtlas {
+        texture.set_filter(gl::LINEAR, gl::LINEAR);
+        texture.set_wrap(gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE);
+        TextureAtlas { texture, regions: Vec::new() }
+    }
+
+    /// Names the `width` by `height` pixel rectangle at (`x`, `y`), returning its index
+    pub fn add_region(&mut self, name: &str, x: u32, y: u32, width: u32, height: u32) -> usize {
+        let size = Vec2::new(self.texture.width() as f32, self.texture.height() as f32);
+        let region = Region {
+            uv_min: Vec2::new(x as f32 / size.x, y as f32 / size.y),
+            uv_max: Vec2::new((x + width) as f32 / size.x, (y + height) as f32 / size.y),
+            width: width as f32,
+            height: height as f32,
+        };
+        self.regions.push((name.to_string(), region));
+        self.regions.len() - 1
+    }
+
+    /// Cuts the texture into a grid of `columns` by `rows` equal cells, named "`prefix`0", "`prefix`1", ... going
+    /// along each row from the top-left.  Returns the index of the first one
+    pub fn add_grid(&mut self, prefix: &str, columns: u32, rows: u32) -> usize {
+        let (cell_width, cell_height) = (self.texture.width() / columns.max(1), self.texture.height() / rows.max(1));
+        let first = self.regions.len();
+        for row in 0..rows {
+            for column in 0..columns {
+                let name = format!("{}{}", prefix, row * columns + column);
+                self.add_region(&name, column * cell_width, row * cell_height, cell_width, cell_height);
+            }
+        }
+        first
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn region(&self, index: usize) -> Region {
+        self.regions[index].1
+    }
+
+    pub fn regions(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// First region called `name`
+    pub fn find(&self, name: &str) -> Option<Region> {
+        self.regions.iter().find(|(n, _)| n == name).map(|&(_, region)| region)
+    }
+}
+
+/// One quad to draw
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    pub region: Region,
+    pub position: Vec2,  // where `origin` ends up, in world pixels
+    pub size: Vec2,      // in world pixels
+    pub origin: Vec2,    // the point it's placed by and rotates around, from (0, 0) at its top-left to (1, 1)
+    pub rotation: f32,   // radians, clockwise on screen
+    pub color: Vec4,     // multiplies the texture
+    pub layer: i32,      // higher layers are drawn on top of lower ones
+}
+
+#[allow(dead_code)]
+impl Sprite {
+    /// `region` at its own size, centered on `position`, untinted and unrotated, on layer 0
+    pub fn new(region: Region, position: Vec2) -> Sprite {
+        Sprite {
+            region,
+            position,
+            size: Vec2::new(region.width, region.height),
+            origin: Vec2::new(0.5, 0.5),
+            rotation: 0.0,
+            color: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            layer: 0,
+        }
+    }
+
+    /// The four corners, clockwise from the top-left, in world pixels
+    fn corners(&self) -> [Vec2; 4] {
+        let (sin, cos) = self.rotation.sin_cos();
+        let local = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let mut corners = [Vec2::new(0.0, 0.0); 4];
+        for (corner, &(x, y)) in corners.iter_mut().zip(local.iter()) {
+            let (dx, dy) = ((x - self.origin.x) * self.size.x, (y - self.origin.y) * self.size.y);
+            // With y pointing down, this turns clockwise on screen
+            *corner = Vec2::new(self.position.x + dx * cos - dy * sin, self.position.y + dx * sin + dy * cos);
+        }
+        corners
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct SpriteVertex {
+    position: Vec2,
+    tex_coord: Vec2,
+    color: Vec4,
+}
+
+impl SpriteVertex {
+    fn layout() -> VertexLayout {
+        let f = std::mem::size_of::<f32>();
+        VertexLayout::new(std::mem::size_of::<SpriteVertex>())
+            .float(attrib::POSITION, 2, 0)
+            .float(attrib::TEX_COORD, 2, 2 * f)
+            .float(attrib::COLOR, 4, 4 * f)
+    }
+}
+
+/// A sprite waiting to be drawn
+struct Queued {
+    layer: i32,
+    texture: gl::types::GLuint,
+    vertices: [SpriteVertex; 4],
+}
+
+/// Collects sprites between `begin()` and `end()`, then draws them in as few draw calls as it can
+pub struct SpriteBatch {
+    program: Program,
+    vao: VertexArray,
+    vbo: ArrayBuffer,
+    _ebo: ElementArrayBuffer,  // the same two triangles per quad every time, so it's filled in once
+    queued: Vec<Queued>,
+    vertices: Vec<SpriteVertex>,
+    view_projection: Mat4,
+    pub render_state: RenderState,
+    draw_calls: usize,
+    sprites_drawn: usize,
+}
+
+#[allow(dead_code)]
+impl SpriteBatch {
+    pub fn new() -> Result<SpriteBatch, String> {
+        SpriteBatch::with_fragment_shader(include_str!("sprite.frag"))
+    }
+
+    /// A batch that colors its sprites with a different fragment shader, like the distance field one text uses.  The
+    /// shader gets the same inputs as `sprite.frag`, and its texture is still called `SpriteTexture`
+    pub fn with_fragment_shader(frag_source: &str) -> Result<SpriteBatch, String> {
+        let vert_shader = Shader::from_vert_source(&CString::new(include_str!("sprite.vert")).unwrap())?;
+        let frag_shader = Shader::from_frag_source(&CString::new(frag_source).map_err(|e| e.to_string())?)?;
+        let program = Program::from_shaders(&[vert_shader, frag_shader])?;
+        program.set_used();
+        program.set_uniform_i32("SpriteTexture", 0);
+
+        let indices: Vec<u32> = (0..MAX_SPRITES_PER_DRAW as u32)
+            .flat_map(|quad| {
+                let first = quad * 4;
+                vec![first, first + 1, first + 2, first, first + 2, first + 3]
+            })
+            .collect();
+        let vao = VertexArray::new();
+        let mut vbo = ArrayBuffer::new();
+        let mut ebo = ElementArrayBuffer::new();
+        vao.bind();
+        vbo.bind();
+        vbo.buffer_data(&vec![SpriteVertex::default(); MAX_SPRITES_PER_DRAW * 4], gl::STREAM_DRAW);
+        SpriteVertex::layout().apply();
+        ebo.bind();
+        ebo.static_draw_data(&indices);
+        vao.unbind();
+        vbo.unbind();
+
+        Ok(SpriteBatch {
+            program,
+            vao,
+            vbo,
+            _ebo: ebo,
+            queued: Vec::new(),
+            vertices: Vec::with_capacity(MAX_SPRITES_PER_DRAW * 4),
+            view_projection: Mat4::identity(),
+            render_state: RenderState::overlay(),
+            draw_calls: 0,
+            sprites_drawn: 0,
+        })
+    }
+
+    /// Starts a new batch, seen through `camera`
+    pub fn begin(&mut self, camera: &Camera2D) {
+        self.begin_with(camera.matrix());
+    }
+
+    /// Starts a new batch whose sprite coordinates get turned into clip space by `view_projection`.  With a 3D
+    /// camera's matrices times a model matrix, this lays the sprites out on a plane somewhere in the world
+    pub fn begin_with(&mut self, view_projection: Mat4) {
+        self.queued.clear();
+        self.view_projection = view_projection;
+    }
+
+    /// Queues a sprite using (a region of) `texture`.  Only the texture's id is kept, so it has to live until `end()`
+    pub fn draw(&mut self, texture: &Texture, sprite: &Sprite) {
+        let corners = sprite.corners();
+        let (uv_min, uv_max) = (sprite.region.uv_min, sprite.region.uv_max);
+        let uvs = [
+            Vec2::new(uv_min.x, uv_min.y),
+            Vec2::new(uv_max.x, uv_min.y),
+            Vec2::new(uv_max.x, uv_max.y),
+            Vec2::new(uv_min.x, uv_max.y),
+        ];
+        let vertex = |i: usize| SpriteVertex { position: corners[i], tex_coord: uvs[i], color: sprite.color };
+        self.queued.push(Queued {
+            layer: sprite.layer,
+            texture: texture.id(),
+            vertices: [vertex(0), vertex(1), vertex(2), vertex(3)],
+        });
+    }
+
+    /// Queues a sprite from `atlas`
+    pub fn draw_from(&mut self, atlas: &TextureAtlas, sprite: &Sprite) {
+        self.draw(atlas.texture(), sprite);
+    }
+
+    /// Sorts everything queued since `begin()` and draws it.  Within a layer, sprites are grouped by texture, so two
+    /// overlapping sprites on the same layer with different textures can come out in either order; put them on
+    /// different layers if it matters.  Sprites with the same layer and texture keep the order they were queued in
+    pub fn end(&mut self, cache: &mut RenderStateCache) {
+        self.draw_calls = 0;
+        self.sprites_drawn = self.queued.len();
+        if self.queued.is_empty() {
+            return;
+        }
+        self.queued.sort_by_key(|sprite| (sprite.layer, sprite.texture));  // stable, so queue order survives
+
+        cache.apply(&self.render_state);
+        self.program.set_used();
+        self.program.set_uniform_mat4("ViewProjection", &self.view_projection);
+        self.vao.bind();
+
+        let mut start = 0;
+        while start < self.queued.len() {
+            let texture = self.queued[start].texture;
+            let run = self.queued[start..].iter()
+                .take(MAX_SPRITES_PER_DRAW)
+                .take_while(|sprite| sprite.texture == texture)
+                .count();
+            self.vertices.clear();
+            for sprite in &self.queued[start..start + run] {
+                self.vertices.extend_from_slice(&sprite.vertices);
+            }
+
+            // Handing `glBufferData` a fresh copy every time lets the driver give us new memory rather than waiting
+            // for the GPU to finish with the last lot ("orphaning" the old buffer)
+            self.vbo.bind();
+            self.vbo.buffer_data(&self.vertices, gl::STREAM_DRAW);
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::DrawElements(gl::TRIANGLES, (run * 6) as gl::types::GLsizei, gl::UNSIGNED_INT, std::ptr::null());
+            }
+            self.draw_calls += 1;
+            start += run;
+        }
+        self.vbo.unbind();
+        self.vao.unbind();
+        self.queued.clear();
+    }
+
+    /// Draw calls the last `end()` needed
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
+    /// Sprites the last `end()` drew
+    pub fn sprites_drawn(&self) -> usize {
+        self.sprites_drawn
+    }
+}
@@ -0,0 +1,21 @@
+#version 330 core
+
+// Sprite corners arrive already placed in the world by `SpriteBatch`, so all that's left is the camera
+
+layout (location = 0) in vec2 Position;
+layout (location = 1) in vec4 Color;
+layout (location = 3) in vec2 TexCoord;
+
+uniform mat4 ViewProjection;
+
+out VS_OUTPUT {
+    vec2 TexCoord;
+    vec4 Color;
+} OUT;
+
+void main()
+{
+    gl_Position = ViewProjection * vec4(Position, 0.0, 1.0);
+    OUT.TexCoord = TexCoord;
+    OUT.Color = Color;
+}
@@ -0,0 +1,223 @@
+use crate::image::Image;
+use crate::math::{Vec2, Vec4};
+use crate::render_state::RenderStateCache;
+use crate::sprite::{Camera2D, Region, Sprite, SpriteBatch, TextureAtlas};
+use crate::texture::Texture;
+
+const CELL: u32 = 64;    // size of each shape in the atlas, in pixels
+const COLUMNS: u32 = 4;  // the atlas is a 4x2 grid of shapes
+const ROWS: u32 = 2;
+const WORLD: (f32, f32) = (2400.0, 1600.0);
+
+/// Same tiny hash as the solar system's asteroids, for sprites that start in the same places every run
+fn hash(n: u32) -> f32 {
+    let n = n.wrapping_mul(0x9E37_79B9) ^ (n >> 15);
+    (n.wrapping_mul(0x85EB_CA6B) >> 8) as f32 / (1 << 24) as f32
+}
+
+/// A white shape on a clear background, so that sprites can tint it any color.  `x` and `y` go from -1 to 1 across
+/// the cell, and the result is how covered that point is
+fn shape_coverage(shape: u32, x: f32, y: f32) -> f32 {
+    let r = (x * x + y * y).sqrt();
+    let edge = |distance: f32| (distance * CELL as f32 * 0.5).clamp(0.0, 1.0);  // a pixel wide fade, for smooth edges
+    match shape {
+        0 => edge(0.9 - r),                                  // circle
+        1 => edge(0.9 - r).min(edge(r - 0.55)),              // ring
+        2 => edge(0.8 - x.abs().max(y.abs())),               // square
+        3 => edge(0.9 - (x.abs() + y.abs())),                // diamond
+        4 => edge(0.85 - y).min(edge(y * 0.5 + 0.45 - x.abs())),  // triangle, pointing up
+        5 => edge(0.8 - x.abs().max(y.abs())).min(edge(x.abs().max(y.abs()) - 0.5)),  // hollow square
+        6 => {
+            // A five pointed star: the radius it reaches out to swings in and out with the angle
+            let angle = y.atan2(x) + std::f32::consts::FRAC_PI_2;
+            let spike = ((angle * 5.0 / 2.0).cos().abs()).powf(4.0);
+            edge(0.35 + 0.55 * spike - r)
+        },
+        _ => edge(0.9 - x.abs()).min(edge(0.25 - y.abs())).max(edge(0.9 - y.abs()).min(edge(0.25 - x.abs()))),  // plus
+    }
+}
+
+fn build_atlas_image() -> Image {
+    let (width, height) = (CELL * COLUMNS, CELL * ROWS);
+    let mut image = Image::solid(width, height, [255, 255, 255, 0]).unwrap();
+    for py in 0..height {
+        for px in 0..width {
+            let shape = py / CELL * COLUMNS + px / CELL;
+            let x = ((px % CELL) as f32 + 0.5) / CELL as f32 * 2.0 - 1.0;
+            let y = ((py % CELL) as f32 + 0.5) / CELL as f32 * 2.0 - 1.0;
+            image.pixels[((py * width + px) * 4 + 3) as usize] = (shape_coverage(shape, x, y) * 255.0) as u8;
+        }
+    }
+    image
+}
+
+/// Grey checks for the ground, so there's something to see the camera move against
+fn build_ground_image() -> Image {
+    let mut image = Image::solid(CELL, CELL, [0, 0, 0, 255]).unwrap();
+    for y in 0..CELL {
+        for x in 0..CELL {
+            let shade = if (x < CELL / 2) == (y < CELL / 2) { 70 } else { 55 };
+            let i = ((y * CELL + x) * 4) as usize;
+            image.pixels[i..i + 3].copy_from_slice(&[shade, shade, shade + 10]);
+        }
+    }
+    image
+}
+
+struct Mover {
+    region: usize,
+    position: Vec2,
+    velocity: Vec2,
+    spin: f32,
+    rotation: f32,
+    scale: f32,
+    color: Vec4,
+    layer: i32,
+}
+
+fn make_mover(i: u32, regions: usize) -> Mover {
+    let h = |k: u32| hash(i * 8 + k);
+    let tint = |k: u32| 0.4 + hash(1_000_000 + i * 3 + k) * 0.6;
+    let angle = h(0) * std::f32::consts::PI * 2.0;
+    let speed = 40.0 + h(1) * 160.0;
+    Mover {
+        region: (h(2) * regions as f32) as usize % regions,
+        position: Vec2::new(h(3) * WORLD.0, h(4) * WORLD.1),
+        velocity: Vec2::new(angle.cos() * speed, angle.sin() * speed),
+        spin: (h(5) - 0.5) * 6.0,
+        rotation: 0.0,
+        scale: 0.25 + h(6) * 0.5,
+        color: Vec4::new(tint(0), tint(1), tint(2), 0.9),
+        layer: 1 + (h(6) * 2.0) as i32,  // smaller ones underneath
+    }
+}
+
+/// Thousands of spinning, tinted shapes bouncing around over a tiled floor, all drawn through one `SpriteBatch`.  The
+/// shapes all come from one atlas texture and the floor tiles from another, so sorting by layer and texture gets the
+/// whole lot down to a handful of draw calls, which the title shows along with the frame time.
+///
+/// Up and Down double and halve the number of sprites.  The mouse wheel zooms towards the mouse, and dragging pans
+pub fn sprite_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+
+    let mut batch = match SpriteBatch::new() {
+        Ok(batch) => batch,
+        Err(e) => {
+            println!("Failed to set up the sprite batch: {}", e);
+            return;
+        },
+    };
+    let atlas_image = build_atlas_image();
+    let mut atlas = TextureAtlas::new(Texture::from_image(&atlas_image, true));
+    atlas.add_grid("shape", COLUMNS, ROWS);
+    let ground = Texture::from_image(&build_ground_image(), true);
+    ground.set_filter(gl::NEAREST, gl::NEAREST);
+    let ground_region = Region::whole(&ground);
+
+    let mut count = 10_000;
+    let mut movers: Vec<Mover> = (0..count).map(|i| make_mover(i, atlas.regions())).collect();
+
+    let (width, height) = window.size();
+    let mut camera = Camera2D::new(width, height);
+    let mut render_state_cache = RenderStateCache::new();
+    let mut dragging = false;
+    let mut mouse = (0, 0);
+    let mut frame_ms = 0.0f64;
+    let frequency = timer.performance_frequency() as f64;
+    let mut last_counter = timer.performance_counter();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Up), .. } => count = (count * 2).min(1 << 18),
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Down), .. } => count = (count / 2).max(1),
+                sdl2::event::Event::MouseButtonDown { .. } => dragging = true,
+                sdl2::event::Event::MouseButtonUp { .. } => dragging = false,
+                sdl2::event::Event::MouseMotion { x, y, xrel, yrel, .. } => {
+                    mouse = (x, y);
+                    if dragging {
+                        camera.position = Vec2::new(
+                            camera.position.x - xrel as f32 / camera.zoom,
+                            camera.position.y - yrel as f32 / camera.zoom,
+                        );
+                    }
+                },
+                sdl2::event::Event::MouseWheel { y, .. } => camera.zoom_at(1.1f32.powi(y), mouse.0, mouse.1),
+                _ => camera.handle_resize(&event),
+            }
+        }
+        if movers.len() != count as usize {
+            movers = (0..count).map(|i| make_mover(i, atlas.regions())).collect();
+        }
+
+        let counter = timer.performance_counter();
+        let dt = (counter - last_counter) as f64 / frequency;
+        last_counter = counter;
+        frame_ms = frame_ms * 0.95 + dt * 1000.0 * 0.05;  // smoothed, so the title is readable
+
+        let dt = dt as f32;
+        for mover in &mut movers {
+            let (position, velocity) = (mover.position, mover.velocity);
+            mover.position = Vec2::new(position.x + velocity.x * dt, position.y + velocity.y * dt);
+            if mover.position.x < 0.0 || mover.position.x > WORLD.0 {
+                mover.velocity.x = -mover.velocity.x;
+            }
+            if mover.position.y < 0.0 || mover.position.y > WORLD.1 {
+                mover.velocity.y = -mover.velocity.y;
+            }
+            mover.rotation += mover.spin * dt;
+        }
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        batch.begin(&camera);
+        for m in &movers {
+            let mut sprite = Sprite::new(atlas.region(m.region), m.position);
+            sprite.size = Vec2::new(CELL as f32 * m.scale, CELL as f32 * m.scale);
+            sprite.rotation = m.rotation;
+            sprite.color = m.color;
+            sprite.layer = m.layer;
+            batch.draw_from(&atlas, &sprite);
+        }
+        // The floor goes in last but comes out first, since it's on the bottom layer
+        let (columns, rows) = ((WORLD.0 / CELL as f32) as u32, (WORLD.1 / CELL as f32) as u32);
+        for i in 0..columns * rows {
+            let position = Vec2::new((i % columns * CELL) as f32, (i / columns * CELL) as f32);
+            let tile = Sprite { origin: Vec2::new(0.0, 0.0), layer: 0, ..Sprite::new(ground_region, position) };
+            batch.draw(&ground, &tile);
+        }
+        batch.end(&mut render_state_cache);
+
+        window.set_title(&format!(
+            "{} sprites, {} draw calls, {:.2} ms per frame",
+            batch.sprites_drawn(),
+            batch.draw_calls(),
+            frame_ms,
+        )).unwrap();
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,63 @@
+#version 330 core
+
+// Screen space ambient occlusion: guesses how hidden each pixel is from light arriving from all around, using nothing
+// but the G-buffer's depth and normals.  Points are picked in a hemisphere above the surface and projected back onto
+// the screen, and every one that turns out to be behind something that was drawn counts as blocked.  Creases and
+// corners have lots of blocked points, so they come out darker.
+//
+// The hemisphere is turned by a different random angle at each pixel of a small tiled noise texture, which swaps
+// banding for noise that ssao_blur.frag then smooths out
+
+#define KERNEL_SIZE 16  // has to match `deferred::SSAO_KERNEL_SIZE`
+
+in vec2 TexCoord;
+
+uniform sampler2D DepthTexture;
+uniform sampler2D NormalTexture;
+uniform sampler2D NoiseTexture;
+uniform vec3 Kernel[KERNEL_SIZE];  // points in a unit hemisphere around +Z, more of them close to the middle
+uniform vec2 NoiseScale;           // how many times the noise texture repeats across the screen
+uniform mat4 View;
+uniform mat4 Projection;
+uniform mat4 InverseProjection;
+uniform float Radius = 0.5;        // how far around each point to look, in world units
+uniform float Bias = 0.025;        // keeps flat surfaces from occluding themselves
+
+out float Occlusion;
+
+vec3 view_position(vec2 uv)
+{
+    float depth = texture(DepthTexture, uv).r;
+    vec4 view = InverseProjection * vec4(vec3(uv, depth) * 2.0 - 1.0, 1.0);
+    return view.xyz / view.w;
+}
+
+void main()
+{
+    if (texture(DepthTexture, TexCoord).r >= 1.0) {
+        Occlusion = 1.0;
+        return;
+    }
+    vec3 p = view_position(TexCoord);
+    vec3 n = normalize(mat3(View) * texture(NormalTexture, TexCoord).xyz);
+
+    // A frame around the normal, turned by the noise
+    vec3 random = vec3(texture(NoiseTexture, TexCoord * NoiseScale).xy, 0.0);
+    vec3 t = normalize(random - n * dot(random, n));
+    vec3 b = cross(n, t);
+    mat3 tbn = mat3(t, b, n);
+
+    float blocked = 0.0;
+    for (int i = 0; i < KERNEL_SIZE; i++) {
+        vec3 sample_position = p + tbn * Kernel[i] * Radius;
+        vec4 clip = Projection * vec4(sample_position, 1.0);
+        vec2 uv = clip.xy / clip.w * 0.5 + 0.5;
+        float scene_z = view_position(uv).z;
+
+        // Something much closer to the camera than the point (like a pillar in front of a wall) isn't near enough to
+        // shade it, so fade those out rather than letting them leave dark halos
+        float in_range = smoothstep(0.0, 1.0, Radius / abs(p.z - scene_z));
+        blocked += (scene_z >= sample_position.z + Bias ? 1.0 : 0.0) * in_range;
+    }
+    Occlusion = 1.0 - blocked / float(KERNEL_SIZE);
+}
@@ -0,0 +1,22 @@
+#version 330 core
+
+// Averages ssao.frag's output over 4x4 pixels, the same size as its noise texture, so that each pixel sees every
+// rotation of the hemisphere once and the noise cancels out
+
+in vec2 TexCoord;
+
+uniform sampler2D OcclusionTexture;
+
+out float Occlusion;
+
+void main()
+{
+    vec2 texel = 1.0 / vec2(textureSize(OcclusionTexture, 0));
+    float total = 0.0;
+    for (int y = -2; y < 2; y++) {
+        for (int x = -2; x < 2; x++) {
+            total += texture(OcclusionTexture, TexCoord + (vec2(x, y) + 0.5) * texel).r;
+        }
+    }
+    Occlusion = total / 16.0;
+}
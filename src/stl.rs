@@ -0,0 +1,204 @@
+// Loader for STL files, which is what CAD programs and 3D printers use.  STL is just a list of triangles, each with its
+// own normal, and comes in two flavours:
+//
+// * ASCII, which looks like
+//
+//       solid name
+//         facet normal 0 0 1
+//           outer loop
+//             vertex 0 0 0
+//             vertex 1 0 0
+//             vertex 0 1 0
+//           endloop
+//         endfacet
+//       endsolid name
+//
+// * binary: an 80 byte header, a little-endian `u32` triangle count, then 50 bytes per triangle (normal, three
+//   corners, and two bytes nobody uses).
+//
+// Annoyingly, plenty of binary files also start with "solid" in their header, so we tell them apart by checking
+// whether the file is exactly the size a binary file with that many triangles would be.  A truncated binary file fails
+// that check too, but it won't be valid text, so anything that starts with "solid" and isn't text is read as binary
+// after all, to say it's too short rather than just that it isn't text.
+//
+// Since every triangle has its own normal, no vertices are shared: each triangle gets three vertices of its own, which
+// gives the flat-shaded look you'd expect from CAD parts.
+
+use crate::math::{Vec2, Vec3};
+use crate::mesh::MeshData;
+use crate::resources::Resources;
+use crate::vertex::Vertex;
+
+#[allow(dead_code)]
+pub fn load(res: &Resources, resource_name: &str) -> Result<MeshData, String> {
+    let bytes = res.load_buffer(resource_name).map_err(|e| format!("{}: {}", resource_name, e))?;
+    parse_stl(&bytes, resource_name)
+}
+
+/// Parses either flavour of STL.  `resource_name` is only used for error messages
+pub fn parse_stl(bytes: &[u8], resource_name: &str) -> Result<MeshData, String> {
+    if is_binary(bytes) {
+        parse_binary(bytes).map_err(|e| format!("{}: {}", resource_name, e))
+    } else {
+        match std::str::from_utf8(bytes) {
+            Ok(source) => parse_ascii(source, resource_name),
+            Err(_) if bytes.len() >= 84 => parse_binary(bytes).map_err(|e| format!("{}: {}", resource_name, e)),
+            Err(_) => Err(format!("{}: file is not valid text", resource_name)),
+        }
+    }
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.len() < 84 {
+        return false;
+    }
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    bytes.len() == 84 + count * 50 || !bytes.starts_with(b"solid")
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<MeshData, String> {
+    if bytes.len() < 84 {
+        return Err("file is too short to be a binary STL".to_string());
+    }
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    if bytes.len() < 84 + count * 50 {
+        return Err(format!("file says it has {} triangles, but is too short for that many", count));
+    }
+
+    let read_vec3 = |at: usize| {
+        let f = |i: usize| f32::from_le_bytes([bytes[at + i], bytes[at + i + 1], bytes[at + i + 2], bytes[at + i + 3]]);
+        Vec3::new(f(0), f(4), f(8))
+    };
+    let mut data = MeshData::new();
+    for i in 0..count {
+        let at = 84 + i * 50;
+        add_triangle(&mut data, read_vec3(at), [read_vec3(at + 12), read_vec3(at + 24), read_vec3(at + 36)]);
+    }
+    Ok(data)
+}
+
+fn parse_ascii(source: &str, resource_name: &str) -> Result<MeshData, String> {
+    let mut data = MeshData::new();
+    let mut normal = Vec3::ZERO;
+    let mut corners: Vec<Vec3> = Vec::with_capacity(3);
+
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: &str| format!("{}:{}: {}", resource_name, line_index + 1, message);
+        let mut parts = line.split_whitespace();
+        let read_vec3 = |parts: &mut std::str::SplitWhitespace| -> Result<Vec3, String> {
+            let mut v = [0.0f32; 3];
+            for value in &mut v {
+                let token = parts.next().ok_or_else(|| error("expected three numbers"))?;
+                *value = token.parse().map_err(|_| error(&format!("invalid number '{}'", token)))?;
+            }
+            Ok(Vec3::new(v[0], v[1], v[2]))
+        };
+
+        match parts.next() {
+            Some("facet") => {
+                if parts.next() != Some("normal") {
+                    return Err(error("expected 'facet normal'"));
+                }
+                normal = read_vec3(&mut parts)?;
+                corners.clear();
+            },
+            Some("vertex") => corners.push(read_vec3(&mut parts)?),
+            Some("endfacet") => {
+                if corners.len() != 3 {
+                    return Err(error(&format!("facet has {} vertices instead of 3", corners.len())));
+                }
+                add_triangle(&mut data, normal, [corners[0], corners[1], corners[2]]);
+            },
+            Some("solid") | Some("outer") | Some("endloop") | Some("endsolid") | None => {},
+            Some(other) => return Err(error(&format!("unknown keyword '{}'", other))),
+        }
+    }
+    Ok(data)
+}
+
+fn add_triangle(data: &mut MeshData, normal: Vec3, corners: [Vec3; 3]) {
+    // Lots of exporters don't bother filling in the normal, so work it out from the corners (which are meant to be in
+    // counter-clockwise order) when it's missing
+    let normal = if normal.length() > 1e-6 {
+        normal.normalize()
+    } else {
+        (corners[1] - corners[0]).cross(corners[2] - corners[0]).normalize()
+    };
+    let first = data.vertices.len() as u32;
+    for &corner in &corners {
+        data.vertices.push(Vertex::new(corner, normal, Vec2::new(0.0, 0.0)));
+    }
+    data.indices.extend_from_slice(&[first, first + 1, first + 2]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two triangles making a square, the second one without a normal so it has to be worked out
+    const TRIANGLES: [([f32; 3], [[f32; 3]; 3]); 2] = [
+        ([0.0, 0.0, 2.0], [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]]),
+        ([0.0, 0.0, 0.0], [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]),
+    ];
+
+    fn ascii() -> Vec<u8> {
+        let mut text = String::from("solid square\n");
+        for (normal, corners) in &TRIANGLES {
+            text += &format!("  facet normal {} {} {}\n    outer loop\n", normal[0], normal[1], normal[2]);
+            for c in corners {
+                text += &format!("      vertex {} {} {}\n", c[0], c[1], c[2]);
+            }
+            text += "    endloop\n  endfacet\n";
+        }
+        text += "endsolid square\n";
+        text.into_bytes()
+    }
+
+    fn binary() -> Vec<u8> {
+        let mut bytes = vec![0u8; 80];
+        bytes[..12].copy_from_slice(b"solid square");  // like plenty of real files, even though it's binary
+        bytes.extend_from_slice(&(TRIANGLES.len() as u32).to_le_bytes());
+        for (normal, corners) in &TRIANGLES {
+            for v in std::iter::once(normal).chain(corners) {
+                for c in v {
+                    bytes.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn ascii_and_binary_match() {
+        let from_ascii = parse_stl(&ascii(), "square.stl").unwrap();
+        let from_binary = parse_stl(&binary(), "square.stl").unwrap();
+        assert_eq!(from_ascii.vertices, from_binary.vertices);
+        assert_eq!(from_ascii.indices, from_binary.indices);
+
+        assert_eq!(from_ascii.indices, vec![0, 1, 2, 3, 4, 5]);  // nothing shared, so every triangle stays flat
+        assert_eq!(from_ascii.vertices[4].position, Vec3::new(1.0, 1.0, 0.0));
+        for vertex in &from_ascii.vertices {
+            assert_eq!(vertex.normal, Vec3::new(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        let text = String::from_utf8(ascii()).unwrap();
+        let error = parse_stl(text.replace("vertex 1 0 0", "vertex 1 0").as_bytes(), "square.stl").unwrap_err();
+        assert_eq!(error, "square.stl:5: expected three numbers");
+
+        let mut bytes = binary();
+        bytes[0] = b'x';  // otherwise it would be tried as text
+        bytes.truncate(bytes.len() - 1);
+        let error = parse_stl(&bytes, "square.stl").unwrap_err();
+        assert_eq!(error, "square.stl: file says it has 2 triangles, but is too short for that many");
+
+        // Same again, but with the "solid" header left in place
+        let mut bytes = binary();
+        bytes.truncate(bytes.len() - 1);
+        let error = parse_stl(&bytes, "square.stl").unwrap_err();
+        assert_eq!(error, "square.stl: file says it has 2 triangles, but is too short for that many");
+    }
+}
@@ -0,0 +1,34 @@
+#version 400 core
+
+// Colors the ground by height and steepness (grass low down, rock on slopes, snow on top), with one sun and a bit of
+// haze in the distance
+in TES_OUTPUT {
+    vec3 WorldPosition;
+    vec3 Normal;
+    vec2 TexCoord;
+} IN;
+
+uniform vec3 SunDirection;  // towards the sun
+uniform vec3 FogColor;
+uniform float FogDistance;
+
+out vec4 Color;
+
+void main()
+{
+    vec3 normal = normalize(IN.Normal);
+    float height = IN.WorldPosition.y / max(HeightScale, 0.001);
+    float steepness = 1.0 - normal.y;
+
+    vec3 grass = vec3(0.25, 0.45, 0.18);
+    vec3 rock = vec3(0.42, 0.38, 0.34);
+    vec3 snow = vec3(0.92, 0.94, 0.97);
+    vec3 albedo = mix(grass, rock, smoothstep(0.15, 0.35, steepness));
+    albedo = mix(albedo, snow, smoothstep(0.65, 0.8, height) * (1.0 - smoothstep(0.3, 0.5, steepness)));
+
+    float diffuse = max(dot(normal, normalize(SunDirection)), 0.0);
+    vec3 color = albedo * (0.25 + 0.85 * diffuse);
+
+    float fog = 1.0 - exp(-distance(CameraPosition, IN.WorldPosition) / FogDistance);
+    Color = vec4(mix(color, FogColor, fog), 1.0);
+}
@@ -0,0 +1,15 @@
+#version 400 core
+
+// Tessellation comes after this, so all there is to do here is hand the patch corners on.  The evaluation shader does
+// the real work of placing vertices (and setting `gl_Position`)
+layout (location = 0) in vec3 Position;
+layout (location = 3) in vec2 TexCoord;
+
+out vec3 ControlPosition;
+out vec2 ControlTexCoord;
+
+void main()
+{
+    ControlPosition = Position;
+    ControlTexCoord = TexCoord;
+}
@@ -0,0 +1,25 @@
+// The heightmap, pasted in after `#version` by `tessellation_demo.rs` so the control and evaluation shaders agree on
+// how high the ground is
+
+uniform sampler2D Heightmap;  // one channel, 0 to 1
+uniform float HeightScale;    // world units for a height of 1
+uniform vec2 TerrainSize;     // world units across X and Z, to work out slopes
+
+float terrain_height(vec2 tex_coord)
+{
+    // Only the fragment shader gets derivatives for picking a mipmap, so everything else has to say which it wants
+    return textureLod(Heightmap, tex_coord, 0.0).r * HeightScale;
+}
+
+// Steps a few texels either way to find which way the ground slopes
+vec3 terrain_normal(vec2 tex_coord)
+{
+    vec2 texel = 1.0 / vec2(textureSize(Heightmap, 0));
+    float left = terrain_height(tex_coord - vec2(texel.x, 0.0));
+    float right = terrain_height(tex_coord + vec2(texel.x, 0.0));
+    float down = terrain_height(tex_coord - vec2(0.0, texel.y));
+    float up = terrain_height(tex_coord + vec2(0.0, texel.y));
+    vec2 span = 2.0 * texel * TerrainSize;
+    // Texture v runs along -Z, so going "up" the texture is going away from the camera
+    return normalize(vec3((left - right) / span.x, 1.0, (up - down) / span.y));
+}
@@ -0,0 +1,114 @@
+// Tessellation: two optional stages between the vertex and fragment shaders that take a "patch" of vertices and split
+// it into lots of little triangles.  The control shader runs once per vertex of each patch, and decides how finely to
+// split it: an outer level for each edge, and inner levels for the middle.  Fixed-function hardware then makes that
+// many new vertices, and the evaluation shader runs once for each of them, given where it sits in the patch
+// (`gl_TessCoord`), to work out where it really goes, which is where things like displacement from a heightmap happen.
+//
+// A patch is just some number of vertices, with no shape of its own, so draws have to say how many go in each one (see
+// `Mesh::draw_patches()`).  If there's no control shader, every patch uses the same levels, from
+// `set_default_levels()`.  Neighbouring patches only line up if they pick the same level for the edge they share,
+// which is why levels are usually worked out from the edge itself rather than the patch as a whole.
+//
+// This needs GL 4.0, so like compute, a demo has to ask for it with `request_context()` before making its window.
+
+use crate::math::{Vec2, Vec3};
+use crate::mesh::Mesh;
+use crate::render_gl;
+use crate::vertex::Vertex;
+
+/// The oldest GL that has tessellation shaders
+pub const MIN_GL_VERSION: (u8, u8) = (4, 0);
+
+/// Asks for a context new enough for tessellation.  Call this where a demo would otherwise ask for 3.3, before
+/// creating the window
+#[allow(dead_code)]
+pub fn request_context(video_subsystem: &sdl2::VideoSubsystem) {
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+    gl_attr.set_context_version(MIN_GL_VERSION.0, MIN_GL_VERSION.1);
+}
+
+/// Whether the current context can tessellate
+#[allow(dead_code)]
+pub fn is_supported() -> bool {
+    render_gl::context_version() >= (MIN_GL_VERSION.0 as i32, MIN_GL_VERSION.1 as i32)
+}
+
+/// The finest a patch can be split, per edge: at least 64
+#[allow(dead_code)]
+pub fn max_level() -> f32 {
+    let mut level: gl::types::GLint = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAX_TESS_GEN_LEVEL, &mut level);
+    }
+    level as f32
+}
+
+/// The most vertices one patch can have: at least 32
+#[allow(dead_code)]
+pub fn max_patch_vertices() -> u32 {
+    let mut count: gl::types::GLint = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAX_PATCH_VERTICES, &mut count);
+    }
+    count as u32
+}
+
+/// Says how many vertices make up each patch in the draws that follow.  `Mesh::draw_patches()` calls this itself
+#[allow(dead_code)]
+pub fn set_patch_vertices(count: u32) {
+    assert!((1..=max_patch_vertices()).contains(&count), "{} vertices per patch isn't allowed", count);
+    unsafe {
+        gl::PatchParameteri(gl::PATCH_VERTICES, count as gl::types::GLint);
+    }
+}
+
+/// The levels patches are split with when the program has no tessellation control shader to choose them.  For quads
+/// all four outer levels (left, bottom, right, top edges) and both inner ones (across, then up) are used; triangles use
+/// the first three outer levels and the first inner one; isolines use the first two outer levels (how many lines, and
+/// how many segments each) and no inner ones
+#[allow(dead_code)]
+pub fn set_default_levels(outer: [f32; 4], inner: [f32; 2]) {
+    unsafe {
+        gl::PatchParameterfv(gl::PATCH_DEFAULT_OUTER_LEVEL, outer.as_ptr());
+        gl::PatchParameterfv(gl::PATCH_DEFAULT_INNER_LEVEL, inner.as_ptr());
+    }
+}
+
+/// Reads back the levels from `set_default_levels()`, as (outer, inner).  GL starts them all at 1
+#[allow(dead_code)]
+pub fn default_levels() -> ([f32; 4], [f32; 2]) {
+    let mut outer = [0.0f32; 4];
+    let mut inner = [0.0f32; 2];
+    unsafe {
+        gl::GetFloatv(gl::PATCH_DEFAULT_OUTER_LEVEL, outer.as_mut_ptr());
+        gl::GetFloatv(gl::PATCH_DEFAULT_INNER_LEVEL, inner.as_mut_ptr());
+    }
+    (outer, inner)
+}
+
+/// A `width` by `depth` grid of quad patches in the XZ plane, facing +Y, for drawing with `draw_patches(4)`.  Each
+/// patch's corners come in the order a `quads` evaluation shader wants to mix them in: (u, v) = (0, 0), (1, 0), (1, 1)
+/// then (0, 1), with u going along +X and v along -Z.  Texture coordinates cover the whole grid once
+#[allow(dead_code)]
+pub fn quad_patches(width: f32, depth: f32, x_patches: u32, z_patches: u32) -> Mesh {
+    let (x_patches, z_patches) = (x_patches.max(1), z_patches.max(1));
+    let mut vertices = Vec::new();
+    for j in 0..=z_patches {
+        for i in 0..=x_patches {
+            let (u, v) = (i as f32 / x_patches as f32, j as f32 / z_patches as f32);
+            let position = Vec3::new((u - 0.5) * width, 0.0, (0.5 - v) * depth);
+            vertices.push(Vertex::new(position, Vec3::Y, Vec2::new(u, v)));
+        }
+    }
+
+    let row = x_patches + 1;
+    let mut indices = Vec::new();
+    for j in 0..z_patches {
+        for i in 0..x_patches {
+            let corner = j * row + i;
+            indices.extend_from_slice(&[corner, corner + 1, corner + row + 1, corner + row]);
+        }
+    }
+    Mesh::new(&vertices, &Vertex::layout(), Some(&indices), gl::PATCHES)
+}
@@ -0,0 +1,236 @@
+use std::ffi::CString;
+
+use crate::camera::{self, Camera, CameraController, CameraUniforms, FlyController};
+use crate::math::{Vec2, Vec3};
+use crate::program::Program;
+use crate::render_gl::{self, Shader};
+use crate::render_state::{PolygonMode, RenderState, RenderStateCache};
+use crate::tessellation;
+use crate::texture::Texture;
+use crate::uniform_block::BlockBuffer;
+
+const HEIGHTMAP_SIZE: u32 = 256;
+const TERRAIN_SIZE: f32 = 64.0;
+const PATCHES: u32 = 16;  // along each side, so 256 patches of 4 by 4 units
+const HEIGHT_SCALE: f32 = 10.0;
+
+/// Same tiny hash as the instancing benchmark's, so the terrain comes out the same every run
+fn hash(n: u32) -> f32 {
+    let n = n.wrapping_mul(0x9E37_79B9) ^ (n >> 15);
+    (n.wrapping_mul(0x85EB_CA6B) >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Random heights on a grid `cells` across, smoothly blended in between, and wrapping at the edges
+fn value_noise(x: f32, y: f32, cells: u32, seed: u32) -> f32 {
+    let corner = |i: u32, j: u32| hash((j % cells) * cells + (i % cells) + seed * 7919);
+    let (i, j) = (x.floor() as u32, y.floor() as u32);
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (u, v) = (smooth(x.fract()), smooth(y.fract()));
+    let bottom = corner(i, j) + (corner(i + 1, j) - corner(i, j)) * u;
+    let top = corner(i, j + 1) + (corner(i + 1, j + 1) - corner(i, j + 1)) * u;
+    bottom + (top - bottom) * v
+}
+
+/// Hills from a few octaves of noise, each twice as fine and half as tall as the last, pushed down at the low end so
+/// there are flat valleys between them
+fn heightmap() -> Vec<f32> {
+    let mut heights = Vec::with_capacity((HEIGHTMAP_SIZE * HEIGHTMAP_SIZE) as usize);
+    for y in 0..HEIGHTMAP_SIZE {
+        for x in 0..HEIGHTMAP_SIZE {
+            let (u, v) = (x as f32 / HEIGHTMAP_SIZE as f32, y as f32 / HEIGHTMAP_SIZE as f32);
+            let (mut height, mut amplitude, mut total, mut cells) = (0.0, 1.0, 0.0, 4);
+            for octave in 0..6 {
+                height += value_noise(u * cells as f32, v * cells as f32, cells, octave) * amplitude;
+                total += amplitude;
+                amplitude *= 0.5;
+                cells *= 2;
+            }
+            heights.push((height / total).powf(2.2).clamp(0.0, 1.0) * 1.6);
+        }
+    }
+    let highest = heights.iter().cloned().fold(0.0f32, f32::max).max(0.001);
+    heights.iter().map(|height| height / highest).collect()
+}
+
+/// Links the terrain program, with the heightmap functions and `Camera` block pasted into every stage after the
+/// vertex shader.  Without a control shader, patches are split by the default levels instead
+fn terrain_program(with_control: bool) -> Result<Program, String> {
+    let stage = |source: &str| {
+        let source = render_gl::insert_after_version(source, include_str!("terrain_common.glsl"));
+        CString::new(camera::with_camera_block(&source)).unwrap()
+    };
+    let mut shaders = vec![Shader::from_vert_source(&CString::new(include_str!("terrain.vert")).unwrap())?];
+    if with_control {
+        shaders.push(Shader::from_tess_control_source(&stage(include_str!("terrain.tesc")))?);
+    }
+    shaders.push(Shader::from_tess_evaluation_source(&stage(include_str!("terrain.tese")))?);
+    shaders.push(Shader::from_frag_source(&stage(include_str!("terrain.frag")))?);
+    Program::from_shaders(&shaders)
+}
+
+/// A terrain drawn from a 16 by 16 grid of flat patches, tessellated on the GPU and lifted up by a heightmap that's
+/// generated on the CPU at startup.  The control shader splits each patch edge more finely the closer it is to the
+/// camera, so the hills nearby are detailed and the ones in the distance cost hardly anything.  T switches to a program
+/// with no control shader, where every patch gets the same default level instead; Up and Down change the closest level
+/// (or the fixed one), and L toggles wireframe to see the triangles.  Before anything's drawn, a control shader is
+/// linked without an evaluation shader, to show what `Program::from_shaders()` says about it.
+///
+/// WASD, Space and LCtrl fly, with the right mouse button held to look around.  This needs OpenGL 4.0, so it asks for
+/// a newer context than most of the other lessons do
+pub fn tessellation_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here.  Tessellation needs 4.0, rather than the 3.3 everything else asks for
+    tessellation::request_context(&video_subsystem);
+    video_subsystem.gl_attr().set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    let fog_color = Vec3::new(0.62, 0.72, 0.82);
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(fog_color.x, fog_color.y, fog_color.z, 1.0);
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+
+    if !tessellation::is_supported() {
+        println!("Failed to set up tessellation: this needs OpenGL {}.{}", tessellation::MIN_GL_VERSION.0,
+                 tessellation::MIN_GL_VERSION.1);
+        return;
+    }
+    let unpaired = Shader::from_tess_control_source(&CString::new(
+        camera::with_camera_block(&render_gl::insert_after_version(include_str!("terrain.tesc"),
+                                                                   include_str!("terrain_common.glsl"))),
+    ).unwrap());
+    if let Ok(unpaired) = unpaired {
+        match Shader::from_vert_source(&CString::new(include_str!("terrain.vert")).unwrap()) {
+            Ok(vert) => match Program::from_shaders(&[vert, unpaired]) {
+                Ok(_) => println!("A control shader with no evaluation shader somehow linked"),
+                Err(e) => println!("As expected, a control shader can't be linked on its own: {}", e),
+            },
+            Err(e) => println!("Failed to compile the terrain's vertex shader: {}", e),
+        }
+    }
+
+    let mut camera_buffer = BlockBuffer::<CameraUniforms>::new(camera::CAMERA_BINDING);
+    let setup = || -> Result<[Program; 2], String> {
+        let programs = [terrain_program(true)?, terrain_program(false)?];
+        for program in &programs {
+            camera_buffer.attach(program, "Camera")?;
+        }
+        Ok(programs)
+    };
+    let programs = match setup() {
+        Ok(programs) => programs,
+        Err(e) => {
+            println!("Failed to set up the terrain: {}", e);
+            return;
+        },
+    };
+
+    let heightmap = Texture::from_f32(HEIGHTMAP_SIZE, HEIGHTMAP_SIZE, 1, &heightmap(), gl::R32F);
+    let terrain = tessellation::quad_patches(TERRAIN_SIZE, TERRAIN_SIZE, PATCHES, PATCHES);
+    let max_level = tessellation::max_level();
+    for program in &programs {
+        program.set_used();
+        program.set_uniform_i32("Heightmap", 0);
+        program.set_uniform_f32("HeightScale", HEIGHT_SCALE);
+        program.set_uniform_vec2("TerrainSize", Vec2::new(TERRAIN_SIZE, TERRAIN_SIZE));
+        program.set_uniform_vec3("SunDirection", Vec3::new(0.6, 0.7, 0.3));
+        program.set_uniform_vec3("FogColor", fog_color);
+        program.set_uniform_f32("FogDistance", 90.0);
+    }
+    programs[0].set_uniform_f32("MinLevel", 1.0);
+    programs[0].set_uniform_f32("NearDistance", 4.0);
+    programs[0].set_uniform_f32("FarDistance", 70.0);
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 16.0, 40.0), width as f32 / height as f32);
+    camera.far = 200.0;
+    let mut controller = FlyController::new();
+    controller.speed = 8.0;
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut render_state = RenderState::opaque();
+    let mut distance_based = true;
+    let mut closest_level = 32.0f32.min(max_level);
+    let mut fixed_level = 8.0f32;
+    let mut time = 0.0f32;
+    let frequency = timer.performance_frequency() as f64;
+    let mut last_counter = timer.performance_counter();
+    let mut changed = true;
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::T), .. } => {
+                    distance_based = !distance_based;
+                    changed = true;
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::L), .. } => {
+                    render_state.polygon_mode = match render_state.polygon_mode {
+                        PolygonMode::Fill => PolygonMode::Line,
+                        _ => PolygonMode::Fill,
+                    };
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(key @ (Keycode::Up | Keycode::Down)), .. } => {
+                    let level = if distance_based { &mut closest_level } else { &mut fixed_level };
+                    let factor = if key == Keycode::Up { 2.0 } else { 0.5 };
+                    *level = (*level * factor).clamp(1.0, max_level);
+                    changed = true;
+                },
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let counter = timer.performance_counter();
+        let dt = ((counter - last_counter) as f64 / frequency) as f32;
+        last_counter = counter;
+        time += dt;
+        controller.update(&mut camera, dt);
+
+        if changed {
+            programs[0].set_used();
+            programs[0].set_uniform_f32("MaxLevel", closest_level);
+            // Only matters to the program without a control shader, but it isn't part of any program: it's context
+            // state, like the patch size
+            tessellation::set_default_levels([fixed_level; 4], [fixed_level; 2]);
+            let levels = if distance_based {
+                format!("levels 1 to {} by distance", closest_level)
+            } else {
+                format!("every patch at level {}", fixed_level)
+            };
+            window.set_title(&format!("{} patches, {}", PATCHES * PATCHES, levels)).unwrap();
+            changed = false;
+        }
+
+        camera_buffer.update(&camera.uniforms(time));
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        render_state_cache.apply(&render_state);
+        let program = &programs[if distance_based { 0 } else { 1 }];
+        program.set_used();
+        heightmap.bind(0);
+        if let Err(e) = terrain.draw_patches(4) {
+            println!("Failed to draw the terrain: {}", e);
+            break 'main;
+        }
+
+        window.gl_swap_window();
+    }
+}
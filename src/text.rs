@@ -0,0 +1,906 @@
+// Text, drawn from a TrueType font.  A TrueType file is a directory of tables, each named by four letters: `cmap` maps
+// characters to glyph numbers, `loca` says where each glyph's outline lives inside `glyf`, `hmtx` says how far to move
+// along after each glyph, and `kern` nudges particular pairs (like "AV") closer together or further apart.  Outlines
+// are closed loops of straight lines and quadratic curves, in "font units" with y going up.
+//
+// Nothing looks at outlines while drawing.  `FontAtlas::bake()` draws every character it'll need into one texture up
+// front, either as plain coverage (sharp at the size it was baked at, blurry or blocky at other sizes) or as a signed
+// distance field, where each texel stores how far it is from the outline.  Blending between texels of a distance
+// field gives a distance that's still about right, so the shader can find a crisp edge at nearly any size or angle.
+//
+// Strings get laid out into one quad per glyph (with kerning, wrapping and alignment) and drawn through a
+// `SpriteBatch`, either in window pixels with a `Camera2D` or on a plane somewhere in the 3D world.
+//
+// Only the old `kern` table is read, so fonts that only keep their kerning in OpenType's `GPOS` table come out
+// unkerned.  Hinting instructions are ignored, and OpenType fonts with CFF outlines (most `.otf` files) aren't
+// supported.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::image::Image;
+use crate::math::{Mat4, Quat, Vec2, Vec3, Vec4};
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::resources::Resources;
+use crate::sprite::{Camera2D, Region, Sprite, SpriteBatch, TextureAtlas};
+use crate::texture::Texture;
+
+/// The characters most text needs, for handing to `FontAtlas::bake()`
+pub const ASCII: &str = concat!(
+    " !\"#$%&'()*+,-./0123456789:;<=>?@",
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`",
+    "abcdefghijklmnopqrstuvwxyz{|}~",
+);
+
+const ATLAS_WIDTH: u32 = 512;
+const COVERAGE_SAMPLES: usize = 4;    // scanlines per row of pixels when baking plain glyphs, for smooth edges
+const DISTANCE_SPREAD: f32 = 6.0;     // how far out from the outline a distance field goes, in baked pixels
+const CURVE_STEPS_PER_EM: f32 = 24.0; // curves get cut into straight pieces about this many to the em
+
+const CUT_SHORT: &str = "font file is cut short";
+
+fn read_u16(bytes: &[u8], at: usize) -> Result<u16, String> {
+    bytes.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]])).ok_or_else(|| CUT_SHORT.to_string())
+}
+
+fn read_i16(bytes: &[u8], at: usize) -> Result<i16, String> {
+    read_u16(bytes, at).map(|value| value as i16)
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Result<u32, String> {
+    bytes.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]])).ok_or_else(|| CUT_SHORT.to_string())
+}
+
+fn read_u8(bytes: &[u8], at: usize) -> Result<u8, String> {
+    bytes.get(at).copied().ok_or_else(|| CUT_SHORT.to_string())
+}
+
+/// A straight piece of a glyph's outline
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    a: Vec2,
+    b: Vec2,
+}
+
+/// A parsed TrueType font.  Distances are all in font units, of which there are `units_per_em()` to the em
+pub struct Font {
+    data: Vec<u8>,
+    units_per_em: f32,
+    ascent: f32,      // how far the tallest glyphs reach above the baseline
+    descent: f32,     // and how far below it the lowest ones go, which is negative
+    line_gap: f32,    // extra space the font wants between lines
+    glyph_count: u16,
+    long_offsets: bool,  // whether `loca` holds u32 offsets, or u16 ones that have been halved
+    loca: usize,
+    glyf: usize,
+    hmtx: usize,
+    long_metrics: u16,   // glyphs past this many all share the last advance in `hmtx`
+    characters: HashMap<char, u16>,
+    kerning: HashMap<(u16, u16), i16>,
+}
+
+#[allow(dead_code)]
+impl Font {
+    pub fn load(res: &Resources, resource_name: &str) -> Result<Font, String> {
+        let bytes = res.load_buffer(resource_name).map_err(|e| format!("{}: {}", resource_name, e))?;
+        Font::parse(bytes).map_err(|e| format!("{}: {}", resource_name, e))
+    }
+
+    pub fn parse(data: Vec<u8>) -> Result<Font, String> {
+        let version = read_u32(&data, 0)?;
+        if data.starts_with(b"OTTO") {
+            return Err("fonts with CFF outlines aren't supported, only TrueType ones".to_string());
+        } else if version != 0x0001_0000 && version != u32::from_be_bytes(*b"true") {
+            return Err("not a TrueType font".to_string());
+        }
+
+        let mut tables: HashMap<[u8; 4], usize> = HashMap::new();
+        for i in 0..read_u16(&data, 4)? as usize {
+            let record = 12 + i * 16;
+            let mut tag = [0u8; 4];
+            tag.copy_from_slice(data.get(record..record + 4).ok_or(CUT_SHORT)?);
+            tables.insert(tag, read_u32(&data, record + 8)? as usize);
+        }
+        let table = |tag: &[u8; 4]| {
+            tables.get(tag).copied().ok_or_else(|| format!("font has no '{}' table", String::from_utf8_lossy(tag)))
+        };
+
+        let head = table(b"head")?;
+        let hhea = table(b"hhea")?;
+        let cmap = table(b"cmap")?;
+        let characters = read_cmap(&data, cmap)?;
+        let kerning = match tables.get(b"kern") {
+            Some(&kern) => read_kern(&data, kern)?,
+            None => HashMap::new(),
+        };
+
+        Ok(Font {
+            units_per_em: read_u16(&data, head + 18)?.max(1) as f32,
+            ascent: read_i16(&data, hhea + 4)? as f32,
+            descent: read_i16(&data, hhea + 6)? as f32,
+            line_gap: read_i16(&data, hhea + 8)? as f32,
+            glyph_count: read_u16(&data, table(b"maxp")? + 4)?,
+            long_offsets: read_i16(&data, head + 50)? != 0,
+            loca: table(b"loca")?,
+            glyf: table(b"glyf")?,
+            hmtx: table(b"hmtx")?,
+            long_metrics: read_u16(&data, hhea + 34)?.max(1),
+            characters,
+            kerning,
+            data,
+        })
+    }
+
+    pub fn units_per_em(&self) -> f32 {
+        self.units_per_em
+    }
+
+    pub fn ascent(&self) -> f32 {
+        self.ascent
+    }
+
+    pub fn descent(&self) -> f32 {
+        self.descent
+    }
+
+    /// Baseline to baseline
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+
+    /// Which glyph draws `c`, if the font has one
+    pub fn glyph_index(&self, c: char) -> Option<u16> {
+        self.characters.get(&c).copied()
+    }
+
+    /// How far to move along after drawing `glyph`
+    pub fn advance(&self, glyph: u16) -> f32 {
+        let index = glyph.min(self.long_metrics - 1) as usize;
+        read_u16(&self.data, self.hmtx + index * 4).unwrap_or(0) as f32
+    }
+
+    /// Extra space to leave between `left` and `right` when they're next to each other, which is usually negative
+    pub fn kerning(&self, left: u16, right: u16) -> f32 {
+        self.kerning.get(&(left, right)).copied().unwrap_or(0) as f32
+    }
+
+    /// Where `glyph`'s outline lives in `glyf`.  Glyphs with nothing to draw, like spaces, start and end in the same
+    /// place
+    fn glyph_range(&self, glyph: u16) -> Result<(usize, usize), String> {
+        if glyph >= self.glyph_count {
+            return Err(format!("glyph {} is past the end of the font", glyph));
+        }
+        let offset = |i: usize| -> Result<usize, String> {
+            if self.long_offsets {
+                Ok(read_u32(&self.data, self.loca + i * 4)? as usize)
+            } else {
+                Ok(read_u16(&self.data, self.loca + i * 2)? as usize * 2)
+            }
+        };
+        Ok((self.glyf + offset(glyph as usize)?, self.glyf + offset(glyph as usize + 1)?))
+    }
+
+    /// `glyph`'s outline as straight edges, in font units
+    fn outline(&self, glyph: u16, depth: u32) -> Result<Vec<Edge>, String> {
+        let (start, end) = self.glyph_range(glyph)?;
+        if end <= start {
+            return Ok(Vec::new());
+        }
+        let data = &self.data;
+        let contours = read_i16(data, start)?;
+        if contours < 0 {
+            return self.composite_outline(start, depth);
+        }
+
+        let contours = contours as usize;
+        let mut ends = Vec::with_capacity(contours);
+        for i in 0..contours {
+            ends.push(read_u16(data, start + 10 + i * 2)? as usize);
+        }
+        let point_count = ends.last().map_or(0, |&last| last + 1);
+        let instructions = read_u16(data, start + 10 + contours * 2)? as usize;
+        let mut at = start + 12 + contours * 2 + instructions;
+
+        // A flag per point comes first, with a bit meaning "this flag again, N more times"
+        let mut flags = Vec::with_capacity(point_count);
+        while flags.len() < point_count {
+            let flag = read_u8(data, at)?;
+            at += 1;
+            let repeats = if flag & 0x08 != 0 {
+                at += 1;
+                read_u8(data, at - 1)? as usize
+            } else {
+                0
+            };
+            flags.extend(std::iter::repeat_n(flag, repeats + 1));
+        }
+        flags.truncate(point_count);
+
+        // Then all the x coordinates, then all the y ones, as changes from the point before.  Each is either a byte
+        // with its sign in the flag, nothing at all (the same as the last point), or a whole i16
+        let mut read_coordinates = |short: u8, same_or_positive: u8| -> Result<Vec<f32>, String> {
+            let mut value = 0i32;
+            let mut values = Vec::with_capacity(point_count);
+            for &flag in &flags {
+                if flag & short != 0 {
+                    let delta = read_u8(data, at)? as i32;
+                    at += 1;
+                    value += if flag & same_or_positive != 0 { delta } else { -delta };
+                } else if flag & same_or_positive == 0 {
+                    value += read_i16(data, at)? as i32;
+                    at += 2;
+                }
+                values.push(value as f32);
+            }
+            Ok(values)
+        };
+        let xs = read_coordinates(0x02, 0x10)?;
+        let ys = read_coordinates(0x04, 0x20)?;
+
+        let step = self.units_per_em / CURVE_STEPS_PER_EM;
+        let mut edges = Vec::new();
+        let mut first = 0;
+        for &last in &ends {
+            if last < first || last >= point_count {
+                return Err(format!("glyph {} has a broken outline", glyph));
+            }
+            let points: Vec<(Vec2, bool)> = (first..=last)
+                .map(|i| (Vec2::new(xs[i], ys[i]), flags[i] & 0x01 != 0))
+                .collect();
+            add_contour(&points, step, &mut edges);
+            first = last + 1;
+        }
+        Ok(edges)
+    }
+
+    /// A glyph made out of other glyphs, each moved and possibly scaled, like an "é" made from an "e" and an accent
+    fn composite_outline(&self, start: usize, depth: u32) -> Result<Vec<Edge>, String> {
+        if depth > 8 {
+            return Err("composite glyphs are nested too deeply".to_string());
+        }
+        let data = &self.data;
+        let f2dot14 = |at: usize| read_i16(data, at).map(|value| value as f32 / 16384.0);
+        let mut at = start + 10;
+        let mut edges = Vec::new();
+        loop {
+            let flags = read_u16(data, at)?;
+            let component = read_u16(data, at + 2)?;
+            at += 4;
+            let (dx, dy) = if flags & 0x0001 != 0 {
+                at += 4;
+                (read_i16(data, at - 4)? as f32, read_i16(data, at - 2)? as f32)
+            } else {
+                at += 2;
+                (read_u8(data, at - 2)? as i8 as f32, read_u8(data, at - 1)? as i8 as f32)
+            };
+            // Without this flag the two numbers are points to line up rather than an offset, which is rare enough
+            // that the component just stays where it is
+            let (dx, dy) = if flags & 0x0002 != 0 { (dx, dy) } else { (0.0, 0.0) };
+            let (xx, xy, yx, yy) = if flags & 0x0008 != 0 {
+                at += 2;
+                let scale = f2dot14(at - 2)?;
+                (scale, 0.0, 0.0, scale)
+            } else if flags & 0x0040 != 0 {
+                at += 4;
+                (f2dot14(at - 4)?, 0.0, 0.0, f2dot14(at - 2)?)
+            } else if flags & 0x0080 != 0 {
+                at += 8;
+                (f2dot14(at - 8)?, f2dot14(at - 6)?, f2dot14(at - 4)?, f2dot14(at - 2)?)
+            } else {
+                (1.0, 0.0, 0.0, 1.0)
+            };
+
+            let transform = |p: Vec2| Vec2::new(p.x * xx + p.y * yx + dx, p.x * xy + p.y * yy + dy);
+            for edge in self.outline(component, depth + 1)? {
+                edges.push(Edge { a: transform(edge.a), b: transform(edge.b) });
+            }
+            if flags & 0x0020 == 0 {
+                break;  // no more components
+            }
+        }
+        Ok(edges)
+    }
+}
+
+/// Reads the best character map the font has: the full Unicode one if it's there, otherwise the one covering the
+/// Basic Multilingual Plane, which is plenty for most text
+fn read_cmap(data: &[u8], cmap: usize) -> Result<HashMap<char, u16>, String> {
+    let mut best: Option<(u16, usize)> = None;
+    for i in 0..read_u16(data, cmap + 2)? as usize {
+        let record = cmap + 4 + i * 8;
+        let (platform, encoding) = (read_u16(data, record)?, read_u16(data, record + 2)?);
+        let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+        let subtable = cmap + read_u32(data, record + 4)? as usize;
+        let format = read_u16(data, subtable)?;
+        if unicode && (format == 4 || format == 12) && best.is_none_or(|(f, _)| format > f) {
+            best = Some((format, subtable));
+        }
+    }
+
+    let mut characters = HashMap::new();
+    let mut insert = |code: u32, glyph: u32| {
+        if let Some(c) = std::char::from_u32(code) {
+            if glyph != 0 {
+                characters.insert(c, glyph as u16);
+            }
+        }
+    };
+    match best {
+        Some((12, table)) => {
+            // Groups of consecutive characters that map to consecutive glyphs
+            for i in 0..read_u32(data, table + 12)? as usize {
+                let group = table + 16 + i * 12;
+                let (first, last) = (read_u32(data, group)?, read_u32(data, group + 4)?);
+                let glyph = read_u32(data, group + 8)?;
+                for code in first..=last.min(0x10FFFF) {
+                    insert(code, glyph + (code - first));
+                }
+            }
+        },
+        Some((_, table)) => {
+            // Segments of characters, each either offset by a fixed amount or looked up in an array
+            let segments = read_u16(data, table + 6)? as usize / 2;
+            let ends = table + 14;
+            let starts = ends + segments * 2 + 2;
+            let deltas = starts + segments * 2;
+            let range_offsets = deltas + segments * 2;
+            for segment in 0..segments {
+                let last = read_u16(data, ends + segment * 2)? as u32;
+                let first = read_u16(data, starts + segment * 2)? as u32;
+                let delta = read_u16(data, deltas + segment * 2)? as u32;
+                let range_offset = read_u16(data, range_offsets + segment * 2)? as usize;
+                for code in first..=last.min(0xFFFE) {
+                    let glyph = if range_offset == 0 {
+                        code + delta
+                    } else {
+                        let at = range_offsets + segment * 2 + range_offset + (code - first) as usize * 2;
+                        match read_u16(data, at)? as u32 {
+                            0 => 0,
+                            glyph => glyph + delta,
+                        }
+                    };
+                    insert(code, glyph & 0xFFFF);
+                }
+            }
+        },
+        None => return Err("font has no Unicode character map".to_string()),
+    }
+    Ok(characters)
+}
+
+/// Reads the horizontal kerning pairs out of a `kern` table
+fn read_kern(data: &[u8], kern: usize) -> Result<HashMap<(u16, u16), i16>, String> {
+    let mut pairs = HashMap::new();
+    if read_u16(data, kern)? != 0 {
+        return Ok(pairs);  // Apple's version of the table, which this doesn't read
+    }
+    let mut subtable = kern + 4;
+    for _ in 0..read_u16(data, kern + 2)? {
+        let length = read_u16(data, subtable + 2)? as usize;
+        let coverage = read_u16(data, subtable + 4)?;
+        // Format 0 (a sorted list of pairs), horizontal, and adjusting along the line rather than across it
+        if coverage >> 8 == 0 && coverage & 0x0007 == 0x0001 {
+            for i in 0..read_u16(data, subtable + 6)? as usize {
+                let pair = subtable + 14 + i * 6;
+                pairs.insert((read_u16(data, pair)?, read_u16(data, pair + 2)?), read_i16(data, pair + 4)?);
+            }
+        }
+        subtable += length;
+    }
+    Ok(pairs)
+}
+
+/// Turns one closed contour of TrueType points into straight edges.  Two curve control points in a row have an
+/// on-curve point halfway between them that isn't stored, so those get put back first
+fn add_contour(points: &[(Vec2, bool)], step: f32, edges: &mut Vec<Edge>) {
+    let mut expanded = Vec::with_capacity(points.len() * 2);
+    for (i, &(point, on_curve)) in points.iter().enumerate() {
+        let (next, next_on_curve) = points[(i + 1) % points.len()];
+        expanded.push((point, on_curve));
+        if !on_curve && !next_on_curve {
+            expanded.push(((point + next) * 0.5, true));
+        }
+    }
+    let start = match expanded.iter().position(|&(_, on_curve)| on_curve) {
+        Some(start) => start,
+        None => return,
+    };
+    expanded.rotate_left(start);
+
+    let mut pen = expanded[0].0;
+    let mut control = None;
+    for &(point, on_curve) in expanded.iter().skip(1).chain(std::iter::once(&expanded[0])) {
+        if !on_curve {
+            control = Some(point);
+            continue;
+        }
+        match control.take() {
+            None => edges.push(Edge { a: pen, b: point }),
+            Some(control) => {
+                let length = (control - pen).length() + (point - control).length();
+                let steps = (length / step).ceil().clamp(1.0, 16.0) as usize;
+                let mut previous = pen;
+                for i in 1..=steps {
+                    let t = i as f32 / steps as f32;
+                    let next = pen * ((1.0 - t) * (1.0 - t)) + control * (2.0 * t * (1.0 - t)) + point * (t * t);
+                    edges.push(Edge { a: previous, b: next });
+                    previous = next;
+                }
+            },
+        }
+        pen = point;
+    }
+}
+
+/// Where `edges` cross the horizontal line at `y`, and which way each was heading (1 for down, -1 for up), from left
+/// to right
+fn crossings(edges: &[Edge], y: f32, hits: &mut Vec<(f32, i32)>) {
+    hits.clear();
+    for edge in edges {
+        let (a, b) = (edge.a, edge.b);
+        // Half open, so that where two edges meet only one of them counts
+        if (a.y <= y) != (b.y <= y) {
+            let t = (y - a.y) / (b.y - a.y);
+            hits.push((a.x + (b.x - a.x) * t, if b.y > a.y { 1 } else { -1 }));
+        }
+    }
+    hits.sort_by(|l, r| l.0.partial_cmp(&r.0).unwrap_or(Ordering::Equal));
+}
+
+/// How much of each pixel is inside the outline, using the non-zero winding rule TrueType asks for.  A few scanlines go
+/// through each row of pixels, and each span between crossings covers the pixels it passes over by however much of
+/// them it overlaps
+fn rasterize_coverage(edges: &[Edge], width: usize, height: usize) -> Vec<f32> {
+    let mut coverage = vec![0.0; width * height];
+    let mut hits = Vec::new();
+    let weight = 1.0 / COVERAGE_SAMPLES as f32;
+    for (y, row) in coverage.chunks_mut(width.max(1)).enumerate() {
+        for sample in 0..COVERAGE_SAMPLES {
+            crossings(edges, y as f32 + (sample as f32 + 0.5) * weight, &mut hits);
+            let mut winding = 0;
+            let mut span_start = 0.0;
+            for &(x, direction) in &hits {
+                let was_inside = winding != 0;
+                winding += direction;
+                if !was_inside && winding != 0 {
+                    span_start = x;
+                } else if was_inside && winding == 0 {
+                    let (from, to) = (span_start.max(0.0), x.min(width as f32));
+                    let pixels = row.iter_mut().enumerate().take(to.ceil().max(0.0) as usize).skip(from as usize);
+                    for (px, pixel) in pixels {
+                        *pixel += (to.min(px as f32 + 1.0) - from.max(px as f32)).max(0.0) * weight;
+                    }
+                }
+            }
+        }
+    }
+    coverage
+}
+
+/// Signed distance from each pixel's center to the outline, positive inside, mapped so that 0.5 is right on the
+/// outline and 0 and 1 are `spread` pixels outside and inside it
+fn rasterize_distance(edges: &[Edge], width: usize, height: usize, spread: f32) -> Vec<f32> {
+    let mut distances = Vec::with_capacity(width * height);
+    let mut hits = Vec::new();
+    for y in 0..height {
+        let center_y = y as f32 + 0.5;
+        crossings(edges, center_y, &mut hits);
+        for x in 0..width {
+            let p = Vec2::new(x as f32 + 0.5, center_y);
+            let nearest = edges.iter().map(|edge| distance_to_edge(p, edge)).fold(f32::INFINITY, f32::min);
+            let winding: i32 = hits.iter().take_while(|&&(hit, _)| hit < p.x).map(|&(_, direction)| direction).sum();
+            let signed = if winding != 0 { nearest } else { -nearest };
+            distances.push((0.5 + signed / (2.0 * spread)).clamp(0.0, 1.0));
+        }
+    }
+    distances
+}
+
+fn distance_to_edge(p: Vec2, edge: &Edge) -> f32 {
+    let along = edge.b - edge.a;
+    let length_squared = along.dot(along);
+    let t = if length_squared > 0.0 { ((p - edge.a).dot(along) / length_squared).clamp(0.0, 1.0) } else { 0.0 };
+    (p - (edge.a + along * t)).length()
+}
+
+/// What gets baked into a `FontAtlas`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AtlasKind {
+    /// How much of each pixel the glyph covers.  Looks best drawn at the size it was baked at
+    Bitmap,
+    /// Distance to the outline, which stays sharp when scaled up, rotated, or seen at an angle in 3D
+    DistanceField,
+}
+
+/// One baked character.  Distances are in baked pixels, with y going down
+#[derive(Debug, Clone, Copy)]
+struct BakedGlyph {
+    region: Option<Region>,  // `None` for characters with nothing to draw, like spaces
+    offset: Vec2,            // from the pen position on the baseline to the top-left of the quad
+    size: Vec2,
+    advance: f32,
+}
+
+/// A font's glyphs drawn into a texture at one size, along with everything needed to lay text out with them
+pub struct FontAtlas {
+    atlas: TextureAtlas,
+    kind: AtlasKind,
+    glyphs: HashMap<char, BakedGlyph>,
+    kerning: HashMap<(char, char), f32>,
+    pixel_size: f32,
+    ascent: f32,
+    line_height: f32,
+}
+
+#[allow(dead_code)]
+impl FontAtlas {
+    /// Bakes `characters` with an em `pixel_size` pixels tall.  Characters the font doesn't have are left out, and
+    /// get drawn as "?" instead (if that was baked).  Distance fields don't need to be baked large to look good big,
+    /// but small details like serifs round off below about 32 pixels
+    pub fn bake(font: &Font, pixel_size: f32, kind: AtlasKind, characters: &str) -> Result<FontAtlas, String> {
+        let scale = pixel_size / font.units_per_em();
+        let padding = match kind {
+            AtlasKind::Bitmap => 1,  // so that linear filtering doesn't pull in the next glyph over
+            AtlasKind::DistanceField => DISTANCE_SPREAD.ceil() as i32 + 1,
+        };
+
+        // Draw every glyph on its own first, so they can be packed by size
+        let mut glyphs = HashMap::new();
+        let mut bitmaps: Vec<(char, usize, usize, Vec<f32>)> = Vec::new();
+        let mut glyph_chars: HashMap<u16, char> = HashMap::new();
+        for c in characters.chars() {
+            let glyph = match font.glyph_index(c) {
+                Some(glyph) if !glyphs.contains_key(&c) => glyph,
+                _ => continue,
+            };
+            glyph_chars.insert(glyph, c);
+            let advance = font.advance(glyph) * scale;
+            let edges = font.outline(glyph, 0)?;
+            if edges.is_empty() {
+                let (offset, size) = (Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0));
+                glyphs.insert(c, BakedGlyph { region: None, offset, size, advance });
+                continue;
+            }
+
+            // Into pixels, with y going down from the baseline, and a border of padding all round
+            let (mut min, mut max) = (Vec2::new(f32::MAX, f32::MAX), Vec2::new(f32::MIN, f32::MIN));
+            for edge in &edges {
+                for p in &[edge.a, edge.b] {
+                    min = Vec2::new(min.x.min(p.x * scale), min.y.min(-p.y * scale));
+                    max = Vec2::new(max.x.max(p.x * scale), max.y.max(-p.y * scale));
+                }
+            }
+            let left = min.x.floor() as i32 - padding;
+            let top = min.y.floor() as i32 - padding;
+            let width = (max.x.ceil() as i32 + padding - left) as usize;
+            let height = (max.y.ceil() as i32 + padding - top) as usize;
+            let to_pixels = |p: Vec2| Vec2::new(p.x * scale - left as f32, -p.y * scale - top as f32);
+            let edges: Vec<Edge> = edges.iter().map(|e| Edge { a: to_pixels(e.a), b: to_pixels(e.b) }).collect();
+
+            let values = match kind {
+                AtlasKind::Bitmap => rasterize_coverage(&edges, width, height),
+                AtlasKind::DistanceField => rasterize_distance(&edges, width, height, DISTANCE_SPREAD),
+            };
+            let (offset, size) = (Vec2::new(left as f32, top as f32), Vec2::new(width as f32, height as f32));
+            glyphs.insert(c, BakedGlyph { region: None, offset, size, advance });
+            bitmaps.push((c, width, height, values));
+        }
+
+        // Pack them into rows, tallest first, so that each row wastes as little space as it can
+        bitmaps.sort_by_key(|&(_, _, height, _)| std::cmp::Reverse(height));
+        let atlas_width = bitmaps.iter().map(|&(_, width, _, _)| width as u32).fold(ATLAS_WIDTH, u32::max);
+        let mut places = Vec::with_capacity(bitmaps.len());
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        for &(_, width, height, _) in &bitmaps {
+            if x + width as u32 > atlas_width {
+                x = 0;
+                y += row_height;
+                row_height = 0;
+            }
+            places.push((x, y));
+            x += width as u32;
+            row_height = row_height.max(height as u32);
+        }
+        let atlas_height = (y + row_height).max(1).next_power_of_two();
+
+        let mut image = Image::solid(atlas_width, atlas_height, [255, 255, 255, 0])?;
+        for (&(_, width, _, ref values), &(x, y)) in bitmaps.iter().zip(&places) {
+            for (i, value) in values.iter().enumerate() {
+                let (px, py) = (x + (i % width) as u32, y + (i / width) as u32);
+                let alpha = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                image.pixels[((py * atlas_width + px) * 4 + 3) as usize] = alpha;
+            }
+        }
+        let mut atlas = TextureAtlas::new(Texture::from_image(&image, false));
+        for (&(c, width, height, _), &(x, y)) in bitmaps.iter().zip(&places) {
+            let index = atlas.add_region(&c.to_string(), x, y, width as u32, height as u32);
+            if let Some(glyph) = glyphs.get_mut(&c) {
+                glyph.region = Some(atlas.region(index));
+            }
+        }
+
+        // Only the pairs where both characters were baked are worth keeping
+        let kerning = font.kerning.iter()
+            .filter_map(|(&(left, right), &value)| {
+                Some(((*glyph_chars.get(&left)?, *glyph_chars.get(&right)?), value as f32 * scale))
+            })
+            .collect();
+
+        Ok(FontAtlas {
+            atlas,
+            kind,
+            glyphs,
+            kerning,
+            pixel_size,
+            ascent: font.ascent() * scale,
+            line_height: font.line_height() * scale,
+        })
+    }
+
+    pub fn kind(&self) -> AtlasKind {
+        self.kind
+    }
+
+    pub fn texture(&self) -> &Texture {
+        self.atlas.texture()
+    }
+
+    /// The em size it was baked at, in pixels
+    pub fn pixel_size(&self) -> f32 {
+        self.pixel_size
+    }
+
+    /// Baseline to baseline for text of `size`
+    pub fn line_height(&self, size: f32) -> f32 {
+        self.line_height * size / self.pixel_size
+    }
+
+    fn glyph(&self, c: char) -> Option<&BakedGlyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    fn kern(&self, previous: Option<char>, c: char, kerning: bool) -> f32 {
+        match previous {
+            Some(previous) if kerning => self.kerning.get(&(previous, c)).copied().unwrap_or(0.0),
+            _ => 0.0,
+        }
+    }
+
+    /// Width of a line at the baked size, leaving out any spaces on the end
+    fn line_width(&self, chars: &[char], kerning: bool) -> f32 {
+        let end = chars.iter().rposition(|&c| c != ' ').map_or(0, |last| last + 1);
+        let mut width = 0.0;
+        let mut previous = None;
+        for &c in &chars[..end] {
+            width += self.kern(previous, c, kerning) + self.glyph(c).map_or(0.0, |glyph| glyph.advance);
+            previous = Some(c);
+        }
+        width
+    }
+
+    /// Breaks `chars` into lines no wider than `max_width` (at the baked size), between words where it can
+    fn wrap(&self, chars: &[char], max_width: f32, kerning: bool, lines: &mut Vec<Vec<char>>) {
+        let mut start = 0;
+        let mut break_at = None;  // the last space on the current line
+        for (i, &c) in chars.iter().enumerate() {
+            if c == ' ' {
+                break_at = Some(i);
+                continue;
+            }
+            if i > start && self.line_width(&chars[start..=i], kerning) > max_width {
+                // A word longer than a whole line has to be split wherever it runs out of room
+                let end = match break_at {
+                    Some(space) if space > start => space,
+                    _ => i,
+                };
+                lines.push(chars[start..end].to_vec());
+                start = end;
+                while chars[start] == ' ' {
+                    start += 1;  // can't run past `i`, which isn't a space
+                }
+                break_at = None;
+            }
+        }
+        lines.push(chars[start..].to_vec());
+    }
+
+    /// Places the glyphs of `text`, with the top-left of the text's box at (0, 0) and y going down.  Newlines start a
+    /// new line, and if `style` has a wrap width, so does running out of room
+    pub fn layout(&self, text: &str, style: &TextStyle) -> TextLayout {
+        let scale = style.size / self.pixel_size;
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let chars: Vec<char> = paragraph.chars().filter(|&c| c != '\r').collect();
+            match style.wrap_width {
+                Some(width) => self.wrap(&chars, width / scale, style.kerning, &mut lines),
+                None => lines.push(chars),
+            }
+        }
+
+        let widths: Vec<f32> = lines.iter().map(|line| self.line_width(line, style.kerning) * scale).collect();
+        let box_width = style.wrap_width.unwrap_or_else(|| widths.iter().cloned().fold(0.0, f32::max));
+        let line_height = self.line_height * style.line_spacing * scale;
+        // Bitmap glyphs drawn at the size they were baked at only stay sharp if their texels line up with pixels
+        let snap = self.kind == AtlasKind::Bitmap && (scale - 1.0).abs() < 1e-3;
+
+        let mut glyphs = Vec::new();
+        for (i, (line, &width)) in lines.iter().zip(&widths).enumerate() {
+            let mut pen = Vec2::new(
+                match style.align {
+                    Align::Left => 0.0,
+                    Align::Center => (box_width - width) * 0.5,
+                    Align::Right => box_width - width,
+                },
+                self.ascent * scale + i as f32 * line_height,
+            );
+            let mut previous = None;
+            for &c in line {
+                pen.x += self.kern(previous, c, style.kerning) * scale;
+                previous = Some(c);
+                let glyph = match self.glyph(c) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                if let Some(region) = glyph.region {
+                    let position = pen + glyph.offset * scale;
+                    let position = if snap { Vec2::new(position.x.round(), position.y.round()) } else { position };
+                    glyphs.push(PlacedGlyph { region, position, size: glyph.size * scale });
+                }
+                pen.x += glyph.advance * scale;
+            }
+        }
+
+        TextLayout { glyphs, size: Vec2::new(box_width, lines.len() as f32 * line_height), lines: lines.len() }
+    }
+
+    /// How big `text` would be, without keeping where every glyph goes
+    pub fn measure(&self, text: &str, style: &TextStyle) -> Vec2 {
+        self.layout(text, style).size
+    }
+}
+
+/// How the lines of a piece of text line up with each other (and with the wrap width, if there is one)
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// How to lay out and draw some text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    pub size: f32,                // height of an em, in whatever units the text is drawn in (pixels, on screen)
+    pub color: Vec4,
+    pub align: Align,
+    pub wrap_width: Option<f32>,  // lines longer than this get broken between words
+    pub line_spacing: f32,        // 1 is the font's own line height
+    pub kerning: bool,
+    pub layer: i32,               // passed on to the sprites, so text can go on top of other sprites
+}
+
+#[allow(dead_code)]
+impl TextStyle {
+    /// White, left aligned, kerned and unwrapped text of `size`
+    pub fn new(size: f32) -> TextStyle {
+        TextStyle {
+            size,
+            color: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            align: Align::Left,
+            wrap_width: None,
+            line_spacing: 1.0,
+            kerning: true,
+            layer: 0,
+        }
+    }
+}
+
+/// One glyph of some laid out text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlacedGlyph {
+    pub region: Region,
+    pub position: Vec2,  // top-left of its quad
+    pub size: Vec2,
+}
+
+/// Text that's been broken into lines and had its glyphs placed, ready to draw as many times as needed
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLayout {
+    pub glyphs: Vec<PlacedGlyph>,
+    pub size: Vec2,  // the box the text fills
+    pub lines: usize,
+}
+
+/// Where text drawn between `TextBatch::begin_world()` and `end()` goes: the top-left of the text at `position`, with
+/// lines running along the rotated x axis and stacking down the rotated y axis.  With no rotation the text faces +z
+#[allow(dead_code)]
+pub fn world_placement(position: Vec3, rotation: Quat) -> Mat4 {
+    // Text is laid out with y going down, so it gets flipped to go the right way up in the world
+    Mat4::from_trs(position, rotation, Vec3::new(1.0, -1.0, 1.0))
+}
+
+/// Draws text from any number of `FontAtlas`es.  Distance field glyphs need a different shader from plain ones, so
+/// there's a `SpriteBatch` for each
+pub struct TextBatch {
+    bitmap: SpriteBatch,
+    distance_field: SpriteBatch,
+    glyphs_drawn: usize,
+}
+
+#[allow(dead_code)]
+impl TextBatch {
+    pub fn new() -> Result<TextBatch, String> {
+        Ok(TextBatch {
+            bitmap: SpriteBatch::new()?,
+            distance_field: SpriteBatch::with_fragment_shader(include_str!("sdf_text.frag"))?,
+            glyphs_drawn: 0,
+        })
+    }
+
+    /// Starts a batch of screen space text, in pixels from the top-left of the window
+    pub fn begin(&mut self, camera: &Camera2D) {
+        self.begin_with(camera.matrix());
+    }
+
+    /// Starts a batch of text on a plane in the world, placed by `placement` (see `world_placement()`).  Text sizes
+    /// are in world units
+    pub fn begin_world(&mut self, view: &Mat4, projection: &Mat4, placement: &Mat4) {
+        self.begin_with(*projection * *view * *placement);
+    }
+
+    fn begin_with(&mut self, view_projection: Mat4) {
+        self.bitmap.begin_with(view_projection);
+        self.distance_field.begin_with(view_projection);
+    }
+
+    /// Lays out and queues `text` with the top-left of its box at `position`.  Returns how big the box is
+    pub fn draw(&mut self, font: &FontAtlas, text: &str, position: Vec2, style: &TextStyle) -> Vec2 {
+        let layout = font.layout(text, style);
+        self.draw_layout(font, &layout, position, style.color, style.layer);
+        layout.size
+    }
+
+    /// Queues text that's already been laid out, which saves doing it again for text that doesn't change
+    pub fn draw_layout(&mut self, font: &FontAtlas, layout: &TextLayout, position: Vec2, color: Vec4, layer: i32) {
+        let batch = match font.kind {
+            AtlasKind::Bitmap => &mut self.bitmap,
+            AtlasKind::DistanceField => &mut self.distance_field,
+        };
+        for glyph in &layout.glyphs {
+            let sprite = Sprite {
+                size: glyph.size,
+                origin: Vec2::new(0.0, 0.0),
+                color,
+                layer,
+                ..Sprite::new(glyph.region, position + glyph.position)
+            };
+            batch.draw(font.texture(), &sprite);
+        }
+    }
+
+    /// Draws everything queued since `begin()`
+    pub fn end(&mut self, cache: &mut RenderStateCache) {
+        self.bitmap.end(cache);
+        self.distance_field.end(cache);
+        self.glyphs_drawn = self.bitmap.sprites_drawn() + self.distance_field.sprites_drawn();
+    }
+
+    /// Defaults to `RenderState::overlay()`.  Text in the world that should hide behind things wants
+    /// `RenderState::transparent()`
+    pub fn set_render_state(&mut self, state: RenderState) {
+        self.bitmap.render_state = state;
+        self.distance_field.render_state = state;
+    }
+
+    /// Glyphs the last `end()` drew
+    pub fn glyphs_drawn(&self) -> usize {
+        self.glyphs_drawn
+    }
+
+    /// Draw calls the last `end()` needed
+    pub fn draw_calls(&self) -> usize {
+        self.bitmap.draw_calls() + self.distance_field.draw_calls()
+    }
+}
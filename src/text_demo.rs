@@ -0,0 +1,190 @@
+use std::path::Path;
+
+use crate::camera::{Camera, CameraController, OrbitController};
+use crate::math::{Quat, Vec2, Vec3, Vec4};
+use crate::render_state::RenderStateCache;
+use crate::resources::Resources;
+use crate::sprite::Camera2D;
+use crate::text::{self, Align, AtlasKind, Font, FontAtlas, TextBatch, TextStyle};
+
+const FONT: &str = "fonts/DejaVuSans.ttf";
+const LABELS: [&str; 8] = ["Mercury", "Venus", "Earth", "Mars", "Jupiter", "Saturn", "Uranus", "Neptune"];
+const KERNING_SAMPLE: &str = "WAVY AVATAR Type";  // pairs that kerning pulls in a long way
+const PARAGRAPH: &str = "Text is laid out one glyph at a time, moving the pen along by each glyph's advance and \
+nudging pairs like AV, To and Wa together with the font's kerning table. When a line gets wider than the wrap width, \
+it's broken at the last space.\nNewlines always start a new line, and every line is lined up on its own.";
+
+/// Text in a few different ways: frame stats and help in small plain bitmap text, a wrapped paragraph that can be
+/// realigned, resized and rewrapped, and planet names standing in a slowly turning ring in the 3D world, which use a
+/// distance field atlas so they stay sharp up close.  The font is `assets/fonts/DejaVuSans.ttf`.
+///
+/// A cycles the paragraph's alignment, Left and Right change its wrap width, and + and - its size.  D switches the
+/// paragraph between the distance field and plain bitmap atlases, which shows how blurry plain glyphs get when scaled,
+/// and K turns kerning on and off.  Dragging orbits the camera
+pub fn text_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+
+    let window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+
+    let res = Resources::from_crate_dir(Path::new("assets"));
+    let setup = || -> Result<(FontAtlas, FontAtlas, TextBatch), String> {
+        let font = Font::load(&res, FONT)?;
+        let bitmap = FontAtlas::bake(&font, 16.0, AtlasKind::Bitmap, text::ASCII)?;
+        let distance_field = FontAtlas::bake(&font, 48.0, AtlasKind::DistanceField, text::ASCII)?;
+        Ok((bitmap, distance_field, TextBatch::new()?))
+    };
+    let (bitmap, distance_field, mut text) = match setup() {
+        Ok(fonts) => fonts,
+        Err(e) => {
+            println!("Failed to set up text: {}", e);
+            return;
+        },
+    };
+
+    let (width, height) = window.size();
+    let mut screen = Camera2D::new(width, height);
+    let mut camera = Camera::new(Vec3::new(0.0, 4.0, 16.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 16.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut paragraph = TextStyle { wrap_width: Some(420.0), ..TextStyle::new(20.0) };
+    let mut use_distance_field = true;
+    let mut glyphs = 0;
+    let mut draw_calls = 0;
+    let mut time = 0.0f32;
+    let mut frame_ms = 0.0f64;
+    let frequency = timer.performance_frequency() as f64;
+    let mut last_counter = timer.performance_counter();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            use sdl2::keyboard::Keycode;
+            let wrap = paragraph.wrap_width.unwrap_or(0.0);
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::A), .. } => {
+                    paragraph.align = match paragraph.align {
+                        Align::Left => Align::Center,
+                        Align::Center => Align::Right,
+                        Align::Right => Align::Left,
+                    };
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
+                    paragraph.wrap_width = Some((wrap - 40.0).max(80.0));
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
+                    paragraph.wrap_width = Some((wrap + 40.0).min(860.0));
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Equals), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(Keycode::KpPlus), .. } => {
+                    paragraph.size = (paragraph.size * 1.25).min(120.0);
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::Minus), .. }
+                | sdl2::event::Event::KeyDown { keycode: Some(Keycode::KpMinus), .. } => {
+                    paragraph.size = (paragraph.size / 1.25).max(6.0);
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::D), .. } => {
+                    use_distance_field = !use_distance_field;
+                },
+                sdl2::event::Event::KeyDown { keycode: Some(Keycode::K), .. } => paragraph.kerning = !paragraph.kerning,
+                _ => {
+                    camera.handle_resize(&event);
+                    screen.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let counter = timer.performance_counter();
+        let dt = (counter - last_counter) as f64 / frequency;
+        last_counter = counter;
+        frame_ms = frame_ms * 0.95 + dt * 1000.0 * 0.05;  // smoothed, so the numbers are readable
+        time += dt as f32;
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        let (view, projection) = (camera.view_matrix(), camera.projection_matrix());
+        let (mut frame_glyphs, mut frame_draw_calls) = (0, 0);
+
+        // Each label sits on its own plane, so each is a batch of its own.  They're drawn furthest first, so that
+        // nearer ones blend over the top of them
+        let mut labels: Vec<(f32, usize)> = (0..LABELS.len())
+            .map(|i| {
+                let angle = i as f32 / LABELS.len() as f32 * std::f32::consts::PI * 2.0 + time * 0.2;
+                (angle, i)
+            })
+            .collect();
+        let label_position = |angle: f32, i: usize| Vec3::new(angle.sin() * 8.0, (i % 2) as f32, angle.cos() * 8.0);
+        labels.sort_by(|a, b| {
+            let distance = |&(angle, i): &(f32, usize)| (label_position(angle, i) - camera.position).length();
+            distance(b).partial_cmp(&distance(a)).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for &(angle, i) in &labels {
+            let style = TextStyle {
+                color: Vec4::new(0.6 + 0.4 * (i % 3) as f32 / 2.0, 0.8, 1.0 - 0.4 * (i % 2) as f32, 1.0),
+                ..TextStyle::new(1.2)
+            };
+            let size = distance_field.measure(LABELS[i], &style);
+            // Facing out from the middle of the ring, centered on its spot
+            let placement = text::world_placement(label_position(angle, i), Quat::from_axis_angle(Vec3::Y, angle));
+            text.begin_world(&view, &projection, &placement);
+            text.draw(&distance_field, LABELS[i], size * -0.5, &style);
+            text.end(&mut render_state_cache);
+            frame_glyphs += text.glyphs_drawn();
+            frame_draw_calls += text.draw_calls();
+        }
+
+        text.begin(&screen);
+        let dim = TextStyle { color: Vec4::new(0.7, 0.7, 0.75, 1.0), ..TextStyle::new(16.0) };
+        let stats = format!(
+            "{:.2} ms per frame ({:.0} fps), {} glyphs in {} draw calls",
+            frame_ms,
+            1000.0 / frame_ms.max(0.001),
+            glyphs,
+            draw_calls,
+        );
+        text.draw(&bitmap, &stats, Vec2::new(12.0, 10.0), &TextStyle::new(16.0));
+        let help = format!(
+            "A: align ({:?})   Left/Right: wrap width ({:.0})   +/-: size ({:.1})\nD: atlas ({})   K: kerning ({})",
+            paragraph.align,
+            paragraph.wrap_width.unwrap_or(0.0),
+            paragraph.size,
+            if use_distance_field { "distance field" } else { "bitmap" },
+            if paragraph.kerning { "on" } else { "off" },
+        );
+        text.draw(&bitmap, &help, Vec2::new(12.0, 10.0 + bitmap.line_height(16.0)), &dim);
+
+        let font = if use_distance_field { &distance_field } else { &bitmap };
+        text.draw(font, PARAGRAPH, Vec2::new(12.0, 80.0), &paragraph);
+        let title = TextStyle { kerning: paragraph.kerning, align: Align::Center, ..TextStyle::new(56.0) };
+        let title_size = font.measure(KERNING_SAMPLE, &title);
+        let title_position = Vec2::new((screen.width - title_size.x) * 0.5, screen.height - 90.0);
+        text.draw(font, KERNING_SAMPLE, title_position, &title);
+        text.end(&mut render_state_cache);
+        glyphs = frame_glyphs + text.glyphs_drawn();
+        draw_calls = frame_draw_calls + text.draw_calls();
+
+        window.gl_swap_window();
+    }
+}
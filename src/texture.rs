@@ -0,0 +1,470 @@
+// A wrapper around a GL 2D texture.  Creating one uploads the pixels and builds mipmaps (smaller copies of the image
+// that GL uses when the texture is far away, so it doesn't shimmer); dropping it frees the texture.
+//
+// Besides 8 bit images, textures can hold floats (for HDR colors and other values outside 0 to 1) or be left empty to
+// render into, and `Cubemap` holds six square faces that a shader looks up by direction rather than by coordinate.
+// `TextureArray` is a stack of same-sized layers that one sampler can pick between, which is how shadow maps for
+// several lights get to share a single texture unit.
+
+use crate::image::{self, Image};
+
+pub struct Texture {
+    id: gl::types::GLuint,
+    width: u32,
+    height: u32,
+}
+
+#[allow(dead_code)]
+impl Texture {
+    /// Uploads tightly packed RGBA8 pixels.  `srgb` should be true for anything that holds colors (like a base color
+    /// map) and false for anything that holds data (normal maps, roughness, ...), so that GL knows whether to convert
+    /// the values out of sRGB when sampling them
+    pub fn from_rgba8(width: u32, height: u32, pixels: &[u8], srgb: bool) -> Texture {
+        assert_eq!(Ok(pixels.len()), image::byte_size(width, height), "wrong number of pixels for the texture size");
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,  // mipmap level
+                (if srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 }) as gl::types::GLint,  // how GL stores it
+                width as gl::types::GLsizei,
+                height as gl::types::GLsizei,
+                0,  // border, which must always be 0
+                gl::RGBA,  // what we're handing over
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr() as *const gl::types::GLvoid,
+            );
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as gl::types::GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        Texture { id, width, height }
+    }
+
+    /// Uploads floating point pixels with `channels` (1 to 4) values each, stored as `internal_format` (like
+    /// `gl::RGB16F`).  No mipmaps, and clamped at the edges, which is what data like lookup tables and environment maps
+    /// want
+    pub fn from_f32(
+        width: u32,
+        height: u32,
+        channels: u32,
+        pixels: &[f32],
+        internal_format: gl::types::GLenum,
+    ) -> Texture {
+        assert_eq!(pixels.len(), (width * height * channels) as usize, "wrong number of pixels for the texture size");
+        let data = pixels.as_ptr() as *const gl::types::GLvoid;
+        Texture::allocate(width, height, internal_format, channel_format(channels), gl::FLOAT, data)
+    }
+
+    /// A texture with no pixels in it yet, for rendering into.  `internal_format` can be a color format (`gl::RGBA8`,
+    /// `gl::RGBA16F`, ...) or a depth one (`gl::DEPTH_COMPONENT24`, ...)
+    pub fn empty(width: u32, height: u32, internal_format: gl::types::GLenum) -> Texture {
+        let (format, data_type) = upload_format(internal_format);
+        Texture::allocate(width, height, internal_format, format, data_type, std::ptr::null())
+    }
+
+    fn allocate(
+        width: u32,
+        height: u32,
+        internal_format: gl::types::GLenum,
+        format: gl::types::GLenum,
+        data_type: gl::types::GLenum,
+        pixels: *const gl::types::GLvoid,
+    ) -> Texture {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                internal_format as gl::types::GLint,
+                width as gl::types::GLsizei,
+                height as gl::types::GLsizei,
+                0,
+                format,
+                data_type,
+                pixels,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        Texture { id, width, height }
+    }
+
+    pub fn from_image(image: &Image, srgb: bool) -> Texture {
+        Texture::from_rgba8(image.width, image.height, &image.pixels, srgb)
+    }
+
+    /// A 1x1 texture, for when a shader wants a texture but the material doesn't have one
+    pub fn solid(rgba: [u8; 4]) -> Texture {
+        Texture::from_rgba8(1, 1, &rgba, false)
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Binds to texture unit `unit`, which is the number you set a `sampler2D` uniform to
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+        }
+    }
+
+    /// Binds to image unit `unit`, for shaders to read and write pixels of directly with `imageLoad()` and
+    /// `imageStore()` (an `image2D` uniform), rather than sampling it.  `access` is `gl::READ_ONLY`, `gl::WRITE_ONLY` or
+    /// `gl::READ_WRITE`, and `format` has to match the format qualifier in the shader (`gl::RGBA8` for `rgba8`, ...).
+    /// Needs GL 4.2
+    pub fn bind_image(&self, unit: u32, access: gl::types::GLenum, format: gl::types::GLenum) {
+        unsafe {
+            gl::BindImageTexture(unit, self.id, 0, gl::FALSE, 0, access, format);
+        }
+    }
+
+    /// Reads the pixels back from the GPU as floats, `channels` (1 to 4) per pixel, starting at the bottom row
+    pub fn read_f32(&self, channels: u32) -> Vec<f32> {
+        let mut pixels = vec![0.0f32; (self.width * self.height * channels) as usize];
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            let data = pixels.as_mut_ptr() as *mut gl::types::GLvoid;
+            gl::GetTexImage(gl::TEXTURE_2D, 0, channel_format(channels), gl::FLOAT, data);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        pixels
+    }
+
+    /// `min` is used when the texture is shrunk on screen (and can use mipmaps), `mag` when it's stretched
+    pub fn set_filter(&self, min: gl::types::GLenum, mag: gl::types::GLenum) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag as gl::types::GLint);
+        }
+    }
+
+    /// What happens outside 0..1: `gl::REPEAT`, `gl::MIRRORED_REPEAT` or `gl::CLAMP_TO_EDGE`
+    pub fn set_wrap(&self, s: gl::types::GLenum, t: gl::types::GLenum) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, s as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, t as gl::types::GLint);
+        }
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+/// The pixel format GL expects for `channels` values per pixel
+fn channel_format(channels: u32) -> gl::types::GLenum {
+    match channels {
+        1 => gl::RED,
+        2 => gl::RG,
+        3 => gl::RGB,
+        _ => gl::RGBA,
+    }
+}
+
+/// A format and type that GL will accept alongside `internal_format` when no pixels are being uploaded.  Even then
+/// they have to be ones that make sense, so depth textures need a depth format
+fn upload_format(internal_format: gl::types::GLenum) -> (gl::types::GLenum, gl::types::GLenum) {
+    match internal_format {
+        gl::DEPTH_COMPONENT16 | gl::DEPTH_COMPONENT24 | gl::DEPTH_COMPONENT32F => (gl::DEPTH_COMPONENT, gl::FLOAT),
+        gl::DEPTH24_STENCIL8 => (gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8),
+        gl::R8 | gl::R16F | gl::R32F => (gl::RED, gl::FLOAT),
+        gl::RG8 | gl::RG16F | gl::RG32F => (gl::RG, gl::FLOAT),
+        gl::RGB8 | gl::RGB16F | gl::RGB32F | gl::R11F_G11F_B10F => (gl::RGB, gl::FLOAT),
+        _ => (gl::RGBA, gl::FLOAT),
+    }
+}
+
+/// Six square faces, one for each side of a cube, looked up in a shader with a direction (a `samplerCube`) instead
+/// of texture coordinates.  GL orders the faces +X, -X, +Y, -Y, +Z, -Z, which is also the order `face` numbers go in
+pub struct Cubemap {
+    id: gl::types::GLuint,
+    size: u32,
+    levels: u32,
+}
+
+#[allow(dead_code)]
+impl Cubemap {
+    /// An empty cubemap with `levels` mip levels (1 for no mipmaps), for rendering into
+    pub fn new(size: u32, levels: u32, internal_format: gl::types::GLenum) -> Cubemap {
+        let (format, data_type) = upload_format(internal_format);
+        let cubemap = Cubemap::allocate(size, levels);
+        for level in 0..levels {
+            for face in 0..6 {
+                cubemap.upload_face(face, level, internal_format, format, data_type, std::ptr::null());
+            }
+        }
+        cubemap
+    }
+
+    /// Uploads floating point pixels with `channels` values each.  `levels` holds the pixels for each mip level, with
+    /// each level being the six faces one after another
+    pub fn from_f32(size: u32, channels: u32, levels: &[Vec<f32>], internal_format: gl::types::GLenum) -> Cubemap {
+        let cubemap = Cubemap::allocate(size, levels.len() as u32);
+        for (level, pixels) in levels.iter().enumerate() {
+            let level_size = (size >> level).max(1);
+            let face_len = (level_size * level_size * channels) as usize;
+            assert_eq!(pixels.len(), face_len * 6, "wrong number of pixels for the cubemap size");
+            for (face, face_pixels) in pixels.chunks_exact(face_len).enumerate() {
+                let data = face_pixels.as_ptr() as *const gl::types::GLvoid;
+                let format = channel_format(channels);
+                cubemap.upload_face(face as u32, level as u32, internal_format, format, gl::FLOAT, data);
+            }
+        }
+        cubemap
+    }
+
+    fn allocate(size: u32, levels: u32) -> Cubemap {
+        let mut id: gl::types::GLuint = 0;
+        let min_filter = if levels > 1 { gl::LINEAR_MIPMAP_LINEAR } else { gl::LINEAR };
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, id);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, min_filter as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAX_LEVEL, levels as gl::types::GLint - 1);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        Cubemap { id, size, levels }
+    }
+
+    fn upload_face(
+        &self,
+        face: u32,
+        level: u32,
+        internal_format: gl::types::GLenum,
+        format: gl::types::GLenum,
+        data_type: gl::types::GLenum,
+        pixels: *const gl::types::GLvoid,
+    ) {
+        let level_size = (self.size >> level).max(1) as gl::types::GLsizei;
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                level as gl::types::GLint,
+                internal_format as gl::types::GLint,
+                level_size,
+                level_size,
+                0,
+                format,
+                data_type,
+                pixels,
+            );
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    /// Width and height of each face at the top mip level
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn levels(&self) -> u32 {
+        self.levels
+    }
+
+    /// Binds to texture unit `unit`, which is the number you set a `samplerCube` uniform to
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+        }
+    }
+
+    /// A cubemap of depth values for a point light's shadows, ready to be read with a `samplerCubeShadow`
+    pub fn depth(size: u32) -> Cubemap {
+        let cubemap = Cubemap::new(size, 1, gl::DEPTH_COMPONENT24);
+        cubemap.set_depth_compare(true);
+        cubemap
+    }
+
+    /// See `TextureArray::set_depth_compare()`
+    pub fn set_depth_compare(&self, enabled: bool) {
+        set_depth_compare(gl::TEXTURE_CUBE_MAP, self.id, enabled);
+    }
+
+    /// Fills in every mip level below the top one by shrinking it
+    pub fn generate_mipmaps(&self) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+            gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+    }
+
+    /// Reads mip level `level` back from the GPU as floats, `channels` per pixel, with the six faces one after another
+    /// (the same layout `from_f32()` takes)
+    pub fn read_f32(&self, level: u32, channels: u32) -> Vec<f32> {
+        let level_size = (self.size >> level).max(1);
+        let face_len = (level_size * level_size * channels) as usize;
+        let mut pixels = vec![0.0f32; face_len * 6];
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            for (face, face_pixels) in pixels.chunks_exact_mut(face_len).enumerate() {
+                gl::GetTexImage(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                    level as gl::types::GLint,
+                    channel_format(channels),
+                    gl::FLOAT,
+                    face_pixels.as_mut_ptr() as *mut gl::types::GLvoid,
+                );
+            }
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        pixels
+    }
+}
+
+impl Drop for Cubemap {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+/// Turns on comparing against the depth in the texture instead of returning it, which is what shadow samplers
+/// (`sampler2DShadow` and friends) expect: a lookup returns how much of the filtered area passed the comparison
+fn set_depth_compare(target: gl::types::GLenum, id: gl::types::GLuint, enabled: bool) {
+    let mode = if enabled { gl::COMPARE_REF_TO_TEXTURE } else { gl::NONE };
+    unsafe {
+        gl::BindTexture(target, id);
+        gl::TexParameteri(target, gl::TEXTURE_COMPARE_MODE, mode as gl::types::GLint);
+        gl::TexParameteri(target, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as gl::types::GLint);
+        gl::BindTexture(target, 0);
+    }
+}
+
+/// `layers` 2D images of the same size and format, read in a shader with a `sampler2DArray` and a coordinate whose
+/// third component picks the layer
+pub struct TextureArray {
+    id: gl::types::GLuint,
+    width: u32,
+    height: u32,
+    layers: u32,
+}
+
+#[allow(dead_code)]
+impl TextureArray {
+    /// An empty array for rendering into, one layer at a time
+    pub fn new(width: u32, height: u32, layers: u32, internal_format: gl::types::GLenum) -> TextureArray {
+        let (format, data_type) = upload_format(internal_format);
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, id);
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                internal_format as gl::types::GLint,
+                width as gl::types::GLsizei,
+                height as gl::types::GLsizei,
+                layers as gl::types::GLsizei,
+                0,
+                format,
+                data_type,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::LINEAR as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::LINEAR as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+        TextureArray { id, width, height, layers }
+    }
+
+    /// Layers of depth values for shadow maps, ready to be read with a `sampler2DArrayShadow`.  Anything looked up
+    /// outside the map counts as the furthest depth there is, so it's never in shadow
+    pub fn depth(width: u32, height: u32, layers: u32) -> TextureArray {
+        let array = TextureArray::new(width, height, layers, gl::DEPTH_COMPONENT24);
+        let border = [1.0f32; 4];
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, array.id);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as gl::types::GLint);
+            gl::TexParameterfv(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+        array.set_depth_compare(true);
+        array
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    /// Binds to texture unit `unit`, which is the number you set a `sampler2DArray` uniform to
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+        }
+    }
+
+    /// Depth textures with comparison turned on can only be read through shadow samplers, and with it off only
+    /// through ordinary ones, so anything that wants to look at the depths themselves (like a debug view) has to
+    /// switch it off first
+    pub fn set_depth_compare(&self, enabled: bool) {
+        set_depth_compare(gl::TEXTURE_2D_ARRAY, self.id, enabled);
+    }
+}
+
+impl Drop for TextureArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+#version 330 core
+
+// Squeezes HDR color (anything from 0 up) into what a screen can show (0 to 1), then encodes it as sRGB.  Lighting
+// math has to happen on linear values, but screens expect sRGB, so this is the one place the conversion happens.
+//
+// The operators are numbered the same as `pbr::ToneMapping`
+
+#define TONE_MAP_CLAMP 0
+#define TONE_MAP_REINHARD 1
+#define TONE_MAP_ACES 2
+#define TONE_MAP_UNCHARTED 3
+
+in vec2 TexCoord;
+
+uniform sampler2D HdrColor;
+uniform float Exposure;  // a multiplier, not in stops
+uniform int Operator;
+
+out vec4 Color;
+
+// Filmic curve from Uncharted 2, by John Hable
+vec3 hable(vec3 x)
+{
+    const float a = 0.15, b = 0.50, c = 0.10, d = 0.20, e = 0.02, f = 0.30;
+    return ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f;
+}
+
+vec3 tone_map(vec3 color)
+{
+    if (Operator == TONE_MAP_REINHARD) {
+        return color / (1.0 + color);
+    } else if (Operator == TONE_MAP_ACES) {
+        // Krzysztof Narkowicz's fit of the ACES filmic curve
+        return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+    } else if (Operator == TONE_MAP_UNCHARTED) {
+        const float white_point = 11.2;
+        return hable(color * 2.0) / hable(vec3(white_point));
+    }
+    return clamp(color, 0.0, 1.0);
+}
+
+vec3 linear_to_srgb(vec3 color)
+{
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, vec3(lessThanEqual(color, vec3(0.0031308))));
+}
+
+void main()
+{
+    vec3 color = texture(HdrColor, TexCoord).rgb * Exposure;
+    Color = vec4(linear_to_srgb(tone_map(color)), 1.0);
+}
@@ -0,0 +1,78 @@
+// Transform feedback: recording what the vertex (or geometry, or tessellation evaluation) shader puts out into buffers,
+// instead of or as well as drawing it.  The recorded vertices can be drawn later, or fed back in as the input of the
+// next pass, which makes it a way to run simulations on the GPU without compute shaders: each frame one buffer goes in,
+// the shader works out the next step for every vertex, and the results come out in another buffer, ready to go in
+// next frame.
+//
+// Which outputs get recorded has to be decided when the program is linked (see
+// `Program::from_shaders_with_feedback()`).  The buffers they go into are bound to numbered binding points with
+// `bind_buffer()`, and have to be big enough for everything that's drawn while capturing.  A buffer can't be read from
+// and recorded into by the same draw, so simulations ping-pong between two of them.
+
+use crate::buffer::{Buffer, BufferType};
+
+/// Points binding point `index` at `buffer`, so a capture records into it.  With interleaved outputs everything goes
+/// to index 0; with separate ones the first output goes to 0, the second to 1, and so on
+#[allow(dead_code)]
+pub fn bind_buffer<B: BufferType>(index: u32, buffer: &Buffer<B>) {
+    unsafe {
+        gl::BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, index, buffer.id());
+    }
+}
+
+/// Records draws into whatever's bound with `bind_buffer()`, and counts how many primitives were recorded
+pub struct TransformFeedback {
+    query: gl::types::GLuint,  // counts `gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN`
+}
+
+#[allow(dead_code)]
+impl TransformFeedback {
+    pub fn new() -> TransformFeedback {
+        let mut query: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenQueries(1, &mut query);
+        }
+        TransformFeedback { query }
+    }
+
+    /// Records everything `draw` draws.  `primitive` is what gets recorded, and has to match what the draws make:
+    /// `gl::POINTS`, `gl::LINES` (for any kind of line draw) or `gl::TRIANGLES` (for any kind of triangle draw), or
+    /// what the geometry shader puts out if there is one.  With `discard` set nothing gets rasterized, which is what
+    /// a pass that's only there to update the buffers wants.  The program doing the drawing has to be in use already,
+    /// since GL fixes which outputs to record when capturing starts
+    pub fn capture<F: FnOnce()>(&self, primitive: gl::types::GLenum, discard: bool, draw: F) {
+        unsafe {
+            if discard {
+                gl::Enable(gl::RASTERIZER_DISCARD);
+            }
+            gl::BeginQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN, self.query);
+            gl::BeginTransformFeedback(primitive);
+        }
+        draw();
+        unsafe {
+            gl::EndTransformFeedback();
+            gl::EndQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN);
+            if discard {
+                gl::Disable(gl::RASTERIZER_DISCARD);
+            }
+        }
+    }
+
+    /// How many primitives the last `capture()` recorded (there has to have been one).  Anything that didn't fit in
+    /// the buffers isn't counted.  This waits for the GPU to finish the capture, so it's best not asked every frame
+    pub fn primitives_written(&self) -> u32 {
+        let mut count: gl::types::GLuint = 0;
+        unsafe {
+            gl::GetQueryObjectuiv(self.query, gl::QUERY_RESULT, &mut count);
+        }
+        count
+    }
+}
+
+impl Drop for TransformFeedback {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteQueries(1, &self.query);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::ffi::CString;
+use std::time::SystemTime;
+
+use crate::render_gl;
+use crate::program;
+use crate::math::{self, Mat4, Quat, Vec3};
+
+/// Same two triangles as before, but now the vertex shader runs them through model, view and projection matrices so
+/// we can spin, scale and move them around instead of being stuck with whatever clip-space coordinates we typed in
+pub fn transformations() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+
+    let window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.5, 0.3, 0.3, 1.0);  // Color that window will default to when everything is cleared
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+
+    let vert_shader = render_gl::Shader::from_vert_source(
+        &CString::new(
+            include_str!("transformations.vert")
+        ).unwrap()
+    ).unwrap();
+
+    let frag_shader = render_gl::Shader::from_frag_source(
+        &CString::new(
+            include_str!("two_vaos_and_two_vbos.frag")
+        ).unwrap()
+    ).unwrap();
+
+    let shader_program = program::Program::from_shaders(
+        &[vert_shader, frag_shader]
+    ).unwrap();
+
+    // The triangles are now described in their own "model space", centered on the origin, and it's up to the model
+    // matrix to put them somewhere in the world
+    let vertices: Vec<f32> = vec![
+        // positions        // colors
+        -0.5, -0.5, 0.0,    1.0, 0.0, 0.0,
+         0.5, -0.5, 0.0,    0.0, 1.0, 0.0,
+         0.0,  0.5, 0.0,    0.0, 0.0, 1.0,
+    ];
+
+    let mut vbo: gl::types::GLuint = 0;
+    let mut vao: gl::types::GLuint = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut vbo);
+        gl::GenVertexArrays(1, &mut vao);
+
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            (vertices.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+            vertices.as_ptr() as *const gl::types::GLvoid,
+            gl::STATIC_DRAW,
+        );
+
+        gl::EnableVertexAttribArray(0);  // this is `layout (location = 0)` in vertex shader
+        gl::VertexAttribPointer(
+            0,
+            3,
+            gl::FLOAT,
+            gl::FALSE,
+            (6 * std::mem::size_of::<f32>()) as gl::types::GLint,
+            std::ptr::null()
+        );
+
+        gl::EnableVertexAttribArray(1);  // this is `layout (location = 1)` in vertex shader
+        gl::VertexAttribPointer(
+            1,
+            3,
+            gl::FLOAT,
+            gl::FALSE,
+            (6 * std::mem::size_of::<f32>()) as gl::types::GLint,
+            (3 * std::mem::size_of::<f32>()) as *const gl::types::GLvoid
+        );
+
+        gl::BindVertexArray(0);
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    }
+
+    // The camera sits back a bit on the +Z axis looking at the origin.  Since it never moves, we only need to build
+    // this matrix once
+    let view = Mat4::look_at(Vec3::new(0.0, 0.0, 3.0), Vec3::ZERO, Vec3::Y);
+    let mut window_size = window.size();
+
+    let start_time = SystemTime::now();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Escape), .. } => break 'main,
+                sdl2::event::Event::Window { win_event: sdl2::event::WindowEvent::Resized(w, h), .. } => {
+                    window_size = (w as u32, h as u32);
+                    unsafe { gl::Viewport(0, 0, w, h); }
+                },
+                _ => {},
+            }
+        }
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+
+        let seconds = start_time.elapsed().unwrap().as_secs_f32();
+
+        // Rebuild the projection every frame in case the window changed shape, otherwise things get stretched
+        let aspect = window_size.0 as f32 / window_size.1.max(1) as f32;
+        let projection = Mat4::perspective(math::radians(45.0), aspect, 0.1, 100.0);
+
+        shader_program.set_used();
+        shader_program.set_uniform_mat4("View", &view);
+        shader_program.set_uniform_mat4("Projection", &projection);
+
+        // First triangle spins around the Y axis while sitting off to the left
+        let model_1 = Mat4::from_trs(
+            Vec3::new(-0.7, 0.0, 0.0),
+            Quat::from_axis_angle(Vec3::Y, seconds),
+            Vec3::ONE,
+        );
+
+        // Second triangle pulses in size and bobs up and down over on the right
+        let model_2 = Mat4::translation(Vec3::new(0.7, (seconds * 2.0).sin() * 0.3, 0.0))
+            * Mat4::rotation_z(-seconds * 0.5)
+            * Mat4::scale(Vec3::splat(0.75 + seconds.sin() * 0.25));
+
+        unsafe {
+            gl::BindVertexArray(vao);
+
+            shader_program.set_uniform_mat4("Model", &model_1);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+            shader_program.set_uniform_mat4("Model", &model_2);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+
+        window.gl_swap_window();
+    }
+}
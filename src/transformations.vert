@@ -0,0 +1,20 @@
+#version 330 core
+
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec3 Color;
+
+// These get multiplied together right-to-left: first place the vertex in the world, then move the world so the camera
+// is at the origin, then squash everything into clip space
+uniform mat4 Model;
+uniform mat4 View;
+uniform mat4 Projection;
+
+out VS_OUTPUT {
+    vec3 Color;
+} OUT;
+
+void main()
+{
+    gl_Position = Projection * View * Model * vec4(Position, 1.0);
+    OUT.Color = Color;
+}
@@ -5,6 +5,7 @@ use std::time::SystemTime;
 
 use crate::render_gl;
 use crate::program;
+use crate::render_state::{RenderState, RenderStateCache};
 
 pub fn two_vaos_and_two_vbos() {
     let _sdl = sdl2::init().unwrap();
@@ -14,6 +15,7 @@ pub fn two_vaos_and_two_vbos() {
     let gl_attr = video_subsystem.gl_attr();
     gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
     gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);                   // Ask for a depth buffer, otherwise depth testing does nothing
 
     let window = video_subsystem.window("Window Title", 900, 700)
         .resizable()
@@ -159,6 +161,13 @@ pub fn two_vaos_and_two_vbos() {
         gl::BindBuffer(gl::ARRAY_BUFFER, 0);
     }
 
+    // Lets us cull (not render) the faces of the triangles that we shouldn't be able to see.  Since we have no way of
+    // seeing these backwards faces, we shouldn't see any difference.  "Front" is defined as the face enclosed by
+    // vertices in the counter-clockwise direction.  This also turns on depth testing, so now the first triangle (at
+    // z = 0.0) covers up the second one (at z = 0.1) where they overlap, no matter which order we draw them in
+    let render_state = RenderState::opaque();
+    let mut render_state_cache = RenderStateCache::new();
+
     'main: loop {
         for event in event_pump.poll_iter() {
             match event {
@@ -168,16 +177,12 @@ pub fn two_vaos_and_two_vbos() {
             }
         }
 
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
-        }
+        // Only the very first frame actually makes any GL calls here, since the state never changes after that
+        render_state_cache.apply(&render_state);
 
-        // Lets us cull (not render) the faces of the triangles that we shouldn't be able to see.  Since we have no way
-        // of seeing these backwards faces, we shouldn't see any difference
         unsafe {
-            gl::Enable(gl::CULL_FACE);  // Tell it to cull
-            gl::CullFace(gl::BACK);     // We want to cull the back faces that we wouldn't be able to see anyways
-            gl::FrontFace(gl::CCW);     // "Front" is defined as the face enclosed by vertices in the counter-clockwise direction
+            // Need to clear the depth buffer too now, otherwise last frame's depths would hide this frame's triangles
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
 
         // Now draw the triangle
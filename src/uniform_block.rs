@@ -0,0 +1,497 @@
+// Rust structs that can be copied straight into a uniform block.
+//
+// GLSL lays out the members of a `layout (std140)` block by rules of its own rather than C's: a `vec3` takes up 12
+// bytes but has to start on a 16 byte boundary, every element of an array starts on a 16 byte boundary however small it
+// is, and so on.  (std430, which shader storage blocks can use, is the same except that arrays and structs don't get
+// rounded up to 16 bytes.)  Getting that padding right by hand, the way `lighting.rs` does by only using vec4s, is easy
+// to get subtly wrong, and the shader then just reads garbage.
+//
+// So instead, a struct declared with `uniform_block!` knows the GLSL name of each of its fields and works out where
+// each one goes by those rules, packing itself into bytes with all the padding in place:
+//
+//     uniform_block! {
+//         pub struct Camera {
+//             pub view: Mat4 => "View",
+//             pub position: Vec3 => "CameraPosition",
+//             pub time: f32 => "Time",  // fits into the end of the vec3's 16 bytes
+//         }
+//     }
+//
+// and `check_block()` compares that layout with the one GL actually gave a program's block, member by member, so that
+// a struct that's drifted out of step with its shader gets caught at startup rather than by staring at a broken frame.
+
+use std::marker::PhantomData;
+
+use crate::buffer::UniformBuffer;
+use crate::math::{Mat4, Vec2, Vec3, Vec4};
+use crate::program::{self, Program};
+
+/// The two sets of packing rules GLSL has for blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum BlockLayout {
+    Std140,  // what uniform blocks use
+    Std430,  // tighter packing, only for shader storage blocks
+}
+
+impl BlockLayout {
+    /// How far apart the elements of an array are, given the size and alignment of one element
+    pub fn array_stride(self, size: usize, alignment: usize) -> usize {
+        let stride = round_up(size, alignment);
+        match self {
+            BlockLayout::Std140 => round_up(stride, 16),
+            BlockLayout::Std430 => stride,
+        }
+    }
+
+    /// The alignment of an array or struct, given the biggest alignment of what's in it
+    pub fn aggregate_alignment(self, alignment: usize) -> usize {
+        match self {
+            BlockLayout::Std140 => round_up(alignment, 16),
+            BlockLayout::Std430 => alignment,
+        }
+    }
+}
+
+/// `value` moved up to the next multiple of `alignment`
+pub fn round_up(value: usize, alignment: usize) -> usize {
+    if alignment == 0 { value } else { value.div_ceil(alignment) * alignment }
+}
+
+/// One member of a block as GL would report it, with the same meaning as `BlockMemberInfo`'s fields.  Arrays of
+/// scalars, vectors or matrices are one member (called `name[0]`), while arrays of structs are every member of every
+/// element, the same as GL lists them
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockMember {
+    pub name: String,
+    pub offset: usize,
+    pub gl_type: gl::types::GLenum,
+    pub array_size: usize,
+    pub array_stride: usize,
+    pub matrix_stride: usize,
+}
+
+/// Something that can go in a block: a scalar, vector or matrix, an array of them, or a `uniform_block!` struct
+pub trait BlockField {
+    /// What GL calls the type, like `gl::FLOAT_VEC3`.  Structs don't have one, so they use 0
+    const GL_TYPE: gl::types::GLenum;
+    const IS_STRUCT: bool = false;
+    const MATRIX_STRIDE: usize = 0;
+
+    fn alignment(layout: BlockLayout) -> usize;
+    fn size(layout: BlockLayout) -> usize;
+
+    /// Writes the value into the start of `bytes`, which is at least `size()` long
+    fn write(&self, layout: BlockLayout, bytes: &mut [u8]);
+
+    /// Adds what GL should report for this field, called `name` and starting `offset` bytes into the block
+    fn members(layout: BlockLayout, name: &str, offset: usize, members: &mut Vec<BlockMember>) {
+        let _ = layout;
+        members.push(BlockMember {
+            name: name.to_string(),
+            offset,
+            gl_type: Self::GL_TYPE,
+            array_size: 1,
+            array_stride: 0,
+            matrix_stride: Self::MATRIX_STRIDE,
+        });
+    }
+}
+
+fn write_floats(values: &[f32], bytes: &mut [u8]) {
+    for (value, out) in values.iter().zip(bytes.chunks_exact_mut(4)) {
+        out.copy_from_slice(&value.to_ne_bytes());
+    }
+}
+
+macro_rules! impl_block_scalar {
+    ($t:ty, $gl_type:expr, $to_bytes:expr) => {
+        impl BlockField for $t {
+            const GL_TYPE: gl::types::GLenum = $gl_type;
+
+            fn alignment(_: BlockLayout) -> usize {
+                4
+            }
+
+            fn size(_: BlockLayout) -> usize {
+                4
+            }
+
+            fn write(&self, _: BlockLayout, bytes: &mut [u8]) {
+                let to_bytes: fn(&$t) -> [u8; 4] = $to_bytes;
+                bytes[..4].copy_from_slice(&to_bytes(self));
+            }
+        }
+    };
+}
+
+impl_block_scalar!(f32, gl::FLOAT, |value| value.to_ne_bytes());
+impl_block_scalar!(i32, gl::INT, |value| value.to_ne_bytes());
+impl_block_scalar!(u32, gl::UNSIGNED_INT, |value| value.to_ne_bytes());
+// GLSL bools in blocks are 4 bytes, anything but 0 being true
+impl_block_scalar!(bool, gl::BOOL, |value| (*value as u32).to_ne_bytes());
+
+macro_rules! impl_block_vector {
+    ($t:ty, $gl_type:expr, $components:expr, $alignment:expr, $($field:ident),+) => {
+        impl BlockField for $t {
+            const GL_TYPE: gl::types::GLenum = $gl_type;
+
+            fn alignment(_: BlockLayout) -> usize {
+                $alignment
+            }
+
+            fn size(_: BlockLayout) -> usize {
+                $components * 4
+            }
+
+            fn write(&self, _: BlockLayout, bytes: &mut [u8]) {
+                write_floats(&[$(self.$field),+], bytes);
+            }
+        }
+    };
+}
+
+impl_block_vector!(Vec2, gl::FLOAT_VEC2, 2, 8, x, y);
+impl_block_vector!(Vec3, gl::FLOAT_VEC3, 3, 16, x, y, z);  // a vec3 is aligned like a vec4, but a float can follow it
+impl_block_vector!(Vec4, gl::FLOAT_VEC4, 4, 16, x, y, z, w);
+
+/// Column-major, the same as it's laid out in memory, so each column is a vec4 one after another
+impl BlockField for Mat4 {
+    const GL_TYPE: gl::types::GLenum = gl::FLOAT_MAT4;
+    const MATRIX_STRIDE: usize = 16;
+
+    fn alignment(_: BlockLayout) -> usize {
+        16
+    }
+
+    fn size(_: BlockLayout) -> usize {
+        64
+    }
+
+    fn write(&self, _: BlockLayout, bytes: &mut [u8]) {
+        write_floats(&self.to_cols_array(), bytes);
+    }
+}
+
+impl<T: BlockField, const N: usize> BlockField for [T; N] {
+    const GL_TYPE: gl::types::GLenum = T::GL_TYPE;
+    const MATRIX_STRIDE: usize = T::MATRIX_STRIDE;
+
+    fn alignment(layout: BlockLayout) -> usize {
+        layout.aggregate_alignment(T::alignment(layout))
+    }
+
+    fn size(layout: BlockLayout) -> usize {
+        layout.array_stride(T::size(layout), T::alignment(layout)) * N
+    }
+
+    fn write(&self, layout: BlockLayout, bytes: &mut [u8]) {
+        let stride = layout.array_stride(T::size(layout), T::alignment(layout));
+        for (i, element) in self.iter().enumerate() {
+            element.write(layout, &mut bytes[i * stride..]);
+        }
+    }
+
+    fn members(layout: BlockLayout, name: &str, offset: usize, members: &mut Vec<BlockMember>) {
+        let stride = layout.array_stride(T::size(layout), T::alignment(layout));
+        if T::IS_STRUCT {
+            for i in 0..N {
+                T::members(layout, &format!("{}[{}]", name, i), offset + i * stride, members);
+            }
+        } else {
+            members.push(BlockMember {
+                name: format!("{}[0]", name),
+                offset,
+                gl_type: T::GL_TYPE,
+                array_size: N,
+                array_stride: stride,
+                matrix_stride: T::MATRIX_STRIDE,
+            });
+        }
+    }
+}
+
+/// The name GL gives a member of a nested struct
+pub fn member_name(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() { name.to_string() } else { format!("{}.{}", prefix, name) }
+}
+
+/// Declares a struct that can go in a uniform block, or inside another one.  Each field is followed by `=>` and the
+/// name it has in GLSL, which is what `check_block()` matches it up by.  The struct derives `Debug`, `Clone` and
+/// `Copy`, so every field type has to be `Copy`
+#[macro_export]
+macro_rules! uniform_block {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty => $glsl:literal),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy)]
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty),*
+        }
+
+        impl $crate::uniform_block::BlockField for $name {
+            const GL_TYPE: gl::types::GLenum = 0;
+            const IS_STRUCT: bool = true;
+
+            fn alignment(layout: $crate::uniform_block::BlockLayout) -> usize {
+                let alignment = 0usize;
+                $(let alignment = alignment.max(<$ty as $crate::uniform_block::BlockField>::alignment(layout));)*
+                layout.aggregate_alignment(alignment)
+            }
+
+            fn size(layout: $crate::uniform_block::BlockLayout) -> usize {
+                let end = 0usize;
+                $(let end = $crate::uniform_block::round_up(
+                    end,
+                    <$ty as $crate::uniform_block::BlockField>::alignment(layout),
+                ) + <$ty as $crate::uniform_block::BlockField>::size(layout);)*
+                // A struct is padded out to its own alignment, so whatever follows it starts on a boundary
+                $crate::uniform_block::round_up(end, Self::alignment(layout))
+            }
+
+            fn write(&self, layout: $crate::uniform_block::BlockLayout, bytes: &mut [u8]) {
+                let offset = 0usize;
+                $(
+                    let offset = $crate::uniform_block::round_up(
+                        offset,
+                        <$ty as $crate::uniform_block::BlockField>::alignment(layout),
+                    );
+                    $crate::uniform_block::BlockField::write(&self.$field, layout, &mut bytes[offset..]);
+                    let offset = offset + <$ty as $crate::uniform_block::BlockField>::size(layout);
+                )*
+                let _ = offset;
+            }
+
+            fn members(
+                layout: $crate::uniform_block::BlockLayout,
+                name: &str,
+                offset: usize,
+                members: &mut Vec<$crate::uniform_block::BlockMember>,
+            ) {
+                let field_offset = 0usize;
+                $(
+                    let field_offset = $crate::uniform_block::round_up(
+                        field_offset,
+                        <$ty as $crate::uniform_block::BlockField>::alignment(layout),
+                    );
+                    <$ty as $crate::uniform_block::BlockField>::members(
+                        layout,
+                        &$crate::uniform_block::member_name(name, $glsl),
+                        offset + field_offset,
+                        members,
+                    );
+                    let field_offset = field_offset + <$ty as $crate::uniform_block::BlockField>::size(layout);
+                )*
+                let _ = field_offset;
+            }
+        }
+    };
+}
+
+/// Packs `value` into bytes laid out by `layout`, with zeros for the padding
+#[allow(dead_code)]
+pub fn to_bytes<T: BlockField>(value: &T, layout: BlockLayout) -> Vec<u8> {
+    let mut bytes = vec![0u8; T::size(layout)];
+    value.write(layout, &mut bytes);
+    bytes
+}
+
+/// Packs `values` one after another the way an array of them is laid out in a block, like the contents of a
+/// `buffer` block ending in `Particle ParticleList[];`
+#[allow(dead_code)]
+pub fn slice_to_bytes<T: BlockField>(values: &[T], layout: BlockLayout) -> Vec<u8> {
+    let stride = layout.array_stride(T::size(layout), T::alignment(layout));
+    let mut bytes = vec![0u8; stride * values.len()];
+    for (i, value) in values.iter().enumerate() {
+        value.write(layout, &mut bytes[i * stride..]);
+    }
+    bytes
+}
+
+/// Every member of a `uniform_block!` struct, where the rules put it
+#[allow(dead_code)]
+pub fn members<T: BlockField>(layout: BlockLayout) -> Vec<BlockMember> {
+    let mut members = Vec::new();
+    T::members(layout, "", 0, &mut members);
+    members
+}
+
+/// Checks that the uniform block `block` in `program` is laid out exactly the way `T` lays itself out with std140's
+/// rules: the same size, the same members with the same types, and each at the same offset with the same strides.
+/// Everything that doesn't match is listed in the error, one line per member
+#[allow(dead_code)]
+pub fn check_block<T: BlockField>(program: &Program, block: &str) -> Result<(), String> {
+    let info = program.uniform_block(block)
+        .ok_or_else(|| format!("the program has no uniform block called `{}`", block))?;
+    let ours = members::<T>(BlockLayout::Std140);
+    let mut problems = Vec::new();
+
+    let size = T::size(BlockLayout::Std140);
+    if info.data_size != size {
+        problems.push(format!("the block is {} bytes, but the struct is {}", info.data_size, size));
+    }
+
+    for member in &ours {
+        let theirs = match info.members.iter().find(|theirs| theirs.name == member.name) {
+            Some(theirs) => theirs,
+            None => {
+                problems.push(format!("`{}` isn't in the block", member.name));
+                continue;
+            },
+        };
+        let mut differences = Vec::new();
+        let mut compare = |what: &str, ours: usize, theirs: usize| {
+            if ours != theirs {
+                differences.push(format!("{} {} here but {} in GL", what, ours, theirs));
+            }
+        };
+        compare("offset", member.offset, theirs.offset);
+        compare("array size", member.array_size, theirs.array_size);
+        compare("array stride", member.array_stride, theirs.array_stride);
+        compare("matrix stride", member.matrix_stride, theirs.matrix_stride);
+        if member.gl_type != theirs.gl_type {
+            let (ours, theirs) = (program::glsl_type_name(member.gl_type), program::glsl_type_name(theirs.gl_type));
+            differences.push(format!("type {} here but {} in GL", ours, theirs));
+        }
+        if !differences.is_empty() {
+            problems.push(format!("`{}`: {}", member.name, differences.join(", ")));
+        }
+    }
+    for theirs in &info.members {
+        if !ours.iter().any(|member| member.name == theirs.name) {
+            problems.push(format!("`{}` is in the block, but not the struct", theirs.name));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "uniform block `{}` doesn't match `{}`:\n    {}",
+            block,
+            std::any::type_name::<T>(),
+            problems.join("\n    "),
+        ))
+    }
+}
+
+/// A uniform buffer holding one `T`, attached to a binding point so that any number of programs can read it
+pub struct BlockBuffer<T: BlockField> {
+    buffer: UniformBuffer,
+    binding: gl::types::GLuint,
+    bytes: Vec<u8>,
+    _marker: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: BlockField> BlockBuffer<T> {
+    /// Makes room for a `T` and attaches the buffer to uniform buffer binding point `binding`
+    pub fn new(binding: gl::types::GLuint) -> BlockBuffer<T> {
+        let mut buffer = UniformBuffer::new();
+        let bytes = vec![0u8; T::size(BlockLayout::Std140)];
+        buffer.bind();
+        buffer.dynamic_draw_data(&bytes);
+        buffer.unbind();
+        buffer.bind_base(binding);
+        BlockBuffer { buffer, binding, bytes, _marker: PhantomData }
+    }
+
+    pub fn binding(&self) -> gl::types::GLuint {
+        self.binding
+    }
+
+    /// Size in bytes, padding and all
+    pub fn size(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Checks that `program`'s block called `block` matches `T` (see `check_block()`), and if so points it at this
+    /// buffer's binding point
+    pub fn attach(&self, program: &Program, block: &str) -> Result<(), String> {
+        check_block::<T>(program, block)?;
+        program.bind_uniform_block(block, self.binding);
+        Ok(())
+    }
+
+    pub fn update(&mut self, value: &T) {
+        value.write(BlockLayout::Std140, &mut self.bytes);
+        self.buffer.bind();
+        self.buffer.sub_data(0, &self.bytes);
+        self.buffer.unbind();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::CameraUniforms;
+
+    crate::uniform_block! {
+        struct Particle {
+            position: Vec3 => "Position",
+            size: f32 => "Size",
+            weights: [f32; 2] => "Weights",
+        }
+    }
+
+    /// (name, offset, size of the array, array stride) for each member
+    fn summary(members: &[BlockMember]) -> Vec<(&str, usize, usize, usize)> {
+        members.iter().map(|m| (m.name.as_str(), m.offset, m.array_size, m.array_stride)).collect()
+    }
+
+    #[test]
+    fn camera_uniforms_std140() {
+        let members = members::<CameraUniforms>(BlockLayout::Std140);
+        assert_eq!(summary(&members), vec![
+            ("View", 0, 1, 0),
+            ("Projection", 64, 1, 0),
+            ("ViewProjection", 128, 1, 0),
+            ("CameraPosition", 192, 1, 0),
+            ("Time", 204, 1, 0),  // packed into the end of the vec3
+        ]);
+        assert_eq!(members[0].matrix_stride, 16);
+        assert_eq!(members[4].gl_type, gl::FLOAT);
+        assert_eq!(CameraUniforms::size(BlockLayout::Std140), 208);
+    }
+
+    #[test]
+    fn vec3_arrays() {
+        // A vec3 is 16 bytes apart in an array under either set of rules, since its alignment is 16 already
+        for &layout in &[BlockLayout::Std140, BlockLayout::Std430] {
+            let mut members = Vec::new();
+            <[Vec3; 4]>::members(layout, "Corners", 0, &mut members);
+            assert_eq!(summary(&members), vec![("Corners[0]", 0, 4, 16)]);
+        }
+
+        // Arrays of floats are only padded out to 16 bytes per element in std140
+        let std140 = members::<Particle>(BlockLayout::Std140);
+        let std430 = members::<Particle>(BlockLayout::Std430);
+        assert_eq!(summary(&std140), vec![("Position", 0, 1, 0), ("Size", 12, 1, 0), ("Weights[0]", 16, 2, 16)]);
+        assert_eq!(summary(&std430), vec![("Position", 0, 1, 0), ("Size", 12, 1, 0), ("Weights[0]", 16, 2, 4)]);
+
+        // ...and an array of structs holding one gets its members listed per element
+        let mut members = Vec::new();
+        <[Particle; 2]>::members(BlockLayout::Std430, "Particles", 0, &mut members);
+        assert_eq!(members[3].name, "Particles[1].Position");
+        assert_eq!(members[3].offset, 32);
+        assert_eq!(Particle::size(BlockLayout::Std140), 48);
+    }
+
+    #[test]
+    fn packs_with_padding() {
+        let particles = [
+            Particle { position: Vec3::new(1.0, 2.0, 3.0), size: 4.0, weights: [5.0, 6.0] },
+            Particle { position: Vec3::new(7.0, 8.0, 9.0), size: 10.0, weights: [11.0, 12.0] },
+        ];
+        let bytes = slice_to_bytes(&particles, BlockLayout::Std430);
+        let floats: Vec<f32> = bytes.chunks_exact(4).map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]])).collect();
+        assert_eq!(floats, vec![
+            1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 0.0, 0.0,
+            7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 0.0, 0.0,
+        ]);
+    }
+}
@@ -0,0 +1,144 @@
+use std::ffi::CString;
+
+use crate::camera::{self, Camera, CameraController, CameraUniforms, OrbitController};
+use crate::math::{Mat4, Quat, Vec3};
+use crate::program::Program;
+use crate::render_gl::Shader;
+use crate::render_state::{RenderState, RenderStateCache};
+use crate::shapes;
+use crate::uniform_block::{self, BlockBuffer};
+
+crate::uniform_block! {
+    /// The `Camera` block as it would look if someone had moved `CameraPosition` to the top of camera.glsl and only
+    /// half updated the Rust side, to show what `check_block()` says about it
+    #[allow(dead_code)]
+    struct OutOfDateCamera {
+        position: Vec3 => "CameraPosition",
+        view: Mat4 => "View",
+        projection: Mat4 => "Projection",
+        time: f32 => "Time",
+    }
+}
+
+fn camera_program(frag: &str) -> Result<Program, String> {
+    let vert_source = camera::with_camera_block(include_str!("shared_camera.vert"));
+    let vert_shader = Shader::from_vert_source(&CString::new(vert_source).unwrap())?;
+    let frag_shader = Shader::from_frag_source(&CString::new(camera::with_camera_block(frag)).unwrap())?;
+    Program::from_shaders(&[vert_shader, frag_shader])
+}
+
+/// Three shapes, each drawn by a different program, all getting the camera from the one uniform buffer that's
+/// uploaded once a frame: a plainly shaded cube, a UV checkered sphere, and a torus with a glow around its edges that
+/// pulses with the block's `Time`.  Before anything's drawn, each program's `Camera` block is checked against
+/// `CameraUniforms`, and a deliberately out of date version of the struct is checked too, to show what a mismatch
+/// looks like (it's printed to the console).  Dragging orbits the camera
+pub fn uniform_block_demo() {
+    let _sdl = sdl2::init().unwrap();
+    let video_subsystem = _sdl.video().unwrap();
+
+    // Set up some things for OpenGL here
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);  // Using OpenGL Core...
+    gl_attr.set_context_version(3, 3);            // ...version 3.3
+    gl_attr.set_depth_size(24);
+
+    let mut window = video_subsystem.window("Window Title", 900, 700)
+        .resizable()
+        .opengl()
+        .build().unwrap();
+    let _gl_context = window.gl_create_context().unwrap();
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    unsafe {
+        gl::Viewport(0, 0, 900, 700);  // Set up viewport for OpenGL
+        gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+    }
+    let mut event_pump = _sdl.event_pump().unwrap();
+    let timer = _sdl.timer().unwrap();
+
+    let mut camera_buffer = BlockBuffer::<CameraUniforms>::new(camera::CAMERA_BINDING);
+    let setup = || -> Result<Vec<Program>, String> {
+        let programs = vec![
+            camera_program(include_str!("mesh.frag"))?,
+            camera_program(include_str!("debug_uv_checker.frag"))?,
+            camera_program(include_str!("camera_rim.frag"))?,
+        ];
+        // Checks every program's block matches `CameraUniforms` before pointing it at the buffer
+        for program in &programs {
+            camera_buffer.attach(program, "Camera")?;
+        }
+        Ok(programs)
+    };
+    let programs = match setup() {
+        Ok(programs) => programs,
+        Err(e) => {
+            println!("Failed to set up the shared camera block: {}", e);
+            return;
+        },
+    };
+    match uniform_block::check_block::<OutOfDateCamera>(&programs[0], "Camera") {
+        Ok(()) => println!("The out of date camera struct somehow matches the block"),
+        Err(e) => println!("As expected, the out of date camera struct doesn't match. {}", e),
+    }
+
+    programs[0].set_used();
+    programs[0].set_uniform_vec3("DiffuseColor", Vec3::new(0.9, 0.6, 0.3));
+    programs[1].set_used();
+    programs[1].set_uniform_f32("CheckerScale", 8.0);
+    programs[2].set_used();
+    programs[2].set_uniform_vec3("RimColor", Vec3::new(0.3, 0.7, 1.0));
+    let meshes = [
+        shapes::cube(1.6, 1).upload(),
+        shapes::uv_sphere(1.0, 32, 16).upload(),
+        shapes::torus(0.9, 0.35, 48, 24).upload(),
+    ];
+
+    let (width, height) = window.size();
+    let mut camera = Camera::new(Vec3::new(0.0, 3.0, 9.0), width as f32 / height as f32);
+    let mut controller = OrbitController::new(Vec3::ZERO, 9.0);
+    controller.attach(&mut camera);
+
+    let mut render_state_cache = RenderStateCache::new();
+    let mut time = 0.0f32;
+    let frequency = timer.performance_frequency() as f64;
+    let mut last_counter = timer.performance_counter();
+    window.set_title(&format!(
+        "{} programs sharing one {} byte camera block",
+        programs.len(),
+        camera_buffer.size(),
+    )).unwrap();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'main,
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Escape), .. } => break 'main,
+                _ => {
+                    camera.handle_resize(&event);
+                    controller.handle_event(&mut camera, &event);
+                },
+            }
+        }
+
+        let counter = timer.performance_counter();
+        time += ((counter - last_counter) as f64 / frequency) as f32;
+        last_counter = counter;
+
+        // One upload, however many programs read it
+        camera_buffer.update(&camera.uniforms(time));
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        render_state_cache.apply(&RenderState::opaque());
+        for (i, (program, mesh)) in programs.iter().zip(meshes.iter()).enumerate() {
+            let position = Vec3::new((i as f32 - 1.0) * 3.0, 0.0, 0.0);
+            let rotation = Quat::from_axis_angle(Vec3::new(0.4, 1.0, 0.2).normalize(), time * 0.5 + i as f32);
+            program.set_used();
+            program.set_uniform_mat4("Model", &Mat4::from_trs(position, rotation, Vec3::ONE));
+            mesh.draw();
+        }
+
+        window.gl_swap_window();
+    }
+}
@@ -0,0 +1,145 @@
+// Every shader in the crate that wants to work with more than one kind of mesh agrees on where each vertex attribute
+// lives, so that things like the debug views can be drawn on top of any geometry without knowing where it came from.
+// These are the numbers that go in `layout (location = N)` in the shaders and the first argument of
+// `gl::VertexAttribPointer()`.
+//
+// A layout can also describe per-instance data rather than per-vertex data.  With a divisor of 1, GL moves on to the
+// next element of the buffer once per instance of an instanced draw instead of once per vertex, so a buffer of
+// transforms and colors can place and tint thousands of copies of a mesh in one draw call.
+
+use crate::math::{Mat4, Vec2, Vec3, Vec4};
+
+#[allow(dead_code)]
+pub mod attrib {
+    pub const POSITION: gl::types::GLuint = 0;
+    pub const COLOR: gl::types::GLuint = 1;
+    pub const NORMAL: gl::types::GLuint = 2;
+    pub const TEX_COORD: gl::types::GLuint = 3;
+    pub const TANGENT: gl::types::GLuint = 4;
+    pub const JOINTS: gl::types::GLuint = 5;   // which bones move a skinned vertex
+    pub const WEIGHTS: gl::types::GLuint = 6;  // and how much each of them counts
+    pub const INSTANCE_COLOR: gl::types::GLuint = 7;
+    pub const INSTANCE_TRANSFORM: gl::types::GLuint = 8;  // a mat4, so it takes up 8, 9, 10 and 11 (one per column)
+    pub const INTENSITY: gl::types::GLuint = 12;  // a scanner's per-point intensity, see `ply::PlyAttribute`
+}
+
+/// Describes where one attribute lives inside a vertex, i.e. everything `gl::VertexAttribPointer()` needs to know
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VertexAttribute {
+    pub location: gl::types::GLuint,
+    pub components: gl::types::GLint,  // 1 to 4
+    pub data_type: gl::types::GLenum,  // `gl::FLOAT`, `gl::UNSIGNED_BYTE`, ...
+    pub normalized: bool,              // for integer types: map 0..255 (or whatever) onto 0.0..1.0
+    pub offset: usize,                 // in bytes, from the start of the vertex
+}
+
+/// How a whole vertex is laid out in a buffer: how far apart consecutive vertices are, and the attributes inside each
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexLayout {
+    pub stride: usize,  // in bytes
+    pub attributes: Vec<VertexAttribute>,
+    pub divisor: gl::types::GLuint,  // 0 to step through the buffer per vertex, N to step once every N instances
+}
+
+#[allow(dead_code)]
+impl VertexLayout {
+    pub fn new(stride: usize) -> VertexLayout {
+        VertexLayout { stride, attributes: Vec::new(), divisor: 0 }
+    }
+
+    /// Makes every attribute in the layout step once per instance rather than once per vertex
+    pub fn per_instance(self) -> VertexLayout {
+        self.with_divisor(1)
+    }
+
+    pub fn with_divisor(mut self, divisor: gl::types::GLuint) -> VertexLayout {
+        self.divisor = divisor;
+        self
+    }
+
+    /// Adds a float attribute, which is what nearly everything is
+    pub fn float(self, location: gl::types::GLuint, components: gl::types::GLint, offset: usize) -> VertexLayout {
+        self.attribute(VertexAttribute { location, components, data_type: gl::FLOAT, normalized: false, offset })
+    }
+
+    /// Adds a `mat4` attribute.  An attribute can hold at most four floats, so a matrix takes four locations in a row
+    /// starting at `location`, one per column
+    pub fn mat4(self, location: gl::types::GLuint, offset: usize) -> VertexLayout {
+        let column = 4 * std::mem::size_of::<f32>();
+        (0..4).fold(self, |layout, i| layout.float(location + i, 4, offset + i as usize * column))
+    }
+
+    pub fn attribute(mut self, attribute: VertexAttribute) -> VertexLayout {
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Tells GL about every attribute.  The VAO and the buffer holding the vertices need to be bound when calling this,
+    /// since that's what the attribute pointers get recorded into
+    pub fn apply(&self) {
+        for attribute in &self.attributes {
+            unsafe {
+                gl::EnableVertexAttribArray(attribute.location);
+                gl::VertexAttribPointer(
+                    attribute.location,
+                    attribute.components,
+                    attribute.data_type,
+                    if attribute.normalized { gl::TRUE } else { gl::FALSE },
+                    self.stride as gl::types::GLint,
+                    attribute.offset as *const gl::types::GLvoid,
+                );
+                gl::VertexAttribDivisor(attribute.location, self.divisor);
+            }
+        }
+    }
+}
+
+/// The vertex format that loaded and generated meshes use.  `#[repr(C)]` makes sure Rust doesn't reorder the fields,
+/// since GL is going to read this memory using the offsets in `Vertex::layout()`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub tex_coord: Vec2,
+    pub tangent: Vec3,
+}
+
+#[allow(dead_code)]
+impl Vertex {
+    pub fn new(position: Vec3, normal: Vec3, tex_coord: Vec2) -> Vertex {
+        Vertex { position, normal, tex_coord, tangent: Vec3::ZERO }
+    }
+
+    pub fn layout() -> VertexLayout {
+        let f = std::mem::size_of::<f32>();
+        VertexLayout::new(std::mem::size_of::<Vertex>())
+            .float(attrib::POSITION, 3, 0)
+            .float(attrib::NORMAL, 3, 3 * f)
+            .float(attrib::TEX_COORD, 2, 6 * f)
+            .float(attrib::TANGENT, 3, 8 * f)
+    }
+}
+
+/// What an instanced draw needs to know about each copy of the mesh: where it goes, and what color to tint it.  Goes
+/// in an `InstanceBuffer`, and shaders read it from `attrib::INSTANCE_TRANSFORM` and `attrib::INSTANCE_COLOR`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instance {
+    pub transform: Mat4,
+    pub color: Vec4,
+}
+
+#[allow(dead_code)]
+impl Instance {
+    pub fn new(transform: Mat4, color: Vec4) -> Instance {
+        Instance { transform, color }
+    }
+
+    pub fn layout() -> VertexLayout {
+        VertexLayout::new(std::mem::size_of::<Instance>())
+            .mat4(attrib::INSTANCE_TRANSFORM, 0)
+            .float(attrib::INSTANCE_COLOR, 4, std::mem::size_of::<Mat4>())
+            .per_instance()
+    }
+}